@@ -1,3 +1,5 @@
+// Requires a nightly toolchain: RUSTFLAGS="--cfg nightly" cargo +nightly bench
+#![cfg(nightly)]
 #![feature(test)]
 extern crate test;
 use std::hint::black_box;
@@ -0,0 +1,124 @@
+//! Binary frames shared with `ftlog::binary`
+//!
+//! All integers are little-endian. Each frame is a `u32` length followed by a body
+//! of that length, whose first byte is its kind:
+//!
+//! - [`TARGET`], target definition: `u64` id, target in UTF-8
+//! - [`RECORD`], record: `i64` unix timestamp in nanoseconds, `u8` level (1 for
+//!   `Error` to 5 for `Trace`), `u64` target id, `u32` length and bytes of message,
+//!   `u32` count of key-values, then `u32` length and bytes of each key and value
+use core::fmt::{Arguments, Write};
+
+use log::Level;
+
+/// Kind of target definition frames
+pub const TARGET: u8 = 0;
+/// Kind of record frames
+pub const RECORD: u8 = 1;
+/// Size of a record frame with empty message and no key-values
+pub const RECORD_OVERHEAD: usize = 4 + 1 + 8 + 1 + 8 + 4 + 4;
+
+/// Id of a target, FNV-1a hash of it
+pub const fn target_id(target: &str) -> u64 {
+    let bytes = target.as_bytes();
+    let mut hash = 0xcbf29ce484222325u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Bytes written into a fixed buffer, dropping what does not fit
+struct Cursor<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Cursor<'_> {
+    fn put(&mut self, bytes: &[u8]) {
+        let n = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+    }
+}
+
+impl Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        // keep whole characters only, so a truncated message is still UTF-8
+        let mut end = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.put(&s.as_bytes()[..end]);
+        if end < s.len() {
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Write the length of the frame starting at `buf[0]` and ending at `len`
+fn finish(buf: &mut [u8], len: usize) -> usize {
+    buf[..4].copy_from_slice(&((len - 4) as u32).to_le_bytes());
+    len
+}
+
+/// Encode a target definition into `buf`, returning the length of the frame
+///
+/// The target is truncated to fit `buf`, which must hold at least 13 bytes.
+pub fn encode_target(buf: &mut [u8], id: u64, target: &str) -> usize {
+    let mut cursor = Cursor { buf, len: 4 };
+    cursor.put(&[TARGET]);
+    cursor.put(&id.to_le_bytes());
+    let _ = cursor.write_str(target);
+    let len = cursor.len;
+    finish(buf, len)
+}
+
+/// Encode a record without key-values into `buf`, returning the length of the frame
+///
+/// The message is truncated to fit `buf`, which must hold at least
+/// [`RECORD_OVERHEAD`] bytes.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than [`RECORD_OVERHEAD`].
+pub fn encode_record(buf: &mut [u8], nanos: i64, level: Level, id: u64, args: Arguments) -> usize {
+    assert!(buf.len() >= RECORD_OVERHEAD, "frame buffer too small");
+    let capacity = buf.len();
+    // leave room for the count of key-values after the message
+    let mut cursor = Cursor {
+        buf: &mut buf[..capacity - 4],
+        len: 4,
+    };
+    cursor.put(&[RECORD]);
+    cursor.put(&nanos.to_le_bytes());
+    cursor.put(&[level as u8]);
+    cursor.put(&id.to_le_bytes());
+    let len_at = cursor.len;
+    cursor.put(&[0; 4]);
+    let _ = cursor.write_fmt(args);
+    let len = cursor.len;
+    let message_len = (len - len_at - 4) as u32;
+    buf[len_at..len_at + 4].copy_from_slice(&message_len.to_le_bytes());
+    buf[len..len + 4].copy_from_slice(&0u32.to_le_bytes());
+    finish(buf, len + 4)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn truncate_message() {
+        let mut buf = [0; RECORD_OVERHEAD + 5];
+        let len = encode_record(&mut buf, 7, Level::Warn, 1, format_args!("ab{}", "cdé"));
+        // `é` takes two bytes and is dropped as a whole
+        assert_eq!(len, RECORD_OVERHEAD + 4);
+        assert_eq!(&buf[..4], &(len as u32 - 4).to_le_bytes());
+        assert_eq!(&buf[22..30], b"\x04\0\0\0abcd");
+        assert_eq!(&buf[30..34], &[0; 4]);
+    }
+}
@@ -0,0 +1,159 @@
+//! `no_std` front-end of ftlog
+//!
+//! Firmware without an allocator or threads can't run the log thread of `ftlog`,
+//! but can still use its cheap call sites: records are encoded at the call site
+//! into the binary frames of `ftlog::binary`, on the stack, and handed to a
+//! [`Sink`] that streams them over UART, RTT or whatever the device has. The host
+//! turns the stream back to text with `ftlog-decode` of `ftlog`.
+//!
+//! ```
+//! use ftlog_core::{info, Sink};
+//!
+//! struct Uart;
+//! impl Sink for Uart {
+//!     fn write(&self, frame: &[u8]) {
+//!         // push `frame` to the UART FIFO
+//!     }
+//! }
+//!
+//! static UART: Uart = Uart;
+//! ftlog_core::set_sink(&UART).unwrap();
+//! ftlog_core::set_max_level(log::LevelFilter::Info);
+//! info!("booted in {}ms", 42);
+//! ```
+//!
+//! Each call site defines its target with a frame before its first record. Call
+//! [`redefine_targets`] when a new reader attaches to the stream, so that it learns
+//! targets defined before. Messages longer than [`FRAME_CAPACITY`] are truncated,
+//! and key-values are not supported.
+//!
+//! Setting the sink needs atomic compare-and-swap, which some targets like
+//! `thumbv6m` lack.
+#![no_std]
+
+use core::cell::UnsafeCell;
+use core::fmt::Arguments;
+use core::sync::atomic::{AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+pub use log::{Level, LevelFilter};
+
+pub mod frame;
+mod macros;
+
+/// Size of the stack buffer a frame is encoded in
+pub const FRAME_CAPACITY: usize = 256;
+
+/// Destination of encoded frames
+pub trait Sink: Sync {
+    /// Write a complete frame, frames must be kept in order and not interleaved
+    fn write(&self, frame: &[u8]);
+
+    /// Current unix time in nanoseconds, zero if the device has no clock
+    fn now_nanos(&self) -> i64 {
+        0
+    }
+}
+
+const UNINITIALIZED: u8 = 0;
+const INITIALIZING: u8 = 1;
+const INITIALIZED: u8 = 2;
+
+struct SinkCell(UnsafeCell<Option<&'static dyn Sink>>);
+
+// written once by `set_sink` before `STATE` becomes `INITIALIZED`, read-only afterwards
+unsafe impl Sync for SinkCell {}
+
+static SINK: SinkCell = SinkCell(UnsafeCell::new(None));
+static STATE: AtomicU8 = AtomicU8::new(UNINITIALIZED);
+static MAX_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Trace as usize);
+static EPOCH: AtomicU32 = AtomicU32::new(0);
+
+/// The sink was already set
+#[derive(Debug)]
+pub struct SetSinkError(());
+
+impl core::fmt::Display for SetSinkError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("ftlog_core sink is already set")
+    }
+}
+
+/// Set the sink of all records, only once
+pub fn set_sink(sink: &'static dyn Sink) -> Result<(), SetSinkError> {
+    match STATE.compare_exchange(
+        UNINITIALIZED,
+        INITIALIZING,
+        Ordering::Acquire,
+        Ordering::Relaxed,
+    ) {
+        Ok(_) => {
+            // SAFETY: only the thread winning the exchange writes, and readers wait
+            // for `INITIALIZED`
+            unsafe { *SINK.0.get() = Some(sink) };
+            STATE.store(INITIALIZED, Ordering::Release);
+            Ok(())
+        }
+        Err(_) => Err(SetSinkError(())),
+    }
+}
+
+fn sink() -> Option<&'static dyn Sink> {
+    if STATE.load(Ordering::Acquire) != INITIALIZED {
+        return None;
+    }
+    // SAFETY: the cell is not written after `INITIALIZED`
+    unsafe { *SINK.0.get() }
+}
+
+/// Set the most verbose level of records to encode, `Trace` by default
+pub fn set_max_level(level: LevelFilter) {
+    MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn max_level() -> LevelFilter {
+    match MAX_LEVEL.load(Ordering::Relaxed) {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Make every call site define its target again before its next record
+pub fn redefine_targets() {
+    EPOCH.store(
+        EPOCH.load(Ordering::Relaxed).wrapping_add(1),
+        Ordering::Relaxed,
+    );
+}
+
+/// Epoch a call site last defined its target in
+#[doc(hidden)]
+pub struct CallSite(AtomicU32);
+
+impl CallSite {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> CallSite {
+        CallSite(AtomicU32::new(u32::MAX))
+    }
+}
+
+#[doc(hidden)]
+pub fn __log(site: &CallSite, level: Level, target: &str, args: Arguments) {
+    let Some(sink) = sink() else {
+        return;
+    };
+    let id = frame::target_id(target);
+    let mut buf = [0; FRAME_CAPACITY];
+    let epoch = EPOCH.load(Ordering::Relaxed);
+    if site.0.load(Ordering::Relaxed) != epoch {
+        site.0.store(epoch, Ordering::Relaxed);
+        let len = frame::encode_target(&mut buf, id, target);
+        sink.write(&buf[..len]);
+    }
+    let len = frame::encode_record(&mut buf, sink.now_nanos(), level, id, args);
+    sink.write(&buf[..len]);
+}
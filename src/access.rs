@@ -0,0 +1,215 @@
+//! HTTP access logs
+//!
+//! [`access_log!`](crate::access_log) logs a request at `Info` level with target
+//! `access` and these key-values, which [`CommonLogFormatter`] and
+//! [`CombinedLogFormatter`] turn into lines of Apache and Nginx:
+//!
+//! - `method`, `path`, `status`, `latency_us`, `bytes` and `user_agent` given to
+//!   the macro
+//! - optional `client`, `user`, `protocol` and `referer` after `;`, other keys are
+//!   kept for other formatters
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use ftlog::access::CombinedLogFormatter;
+//! use ftlog::access_log;
+//!
+//! let access = ftlog::builder()
+//!     .formatter(CombinedLogFormatter::new())
+//!     .build()
+//!     .unwrap();
+//! let latency = Duration::from_micros(1250);
+//! access_log!(logger: access, "GET", "/index.html", 200, latency, 2326, "curl/8.0");
+//! access_log!(logger: access, "POST", "/login", 302, latency, 0, "Mozilla/5.0";
+//!     client = "10.0.0.7", user = "alice", referer = "https://example.com/");
+//! // Output:
+//! // - - - [14/Jun/2023:11:13:26 +0800] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/8.0"
+//! // 10.0.0.7 - alice [14/Jun/2023:11:13:26 +0800] "POST /login HTTP/1.1" 302 - "https://example.com/" "Mozilla/5.0"
+//! ```
+//!
+//! Without `logger:` records go to the global logger, use a standalone logger as
+//! above to keep access logs in their own format and file. Key-values are only
+//! captured with `kv` feature, which is enabled by default.
+use std::fmt::Display;
+use std::io::Write;
+use std::time::Duration;
+
+use log::kv::Value;
+use log::{Log, Metadata, Record};
+
+use crate::format::{LogRecord, RecordFormatter};
+
+/// Request logged by [`access_log!`](crate::access_log)
+#[doc(hidden)]
+pub struct Request<'a> {
+    pub method: &'a dyn Display,
+    pub path: &'a dyn Display,
+    pub status: u16,
+    pub latency: Duration,
+    pub bytes: u64,
+    pub user_agent: &'a dyn Display,
+}
+
+#[doc(hidden)]
+pub fn log_access(
+    logger: &dyn Log,
+    metadata: Metadata,
+    request: Request,
+    extra: &[(&str, &dyn Display)],
+    location: (&'static str, &'static str, u32),
+) {
+    let mut key_values = vec![
+        ("method", Value::from_dyn_display(request.method)),
+        ("path", Value::from_dyn_display(request.path)),
+        ("status", Value::from(request.status)),
+        (
+            "latency_us",
+            Value::from(request.latency.as_micros() as u64),
+        ),
+        ("bytes", Value::from(request.bytes)),
+        ("user_agent", Value::from_dyn_display(request.user_agent)),
+    ];
+    key_values.extend(
+        extra
+            .iter()
+            .map(|(key, value)| (*key, Value::from_dyn_display(*value))),
+    );
+    let (module_path, file, line) = location;
+    logger.log(
+        &Record::builder()
+            .metadata(metadata)
+            .module_path_static(Some(module_path))
+            .file_static(Some(file))
+            .line(Some(line))
+            .key_values(&key_values)
+            .args(format_args!(
+                "{} {} {}",
+                request.method, request.path, request.status
+            ))
+            .build(),
+    );
+}
+
+/// Apache Common Log Format, e.g.
+/// `10.0.0.7 - alice [14/Jun/2023:11:13:26 +0800] "GET /index.html HTTP/1.1" 200 2326`
+///
+/// Missing values are written as `-`, the protocol defaults to `HTTP/1.1`, and zero
+/// bytes as `-` like Apache does. Time is in the timezone configured by builder.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommonLogFormatter {
+    latency: bool,
+}
+
+impl CommonLogFormatter {
+    pub fn new() -> CommonLogFormatter {
+        Self::default()
+    }
+
+    /// Append latency in microseconds, like `%D` of Apache
+    pub fn latency(mut self, latency: bool) -> CommonLogFormatter {
+        self.latency = latency;
+        self
+    }
+}
+
+/// Apache Combined Log Format, Common Log Format followed by quoted referer and user
+/// agent, which is also the default format of Nginx
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CombinedLogFormatter {
+    latency: bool,
+}
+
+impl CombinedLogFormatter {
+    pub fn new() -> CombinedLogFormatter {
+        Self::default()
+    }
+
+    /// Append latency in microseconds, like `%D` of Apache
+    pub fn latency(mut self, latency: bool) -> CombinedLogFormatter {
+        self.latency = latency;
+        self
+    }
+}
+
+fn field<'a>(record: &LogRecord<'a>, name: &str) -> Option<&'a str> {
+    record
+        .key_values()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+        .filter(|value| !value.is_empty())
+}
+
+/// Write `value` within quotes, escaping quotes and backslashes like Apache
+fn write_quoted(buf: &mut Vec<u8>, value: &str) {
+    buf.push(b'"');
+    for byte in value.bytes() {
+        if byte == b'"' || byte == b'\\' {
+            buf.push(b'\\');
+        }
+        buf.push(byte);
+    }
+    buf.push(b'"');
+}
+
+fn write_common(record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+    let time = record.time();
+    let offset = time.offset();
+    write!(
+        buf,
+        "{} - {} [{:02}/{}/{}:{:02}:{:02}:{:02} {}{:02}{:02}] ",
+        field(record, "client").unwrap_or("-"),
+        field(record, "user").unwrap_or("-"),
+        time.day(),
+        &time.month().to_string()[..3],
+        time.year(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+        if offset.is_negative() { '-' } else { '+' },
+        offset.whole_hours().abs(),
+        offset.minutes_past_hour().abs(),
+    )?;
+    let request = format!(
+        "{} {} {}",
+        field(record, "method").unwrap_or("-"),
+        field(record, "path").unwrap_or("-"),
+        field(record, "protocol").unwrap_or("HTTP/1.1"),
+    );
+    write_quoted(buf, &request);
+    let bytes = field(record, "bytes").filter(|x| *x != "0");
+    write!(
+        buf,
+        " {} {}",
+        field(record, "status").unwrap_or("-"),
+        bytes.unwrap_or("-")
+    )
+}
+
+fn write_latency(record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+    writeln!(buf, " {}", field(record, "latency_us").unwrap_or("-"))
+}
+
+impl RecordFormatter for CommonLogFormatter {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        write_common(record, buf)?;
+        if self.latency {
+            write_latency(record, buf)?;
+        }
+        Ok(())
+    }
+}
+
+impl RecordFormatter for CombinedLogFormatter {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        write_common(record, buf)?;
+        buf.push(b' ');
+        write_quoted(buf, field(record, "referer").unwrap_or("-"));
+        buf.push(b' ');
+        write_quoted(buf, field(record, "user_agent").unwrap_or("-"));
+        if self.latency {
+            write_latency(record, buf)?;
+        }
+        Ok(())
+    }
+}
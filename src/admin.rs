@@ -0,0 +1,371 @@
+//! HTTP admin endpoint
+//!
+//! [`Admin`] handles two endpoints of a service's admin interface:
+//!
+//! | request                          | effect                                              |
+//! |----------------------------------|-----------------------------------------------------|
+//! | `GET /loglevel`                  | print the max level                                 |
+//! | `GET /loglevel?target=<target>`  | print the level of records of the target            |
+//! | `PUT /loglevel` with `<level>`   | set the max level, see [`set_level`](crate::set_level) |
+//! | `PUT /loglevel?target=<prefix>` with `<level>` or `reset` | set or remove the level of a target prefix, see [`set_target_level`](crate::set_target_level) |
+//! | `GET /logtail`                   | stream lines written by appenders as server-sent events |
+//! | `GET /logtail?level=<level>`     | stream lines of records of the level or more severe |
+//!
+//! Paths are matched by suffix, so the handler can be mounted under any prefix.
+//! [`Admin::serve`] runs a minimal HTTP/1.1 server for services without one:
+//!
+//! ```no_run
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! ftlog::admin::Admin::global()
+//!     .unwrap()
+//!     .serve("127.0.0.1:9901")
+//!     .unwrap();
+//! ```
+//!
+//! ```shell
+//! $ curl -X PUT -d debug localhost:9901/loglevel
+//! $ curl -N localhost:9901/logtail?level=warn
+//! data: 2024-01-01 00:00:00.000+08 0ms WARN main [src/main.rs:12] disk almost full
+//!
+//! ```
+//!
+//! Or call [`Admin::handle`] from routes of an existing server. [`Events`] blocks
+//! while waiting for lines, so async servers should iterate it on a blocking thread:
+//!
+//! ```ignore
+//! async fn loglevel(req: axum::extract::Request) -> axum::response::Response {
+//!     let (parts, body) = req.into_parts();
+//!     let body = axum::body::to_bytes(body, 4096).await.unwrap();
+//!     let resp = ADMIN.handle(&http::Request::from_parts(parts, body));
+//!     resp.map(|body| match body {
+//!         ftlog::admin::Body::Full(bytes) => axum::body::Body::from(bytes),
+//!         ftlog::admin::Body::Events(_) => unreachable!(),
+//!     })
+//! }
+//! ```
+//!
+//! There is no authentication: do not expose the endpoints to untrusted networks.
+//! Lines of `/logtail` are copied after being written by log thread; a slow client
+//! misses lines rather than slowing down logging.
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use http::{header, Method, Request, Response, StatusCode};
+use log::LevelFilter;
+
+use crate::tap::FormattedRecord;
+use crate::{Logger, Shared, GLOBAL_PIPELINE};
+
+/// Interval of comments keeping idle `/logtail` connections open
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// Max size of request bodies read by [`Admin::serve`]
+const MAX_BODY: usize = 4096;
+
+/// Handler of `/loglevel` and `/logtail`, see [module](self) docs
+#[derive(Clone)]
+pub struct Admin {
+    shared: Weak<Shared>,
+}
+
+/// Body of a response of [`Admin::handle`]
+pub enum Body {
+    /// The whole body
+    Full(Vec<u8>),
+    /// An endless stream of server-sent events
+    Events(Events),
+}
+
+/// Server-sent events of lines written by appenders
+///
+/// Each item is a complete event. The iterator blocks until a line is written, yields
+/// a comment every 15 seconds without lines to keep the connection open, and ends when
+/// the logger is dropped.
+pub struct Events {
+    receiver: Receiver<Arc<FormattedRecord>>,
+    level: LevelFilter,
+}
+
+impl Iterator for Events {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        loop {
+            match self.receiver.recv_timeout(KEEPALIVE) {
+                Ok(record) if record.level <= self.level => {
+                    let mut event = Vec::with_capacity(record.line.len() + 8);
+                    for line in record.line.lines() {
+                        event.extend_from_slice(b"data: ");
+                        event.extend_from_slice(line.as_bytes());
+                        event.push(b'\n');
+                    }
+                    event.push(b'\n');
+                    return Some(event);
+                }
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => return Some(b": keepalive\n\n".to_vec()),
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+fn response(status: StatusCode, body: impl Into<Vec<u8>>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::Full(body.into()))
+        .unwrap()
+}
+
+/// Value of `key` in the query string of `req`, percent-decoded
+fn query<B>(req: &Request<B>, key: &str) -> Option<String> {
+    req.uri()
+        .query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| decode(v))
+}
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|x| u8::from_str_radix(x, 16).ok())
+                {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b'+' => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+impl Admin {
+    /// Handler managing `logger`
+    ///
+    /// The handler does not keep the logger alive: once it is dropped, requests get
+    /// `503 Service Unavailable`.
+    pub fn new(logger: &Logger) -> Admin {
+        Admin {
+            shared: Arc::downgrade(&logger.shared),
+        }
+    }
+
+    /// Handler managing the global logger, `None` if ftlog is not installed as the
+    /// global logger
+    pub fn global() -> Option<Admin> {
+        GLOBAL_PIPELINE.get().map(|shared| Admin {
+            shared: Arc::downgrade(shared),
+        })
+    }
+
+    /// Handle a request, whose body is a level for `PUT /loglevel`
+    pub fn handle<B: AsRef<[u8]>>(&self, req: &Request<B>) -> Response<Body> {
+        let Some(shared) = self.shared.upgrade() else {
+            return response(StatusCode::SERVICE_UNAVAILABLE, "logger is dropped\n");
+        };
+        let path = req.uri().path().trim_end_matches('/');
+        if path.ends_with("/loglevel") {
+            loglevel(&shared, req)
+        } else if path.ends_with("/logtail") {
+            logtail(&shared, req)
+        } else {
+            response(StatusCode::NOT_FOUND, "not found\n")
+        }
+    }
+
+    /// Serve the endpoints over HTTP/1.1 at `addr` in background threads, returning
+    /// the bound address
+    ///
+    /// Each connection is served by its own thread and closed after one request.
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> std::io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let admin = self.clone();
+        std::thread::Builder::new()
+            .name("logger-admin".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if admin.shared.strong_count() == 0 {
+                        return;
+                    }
+                    let admin = admin.clone();
+                    let _ = std::thread::Builder::new()
+                        .name("logger-admin-conn".to_string())
+                        .spawn(move || {
+                            let _ = admin.serve_connection(stream);
+                        });
+                }
+            })?;
+        Ok(local_addr)
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let resp = match read_request(stream) {
+            Ok(req) => self.handle(&req),
+            Err(e) => response(StatusCode::BAD_REQUEST, format!("{}\n", e)),
+        };
+        let (parts, body) = resp.into_parts();
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\n",
+            parts.status.as_u16(),
+            parts.status.canonical_reason().unwrap_or_default()
+        )?;
+        for (name, value) in &parts.headers {
+            write!(writer, "{}: ", name)?;
+            writer.write_all(value.as_bytes())?;
+            writer.write_all(b"\r\n")?;
+        }
+        writer.write_all(b"Connection: close\r\n")?;
+        match body {
+            Body::Full(bytes) if parts.status == StatusCode::NO_CONTENT => {
+                writer.write_all(b"\r\n")?;
+                drop(bytes);
+            }
+            Body::Full(bytes) => {
+                write!(writer, "Content-Length: {}\r\n\r\n", bytes.len())?;
+                writer.write_all(&bytes)?;
+            }
+            Body::Events(events) => {
+                writer.write_all(b"\r\n")?;
+                writer.flush()?;
+                for event in events {
+                    writer.write_all(&event)?;
+                    writer.flush()?;
+                }
+            }
+        }
+        writer.flush()
+    }
+}
+
+fn loglevel<B: AsRef<[u8]>>(shared: &Arc<Shared>, req: &Request<B>) -> Response<Body> {
+    let target = query(req, "target");
+    match *req.method() {
+        Method::GET => {
+            let level = match target {
+                Some(target) => shared.level_of(&target),
+                None => shared.level.get(),
+            };
+            let level = level.as_str().to_lowercase();
+            response(StatusCode::OK, format!("{}\n", level))
+        }
+        Method::PUT => {
+            let body = String::from_utf8_lossy(req.body().as_ref());
+            let level = match body.trim() {
+                "reset" if target.is_some() => None,
+                level => match LevelFilter::from_str(level) {
+                    Ok(level) => Some(level),
+                    Err(_) => return invalid_level(level),
+                },
+            };
+            match (target, level) {
+                (Some(prefix), level) => shared.set_target_level(&prefix, level),
+                (None, Some(level)) => shared.set_level(level),
+                (None, None) => unreachable!(),
+            }
+            Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::Full(Vec::new()))
+                .unwrap()
+        }
+        _ => method_not_allowed("GET, PUT"),
+    }
+}
+
+fn logtail<B>(shared: &Arc<Shared>, req: &Request<B>) -> Response<Body> {
+    if req.method() != Method::GET {
+        return method_not_allowed("GET");
+    }
+    let level = match query(req, "level") {
+        Some(level) => match LevelFilter::from_str(&level) {
+            Ok(level) => level,
+            Err(_) => return invalid_level(&level),
+        },
+        None => LevelFilter::Trace,
+    };
+    let events = Events {
+        receiver: shared.tap.subscribe(),
+        level,
+    };
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::Events(events))
+        .unwrap()
+}
+
+fn invalid_level(level: &str) -> Response<Body> {
+    response(
+        StatusCode::BAD_REQUEST,
+        format!("invalid level {}\n", level),
+    )
+}
+
+fn method_not_allowed(allow: &'static str) -> Response<Body> {
+    let mut resp = response(StatusCode::METHOD_NOT_ALLOWED, "method not allowed\n");
+    resp.headers_mut()
+        .insert(header::ALLOW, header::HeaderValue::from_static(allow));
+    resp
+}
+
+/// Read the request line, headers and body of an HTTP/1.1 request
+fn read_request(stream: TcpStream) -> std::io::Result<Request<Vec<u8>>> {
+    let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut words = line.split_whitespace();
+    let (Some(method), Some(uri)) = (words.next(), words.next()) else {
+        return Err(invalid("invalid request line"));
+    };
+    let mut req = Request::builder().method(method).uri(uri);
+    let mut length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("unexpected end of headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(invalid("invalid header"));
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid content length"))?;
+        }
+        req = req.header(name, value);
+    }
+    if length > MAX_BODY {
+        return Err(invalid("request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    req.body(body).map_err(|e| invalid(&e.to_string()))
+}
@@ -0,0 +1,304 @@
+//! Tamper-evident audit log
+//!
+//! [`AuditFileAppender`] chains records with SHA-256, each record is written after
+//! the hash of itself and every record before it. Editing, inserting or removing
+//! a record changes all hashes after it, so [`verify`] finds the first record that
+//! does not match. Every `checkpoint` records, and when the appender is dropped,
+//! the chain hash is signed with HMAC-SHA256 by a secret key, so that a forger who
+//! rewrites the whole chain cannot produce valid checkpoints without the key.
+//!
+//! ```rust
+//! use ftlog::appender::audit::{self, AuditFileAppender};
+//!
+//! let appender = AuditFileAppender::builder()
+//!     .path("./audit.log")
+//!     .key(b"secret kept by compliance team".to_vec())
+//!     .checkpoint(1000)
+//!     .build()
+//!     .unwrap();
+//! let _guard = ftlog::builder().root(appender).try_init().unwrap();
+//! log::info!("user alice granted admin");
+//! # drop(_guard);
+//!
+//! let file = std::io::BufReader::new(std::fs::File::open("./audit.log").unwrap());
+//! let verified = audit::verify(file, b"secret kept by compliance team").unwrap();
+//! assert!(verified.sealed);
+//! # std::fs::remove_file("./audit.log").unwrap();
+//! ```
+//!
+//! # Layout
+//!
+//! Each record is written as `<hash> <len> <record>`, where `hash` is 64 hex digits
+//! of `SHA-256(previous hash || record)`, starting from 32 zero bytes, and `len` is
+//! the byte length of the record including its trailing newline. A checkpoint is a
+//! line `#checkpoint <count> <hash> <mac>`, where `count` is the number of records
+//! so far and `mac` is the hex of `HMAC-SHA256(key, "ftlog-audit" || count as
+//! little-endian u64 || hash)`.
+//!
+//! Appending to an existing file verifies it first and continues its chain. A file
+//! truncated right after a checkpoint still verifies, keep the count or hash of the
+//! last checkpoint elsewhere to detect that. Rotation is not supported, since a
+//! chain spans one file.
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use typed_builder::TypedBuilder;
+
+const CHECKPOINT: &str = "#checkpoint";
+
+/// Error of [`verify`] and of opening an [`AuditFileAppender`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AuditError {
+    Io(std::io::Error),
+    /// input is not in audit log layout from this byte offset
+    Malformed {
+        offset: u64,
+    },
+    /// hash of the record with this index (starting from 0) does not match
+    Tampered {
+        record: u64,
+    },
+    /// checkpoint after this number of records has a wrong count, hash or signature
+    BadCheckpoint {
+        records: u64,
+    },
+}
+
+impl Display for AuditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditError::Io(e) => write!(f, "failed to read audit log: {}", e),
+            AuditError::Malformed { offset } => {
+                write!(f, "malformed audit log at byte {}", offset)
+            }
+            AuditError::Tampered { record } => {
+                write!(f, "audit log tampered at record {}", record)
+            }
+            AuditError::BadCheckpoint { records } => {
+                write!(f, "invalid checkpoint after {} records", records)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuditError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AuditError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AuditError {
+    fn from(e: std::io::Error) -> Self {
+        AuditError::Io(e)
+    }
+}
+
+/// Result of a successful [`verify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verified {
+    pub records: u64,
+    pub checkpoints: u64,
+    /// whether the log ends with a checkpoint, i.e. no record after the last one
+    pub sealed: bool,
+}
+
+/// State of a hash chain
+struct Chain {
+    hash: [u8; 32],
+    records: u64,
+}
+
+impl Chain {
+    fn new() -> Chain {
+        Chain {
+            hash: [0; 32],
+            records: 0,
+        }
+    }
+
+    fn push(&mut self, record: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update(self.hash);
+        hasher.update(record);
+        self.hash = hasher.finalize().into();
+        self.records += 1;
+    }
+
+    fn mac(&self, key: &[u8]) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(b"ftlog-audit");
+        mac.update(&self.records.to_le_bytes());
+        mac.update(&self.hash);
+        mac.finalize().into_bytes().into()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Check the hash chain and checkpoints of an audit log written with `key`
+pub fn verify<R: BufRead>(reader: R, key: &[u8]) -> Result<Verified, AuditError> {
+    replay(reader, key).map(|(verified, _)| verified)
+}
+
+fn replay<R: BufRead>(mut reader: R, key: &[u8]) -> Result<(Verified, Chain), AuditError> {
+    let mut chain = Chain::new();
+    let mut verified = Verified {
+        records: 0,
+        checkpoints: 0,
+        sealed: false,
+    };
+    let mut offset = 0u64;
+    let mut head = Vec::new();
+    loop {
+        head.clear();
+        let read = reader.read_until(b' ', &mut head)?;
+        if read == 0 {
+            break;
+        }
+        let start = offset;
+        let malformed = || AuditError::Malformed { offset: start };
+        let word = head.strip_suffix(b" ").ok_or_else(malformed)?;
+        if word == CHECKPOINT.as_bytes() {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            offset += (read + line.len()) as u64;
+            let line = line.strip_suffix('\n').ok_or_else(malformed)?;
+            let bad = AuditError::BadCheckpoint {
+                records: chain.records,
+            };
+            let mut parts = line.split(' ');
+            let (Some(count), Some(hash), Some(mac), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(bad);
+            };
+            if count != chain.records.to_string()
+                || hash != hex(&chain.hash)
+                || mac != hex(&chain.mac(key))
+            {
+                return Err(bad);
+            }
+            verified.checkpoints += 1;
+            verified.sealed = true;
+            continue;
+        }
+        let mut len = Vec::new();
+        reader.read_until(b' ', &mut len)?;
+        let record_len = std::str::from_utf8(&len)
+            .ok()
+            .and_then(|x| x.strip_suffix(' '))
+            .and_then(|x| x.parse::<usize>().ok())
+            .ok_or_else(malformed)?;
+        let mut record = vec![0; record_len];
+        reader.read_exact(&mut record).map_err(|_| malformed())?;
+        offset += (read + len.len() + record_len) as u64;
+        let index = chain.records;
+        chain.push(&record);
+        if word != hex(&chain.hash).as_bytes() {
+            return Err(AuditError::Tampered { record: index });
+        }
+        verified.records += 1;
+        verified.sealed = false;
+    }
+    Ok((verified, chain))
+}
+
+/// Appender writing a tamper-evident audit log, see [module doc](self)
+pub struct AuditFileAppender {
+    file: BufWriter<File>,
+    chain: Chain,
+    key: Vec<u8>,
+    checkpoint: u64,
+}
+
+#[derive(TypedBuilder)]
+#[builder(build_method(vis = "", name = __build), builder_method(vis = ""))]
+pub struct AuditFileAppenderBuilder {
+    #[builder(setter(transform = |x: impl AsRef<Path>| x.as_ref().to_path_buf()))]
+    path: PathBuf,
+    /// secret key signing checkpoints
+    #[builder(setter(into))]
+    key: Vec<u8>,
+    /// write a checkpoint every this number of records, 0 for only when dropped
+    #[builder(default = 1000)]
+    checkpoint: u64,
+}
+
+#[allow(dead_code, non_camel_case_types, missing_docs)]
+impl<__checkpoint: typed_builder::Optional<u64>>
+    AuditFileAppenderBuilderBuilder<((PathBuf,), (Vec<u8>,), __checkpoint)>
+{
+    /// Open the file, verifying and continuing the chain of an existing file
+    pub fn build(self) -> Result<AuditFileAppender, AuditError> {
+        let AuditFileAppenderBuilder {
+            path,
+            key,
+            checkpoint,
+        } = self.__build();
+        let chain = match File::open(&path) {
+            Ok(file) => replay(BufReader::new(file), &key)?.1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Chain::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(AuditFileAppender {
+            file: BufWriter::new(file),
+            chain,
+            key,
+            checkpoint,
+        })
+    }
+}
+
+impl AuditFileAppender {
+    pub fn builder() -> AuditFileAppenderBuilderBuilder {
+        AuditFileAppenderBuilder::builder()
+    }
+
+    fn write_checkpoint(&mut self) -> std::io::Result<()> {
+        writeln!(
+            self.file,
+            "{} {} {} {}",
+            CHECKPOINT,
+            self.chain.records,
+            hex(&self.chain.hash),
+            hex(&self.chain.mac(&self.key))
+        )
+    }
+}
+
+impl Write for AuditFileAppender {
+    fn write(&mut self, record: &[u8]) -> std::io::Result<usize> {
+        self.chain.push(record);
+        write!(self.file, "{} {} ", hex(&self.chain.hash), record.len())?;
+        self.file.write_all(record)?;
+        if self.chain.records.is_multiple_of(self.checkpoint) {
+            self.write_checkpoint()?;
+        }
+        Ok(record.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AuditFileAppender {
+    fn drop(&mut self) {
+        let sealed = self.chain.records.is_multiple_of(self.checkpoint);
+        if !sealed {
+            let _ = self.write_checkpoint();
+        }
+        let _ = self.file.flush();
+    }
+}
@@ -0,0 +1,362 @@
+//! Write colored lines to the console
+//!
+//! [`ConsoleAppender`] writes to standard output or error, and keeps color codes of
+//! [`Builder::color`](crate::Builder::color) when the stream is a terminal.
+//! Following the [`NO_COLOR`](https://no-color.org) and
+//! [`CLICOLOR_FORCE`](https://bixense.com/clicolors) conventions, colors are dropped
+//! when `NO_COLOR` is set and not empty, and kept even for a pipe when
+//! `CLICOLOR_FORCE` is set and not `0`. [`ConsoleAppender::color`] overrides both.
+//!
+//! ```
+//! use ftlog::appender::console::ConsoleAppender;
+//! use ftlog::appender::Sink;
+//!
+//! let _guard = ftlog::builder()
+//!     .color(true)
+//!     .root(Sink::new(ConsoleAppender::stderr()))
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! Lines are printed above progress bars when the appender is given a way to
+//! suspend them, see [`ConsoleAppender::suspend`].
+//!
+//! On Windows, virtual terminal processing is enabled for the console so that
+//! ANSI codes are understood by cmd and PowerShell. Consoles without it, before
+//! Windows 10, get colors set with `SetConsoleTextAttribute` instead.
+use std::env::var_os;
+use std::io::{stderr, stdout, IsTerminal, Stderr, Stdout, Write};
+
+use super::Appender;
+
+/// Whether a [`ConsoleAppender`] keeps color codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Keep colors for a terminal, unless `NO_COLOR` or `CLICOLOR_FORCE` say
+    /// otherwise
+    #[default]
+    Auto,
+    /// Always keep colors, e.g. for a pager reading a pipe
+    Always,
+    /// Never keep colors
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to keep colors for a stream, which is a `terminal` or not
+    pub(crate) fn colors(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let set = |name| var_os(name).filter(|x| !x.is_empty());
+                if set("NO_COLOR").is_some() {
+                    false
+                } else if set("CLICOLOR_FORCE").is_some_and(|x| x != "0") {
+                    true
+                } else {
+                    terminal
+                }
+            }
+        }
+    }
+}
+
+enum Stream {
+    Stdout(Stdout),
+    Stderr(Stderr),
+}
+
+impl Stream {
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Stream::Stdout(stdout) => stdout,
+            Stream::Stderr(stderr) => stderr,
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        match self {
+            Stream::Stdout(stdout) => stdout.is_terminal(),
+            Stream::Stderr(stderr) => stderr.is_terminal(),
+        }
+    }
+}
+
+/// Stream with the way to write colors to it
+struct Output {
+    stream: Stream,
+    /// Windows console without virtual terminal processing
+    #[cfg(windows)]
+    legacy: Option<windows::Legacy>,
+}
+
+impl Output {
+    fn new(stream: Stream) -> Output {
+        Output {
+            stream,
+            #[cfg(windows)]
+            legacy: None,
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        #[cfg(windows)]
+        if let Some(legacy) = &self.legacy {
+            return legacy.write(&mut self.stream, buf);
+        }
+        self.stream.writer().write_all(buf)
+    }
+}
+
+type Suspend = Box<dyn Fn(&mut dyn FnMut()) + Send>;
+
+/// Appender writing to standard output or error, see [module](self) docs
+pub struct ConsoleAppender {
+    output: Output,
+    colors: bool,
+    suspend: Option<Suspend>,
+}
+
+impl ConsoleAppender {
+    /// Write to standard output
+    pub fn stdout() -> ConsoleAppender {
+        ConsoleAppender::new(Stream::Stdout(stdout()))
+    }
+
+    /// Write to standard error
+    pub fn stderr() -> ConsoleAppender {
+        ConsoleAppender::new(Stream::Stderr(stderr()))
+    }
+
+    fn new(stream: Stream) -> ConsoleAppender {
+        ConsoleAppender {
+            output: Output::new(stream),
+            colors: false,
+            suspend: None,
+        }
+        .color(ColorChoice::Auto)
+    }
+
+    /// Whether to keep color codes, defaults to [`ColorChoice::Auto`]
+    pub fn color(mut self, choice: ColorChoice) -> ConsoleAppender {
+        self.colors = choice.colors(self.output.stream.is_terminal());
+        #[cfg(windows)]
+        {
+            self.output.legacy = None;
+        }
+        #[cfg(windows)]
+        if self.colors {
+            let console = windows::handle(&self.output.stream);
+            if !windows::enable_virtual_terminal(console) {
+                self.output.legacy = windows::Legacy::new(console);
+            }
+        }
+        self
+    }
+
+    /// Write each line within `suspend`, which hides progress bars or other live
+    /// output while the given closure runs and draws them again below the line
+    ///
+    /// With [indicatif](https://docs.rs/indicatif), pass the `suspend` method of the
+    /// `ProgressBar` or `MultiProgress` drawing to the same stream:
+    ///
+    /// ```ignore
+    /// let bars = indicatif::MultiProgress::new();
+    /// let console = ConsoleAppender::stderr().suspend({
+    ///     let bars = bars.clone();
+    ///     move |write| bars.suspend(write)
+    /// });
+    /// ```
+    pub fn suspend<F>(mut self, suspend: F) -> ConsoleAppender
+    where
+        F: Fn(&mut dyn FnMut()) + Send + 'static,
+    {
+        self.suspend = Some(Box::new(suspend));
+        self
+    }
+}
+
+impl Write for ConsoleAppender {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        let Some(suspend) = &self.suspend else {
+            return self.output.write_all(buf);
+        };
+        // flush before progress bars are drawn again
+        let mut result = Ok(());
+        suspend(&mut || {
+            result = self
+                .output
+                .write_all(buf)
+                .and_then(|_| self.output.stream.writer().flush())
+        });
+        result
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.output.stream.writer().flush()
+    }
+}
+
+impl Appender for ConsoleAppender {
+    fn colors(&self) -> bool {
+        self.colors
+    }
+}
+
+/// Enable ANSI codes for a console stream, true if they are understood
+///
+/// Always true but on Windows, where virtual terminal processing is turned on.
+pub(crate) fn enable_ansi(stdout: bool) -> bool {
+    #[cfg(windows)]
+    {
+        let stream = match stdout {
+            true => Stream::Stdout(stdout()),
+            false => Stream::Stderr(stderr()),
+        };
+        windows::enable_virtual_terminal(windows::handle(&stream))
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = stdout;
+        true
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::os::windows::io::AsRawHandle;
+
+    use super::Stream;
+
+    type Handle = *mut std::ffi::c_void;
+
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+    const FOREGROUND_MASK: u16 = 0x000f;
+    const FOREGROUND_INTENSITY: u16 = 0x0008;
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetConsoleMode(console: Handle, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: Handle, mode: u32) -> i32;
+        fn GetConsoleScreenBufferInfo(console: Handle, info: *mut ConsoleScreenBufferInfo) -> i32;
+        fn SetConsoleTextAttribute(console: Handle, attributes: u16) -> i32;
+    }
+
+    pub(super) fn handle(stream: &Stream) -> Handle {
+        match stream {
+            Stream::Stdout(stdout) => stdout.as_raw_handle(),
+            Stream::Stderr(stderr) => stderr.as_raw_handle(),
+        }
+    }
+
+    /// Turn on virtual terminal processing, false if the console does not support it
+    pub(super) fn enable_virtual_terminal(console: Handle) -> bool {
+        let mut mode = 0;
+        // SAFETY: `console` is a handle of a standard stream, `mode` outlives the call
+        unsafe {
+            GetConsoleMode(console, &mut mode) != 0
+                && (mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                    || SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0)
+        }
+    }
+
+    /// Console colored by setting text attributes, translating SGR color codes
+    pub(super) struct Legacy {
+        /// attributes of the console before the first line, restored on reset
+        original: u16,
+    }
+
+    impl Legacy {
+        pub(super) fn new(console: Handle) -> Option<Legacy> {
+            let mut info = ConsoleScreenBufferInfo::default();
+            // SAFETY: `info` is a valid `CONSOLE_SCREEN_BUFFER_INFO` outliving the call
+            let ok = unsafe { GetConsoleScreenBufferInfo(console, &mut info) != 0 };
+            ok.then_some(Legacy {
+                original: info.attributes,
+            })
+        }
+
+        /// Attributes for the parameters of an SGR sequence, e.g. `1;31`
+        fn attributes(&self, params: &[u8], mut current: u16) -> u16 {
+            for param in params.split(|b| *b == b';') {
+                // ANSI and console orders of red, green and blue are reversed
+                current = match param {
+                    b"" | b"0" => self.original,
+                    b"1" => current | FOREGROUND_INTENSITY,
+                    b"22" => current & !FOREGROUND_INTENSITY,
+                    b"39" => (current & !FOREGROUND_MASK) | (self.original & FOREGROUND_MASK),
+                    [b'3', color @ b'0'..=b'7'] => {
+                        let ansi = (color - b'0') as u16;
+                        let rgb = ((ansi & 1) << 2) | (ansi & 2) | ((ansi & 4) >> 2);
+                        (current & !0x0007) | rgb
+                    }
+                    _ => current,
+                };
+            }
+            current
+        }
+
+        /// Write `buf`, setting attributes for color codes instead of writing them
+        pub(super) fn write(&self, stream: &mut Stream, buf: &[u8]) -> std::io::Result<()> {
+            let console = handle(stream);
+            let writer = stream.writer();
+            let mut current = self.original;
+            let mut rest = buf;
+            while let Some(start) = rest.windows(2).position(|x| x == b"\x1b[") {
+                let Some(len) = rest[start + 2..]
+                    .iter()
+                    .position(|b| (0x40..=0x7e).contains(b))
+                else {
+                    break;
+                };
+                let end = start + 2 + len;
+                writer.write_all(&rest[..start])?;
+                if rest[end] == b'm' {
+                    writer.flush()?;
+                    current = self.attributes(&rest[start + 2..end], current);
+                    // SAFETY: `console` is a handle of the stream written to
+                    unsafe { SetConsoleTextAttribute(console, current) };
+                }
+                rest = &rest[end + 1..];
+            }
+            writer.write_all(rest)
+        }
+    }
+}
@@ -57,6 +57,11 @@
 //!
 //! **ATTENTION**: Any files that matchs the pattern will be deleted.
 //!
+//! Rotated files compressed by external tools, like `current-20221026T1351.log.gz`,
+//! match the pattern too. As compressing may touch a file long after its last record,
+//! the age of a compressed file counts from the end of its rotation period at the
+//! latest.
+//!
 //! ```rust
 //! use ftlog::{appender::{Period, FileAppender, Duration}};
 //! // clean files named like `current-\d{8}T\d{4}.log`.
@@ -67,6 +72,56 @@
 //! let appender = FileAppender::builder().path("./mylog.log").rotate(Period::Minute).expire(Duration::days(7)).build();
 //! ```
 //!
+//! ## File header
+//!
+//! A header can be written at the start of each new file, including files created
+//! by rotation. It is not written when appending to an existing non-empty file.
+//!
+//! ```rust
+//! use ftlog::appender::{FileAppender, Period};
+//!
+//! let appender = FileAppender::builder()
+//!     .path("./mylog.log")
+//!     .rotate(Period::Day)
+//!     .header(|| "#Software: billing 1.0\n".to_string())
+//!     .build();
+//! ```
+//!
+//! ## Placeholders
+//!
+//! The path can contain `${NAME}` placeholders, replaced when the appender is built,
+//! so that a config shared by instances writes to a file per instance:
+//!
+//! - `${PID}`: id of the process
+//! - `${HOSTNAME}`: environment variable `HOSTNAME`, or the name of the machine
+//! - any other name: the environment variable of that name, e.g. `${POD_NAME}`
+//!
+//! Building fails with [`AppenderError::Placeholder`] when a variable is not set.
+//!
+//! ```rust
+//! use ftlog::appender::FileAppender;
+//!
+//! // e.g. ./app-1234.log
+//! let appender = FileAppender::new("./app-${PID}.log");
+//! # drop(appender);
+//! # std::fs::remove_file(format!("./app-{}.log", std::process::id())).unwrap();
+//! ```
+//!
+//! ## Line ending and encoding
+//!
+//! Lines end with `\n` and are written in UTF-8 by default. For consumers which can
+//! only read Windows line endings, or a legacy encoding with `encoding` feature, lines
+//! and headers are converted before written. Leave these unset for binary formats.
+//!
+//! ```rust
+//! use ftlog::appender::{FileAppender, LineEnding};
+//!
+//! let appender = FileAppender::builder()
+//!     .path("./mylog.log")
+//!     .line_ending(LineEnding::Crlf)
+//!     .build();
+//! ```
+//!
 //! ## Rotation timezone
 //!
 //! By default, rotation is done by local timezone.
@@ -83,24 +138,25 @@
 //!     .timezone(LogTimezone::Utc)
 //!     .build();
 //! ```
-#[cfg(not(feature = "tsc"))]
-use std::time::Instant;
 use std::{
     borrow::Cow,
     fs::{File, OpenOptions},
     io::{BufWriter, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
+    time::SystemTime,
 };
 
-#[cfg(feature = "tsc")]
-use minstant::Instant;
-use time::{Date, Duration, Month, OffsetDateTime, Time, UtcOffset};
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
 use typed_builder::TypedBuilder;
 
-use crate::{local_timezone, LogTimezone};
+use crate::clock::{Clock, Stamp};
+use crate::stats::count_rotation;
+use crate::{local_timezone, AppenderError, InternalError, LogTimezone};
 
 /// Log rotation frequency
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Period {
     /// rotate log every minute
     Minute,
@@ -113,8 +169,117 @@ pub enum Period {
     /// rotate log every year
     Year,
 }
+
+/// Line terminator of written lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as lines are formatted
+    #[default]
+    Lf,
+    /// `\r\n`, for Windows tools
+    Crlf,
+}
+
+/// Character encoding of written lines
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// as lines are formatted
+    #[default]
+    Utf8,
+    /// an encoding of [`encoding_rs`], e.g. `GBK` or `SHIFT_JIS`, where characters
+    /// it can not represent are written as HTML numeric character references
+    ///
+    /// UTF-16 encodings are written as UTF-8, since `encoding_rs` does not encode
+    /// them.
+    #[cfg(feature = "encoding")]
+    Legacy(&'static encoding_rs::Encoding),
+}
+
+#[cfg(feature = "encoding")]
+impl From<&'static encoding_rs::Encoding> for Encoding {
+    fn from(encoding: &'static encoding_rs::Encoding) -> Self {
+        Encoding::Legacy(encoding)
+    }
+}
+
+/// Conversion of lines before written to file
+#[derive(Clone, Copy)]
+struct Convert {
+    line_ending: LineEnding,
+    encoding: Encoding,
+}
+
+impl Convert {
+    #[inline]
+    fn apply<'a>(&self, text: &'a [u8]) -> Cow<'a, [u8]> {
+        let text = match self.line_ending {
+            LineEnding::Lf => Cow::Borrowed(text),
+            LineEnding::Crlf => crlf(text),
+        };
+        match self.encoding {
+            Encoding::Utf8 => text,
+            #[cfg(feature = "encoding")]
+            Encoding::Legacy(encoding) => {
+                let text = String::from_utf8_lossy(&text);
+                let (encoded, _, _) = encoding.encode(&text);
+                Cow::Owned(encoded.into_owned())
+            }
+        }
+    }
+}
+
+/// Replace `${NAME}` placeholders in `path`, see [module doc](self#placeholders)
+pub(crate) fn expand(path: &Path) -> Result<PathBuf, AppenderError> {
+    let text = path.to_string_lossy();
+    if !text.contains("${") {
+        return Ok(path.to_path_buf());
+    }
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = &*text;
+    while let Some((name, start, end)) = rest.find("${").and_then(|start| {
+        let len = rest[start + 2..].find('}')?;
+        Some((&rest[start + 2..start + 2 + len], start, start + 3 + len))
+    }) {
+        let value = match name {
+            "PID" => Some(std::process::id().to_string()),
+            "HOSTNAME" => crate::hostname(),
+            name => std::env::var(name).ok(),
+        };
+        let value = value.ok_or_else(|| AppenderError::Placeholder {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+        })?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&value);
+        rest = &rest[end..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded.into())
+}
+
+/// Replace `\n` not following `\r` with `\r\n`
+fn crlf(text: &[u8]) -> Cow<'_, [u8]> {
+    let lf = text
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| **c == b'\n' && (*i == 0 || text[i - 1] != b'\r'))
+        .count();
+    if lf == 0 {
+        return Cow::Borrowed(text);
+    }
+    let mut converted = Vec::with_capacity(text.len() + lf);
+    for (i, c) in text.iter().enumerate() {
+        if *c == b'\n' && (i == 0 || text[i - 1] != b'\r') {
+            converted.push(b'\r');
+        }
+        converted.push(*c);
+    }
+    Cow::Owned(converted)
+}
+
 struct Rotate {
-    start: Instant,
+    start: Stamp,
     wait: Duration,
 
     period: Period,
@@ -132,92 +297,129 @@ pub struct FileAppenderBuilder {
     expire: Option<Duration>,
     #[builder(default=LogTimezone::Local)]
     timezone: LogTimezone,
+    /// read time from a custom clock, e.g. to test rotation, see [`crate::clock`]
+    #[builder(default, setter(transform = |clock: impl Clock + 'static| Some(Arc::new(clock) as Arc<dyn Clock>)))]
+    clock: Option<Arc<dyn Clock>>,
+    /// text written at the start of each new file, see [module doc](self#file-header)
+    #[builder(default, setter(transform = |header: impl Fn() -> String + Send + Sync + 'static| Some(Arc::new(header) as Header)))]
+    header: Option<Header>,
+    /// line terminator, see [module doc](self#line-ending-and-encoding)
+    #[builder(default)]
+    line_ending: LineEnding,
+    /// character encoding, see [module doc](self#line-ending-and-encoding)
+    #[builder(default, setter(into))]
+    encoding: Encoding,
+}
+
+/// Make the header of a new file
+type Header = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Open `path` for appending, starting with `header` if the file is empty
+fn open(
+    path: &Path,
+    header: Option<&Header>,
+    convert: Convert,
+) -> std::io::Result<BufWriter<File>> {
+    let mut file = BufWriter::new(OpenOptions::new().create(true).append(true).open(path)?);
+    if let Some(header) = header {
+        if file.get_ref().metadata()?.len() == 0 {
+            file.write_all(&convert.apply(header().as_bytes()))?;
+        }
+    }
+    Ok(file)
 }
 
 #[allow(dead_code, non_camel_case_types, missing_docs)]
-#[automatically_derived]
 impl<
         __rotate: typed_builder::Optional<Option<Period>>,
         __expire: typed_builder::Optional<Option<Duration>>,
         __timezone: typed_builder::Optional<LogTimezone>,
-    > FileAppenderBuilderBuilder<((PathBuf,), __rotate, __expire, __timezone)>
+        __clock: typed_builder::Optional<Option<Arc<dyn Clock>>>,
+        __header: typed_builder::Optional<Option<Header>>,
+        __line_ending: typed_builder::Optional<LineEnding>,
+        __encoding: typed_builder::Optional<Encoding>,
+    >
+    FileAppenderBuilderBuilder<(
+        (PathBuf,),
+        __rotate,
+        __expire,
+        __timezone,
+        __clock,
+        __header,
+        __line_ending,
+        __encoding,
+    )>
 {
+    /// Build the appender
+    ///
+    /// # Panics
+    ///
+    /// Panics if the log file cannot be opened, see `try_build`.
     pub fn build(self) -> FileAppender {
-        let builder = self.__build();
-        match (builder.rotate, builder.expire) {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Build the appender, failing if the log file cannot be opened or a
+    /// [placeholder](self#placeholders) of its path has no value
+    pub fn try_build(self) -> Result<FileAppender, AppenderError> {
+        let mut builder = self.__build();
+        builder.path = expand(&builder.path)?;
+        let convert = Convert {
+            line_ending: builder.line_ending,
+            encoding: builder.encoding,
+        };
+        let failed = |path: &Path| {
+            let path = path.to_path_buf();
+            |source| AppenderError::Open { path, source }
+        };
+        let rotate = match (builder.rotate, builder.expire) {
             // rotate with auto clean
-            (Some(period), Some(expire)) => {
-                let (start, wait) = FileAppender::until(period, &builder.timezone);
-                let path = FileAppender::file(&builder.path, period, &builder.timezone);
-                let mut file = BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(&path)
-                        .unwrap(),
-                );
-                let p = builder.path.clone();
-                let del_msg = clean_expire_log(p, period, expire);
-                if !del_msg.is_empty() {
-                    file.write_fmt(format_args!("Log file deleted: {}", del_msg))
-                        .unwrap_or_else(|_| {
-                            panic!("Write msg to \"{}\" failed", path.to_string_lossy())
-                        });
+            (Some(period), expire) => {
+                let clock = builder.clock.as_deref();
+                let (start, wait) = FileAppender::until(period, &builder.timezone, clock);
+                let path = FileAppender::file(&builder.path, period, &builder.timezone, clock);
+                let mut file =
+                    open(&path, builder.header.as_ref(), convert).map_err(failed(&path))?;
+                if let Some(expire) = expire {
+                    let p = builder.path.clone();
+                    let offset = FileAppender::offset_from_timezone(&builder.timezone);
+                    let del_msg = clean_expire_log(p, period, expire, offset, wall_now(clock));
+                    if !del_msg.is_empty() {
+                        file.write_fmt(format_args!("Log file deleted: {}", del_msg))
+                            .map_err(failed(&path))?;
+                    }
                 }
-                FileAppender {
+                Some((
                     file,
-                    path: builder.path,
-                    rotate: Some(Rotate {
+                    Rotate {
                         start,
                         wait,
                         period,
-                        expire: Some(expire),
-                    }),
-                    timezone: builder.timezone,
-                }
-            }
-            // rotate only
-            (Some(period), None) => {
-                let (start, wait) = FileAppender::until(period, &builder.timezone);
-                let path = FileAppender::file(&builder.path, period, &builder.timezone);
-                let file = BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(path)
-                        .unwrap(),
-                );
-                FileAppender {
-                    file,
-                    path: builder.path,
-                    rotate: Some(Rotate {
-                        start,
-                        wait,
-                        period,
-                        expire: None,
-                    }),
-                    timezone: builder.timezone,
-                }
+                        expire,
+                    },
+                ))
             }
             // single file
-            _ => FileAppender {
-                file: BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(&builder.path)
-                        .unwrap_or_else(|_| {
-                            panic!(
-                                "Fail to create log file: {}",
-                                builder.path.to_string_lossy()
-                            )
-                        }),
-                ),
-                path: builder.path,
-                rotate: None,
-                timezone: builder.timezone,
-            },
-        }
+            (None, _) => None,
+        };
+        let (file, rotate) = match rotate {
+            Some((file, rotate)) => (file, Some(rotate)),
+            None => (
+                open(&builder.path, builder.header.as_ref(), convert)
+                    .map_err(failed(&builder.path))?,
+                None,
+            ),
+        };
+        Ok(FileAppender {
+            file,
+            path: builder.path,
+            rotate,
+            timezone: builder.timezone,
+            clock: builder.clock,
+            header: builder.header,
+            convert,
+            generation: REOPEN.load(Ordering::Relaxed),
+        })
     }
 }
 
@@ -227,6 +429,23 @@ pub struct FileAppender {
     path: PathBuf,
     rotate: Option<Rotate>,
     timezone: LogTimezone,
+    clock: Option<Arc<dyn Clock>>,
+    header: Option<Header>,
+    convert: Convert,
+    /// value of `REOPEN` when the file was opened
+    generation: u64,
+}
+
+/// Incremented by [`reopen`]
+static REOPEN: AtomicU64 = AtomicU64::new(0);
+
+/// Reopen files of all `FileAppender`s in the process before their next write
+///
+/// Use it after moving log files away, e.g. by `logrotate` without `copytruncate`,
+/// so that appenders stop writing to the moved files. A rotating appender opens the
+/// file of the current period.
+pub fn reopen() {
+    REOPEN.fetch_add(1, Ordering::Relaxed);
 }
 
 impl FileAppender {
@@ -251,9 +470,15 @@ impl FileAppender {
         FileAppenderBuilder::builder()
     }
 
-    fn file<T: AsRef<Path>>(path: T, period: Period, timezone: &LogTimezone) -> PathBuf {
+    fn file<T: AsRef<Path>>(
+        path: T,
+        period: Period,
+        timezone: &LogTimezone,
+        clock: Option<&dyn Clock>,
+    ) -> PathBuf {
         let p = path.as_ref();
-        let dt = OffsetDateTime::now_utc().to_offset(Self::offset_from_timezone(timezone));
+        let dt =
+            OffsetDateTime::from(wall_now(clock)).to_offset(Self::offset_from_timezone(timezone));
         let ts = match period {
             Period::Year => format!("{}", dt.year()),
             Period::Month => format!("{}{:02}", dt.year(), dt.month() as u8),
@@ -279,7 +504,7 @@ impl FileAppender {
             let file_name = p
                 .file_stem()
                 .map(|x| format!("{}-{}.{}", x.to_string_lossy(), ts, ext.to_string_lossy()))
-                .expect("invalid file name");
+                .unwrap_or_else(|| format!("log-{}.{}", ts, ext.to_string_lossy()));
             p.with_file_name(file_name)
         } else {
             p.with_file_name(format!(
@@ -292,7 +517,7 @@ impl FileAppender {
         }
     }
 
-    fn offset_from_timezone(timezone: &LogTimezone) -> UtcOffset {
+    pub(crate) fn offset_from_timezone(timezone: &LogTimezone) -> UtcOffset {
         match timezone {
             LogTimezone::Local => local_timezone(),
             LogTimezone::Utc => UtcOffset::UTC,
@@ -300,15 +525,20 @@ impl FileAppender {
         }
     }
 
-    fn until(period: Period, timezone: &LogTimezone) -> (Instant, Duration) {
-        let tm_now = OffsetDateTime::now_utc().to_offset(Self::offset_from_timezone(timezone));
-        let now = Instant::now();
+    fn until(
+        period: Period,
+        timezone: &LogTimezone,
+        clock: Option<&dyn Clock>,
+    ) -> (Stamp, Duration) {
+        let tm_now =
+            OffsetDateTime::from(wall_now(clock)).to_offset(Self::offset_from_timezone(timezone));
+        let now = Stamp::now(clock);
         let tm_next = Self::next(&tm_now, period);
         (now, tm_next - tm_now)
     }
 
     #[inline]
-    fn next(now: &OffsetDateTime, period: Period) -> OffsetDateTime {
+    pub(crate) fn next(now: &OffsetDateTime, period: Period) -> OffsetDateTime {
         let tm_next = match period {
             Period::Year => Date::from_ordinal_date(now.year() + 1, 1)
                 .unwrap()
@@ -334,9 +564,19 @@ impl FileAppender {
     }
 
     /// Create a file appender that write log to file
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file cannot be opened, see [`FileAppender::try_new`].
     pub fn new<T: AsRef<Path>>(path: T) -> Self {
         Self::builder().path(path).build()
     }
+
+    /// Create a file appender that write log to file, failing if the file cannot be
+    /// opened
+    pub fn try_new<T: AsRef<Path>>(path: T) -> Result<Self, AppenderError> {
+        Self::builder().path(path).try_build()
+    }
     /// Create a file appender that rotate a new file every given period
     pub fn rotate<T: AsRef<Path>>(path: T, period: Period) -> Self {
         Self::builder().path(path).rotate(period).build()
@@ -354,55 +594,107 @@ impl FileAppender {
     }
 }
 
-fn clean_expire_log(path: PathBuf, rotate_period: Period, keep_duration: Duration) -> String {
-    let dir = path.parent().unwrap().to_path_buf();
-    let dir = if dir.is_dir() {
-        dir
-    } else {
-        PathBuf::from(".")
+/// Split the name of a file created by rotation, like `app-20240101.log` or its
+/// compressed `app-20240101.log.gz`, into the stem of the configured path, the
+/// rotation period and the start of the period in rotation timezone
+pub(crate) fn parse_rotated(name: &str) -> Option<(&str, Period, PrimitiveDateTime)> {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    let (stem, time) = Path::new(name).file_stem()?.to_str()?.rsplit_once('-')?;
+    let period = match time.len() {
+        13 => Period::Minute,
+        11 => Period::Hour,
+        8 => Period::Day,
+        6 => Period::Month,
+        4 => Period::Year,
+        _ => return None,
+    };
+    let check = |(ix, x): (usize, char)| match ix {
+        8 => x == 'T',
+        _ => x.is_ascii_digit(),
     };
-    let to_remove = std::fs::read_dir(dir)
-        .unwrap()
+    if !time.chars().enumerate().all(check) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>, default: u8| {
+        time.get(range).map_or(Some(default), |x| x.parse().ok())
+    };
+    let date = Date::from_calendar_date(
+        time[..4].parse().ok()?,
+        Month::try_from(field(4..6, 1)?).ok()?,
+        field(6..8, 1)?,
+    )
+    .ok()?;
+    let time = Time::from_hms(field(9..11, 0)?, field(11..13, 0)?, 0).ok()?;
+    Some((stem, period, date.with_time(time)))
+}
+
+/// Current time of `clock`, or of the system if there is no clock
+fn wall_now(clock: Option<&dyn Clock>) -> SystemTime {
+    clock.map_or_else(SystemTime::now, |clock| clock.now())
+}
+
+fn clean_expire_log(
+    path: PathBuf,
+    rotate_period: Period,
+    keep_duration: Duration,
+    offset: UtcOffset,
+    now: SystemTime,
+) -> String {
+    let dir = path
+        .parent()
+        .filter(|dir| dir.is_dir())
+        .map_or_else(|| PathBuf::from("."), |dir| dir.to_path_buf());
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(source) => {
+            crate::internal_error(InternalError::Cleanup { path, source });
+            return String::new();
+        }
+    };
+    let to_remove = entries
         .filter_map(|f| f.ok())
         .filter(|x| x.file_type().map(|x| x.is_file()).unwrap_or(false))
         .filter(|x| {
-            let p = x.path();
-            let name = p.file_stem().unwrap().to_string_lossy();
-            if let Some((stem, time)) = name.rsplit_once('-') {
-                let check = |(ix, x): (usize, char)| match ix {
-                    8 => x == 'T',
-                    _ => x.is_ascii_digit(),
-                };
-                let len = match rotate_period {
-                    Period::Minute => time.len() == 13,
-                    Period::Hour => time.len() == 11,
-                    Period::Day => time.len() == 8,
-                    Period::Month => time.len() == 6,
-                    Period::Year => time.len() == 4,
-                };
-                len && time.chars().enumerate().all(check)
-                    && path
-                        .file_stem()
-                        .map(|x| x.to_string_lossy() == stem)
-                        .unwrap_or(false)
-            } else {
-                false
+            let name = x.file_name();
+            let Some(name) = name.to_str() else {
+                return false;
+            };
+            let Some((stem, period, start)) = parse_rotated(name) else {
+                return false;
+            };
+            if period != rotate_period
+                || !path
+                    .file_stem()
+                    .map(|x| x.to_string_lossy() == stem)
+                    .unwrap_or(false)
+            {
+                return false;
             }
-        })
-        .filter(|x| {
-            x.metadata()
-                .ok()
-                .and_then(|x| x.modified().ok())
-                .map(|time| {
-                    time.elapsed()
-                        .map(|elapsed| elapsed > keep_duration)
-                        .unwrap_or(false)
-                })
+            let Some(mut modified) = x.metadata().ok().and_then(|x| x.modified().ok()) else {
+                return false;
+            };
+            // compressing a rotated file may touch it long after the last record,
+            // which is written before the end of its period
+            if name.ends_with(".gz") {
+                let end = FileAppender::next(&start.assume_offset(offset), period);
+                modified = modified.min(end.into());
+            }
+            now.duration_since(modified)
+                .map(|elapsed| elapsed > keep_duration)
                 .unwrap_or(false)
         });
 
     to_remove
-        .filter(|f| std::fs::remove_file(f.path()).is_ok())
+        .filter(|f| match std::fs::remove_file(f.path()) {
+            Ok(()) => true,
+            Err(source) => {
+                crate::internal_error(InternalError::Cleanup {
+                    path: f.path(),
+                    source,
+                });
+                false
+            }
+        })
         .map(|x| x.file_name().to_string_lossy().to_string())
         .collect::<Vec<_>>()
         .join(", ")
@@ -417,35 +709,59 @@ impl Write for FileAppender {
             expire: keep,
         }) = &mut self.rotate
         {
-            if start.elapsed() > *wait {
-                // close current file and create new file
+            let clock = self.clock.as_deref();
+            if Stamp::now(clock).since(*start) > *wait {
+                // close current file and create new file, retried on the next write
+                // if it fails
                 self.file.flush()?;
-                let path = Self::file(&self.path, *period, &self.timezone);
+                let path = Self::file(&self.path, *period, &self.timezone, clock);
+                let file = open(&path, self.header.as_ref(), self.convert).map_err(|e| {
+                    std::io::Error::new(e.kind(), format!("fail to open {}: {}", path.display(), e))
+                })?;
                 // remove outdated log files
                 if let Some(keep_duration) = keep {
                     let keep_duration = *keep_duration;
                     let path = self.path.clone();
                     let period = *period;
+                    let offset = Self::offset_from_timezone(&self.timezone);
+                    let now = wall_now(clock);
+                    // failures are reported to the logger of this appender
+                    let handler = crate::error_handler();
                     std::thread::spawn(move || {
-                        let del_msg = clean_expire_log(path, period, keep_duration);
-                        if !del_msg.is_empty() {
-                            crate::info!("Log file deleted: {}", del_msg);
-                        }
+                        crate::with_error_handler(&handler, || {
+                            let del_msg =
+                                clean_expire_log(path, period, keep_duration, offset, now);
+                            if !del_msg.is_empty() {
+                                crate::info!("Log file deleted: {}", del_msg);
+                            }
+                        })
                     });
                 };
 
                 // rotate file
-                self.file = BufWriter::new(
-                    OpenOptions::new()
-                        .create(true)
-                        .append(true)
-                        .open(path)
-                        .unwrap(),
-                );
-                (*start, *wait) = Self::until(*period, &self.timezone);
+                self.file = file;
+                (*start, *wait) = Self::until(*period, &self.timezone, clock);
+                count_rotation();
             }
         };
-        self.file.write_all(record).map(|_| record.len())
+        let generation = REOPEN.load(Ordering::Relaxed);
+        if generation != self.generation {
+            self.file.flush()?;
+            let path = match &self.rotate {
+                Some(rotate) => Self::file(
+                    &self.path,
+                    rotate.period,
+                    &self.timezone,
+                    self.clock.as_deref(),
+                ),
+                None => self.path.clone(),
+            };
+            self.file = open(&path, self.header.as_ref(), self.convert)?;
+            self.generation = generation;
+        }
+        self.file
+            .write_all(&self.convert.apply(record))
+            .map(|_| record.len())
     }
 
     #[inline]
@@ -1,10 +1,163 @@
 //! Useful appenders
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod console;
 pub mod file;
+pub mod per_thread;
+pub mod spill;
+pub mod tcp;
+pub mod timeout;
 
-pub use file::{FileAppender, Period};
-use std::io::Write;
+use console::ColorChoice;
+pub use file::{Encoding, FileAppender, LineEnding, Period};
+use std::any::TypeId;
+use std::io::{stderr, stdout, IsTerminal, Stderr, Stdout, Write};
 pub use time::Duration;
 
+/// An appender with hooks called by log thread, beyond writing lines
+///
+/// Any [`Write`] can be used as an appender, with default hooks. Implement this
+/// trait and pass the appender wrapped in [`Sink::new`] to take part in shutdown and
+/// report health.
+///
+/// ```
+/// use std::io::Write;
+/// use std::net::TcpStream;
+///
+/// use ftlog::appender::{Appender, Sink};
+///
+/// struct Remote(TcpStream);
+///
+/// impl Write for Remote {
+///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+///         self.0.write(buf)
+///     }
+///
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         self.0.flush()
+///     }
+/// }
+///
+/// impl Appender for Remote {
+///     fn on_shutdown(&mut self) -> std::io::Result<()> {
+///         self.0.shutdown(std::net::Shutdown::Write)
+///     }
+///
+///     fn healthcheck(&mut self) -> std::io::Result<()> {
+///         self.0.peer_addr().map(|_| ())
+///     }
+/// }
+///
+/// # fn main() -> std::io::Result<()> {
+/// # if false {
+/// let _guard = ftlog::builder()
+///     .root(Sink::new(Remote(TcpStream::connect("127.0.0.1:5140")?)))
+///     .try_init()
+///     .unwrap();
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+pub trait Appender: Write + Send {
+    /// Flush buffered lines when the logger shuts down, giving up after `timeout`
+    /// if the appender is able to
+    ///
+    /// The timeout is what is left of the shutdown timeout of the builder. Without a
+    /// shutdown timeout, [`Write::flush`] is called instead.
+    fn flush_with_deadline(&mut self, timeout: std::time::Duration) -> std::io::Result<()> {
+        let _ = timeout;
+        self.flush()
+    }
+
+    /// Called once after the last line is written and flushed when the logger shuts
+    /// down, e.g. to close a connection
+    fn on_shutdown(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Check whether the appender is able to write, called by log thread while idle
+    ///
+    /// The result is reported by [`AppenderStats`](crate::stats::AppenderStats), and
+    /// an appender failing it is quarantined, see [`reopen`](Appender::reopen).
+    fn healthcheck(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Reopen the sink, e.g. reconnect, while the appender is quarantined
+    ///
+    /// An appender is quarantined after repeated failed writes or a failed
+    /// healthcheck, and log thread stops writing to it. Once a second, it calls
+    /// `reopen` then [`healthcheck`](Appender::healthcheck), and writes to the
+    /// appender again if both succeed. The default does nothing, so writes are simply
+    /// retried.
+    fn reopen(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Whether lines may keep ANSI color codes, e.g. when writing to a terminal
+    ///
+    /// Asked once when the logger is built. Color codes, e.g. of
+    /// [`Builder::color`](crate::Builder::color), are stripped from lines written to
+    /// appenders returning `false`, the default, so that files stay clean when they
+    /// share a colorizing format with the console.
+    fn colors(&self) -> bool {
+        false
+    }
+}
+
+/// An appender accepted by the builder, either any [`Write`] or an [`Appender`]
+/// created by [`Sink::new`]
+pub struct Sink(pub(crate) Box<dyn Appender>);
+
+impl Sink {
+    /// Use the hooks of `appender`
+    pub fn new(appender: impl Appender + 'static) -> Sink {
+        Sink(Box::new(appender))
+    }
+}
+
+/// A writer with default hooks
+struct Plain<W>(W);
+
+impl<W: Write> Write for Plain<W> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.0.write_all(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: Write + Send + 'static> Appender for Plain<W> {
+    /// Standard output and error keep colors like a
+    /// [`ConsoleAppender`](console::ConsoleAppender) with [`ColorChoice::Auto`],
+    /// without support of consoles of older Windows
+    fn colors(&self) -> bool {
+        let writer = TypeId::of::<W>();
+        if writer == TypeId::of::<Stdout>() {
+            ColorChoice::Auto.colors(stdout().is_terminal()) && console::enable_ansi(true)
+        } else if writer == TypeId::of::<Stderr>() {
+            ColorChoice::Auto.colors(stderr().is_terminal()) && console::enable_ansi(false)
+        } else {
+            false
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> From<W> for Sink {
+    fn from(writer: W) -> Sink {
+        Sink(Box::new(Plain(writer)))
+    }
+}
+
 /// Chain multiple appenders
 ///
 /// This can help when you want to log the same content to multiple destinations
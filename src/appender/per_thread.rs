@@ -0,0 +1,280 @@
+//! Per-thread log files
+//!
+//! For extreme producers, even the channel to log thread can become a bottleneck.
+//! With [`Builder::per_thread`](crate::Builder::per_thread), each thread formats its
+//! records at log call and writes them to a file of its own, bypassing the channel
+//! and log thread entirely. Files are named after the configured path with a thread
+//! suffix, like `app-t0.log`, `app-t1.log`, numbered in the order threads first log.
+//!
+//! ```no_run
+//! let _guard = ftlog::builder()
+//!     .per_thread("./app.log")
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! Appenders, routes and filters to appenders of the builder are not used for
+//! records, neither is `limit` of log calls, which is applied by log thread. Other
+//! call site options, like levels, sampling and scrubbing, still apply. Files are
+//! buffered, and flushed by [`Log::flush`](log::Log::flush) and when the logger is
+//! dropped or shut down.
+//!
+//! [`merge`] interleaves the files by timestamp afterwards:
+//!
+//! ```no_run
+//! let mut out = std::fs::File::create("./app.log").unwrap();
+//! ftlog::appender::per_thread::merge("./app.log", &mut out).unwrap();
+//! ```
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::clock::{Clock, Stamp};
+use crate::format::parse_time;
+use crate::worker::{LogMsg, Renderer};
+
+type SharedFile = Arc<Mutex<BufWriter<File>>>;
+type ThreadFile = (usize, Weak<Mutex<BufWriter<File>>>);
+
+/// Distinguishes files of loggers in [`FILES`]
+static NEXT_LOGGER: AtomicUsize = AtomicUsize::new(0);
+/// Numbers threads in file names
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+    /// files of current thread, by logger
+    static FILES: RefCell<Vec<ThreadFile>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Per-thread files of a logger
+pub(crate) struct PerThread {
+    id: usize,
+    path: PathBuf,
+    renderer: Arc<Renderer>,
+    clock: Option<Arc<dyn Clock>>,
+    /// files of all threads, kept to flush them from any thread
+    files: Mutex<Vec<SharedFile>>,
+}
+
+/// Path of the file of `thread`, like `app-t0.log` for `app.log`
+fn thread_path(path: &Path, thread: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|x| x.to_string_lossy())
+        .unwrap_or("log".into());
+    let name = match path.extension() {
+        Some(ext) => format!("{}-t{}.{}", stem, thread, ext.to_string_lossy()),
+        None => format!("{}-t{}", stem, thread),
+    };
+    path.with_file_name(name)
+}
+
+impl PerThread {
+    pub(crate) fn new(
+        path: PathBuf,
+        renderer: Arc<Renderer>,
+        clock: Option<Arc<dyn Clock>>,
+    ) -> PerThread {
+        PerThread {
+            id: NEXT_LOGGER.fetch_add(1, Ordering::Relaxed),
+            path,
+            renderer,
+            clock,
+            files: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Open the file of current thread
+    fn open(&self) -> std::io::Result<SharedFile> {
+        let path = thread_path(&self.path, THREAD.with(|x| *x));
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let file = Arc::new(Mutex::new(BufWriter::new(file)));
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.push(file.clone());
+        Ok(file)
+    }
+
+    /// Render `msg` and write it to the file of current thread, returning the line
+    pub(crate) fn write(&self, msg: LogMsg) -> Option<Vec<u8>> {
+        let now = msg
+            .time
+            .unwrap_or_else(|| Stamp::now(self.clock.as_deref()));
+        let mut line = self.renderer.render_now(msg, now)?;
+        // files do not keep colors
+        if !self.renderer.binary() {
+            if let Cow::Owned(plain) = crate::sanitize::strip_ansi(&line) {
+                line = plain;
+            }
+        }
+        let result = FILES.with(|files| {
+            let mut files = files.borrow_mut();
+            let file = match files.iter().find(|(id, _)| *id == self.id) {
+                Some((_, file)) => file.upgrade(),
+                None => None,
+            };
+            let file = match file {
+                Some(file) => file,
+                None => {
+                    let file = self.open()?;
+                    // forget files of dropped loggers
+                    files.retain(|(_, x)| x.strong_count() > 0);
+                    files.push((self.id, Arc::downgrade(&file)));
+                    file
+                }
+            };
+            let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+            file.write_all(&line)
+        });
+        if let Err(source) = result {
+            crate::internal_error(crate::InternalError::Write {
+                appender: "per-thread".to_string(),
+                source,
+            });
+        }
+        Some(line)
+    }
+
+    /// Flush files of all threads
+    pub(crate) fn flush(&self) -> std::io::Result<()> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        for file in files.iter() {
+            file.lock().unwrap_or_else(|e| e.into_inner()).flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-thread files of `path`, like `app-t0.log` and `app-t1.log` for `app.log`,
+/// ordered by thread number
+pub fn files(path: impl AsRef<Path>) -> std::io::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let stem = path.file_stem().map(|x| x.to_string_lossy());
+    let ext = path.extension().map(|x| x.to_string_lossy());
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let name = match &ext {
+            Some(ext) => name.strip_suffix(&format!(".{}", ext)),
+            None => Some(name),
+        };
+        let thread = name
+            .and_then(|x| x.rsplit_once("-t"))
+            .filter(|(x, n)| Some(*x) == stem.as_deref() && n.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|(_, n)| n.parse::<usize>().ok());
+        if let Some(thread) = thread {
+            files.push((thread, entry.path()));
+        }
+    }
+    files.sort();
+    Ok(files.into_iter().map(|(_, x)| x).collect())
+}
+
+/// Lines of a record and the file it is read from
+struct Pending {
+    time: Option<OffsetDateTime>,
+    ix: usize,
+    text: Vec<u8>,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.ix) == (other.time, other.ix)
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.time, self.ix).cmp(&(other.time, other.ix))
+    }
+}
+
+/// Reader of records of a file, a record being a line with a timestamp and the
+/// following lines without one. Timestamps without offset are compared as UTC.
+struct Records {
+    reader: BufReader<File>,
+    /// line read ahead, the start of the next record
+    line: Vec<u8>,
+}
+
+impl Records {
+    /// Read the next record, starting with the line read ahead if any
+    fn next(&mut self, ix: usize) -> std::io::Result<Option<Pending>> {
+        if self.line.is_empty() && self.reader.read_until(b'\n', &mut self.line)? == 0 {
+            return Ok(None);
+        }
+        let time = parse_time(&String::from_utf8_lossy(&self.line), UtcOffset::UTC);
+        let mut text = std::mem::take(&mut self.line);
+        loop {
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                break;
+            }
+            if parse_time(&String::from_utf8_lossy(&self.line), UtcOffset::UTC).is_some() {
+                break;
+            }
+            text.append(&mut self.line);
+        }
+        if !text.ends_with(b"\n") {
+            text.push(b'\n');
+        }
+        Ok(Some(Pending { time, ix, text }))
+    }
+}
+
+/// Interleave per-thread files of `path` by timestamp into `out`, see [`files`]
+///
+/// Lines without a timestamp, e.g. of a multi-line message, stay with the record
+/// before them. Records of the same time keep the order of thread numbers.
+pub fn merge<W: Write>(path: impl AsRef<Path>, out: W) -> std::io::Result<()> {
+    merge_files(&files(path)?, out)
+}
+
+/// Interleave records of `files` by timestamp into `out`, see [`merge`]
+pub fn merge_files<W: Write>(files: &[PathBuf], mut out: W) -> std::io::Result<()> {
+    let mut readers = files
+        .iter()
+        .map(|path| {
+            Ok(Records {
+                reader: BufReader::new(File::open(path)?),
+                line: Vec::new(),
+            })
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let mut heap = BinaryHeap::new();
+    for (ix, reader) in readers.iter_mut().enumerate() {
+        if let Some(record) = reader.next(ix)? {
+            heap.push(Reverse(record));
+        }
+    }
+    while let Some(Reverse(record)) = heap.pop() {
+        out.write_all(&record.text)?;
+        if let Some(next) = readers[record.ix].next(record.ix)? {
+            heap.push(Reverse(next));
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,245 @@
+//! Disk buffer for network appenders
+//!
+//! [`Spill`] wraps an appender, typically a [`TcpAppender`](super::tcp::TcpAppender),
+//! and keeps the lines it fails to write in segment files of a directory, bounded in
+//! total size. Lines written while there is a backlog are appended to it to keep
+//! their order. The backlog is replayed oldest first once writing succeeds again,
+//! attempted at most once a second, when lines are written or appenders flushed.
+//!
+//! ```no_run
+//! use ftlog::appender::spill::Spill;
+//! use ftlog::appender::tcp::TcpAppender;
+//! use ftlog::appender::Sink;
+//!
+//! // keep up to 256 MiB while the collector is unreachable
+//! let appender = Spill::new(
+//!     TcpAppender::new("127.0.0.1:5140"),
+//!     "/var/spool/app-logs",
+//!     256 << 20,
+//! )
+//! .unwrap();
+//! let _guard = ftlog::builder()
+//!     .root(Sink::new(appender))
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! When the backlog exceeds its bound, its oldest segment is deleted. Segments left
+//! by a previous run in the directory are replayed too, so a line may be written
+//! twice if the process exits while replaying.
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::Appender;
+
+/// Size from which lines are spilled to a new segment
+const SEGMENT_SIZE: u64 = 1 << 20;
+/// Interval of attempts to replay the backlog
+const RETRY: Duration = Duration::from_secs(1);
+
+/// Segment files of the backlog, oldest first
+struct Segments {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// id and size of segments
+    files: VecDeque<(u64, u64)>,
+    /// the newest segment, while it is appended to
+    writer: Option<BufWriter<File>>,
+    /// bytes of the oldest segment already replayed
+    replayed: usize,
+    next_id: u64,
+}
+
+impl Segments {
+    fn open(dir: PathBuf, max_bytes: u64) -> std::io::Result<Segments> {
+        std::fs::create_dir_all(&dir)?;
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let id = name
+                .to_str()
+                .and_then(|x| x.strip_prefix("spill-"))
+                .and_then(|x| x.strip_suffix(".seg"))
+                .and_then(|x| x.parse::<u64>().ok());
+            if let Some(id) = id {
+                files.push((id, entry.metadata()?.len()));
+            }
+        }
+        files.sort();
+        Ok(Segments {
+            next_id: files.last().map_or(0, |(id, _)| id + 1),
+            files: files.into(),
+            dir,
+            max_bytes,
+            writer: None,
+            replayed: 0,
+        })
+    }
+
+    fn path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("spill-{:020}.seg", id))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    fn bytes(&self) -> u64 {
+        self.files.iter().map(|(_, size)| size).sum()
+    }
+
+    /// Append a line, as its length followed by its bytes
+    fn push(&mut self, line: &[u8]) -> std::io::Result<()> {
+        let full = self
+            .files
+            .back()
+            .is_none_or(|(_, size)| *size >= SEGMENT_SIZE);
+        if self.writer.is_none() || full {
+            let id = self.next_id;
+            self.next_id += 1;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(id))?;
+            self.writer = Some(BufWriter::new(file));
+            self.files.push_back((id, 0));
+        }
+        if let (Some(writer), Some((_, size))) = (&mut self.writer, self.files.back_mut()) {
+            writer.write_all(&(line.len() as u32).to_le_bytes())?;
+            writer.write_all(line)?;
+            *size += 4 + line.len() as u64;
+        }
+        while self.bytes() > self.max_bytes && self.files.len() > 1 {
+            if let Some((id, bytes)) = self.files.pop_front() {
+                self.replayed = 0;
+                std::fs::remove_file(self.path(id))?;
+                crate::internal_error(crate::InternalError::SpillDropped {
+                    dir: self.dir.clone(),
+                    bytes,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the backlog to `appender`, oldest first, stopping at the first failure
+    fn replay(&mut self, appender: &mut dyn Appender) -> std::io::Result<()> {
+        while let Some((id, _)) = self.files.front().copied() {
+            if self.files.len() == 1 {
+                // new lines go to a new segment from now
+                if let Some(mut writer) = self.writer.take() {
+                    writer.flush()?;
+                }
+            }
+            let path = self.path(id);
+            let data = std::fs::read(&path)?;
+            while let Some(len) = data.get(self.replayed..self.replayed + 4) {
+                let start = self.replayed + 4;
+                let len = u32::from_le_bytes(len.try_into().unwrap_or_default()) as usize;
+                let Some(line) = data.get(start..start + len) else {
+                    break;
+                };
+                appender.write_all(line)?;
+                self.replayed = start + len;
+            }
+            std::fs::remove_file(&path)?;
+            self.files.pop_front();
+            self.replayed = 0;
+        }
+        appender.flush()
+    }
+}
+
+/// Appender keeping lines its inner appender fails to write on disk, see
+/// [module](self) docs
+pub struct Spill {
+    inner: Box<dyn Appender>,
+    segments: Segments,
+    last_retry: Option<Instant>,
+}
+
+impl Spill {
+    /// Keep lines `inner` fails to write in `dir`, up to `max_bytes`
+    ///
+    /// Fails if `dir` cannot be created or read.
+    pub fn new(
+        inner: impl Appender + 'static,
+        dir: impl AsRef<Path>,
+        max_bytes: u64,
+    ) -> std::io::Result<Spill> {
+        Ok(Spill {
+            inner: Box::new(inner),
+            segments: Segments::open(dir.as_ref().to_path_buf(), max_bytes)?,
+            last_retry: None,
+        })
+    }
+
+    /// Bytes of lines waiting to be replayed
+    pub fn backlog(&self) -> u64 {
+        self.segments.bytes()
+    }
+
+    /// Replay the backlog if an attempt is due
+    fn retry(&mut self) -> std::io::Result<()> {
+        if self.segments.is_empty() || self.last_retry.is_some_and(|x| x.elapsed() < RETRY) {
+            return Ok(());
+        }
+        self.last_retry = Some(Instant::now());
+        self.segments.flush()?;
+        self.segments.replay(self.inner.as_mut())
+    }
+}
+
+impl Write for Spill {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _ = self.retry();
+        if self.segments.is_empty() && self.inner.write_all(buf).is_ok() {
+            return Ok(buf.len());
+        }
+        self.segments.push(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.segments.flush()?;
+        let _ = self.retry();
+        if self.segments.is_empty() {
+            self.inner.flush()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Appender for Spill {
+    fn flush_with_deadline(&mut self, timeout: Duration) -> std::io::Result<()> {
+        self.segments.flush()?;
+        if !self.segments.is_empty() {
+            self.last_retry = None;
+            let _ = self.retry();
+        }
+        self.inner.flush_with_deadline(timeout)
+    }
+
+    fn on_shutdown(&mut self) -> std::io::Result<()> {
+        self.segments.flush()?;
+        self.inner.on_shutdown()
+    }
+
+    fn healthcheck(&mut self) -> std::io::Result<()> {
+        // lines are kept on disk while the inner appender is failing
+        let _ = self.inner.healthcheck();
+        Ok(())
+    }
+}
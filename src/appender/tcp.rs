@@ -0,0 +1,287 @@
+//! Ship lines to a collector over TCP
+//!
+//! [`TcpAppender`] connects on first write, and reconnects on the next write after
+//! the connection fails. With the `tls` feature, [`TcpAppender::tls`] encrypts the
+//! connection with rustls, for collectors reached over the public internet.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use ftlog::appender::tcp::TcpAppender;
+//! use ftlog::appender::Sink;
+//!
+//! let _guard = ftlog::builder()
+//!     .root(Sink::new(
+//!         TcpAppender::new("127.0.0.1:5140").timeout(Duration::from_secs(1)),
+//!     ))
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! A write failing with the connection is lost, and log thread quarantines the
+//! appender after repeated failures, reconnecting once a second until it succeeds.
+//! Wrap it in [`Timeout`](super::timeout::Timeout) to keep a stalled collector
+//! from blocking log thread, and in [`Spill`](super::spill::Spill) to keep lines on
+//! disk while the collector is unreachable.
+use std::io::{Error, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(feature = "tls")]
+use std::path::Path;
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "tls")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+use super::Appender;
+
+enum Connection {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Connection {
+    fn stream(&mut self) -> &mut dyn Write {
+        match self {
+            Connection::Plain(stream) => stream,
+            #[cfg(feature = "tls")]
+            Connection::Tls(stream) => stream,
+        }
+    }
+}
+
+/// Appender writing lines to a TCP connection, see [module](self) docs
+pub struct TcpAppender {
+    addr: String,
+    timeout: Option<Duration>,
+    #[cfg(feature = "tls")]
+    tls: Option<(Arc<ClientConfig>, ServerName<'static>)>,
+    connection: Option<Connection>,
+}
+
+impl TcpAppender {
+    /// Connect to `addr`, like `collector.example.com:5140`
+    pub fn new(addr: impl Into<String>) -> TcpAppender {
+        TcpAppender {
+            addr: addr.into(),
+            timeout: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+            connection: None,
+        }
+    }
+
+    /// Give up connecting and writing after `timeout`, instead of waiting for the
+    /// system to
+    pub fn timeout(mut self, timeout: Duration) -> TcpAppender {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Encrypt the connection with `tls`
+    ///
+    /// The certificate of the collector is verified against the host of the
+    /// address, unless [`Tls::server_name`] is set. Fails if the client certificate
+    /// does not match its key.
+    ///
+    /// ```no_run
+    /// use ftlog::appender::tcp::{TcpAppender, Tls};
+    ///
+    /// let appender = TcpAppender::new("logs.example.com:6514")
+    ///     .tls(
+    ///         Tls::new()
+    ///             .client_cert("./client.pem", "./client.key")
+    ///             .unwrap(),
+    ///     )
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls: Tls) -> std::io::Result<TcpAppender> {
+        let host = match tls.server_name {
+            Some(name) => name,
+            None => match self.addr.rsplit_once(':') {
+                Some((host, _)) => host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_string(),
+                None => self.addr.clone(),
+            },
+        };
+        let server_name = ServerName::try_from(host).map_err(invalid)?;
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(invalid)?
+            .with_root_certificates(tls.roots);
+        let config = match tls.client {
+            Some((certs, key)) => config.with_client_auth_cert(certs, key).map_err(invalid)?,
+            None => config.with_no_client_auth(),
+        };
+        self.tls = Some((Arc::new(config), server_name));
+        Ok(self)
+    }
+
+    fn connect(&self) -> std::io::Result<Connection> {
+        let stream = match self.timeout {
+            Some(timeout) => {
+                let mut last = Error::new(ErrorKind::NotFound, "no address resolved");
+                let mut connected = None;
+                for addr in self.addr.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, timeout) {
+                        Ok(stream) => {
+                            connected = Some(stream);
+                            break;
+                        }
+                        Err(e) => last = e,
+                    }
+                }
+                connected.ok_or(last)?
+            }
+            None => TcpStream::connect(&self.addr)?,
+        };
+        stream.set_write_timeout(self.timeout)?;
+        stream.set_nodelay(true)?;
+        #[cfg(feature = "tls")]
+        if let Some((config, server_name)) = &self.tls {
+            let tls =
+                ClientConnection::new(config.clone(), server_name.clone()).map_err(invalid)?;
+            let mut stream = StreamOwned::new(tls, stream);
+            // handshake now, so that failures show up as connection errors
+            while stream.conn.is_handshaking() {
+                stream.conn.complete_io(&mut stream.sock)?;
+            }
+            return Ok(Connection::Tls(Box::new(stream)));
+        }
+        Ok(Connection::Plain(stream))
+    }
+
+    /// Run `f` on the connection, connecting first if needed, and drop the
+    /// connection if it fails
+    fn with_connection<T, F>(&mut self, f: F) -> std::io::Result<T>
+    where
+        F: FnOnce(&mut dyn Write) -> std::io::Result<T>,
+    {
+        let connection = match &mut self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(self.connect()?),
+        };
+        let result = f(connection.stream());
+        if result.is_err() {
+            self.connection = None;
+        }
+        result
+    }
+}
+
+#[cfg(feature = "tls")]
+fn invalid(e: impl std::fmt::Display) -> Error {
+    Error::new(ErrorKind::InvalidInput, e.to_string())
+}
+
+impl Write for TcpAppender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.with_connection(|stream| stream.write_all(buf).map(|_| buf.len()))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.connection {
+            Some(_) => self.with_connection(|stream| stream.flush()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Appender for TcpAppender {
+    fn on_shutdown(&mut self) -> std::io::Result<()> {
+        match self.connection.take() {
+            Some(Connection::Plain(stream)) => stream.shutdown(std::net::Shutdown::Write),
+            #[cfg(feature = "tls")]
+            Some(Connection::Tls(mut stream)) => {
+                stream.conn.send_close_notify();
+                stream.flush()
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn healthcheck(&mut self) -> std::io::Result<()> {
+        match &self.connection {
+            Some(_) => Ok(()),
+            None => self.connect().map(|x| self.connection = Some(x)),
+        }
+    }
+
+    fn reopen(&mut self) -> std::io::Result<()> {
+        self.connection = Some(self.connect()?);
+        Ok(())
+    }
+}
+
+/// TLS settings of a [`TcpAppender`]
+///
+/// Collectors are trusted if their certificate is issued by a root of the Mozilla
+/// CA program, or by a CA added with [`Tls::ca_file`].
+#[cfg(feature = "tls")]
+pub struct Tls {
+    roots: RootCertStore,
+    client: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+    server_name: Option<String>,
+}
+
+#[cfg(feature = "tls")]
+impl Default for Tls {
+    fn default() -> Self {
+        Tls::new()
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Tls {
+    /// Trust roots of the Mozilla CA program, without client certificate
+    pub fn new() -> Tls {
+        Tls {
+            roots: RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            },
+            client: None,
+            server_name: None,
+        }
+    }
+
+    /// Also trust CAs in the PEM file at `path`, e.g. of a private collector
+    pub fn ca_file(mut self, path: impl AsRef<Path>) -> std::io::Result<Tls> {
+        for cert in CertificateDer::pem_file_iter(path).map_err(invalid)? {
+            self.roots.add(cert.map_err(invalid)?).map_err(invalid)?;
+        }
+        Ok(self)
+    }
+
+    /// Authenticate with the certificate chain and private key in PEM files, for
+    /// collectors requiring client certificates
+    pub fn client_cert(
+        mut self,
+        cert: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+    ) -> std::io::Result<Tls> {
+        let certs = CertificateDer::pem_file_iter(cert)
+            .map_err(invalid)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+        let key = PrivateKeyDer::from_pem_file(key).map_err(invalid)?;
+        self.client = Some((certs, key));
+        Ok(self)
+    }
+
+    /// Verify the certificate of the collector against `name` instead of the host
+    /// of the address
+    pub fn server_name(mut self, name: impl Into<String>) -> Tls {
+        self.server_name = Some(name.into());
+        self
+    }
+}
@@ -0,0 +1,197 @@
+//! Write timeouts for slow sinks
+//!
+//! A network sink, e.g. a `TcpStream` to a collector, may stall for a long time
+//! without failing, blocking log thread and, once the queue is full, log calls.
+//! [`Timeout`] writes to the sink in a thread of its own, and gives up on a line
+//! when the sink does not keep up within the timeout. The sink is then marked
+//! degraded: later lines are given up at once, without waiting, until the sink
+//! catches up. Lines given up are written to a fallback appender if any, otherwise
+//! the write fails with [`ErrorKind::TimedOut`], and log thread quarantines the sink
+//! after repeated failures, see [`Appender::reopen`].
+//!
+//! ```no_run
+//! use std::net::TcpStream;
+//! use std::time::Duration;
+//!
+//! use ftlog::appender::timeout::Timeout;
+//! use ftlog::appender::{FileAppender, Sink};
+//!
+//! let stream = TcpStream::connect("127.0.0.1:5140").unwrap();
+//! let _guard = ftlog::builder()
+//!     .root(Sink::new(
+//!         Timeout::new(stream, Duration::from_millis(100))
+//!             .fallback(FileAppender::new("./fallback.log")),
+//!     ))
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! Write errors of the sink are returned by the next write or flush. Wrapped in
+//! [`Sink::new`](super::Sink::new), a degraded sink without fallback also fails its
+//! healthcheck.
+use std::io::{Error, ErrorKind, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{bounded, Sender};
+
+use super::Appender;
+
+/// Lines buffered for the sink before writes wait for it
+const CAPACITY: usize = 1024;
+
+enum Op {
+    Write(Vec<u8>),
+    Flush(Sender<std::io::Result<()>>),
+}
+
+/// State shared with the thread writing to the sink
+#[derive(Default)]
+struct State {
+    /// the sink did not keep up within the timeout
+    degraded: AtomicBool,
+    /// first write error of the sink since last reported
+    error: Mutex<Option<Error>>,
+}
+
+/// Appender giving up on lines a sink does not accept within a timeout, see
+/// [module](self) docs
+pub struct Timeout {
+    sender: Sender<Op>,
+    timeout: Duration,
+    fallback: Option<Box<dyn Write + Send>>,
+    state: Arc<State>,
+}
+
+impl Timeout {
+    /// Write to `sink`, waiting at most `timeout` for it to accept a line
+    pub fn new(sink: impl Write + Send + 'static, timeout: Duration) -> Timeout {
+        let (sender, receiver) = bounded::<Op>(CAPACITY);
+        let state = Arc::new(State::default());
+        let shared = state.clone();
+        let mut sink = sink;
+        std::thread::Builder::new()
+            .name("logger-sink".to_string())
+            .spawn(move || {
+                for op in receiver.iter() {
+                    match op {
+                        Op::Write(line) => {
+                            if let Err(e) = sink.write_all(&line) {
+                                let mut error =
+                                    shared.error.lock().unwrap_or_else(|e| e.into_inner());
+                                error.get_or_insert(e);
+                            }
+                        }
+                        Op::Flush(reply) => {
+                            let _ = reply.send(sink.flush());
+                        }
+                    }
+                    if receiver.is_empty() {
+                        shared.degraded.store(false, Ordering::Relaxed);
+                    }
+                }
+            })
+            .expect("failed to spawn sink thread");
+        Timeout {
+            sender,
+            timeout,
+            fallback: None,
+            state,
+        }
+    }
+
+    /// Write lines the sink does not accept in time to `fallback` instead of
+    /// discarding them
+    pub fn fallback(mut self, fallback: impl Write + Send + 'static) -> Timeout {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Whether the sink fell behind and lines are given up without waiting
+    pub fn degraded(&self) -> bool {
+        self.state.degraded.load(Ordering::Relaxed)
+    }
+
+    fn take_error(&self) -> std::io::Result<()> {
+        match self
+            .state
+            .error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Queue a line for the sink, waiting at most the timeout unless degraded,
+    /// returning whether it is queued
+    fn send(&self, line: Vec<u8>) -> bool {
+        let op = Op::Write(line);
+        if self.degraded() {
+            return self.sender.try_send(op).is_ok();
+        }
+        let queued = self.sender.send_timeout(op, self.timeout).is_ok();
+        if !queued {
+            self.state.degraded.store(true, Ordering::Relaxed);
+        }
+        queued
+    }
+
+    fn flush_within(&mut self, timeout: Duration) -> std::io::Result<()> {
+        let start = Instant::now();
+        let (reply, done) = bounded(1);
+        // wait for the sink even if degraded, as it may be catching up
+        if self.sender.send_timeout(Op::Flush(reply), timeout).is_err() {
+            self.state.degraded.store(true, Ordering::Relaxed);
+            return Err(timed_out());
+        }
+        let flushed = match done.recv_timeout(timeout.saturating_sub(start.elapsed())) {
+            Ok(flushed) => flushed,
+            Err(_) => {
+                self.state.degraded.store(true, Ordering::Relaxed);
+                Err(timed_out())
+            }
+        };
+        if let Some(fallback) = &mut self.fallback {
+            fallback.flush()?;
+        }
+        self.take_error().and(flushed)
+    }
+}
+
+fn timed_out() -> Error {
+    Error::new(ErrorKind::TimedOut, "sink stalled")
+}
+
+impl Write for Timeout {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.take_error()?;
+        if self.send(buf.to_vec()) {
+            return Ok(buf.len());
+        }
+        match &mut self.fallback {
+            Some(fallback) => fallback.write_all(buf).map(|_| buf.len()),
+            None => Err(timed_out()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flush_within(self.timeout)
+    }
+}
+
+impl Appender for Timeout {
+    fn flush_with_deadline(&mut self, timeout: Duration) -> std::io::Result<()> {
+        self.flush_within(timeout.min(self.timeout))
+    }
+
+    fn healthcheck(&mut self) -> std::io::Result<()> {
+        if self.degraded() && self.fallback.is_none() {
+            return Err(timed_out());
+        }
+        self.take_error()
+    }
+}
@@ -0,0 +1,136 @@
+//! Records batched per thread before they are sent to log thread
+//!
+//! With [`Builder::low_contention`](crate::Builder::low_contention), each thread
+//! accumulates records in a batch of its own, sent to log thread as one message once
+//! it is full, lingers for [`LINGER`], or holds an `Error` record. A background
+//! thread sends batches of threads that stopped logging, and flushing the logger
+//! sends all of them first.
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use crate::worker::{LogMsg, LoggerInput};
+use crate::Shared;
+
+/// Records of a batch sent as soon as it is full
+const SIZE: usize = 32;
+/// Longest time a record waits in a batch
+pub(crate) const LINGER: Duration = Duration::from_micros(300);
+
+type SharedSlot = Arc<Mutex<Slot>>;
+
+thread_local! {
+    // batches of the calling thread, by id of logger
+    static SLOTS: RefCell<Vec<(usize, Weak<Mutex<Slot>>)>> = const { RefCell::new(Vec::new()) };
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+struct Slot {
+    msgs: Vec<LogMsg>,
+    /// when the first record of the batch was added
+    since: Instant,
+}
+
+impl Slot {
+    /// Send the batch while holding the slot, so batches of a thread keep their order
+    fn send<F: FnOnce(Vec<LogMsg>)>(&mut self, send: F) {
+        if !self.msgs.is_empty() {
+            send(std::mem::replace(&mut self.msgs, Vec::with_capacity(SIZE)));
+        }
+    }
+}
+
+/// Batches of all threads logging to a logger
+pub(crate) struct Batches {
+    // unique among loggers, so a new logger never gets records of a dropped one
+    id: usize,
+    slots: Mutex<Vec<SharedSlot>>,
+}
+
+impl Batches {
+    pub(crate) fn new() -> Batches {
+        Batches {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            slots: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn slot(&self) -> SharedSlot {
+        SLOTS.with(|slots| {
+            let mut slots = slots.borrow_mut();
+            if let Some(slot) = slots
+                .iter()
+                .find(|(id, _)| *id == self.id)
+                .and_then(|(_, x)| x.upgrade())
+            {
+                return slot;
+            }
+            let slot = Arc::new(Mutex::new(Slot {
+                msgs: Vec::with_capacity(SIZE),
+                since: Instant::now(),
+            }));
+            self.slots
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(slot.clone());
+            // forget batches of dropped loggers
+            slots.retain(|(_, x)| x.strong_count() > 0);
+            slots.push((self.id, Arc::downgrade(&slot)));
+            slot
+        })
+    }
+
+    /// Add `msg` to the batch of the calling thread, passing the batch to `send` once
+    /// it is due, or at once if `urgent`
+    pub(crate) fn push<F: FnOnce(Vec<LogMsg>)>(&self, msg: LogMsg, urgent: bool, send: F) {
+        let slot = self.slot();
+        let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.msgs.is_empty() {
+            slot.since = Instant::now();
+        }
+        slot.msgs.push(msg);
+        if urgent || slot.msgs.len() >= SIZE || slot.since.elapsed() >= LINGER {
+            slot.send(send);
+        }
+    }
+
+    /// Pass batches of all threads to `send`, or only those lingering for too long if
+    /// `stale`
+    pub(crate) fn flush<F: FnMut(Vec<LogMsg>)>(&self, stale: bool, mut send: F) {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        for slot in slots.iter() {
+            let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+            if !stale || slot.since.elapsed() >= LINGER {
+                slot.send(&mut send);
+            }
+        }
+        // forget batches of exited threads once they are sent
+        slots.retain(|x| {
+            Arc::strong_count(x) > 1 || !x.lock().unwrap_or_else(|e| e.into_inner()).msgs.is_empty()
+        });
+    }
+}
+
+/// Send lingering batches of `shared` in a background thread
+pub(crate) fn spawn(shared: &Arc<Shared>) -> std::io::Result<()> {
+    let shared: Weak<Shared> = Arc::downgrade(shared);
+    std::thread::Builder::new()
+        .name("logger-batch".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(LINGER);
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            if shared.closed.load(Ordering::Relaxed) {
+                return;
+            }
+            if let Some(batches) = &shared.batches {
+                batches.flush(true, |batch| {
+                    let _ = shared.queue.send(LoggerInput::Batch(batch));
+                });
+            }
+        })?;
+    Ok(())
+}
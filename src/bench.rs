@@ -0,0 +1,105 @@
+//! Self-benchmark of a logger configuration
+//!
+//! [`run`] builds a logger from a [`Builder`] with the root appender replaced by
+//! `std::io::sink()`, drives it with several producer threads, and reports latency
+//! of log calls and throughput of log thread(s). This helps to compare
+//! configurations, e.g. channel capacity, overflow policy or number of workers, on
+//! the target hardware.
+//!
+//! ```
+//! use ftlog::bench::{self, BenchConfig};
+//!
+//! let config = BenchConfig {
+//!     threads: 2,
+//!     records_per_thread: 10_000,
+//!     ..Default::default()
+//! };
+//! let report = bench::run(ftlog::builder().workers(2), &config).unwrap();
+//! println!(
+//!     "{:.0} records/s, p99 log call latency: {:?}",
+//!     report.throughput, report.call_latency.p99
+//! );
+//! ```
+use std::io::Error as IoError;
+use std::time::{Duration, Instant};
+
+use log::Level;
+
+use crate::stats::{Histogram, LatencyPercentiles};
+use crate::{log_to, Builder, Log};
+
+/// Workload of [`run`]
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// number of producer threads, defaults to 4
+    pub threads: usize,
+    /// number of log calls of each producer thread, defaults to 100_000
+    pub records_per_thread: usize,
+    /// level of log calls, defaults to `Info`
+    pub level: Level,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            threads: 4,
+            records_per_thread: 100_000,
+            level: Level::Info,
+        }
+    }
+}
+
+/// Result of [`run`]
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    /// number of log calls made
+    pub records: u64,
+    /// number of records discarded because the channel was full
+    pub dropped: u64,
+    /// time from the first log call until all records are written
+    pub elapsed: Duration,
+    /// records handled by log thread(s) per second
+    pub throughput: f64,
+    /// latency of log calls in producer threads
+    pub call_latency: LatencyPercentiles,
+}
+
+/// Run the benchmark with a logger built from `builder`
+///
+/// Only the root appender is replaced, records routed or redirected to other
+/// appenders are still written to them.
+pub fn run(builder: Builder, config: &BenchConfig) -> Result<BenchReport, IoError> {
+    let logger = builder.root(std::io::sink()).build()?;
+    let latency = Histogram::new();
+
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        for thread in 0..config.threads {
+            let (logger, latency) = (&logger, &latency);
+            scope.spawn(move || {
+                for i in 0..config.records_per_thread {
+                    let call = Instant::now();
+                    log_to!(
+                        logger,
+                        config.level,
+                        "bench record {} of thread {}",
+                        i,
+                        thread
+                    );
+                    latency.record(call.elapsed());
+                }
+            });
+        }
+    });
+    logger.flush();
+    let elapsed = start.elapsed();
+
+    let records = (config.threads * config.records_per_thread) as u64;
+    Ok(BenchReport {
+        records,
+        dropped: logger.stats().dropped,
+        elapsed,
+        throughput: records as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE),
+        call_latency: latency.snapshot(),
+    })
+}
@@ -0,0 +1,36 @@
+//! Convert logs written by `ftlog::binary::BinaryFormat` to text
+//!
+//! Usage: `ftlog-decode [FILE]...`, reading stdin without files. Rotated files
+//! should be given in order, so targets defined in earlier files are resolved.
+use std::fs::File;
+use std::io::{stdin, stdout, BufReader, BufWriter, Read};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.iter().any(|x| x == "-h" || x == "--help") {
+        println!("Usage: ftlog-decode [FILE]...\nConvert binary ftlog logs to text, reading stdin without files");
+        return ExitCode::SUCCESS;
+    }
+    let mut input: Box<dyn Read> = Box::new(std::io::empty());
+    if paths.is_empty() {
+        input = Box::new(stdin().lock());
+    }
+    for path in &paths {
+        match File::open(path) {
+            Ok(file) => input = Box::new(input.chain(file)),
+            Err(e) => {
+                eprintln!("ftlog-decode: {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let output = BufWriter::new(stdout().lock());
+    match ftlog::binary::decode(BufReader::new(input), output) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ftlog-decode: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
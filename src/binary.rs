@@ -0,0 +1,318 @@
+//! Compact binary log format
+//!
+//! [`BinaryFormat`] is a [`RecordFormatter`] that writes length-prefixed binary
+//! frames instead of text lines, which saves disk space and the cost of formatting
+//! timestamps when capturing huge volume of logs. Targets are interned, a target
+//! is written in full only once and referred by a numeric id afterwards.
+//! [`Decoder`] and the `ftlog-decode` binary convert the frames back to text.
+//!
+//! ```
+//! use ftlog::appender::FileAppender;
+//! use ftlog::binary::BinaryFormat;
+//!
+//! let _guard = ftlog::builder()
+//!     .formatter(BinaryFormat::new())
+//!     .root(FileAppender::new("./current.bin"))
+//!     .try_init()
+//!     .unwrap();
+//! log::info!(user = "alice"; "logged in");
+//! # drop(_guard);
+//! # std::fs::remove_file("./current.bin").unwrap();
+//! ```
+//!
+//! ```shell
+//! $ ftlog-decode current.bin
+//! 2023-06-14T03:13:26.160123Z INFO main user=alice logged in
+//! ```
+//!
+//! # Layout
+//!
+//! All integers are little-endian. Each frame is a `u32` length followed by a body
+//! of that length, whose first byte is its kind:
+//!
+//! - `0`, target definition: `u64` id, target in UTF-8
+//! - `1`, record: `i64` unix timestamp in nanoseconds, `u8` level (1 for `Error`
+//!   to 5 for `Trace`), `u64` target id, `u32` length and bytes of message, `u32`
+//!   count of key-values, then `u32` length and bytes of each key and value
+//!
+//! The layout is shared with `ftlog-core`, a `no_std` front-end for embedded devices
+//! whose output is decoded the same way.
+//!
+//! Ids are hashes of targets, stable across processes appending to the same file.
+//! A target is defined before it is first referred by each log thread, and again
+//! every minute, so a rotated file can be decoded alone except records in its first
+//! minute, whose targets print as `#<id>`. Decode rotated files in order to resolve
+//! all of them.
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::io::{Error, ErrorKind, Read, Write};
+use std::sync::Mutex;
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+use ftlog_core::frame::{target_id, RECORD, TARGET};
+use log::Level;
+use time::OffsetDateTime;
+
+use crate::format::{LogRecord, RecordFormatter};
+
+/// how often targets are defined again
+const REDEFINE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Targets defined by a log thread
+struct Defined {
+    ids: HashSet<u64>,
+    since: Instant,
+}
+
+/// Formatter writing records as binary frames, see [module doc](self)
+#[derive(Default)]
+pub struct BinaryFormat {
+    // each formatter thread defines targets before its own records, so that a record
+    // never refers to a definition written after it when lines are rendered in parallel
+    defined: Mutex<HashMap<ThreadId, Defined>>,
+}
+
+impl BinaryFormat {
+    pub fn new() -> BinaryFormat {
+        Self::default()
+    }
+
+    /// Whether `id` must be defined before the record
+    fn define(&self, id: u64) -> bool {
+        let mut defined = self.defined.lock().unwrap_or_else(|e| e.into_inner());
+        let defined = defined
+            .entry(std::thread::current().id())
+            .or_insert_with(|| Defined {
+                ids: HashSet::new(),
+                since: Instant::now(),
+            });
+        if defined.since.elapsed() > REDEFINE_INTERVAL {
+            defined.ids.clear();
+            defined.since = Instant::now();
+        }
+        defined.ids.insert(id)
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) -> std::io::Result<()> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "field too long"))?;
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Write a frame whose body is written by `body` after the kind byte
+fn put_frame<F>(buf: &mut Vec<u8>, kind: u8, body: F) -> std::io::Result<()>
+where
+    F: FnOnce(&mut Vec<u8>) -> std::io::Result<()>,
+{
+    let start = buf.len();
+    buf.extend_from_slice(&[0; 4]);
+    buf.push(kind);
+    body(buf)?;
+    let len = u32::try_from(buf.len() - start - 4)
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "record too long"))?;
+    buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+impl RecordFormatter for BinaryFormat {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let target = record.target();
+        let id = target_id(target);
+        if self.define(id) {
+            put_frame(buf, TARGET, |buf| {
+                buf.extend_from_slice(&id.to_le_bytes());
+                buf.extend_from_slice(target.as_bytes());
+                Ok(())
+            })?;
+        }
+        put_frame(buf, RECORD, |buf| {
+            let nanos = record.time().unix_timestamp_nanos() as i64;
+            buf.extend_from_slice(&nanos.to_le_bytes());
+            buf.push(record.level() as u8);
+            buf.extend_from_slice(&id.to_le_bytes());
+            put_bytes(buf, record.args().as_bytes())?;
+            let count_at = buf.len();
+            buf.extend_from_slice(&[0; 4]);
+            let mut count = 0u32;
+            for (key, value) in record.key_values() {
+                put_bytes(buf, key.as_bytes())?;
+                put_bytes(buf, value.as_bytes())?;
+                count += 1;
+            }
+            buf[count_at..count_at + 4].copy_from_slice(&count.to_le_bytes());
+            Ok(())
+        })
+    }
+
+    fn binary(&self) -> bool {
+        true
+    }
+}
+
+/// A record read by [`Decoder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedRecord {
+    /// time of the log call in UTC
+    pub time: OffsetDateTime,
+    pub level: Level,
+    /// `#<id>` if the target is not defined before the record
+    pub target: String,
+    pub args: String,
+    pub key_values: Vec<(String, String)>,
+}
+
+/// Text form like `2023-06-14T03:13:26.160123Z INFO main user=alice logged in`
+impl Display for DecodedRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = self
+            .time
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|_| std::fmt::Error)?;
+        write!(f, "{} {} {}", time, self.level, self.target)?;
+        for (key, value) in &self.key_values {
+            write!(f, " {}={}", key, value)?;
+        }
+        write!(f, " {}", self.args)
+    }
+}
+
+/// Read records from frames written by [`BinaryFormat`]
+///
+/// Iterates over records, target definitions are consumed silently. An error is
+/// returned for a truncated or malformed frame, e.g. the last frame of a file
+/// written by a crashed process.
+pub struct Decoder<R> {
+    reader: R,
+    targets: HashMap<u64, String>,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R) -> Decoder<R> {
+        Decoder {
+            reader,
+            targets: HashMap::new(),
+        }
+    }
+
+    /// Read the next record, `None` at the end of input
+    pub fn next_record(&mut self) -> std::io::Result<Option<DecodedRecord>> {
+        loop {
+            let mut len = [0; 4];
+            match self.reader.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            }
+            let mut frame = vec![0; u32::from_le_bytes(len) as usize];
+            self.reader.read_exact(&mut frame)?;
+            let mut body = Body(&frame);
+            match body.u8()? {
+                TARGET => {
+                    let id = body.u64()?;
+                    let target = String::from_utf8_lossy(body.0).into_owned();
+                    self.targets.insert(id, target);
+                }
+                RECORD => return self.record(body).map(Some),
+                kind => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unknown frame kind {}", kind),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn record(&self, mut body: Body) -> std::io::Result<DecodedRecord> {
+        let time = OffsetDateTime::from_unix_timestamp_nanos(body.i64()? as i128)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let level = match body.u8()? {
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            5 => Level::Trace,
+            level => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown level {}", level),
+                ))
+            }
+        };
+        let id = body.u64()?;
+        let target = match self.targets.get(&id) {
+            Some(target) => target.clone(),
+            None => format!("#{:x}", id),
+        };
+        let args = body.string()?;
+        let count = body.u32()?;
+        let mut key_values = Vec::new();
+        for _ in 0..count {
+            key_values.push((body.string()?, body.string()?));
+        }
+        Ok(DecodedRecord {
+            time,
+            level,
+            target,
+            args,
+            key_values,
+        })
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = std::io::Result<DecodedRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Convert frames in `input` to text lines in `output`, returning the number of records
+pub fn decode<R: Read, W: Write>(input: R, mut output: W) -> std::io::Result<u64> {
+    let mut count = 0;
+    for record in Decoder::new(input) {
+        writeln!(output, "{}", record?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Remaining bytes of a frame body
+struct Body<'a>(&'a [u8]);
+
+impl<'a> Body<'a> {
+    fn take(&mut self, len: usize) -> std::io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(Error::new(ErrorKind::InvalidData, "frame too short"));
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> std::io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> std::io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> std::io::Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
@@ -0,0 +1,342 @@
+//! Source of time
+//!
+//! ftlog reads a monotonic clock by default, or TSC with `tsc` feature, and maps it to
+//! wall-clock time anchored to the system clock, which is read again every second.
+//! The system clock stepped forward, e.g. after a suspend, is followed at once. Steps
+//! backward, e.g. by NTP, are caught up with by slowing timestamps down to at most
+//! half speed, so that timestamps never go backwards within a file. A custom
+//! [`Clock`] set with [`Builder::clock`](crate::Builder::clock) is used for
+//! timestamps, delays and log interval limits instead, and one set with
+//! `FileAppender::builder().clock()` decides when files are rotated and which ones
+//! are outdated. This allows tests to freeze time with [`ManualClock`], and targets
+//! without a usable system clock to supply their own.
+//!
+//! ```
+//! use std::sync::Arc;
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! use ftlog::clock::ManualClock;
+//!
+//! let clock = Arc::new(ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_666_627_200)));
+//! let logger = ftlog::builder().clock(clock.clone()).build().unwrap();
+//! ftlog::log_to!(logger, ftlog::Level::Info, "at 2022-10-24 16:00:00");
+//! clock.advance(Duration::from_secs(60));
+//! ftlog::log_to!(logger, ftlog::Level::Info, "at 2022-10-24 16:01:00");
+//! ```
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use time::OffsetDateTime;
+
+use crate::tm::{self, Time};
+
+/// A source of wall-clock and monotonic time
+pub trait Clock: Send + Sync {
+    /// Current wall-clock time
+    fn now(&self) -> SystemTime;
+
+    /// Current monotonic time, for measuring elapsed time
+    fn instant(&self) -> Instant;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        (**self).now()
+    }
+
+    #[inline]
+    fn instant(&self) -> Instant {
+        (**self).instant()
+    }
+}
+
+/// Clock of the operating system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    #[inline]
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that stands still until advanced, for tests
+pub struct ManualClock {
+    start: SystemTime,
+    anchor: Instant,
+    elapsed_nanos: AtomicU64,
+}
+
+impl ManualClock {
+    /// Create a clock frozen at `start`
+    pub fn new(start: SystemTime) -> Self {
+        ManualClock {
+            start,
+            anchor: Instant::now(),
+            elapsed_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        let nanos = duration.as_nanos().min(u64::MAX as u128) as u64;
+        self.elapsed_nanos.fetch_add(nanos, Ordering::SeqCst);
+    }
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_nanos.load(Ordering::SeqCst))
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        self.start + self.elapsed()
+    }
+
+    fn instant(&self) -> Instant {
+        self.anchor + self.elapsed()
+    }
+}
+
+/// A clock reading wall-clock time from a closure, e.g. to simulate a day or month
+/// boundary in tests
+///
+/// Monotonic time follows wall-clock time since the first reading, and stands still
+/// while wall-clock time is before it.
+///
+/// ```
+/// use std::sync::atomic::{AtomicI64, Ordering};
+/// use std::sync::Arc;
+///
+/// use ftlog::appender::{FileAppender, Period};
+/// use ftlog::clock::FnClock;
+/// use time::OffsetDateTime;
+///
+/// let now = Arc::new(AtomicI64::new(1_666_627_200));
+/// let read = {
+///     let now = now.clone();
+///     move || OffsetDateTime::from_unix_timestamp(now.load(Ordering::Relaxed)).unwrap()
+/// };
+/// let appender = FileAppender::builder()
+///     .path("./fn-clock.log")
+///     .rotate(Period::Day)
+///     .clock(FnClock::new(read.clone()))
+///     .build();
+/// let logger = ftlog::builder().with_now(read).root(appender).build().unwrap();
+/// now.fetch_add(86_400, Ordering::Relaxed);
+/// # drop(logger);
+/// # for file in std::fs::read_dir(".").unwrap().filter_map(|x| x.ok()) {
+/// #     if file.file_name().to_string_lossy().starts_with("fn-clock") {
+/// #         std::fs::remove_file(file.path()).unwrap();
+/// #     }
+/// # }
+/// ```
+pub struct FnClock<F> {
+    now: F,
+    anchor: Instant,
+    start: OffsetDateTime,
+}
+
+impl<F: Fn() -> OffsetDateTime + Send + Sync> FnClock<F> {
+    pub fn new(now: F) -> Self {
+        let start = now();
+        FnClock {
+            now,
+            anchor: Instant::now(),
+            start,
+        }
+    }
+}
+
+impl<F: Fn() -> OffsetDateTime + Send + Sync> Clock for FnClock<F> {
+    fn now(&self) -> SystemTime {
+        (self.now)().into()
+    }
+
+    fn instant(&self) -> Instant {
+        let elapsed: Duration = ((self.now)() - self.start).try_into().unwrap_or_default();
+        self.anchor + elapsed
+    }
+}
+
+/// Interval of reading the system clock again to anchor monotonic time
+const RESYNC: Duration = Duration::from_secs(1);
+
+/// Wall-clock time of a monotonic instant and the pace of time after it
+#[derive(Clone, Copy)]
+struct Anchor {
+    instant: Time,
+    unix_nanos: i128,
+    /// 1 but while catching up with a system clock that is behind
+    rate: f64,
+}
+
+impl Anchor {
+    fn now() -> Anchor {
+        Anchor {
+            instant: tm::now(),
+            unix_nanos: OffsetDateTime::now_utc().unix_timestamp_nanos(),
+            rate: 1.0,
+        }
+    }
+
+    fn unix_nanos(&self, time: Time) -> i128 {
+        let scale = |elapsed: Duration| {
+            if self.rate < 1.0 {
+                (elapsed.as_nanos() as f64 * self.rate) as i128
+            } else {
+                elapsed.as_nanos() as i128
+            }
+        };
+        if time >= self.instant {
+            self.unix_nanos + scale(tm::duration(self.instant, time))
+        } else {
+            self.unix_nanos - scale(tm::duration(time, self.instant))
+        }
+    }
+
+    /// Anchor at `time`, when the system clock reads `wall`
+    fn follow(&self, time: Time, wall: i128) -> Anchor {
+        let mapped = self.unix_nanos(time);
+        let behind = mapped - wall;
+        if behind > 0 {
+            // catch up within the next interval, at half speed at the slowest
+            Anchor {
+                instant: time,
+                unix_nanos: mapped,
+                rate: (1.0 - behind as f64 / RESYNC.as_nanos() as f64).max(0.5),
+            }
+        } else {
+            Anchor {
+                instant: time,
+                unix_nanos: wall,
+                rate: 1.0,
+            }
+        }
+    }
+}
+
+/// The current anchor, and the one before for instants preceding it
+static ANCHORS: Mutex<Option<(Anchor, Anchor)>> = Mutex::new(None);
+
+/// Wall-clock time of a monotonic instant, see [module](self) docs
+pub(crate) fn wall_time(time: Time) -> OffsetDateTime {
+    let mut anchors = ANCHORS.lock().unwrap_or_else(|e| e.into_inner());
+    let (current, previous) = anchors.get_or_insert_with(|| {
+        let anchor = Anchor::now();
+        (anchor, anchor)
+    });
+    if tm::duration(current.instant, time) >= RESYNC {
+        // anchor at `time`, so that lines queued behind it keep the same timestamps
+        let system = Anchor::now();
+        let wall = system.unix_nanos - tm::duration(time, system.instant).as_nanos() as i128;
+        *previous = *current;
+        *current = current.follow(time, wall);
+    }
+    let anchor = if time >= current.instant {
+        current
+    } else {
+        previous
+    };
+    OffsetDateTime::from_unix_timestamp_nanos(anchor.unix_nanos(time))
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+}
+
+/// Time of a log message, read from the default time source or a custom clock
+#[derive(Clone, Copy)]
+pub(crate) enum Stamp {
+    Default(Time),
+    Custom(SystemTime, Instant),
+}
+
+impl Stamp {
+    #[inline]
+    pub(crate) fn now(clock: Option<&dyn Clock>) -> Stamp {
+        match clock {
+            None => Stamp::Default(tm::now()),
+            Some(clock) => Stamp::Custom(clock.now(), clock.instant()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn to_utc(self) -> OffsetDateTime {
+        match self {
+            Stamp::Default(time) => tm::to_utc(time),
+            Stamp::Custom(time, _) => time.into(),
+        }
+    }
+
+    /// Time elapsed since `earlier`, zero if it is later than `self`
+    #[inline]
+    pub(crate) fn since(self, earlier: Stamp) -> Duration {
+        match (earlier, self) {
+            (Stamp::Default(from), Stamp::Default(to)) => tm::duration(from, to),
+            (Stamp::Custom(_, from), Stamp::Custom(_, to)) => to.saturating_duration_since(from),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl PartialEq for Stamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Stamp {}
+
+impl PartialOrd for Stamp {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Stamps of a logger are taken from the same clock, so they are of the same variant
+impl Ord for Stamp {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (self, other) {
+            (Stamp::Default(a), Stamp::Default(b)) => a.cmp(b),
+            (Stamp::Custom(a, _), Stamp::Custom(b, _)) => a.cmp(b),
+            (Stamp::Default(_), Stamp::Custom(..)) => std::cmp::Ordering::Less,
+            (Stamp::Custom(..), Stamp::Default(_)) => std::cmp::Ordering::Greater,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn system_clock_stepped() {
+        const MS: i128 = 1_000_000;
+        let start = Anchor::now();
+        let at = |ms: u64| start.instant + Duration::from_millis(ms);
+
+        // stepped back by 200ms: time slows down instead of going backwards
+        let slow = start.follow(at(1000), start.unix_nanos + 800 * MS);
+        assert_eq!(slow.unix_nanos, start.unix_nanos + 1000 * MS);
+        assert!((slow.rate - 0.8).abs() < 1e-9);
+        assert_eq!(slow.unix_nanos(at(2000)), start.unix_nanos + 1800 * MS);
+        let caught_up = slow.follow(at(2000), start.unix_nanos + 1800 * MS);
+        assert_eq!(caught_up.rate, 1.0);
+
+        // stepped back by an hour: half speed at the slowest
+        let slow = start.follow(at(1000), start.unix_nanos - 3_600_000 * MS);
+        assert_eq!(slow.rate, 0.5);
+        assert!(slow.unix_nanos(at(1001)) > slow.unix_nanos(at(1000)));
+
+        // stepped forward: followed at once
+        let ahead = start.follow(at(1000), start.unix_nanos + 5000 * MS);
+        assert_eq!(ahead.unix_nanos(at(1000)), start.unix_nanos + 5000 * MS);
+        assert_eq!(ahead.rate, 1.0);
+    }
+}
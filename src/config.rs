@@ -0,0 +1,849 @@
+//! log4rs-style configuration
+//!
+//! Requires `config` feature. A configuration in the layout of log4rs, with
+//! appenders, encoders, a root logger and per-target loggers, is turned into a
+//! [`Builder`], so existing deployments can switch to ftlog without rewriting their
+//! configuration files.
+//!
+//! ```
+//! let config = r#"
+//! appenders:
+//!   stdout:
+//!     kind: console
+//!     encoder:
+//!       pattern: "{d(%Y-%m-%d %H:%M:%S)} {l} {t} - {m}{n}"
+//!   requests:
+//!     kind: file
+//!     path: log/requests.log
+//! root:
+//!   level: warn
+//!   appenders:
+//!     - stdout
+//! loggers:
+//!   app::backend::db:
+//!     level: info
+//!   app::requests:
+//!     level: info
+//!     appenders:
+//!       - requests
+//!     additive: false
+//! "#;
+//! let _guard = ftlog::config::from_yaml(config).unwrap().try_init().unwrap();
+//! # std::fs::remove_dir_all("log").unwrap();
+//! ```
+//!
+//! Supported appender kinds are `console`, `file` and `rolling_file`. Rolling files
+//! are rotated by a `time` trigger with an interval of one minute, hour, day, month
+//! or year, and a `fixed_window` roller keeps `count` periods of logs.
+//! Paths of files can contain [placeholders](crate::appender::file#placeholders)
+//! like `${HOSTNAME}`.
+//! Encoders are `pattern` encoders, see [`from_yaml`] for the recognized
+//! specifiers.
+//!
+//! ftlog formats a record once for all appenders, so all encoders in a
+//! configuration must have the same pattern, which also applies to appenders
+//! without an encoder. Levels of loggers are checked at call site on every log
+//! call. Names of appenders and loggers are leaked, as ftlog requires
+//! `&'static str` names, and `refresh_rate` is ignored.
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, OpenOptions};
+use std::io::{sink, stderr, stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use time::format_description::OwnedFormatItem;
+
+use crate::appender::file::expand;
+use crate::appender::{ChainAppenders, Duration, FileAppender, Period};
+use crate::format::{LogRecord, RecordFormatter};
+use crate::{AppenderError, Builder, ConfigError, InitError, LevelFilter, Location};
+
+/// Pattern of log4rs encoders without an explicit pattern
+const DEFAULT_PATTERN: &str = "{d} {l} {t} - {m}{n}";
+
+/// Load a YAML configuration file
+///
+/// Syntax errors and settings of the wrong type are reported as
+/// [`ConfigError::Parse`] with their location in the file.
+pub fn load(path: impl AsRef<Path>) -> Result<Builder, InitError> {
+    let path = path.as_ref();
+    parse(&std::fs::read_to_string(path)?, Some(path))?.into_builder()
+}
+
+/// Parse a YAML configuration
+///
+/// Recognized specifiers of pattern encoders are `d`/`date` with an optional
+/// strftime format, `l`/`level`, `t`/`target`, `m`/`message`, `n`, `M`/`module`,
+/// `f`/`file`, `L`/`line`, `T`/`thread`, `I`/`thread_id`, `P`/`pid`, `X`/`mdc`
+/// taking a key and an optional default, which reads key-values of the record, and
+/// `h`/`highlight`, which writes its argument without color. Specifiers take an
+/// optional `:` followed by `<` or `>` alignment, minimum width and `.` maximum width.
+pub fn from_yaml(yaml: &str) -> Result<Builder, InitError> {
+    parse(yaml, None)?.into_builder()
+}
+
+fn parse(yaml: &str, path: Option<&Path>) -> Result<Config, ConfigError> {
+    serde_yaml::from_str::<Config>(yaml).map_err(|e| ConfigError::Parse {
+        location: e.location().map(|x| Location {
+            path: path.map(|x| x.to_path_buf()),
+            line: x.line(),
+            column: x.column(),
+        }),
+        message: e.to_string(),
+    })
+}
+
+/// A log4rs-style configuration
+///
+/// Besides YAML, it can be deserialized from any format supported by serde.
+#[derive(Debug, Default, Deserialize)]
+#[non_exhaustive]
+pub struct Config {
+    #[serde(default)]
+    pub appenders: BTreeMap<String, AppenderConfig>,
+    #[serde(default)]
+    pub root: RootConfig,
+    #[serde(default)]
+    pub loggers: BTreeMap<String, LoggerConfig>,
+}
+
+/// An appender, selected by `kind`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AppenderConfig {
+    Console {
+        #[serde(default)]
+        target: ConsoleTarget,
+        encoder: Option<EncoderConfig>,
+    },
+    File {
+        path: PathBuf,
+        #[serde(default = "default_append")]
+        append: bool,
+        encoder: Option<EncoderConfig>,
+    },
+    RollingFile {
+        path: PathBuf,
+        policy: PolicyConfig,
+        encoder: Option<EncoderConfig>,
+    },
+}
+
+fn default_append() -> bool {
+    true
+}
+
+/// Output stream of a console appender
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsoleTarget {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+/// Layout of log lines
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct EncoderConfig {
+    #[serde(default = "default_encoder")]
+    pub kind: String,
+    pub pattern: Option<String>,
+}
+
+fn default_encoder() -> String {
+    "pattern".to_string()
+}
+
+/// Rotation policy of a rolling file appender
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct PolicyConfig {
+    pub trigger: TriggerConfig,
+    pub roller: Option<RollerConfig>,
+}
+
+/// When a rolling file is rotated
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TriggerConfig {
+    /// rotate every `interval`, e.g. `1 day`
+    Time { interval: String },
+    /// rotate by file size, which is not supported by ftlog
+    Size { limit: serde_yaml::Value },
+}
+
+/// What happens to rotated files
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RollerConfig {
+    /// keep rotated files
+    Delete,
+    /// keep files of the last `count` periods
+    FixedWindow { count: u32, pattern: Option<String> },
+}
+
+/// The root logger
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct RootConfig {
+    #[serde(default = "default_level")]
+    pub level: LevelFilter,
+    #[serde(default)]
+    pub appenders: Vec<String>,
+}
+
+impl Default for RootConfig {
+    fn default() -> Self {
+        RootConfig {
+            level: default_level(),
+            appenders: Vec::new(),
+        }
+    }
+}
+
+fn default_level() -> LevelFilter {
+    LevelFilter::Debug
+}
+
+/// Settings of records whose target is the logger name or in its submodules
+#[derive(Debug, Deserialize)]
+#[non_exhaustive]
+pub struct LoggerConfig {
+    /// inherited from the closest parent logger when omitted
+    pub level: Option<LevelFilter>,
+    #[serde(default)]
+    pub appenders: Vec<String>,
+    /// also write to appenders of the parent logger
+    #[serde(default = "default_append")]
+    pub additive: bool,
+}
+
+fn invalid(msg: impl Into<String>) -> InitError {
+    InitError::InvalidConfig(ConfigError::Invalid(msg.into()))
+}
+
+fn leak(name: &str) -> &'static str {
+    Box::leak(name.to_string().into_boxed_str())
+}
+
+/// Whether `target` is `logger` or in one of its submodules
+fn in_module(target: &str, logger: &str) -> bool {
+    target
+        .strip_prefix(logger)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Appender written by several loggers
+#[derive(Clone)]
+struct SharedAppender(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl Write for SharedAppender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+}
+
+/// Create the parent directory of a log file, and fail early if it cannot be opened
+fn prepare(path: &Path, append: bool) -> Result<(), AppenderError> {
+    let failed = |source| AppenderError::Open {
+        path: path.to_path_buf(),
+        source,
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        create_dir_all(dir).map_err(failed)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(append)
+        .write(true)
+        .truncate(!append)
+        .open(path)
+        .map_err(failed)?;
+    Ok(())
+}
+
+/// Parse an interval of log4rs time trigger, e.g. `1 day`
+fn parse_period(interval: &str) -> Result<Period, InitError> {
+    let mut words = interval.split_whitespace();
+    let (count, unit) = match (words.next(), words.next(), words.next()) {
+        (Some(unit), None, _) => ("1", unit),
+        (Some(count), Some(unit), None) => (count, unit),
+        _ => return Err(invalid(format!("invalid interval `{}`", interval))),
+    };
+    let period = match unit.trim_end_matches('s') {
+        "minute" | "min" => Period::Minute,
+        "hour" => Period::Hour,
+        "day" => Period::Day,
+        "month" => Period::Month,
+        "year" => Period::Year,
+        _ => return Err(invalid(format!("invalid interval `{}`", interval))),
+    };
+    if count != "1" {
+        return Err(invalid(format!(
+            "ftlog only rotates every single {}, got `{}`",
+            unit.trim_end_matches('s'),
+            interval
+        )));
+    }
+    Ok(period)
+}
+
+fn period_length(period: Period) -> Duration {
+    match period {
+        Period::Minute => Duration::minutes(1),
+        Period::Hour => Duration::hours(1),
+        Period::Day => Duration::days(1),
+        Period::Month => Duration::days(31),
+        Period::Year => Duration::days(366),
+    }
+}
+
+impl AppenderConfig {
+    fn encoder(&self) -> Option<&EncoderConfig> {
+        match self {
+            AppenderConfig::Console { encoder, .. }
+            | AppenderConfig::File { encoder, .. }
+            | AppenderConfig::RollingFile { encoder, .. } => encoder.as_ref(),
+        }
+    }
+
+    fn open(&self) -> Result<Box<dyn Write + Send>, InitError> {
+        Ok(match self {
+            AppenderConfig::Console {
+                target: ConsoleTarget::Stdout,
+                ..
+            } => Box::new(stdout()),
+            AppenderConfig::Console {
+                target: ConsoleTarget::Stderr,
+                ..
+            } => Box::new(stderr()),
+            AppenderConfig::File { path, append, .. } => {
+                let path = &expand(path)?;
+                prepare(path, *append)?;
+                Box::new(FileAppender::try_new(path)?)
+            }
+            AppenderConfig::RollingFile { path, policy, .. } => {
+                let path = &expand(path)?;
+                let period = match &policy.trigger {
+                    TriggerConfig::Time { interval } => parse_period(interval)?,
+                    TriggerConfig::Size { .. } => {
+                        return Err(invalid("size trigger is not supported by ftlog"))
+                    }
+                };
+                if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    create_dir_all(dir).map_err(|source| AppenderError::Open {
+                        path: path.clone(),
+                        source,
+                    })?;
+                }
+                let builder = FileAppender::builder().path(path).rotate(period);
+                match policy.roller {
+                    Some(RollerConfig::FixedWindow { count, .. }) => {
+                        Box::new(builder.expire(period_length(period) * count).try_build()?)
+                    }
+                    _ => Box::new(builder.try_build()?),
+                }
+            }
+        })
+    }
+}
+
+impl EncoderConfig {
+    fn pattern(&self) -> Result<&str, InitError> {
+        if self.kind != "pattern" {
+            return Err(invalid(format!(
+                "encoder `{}` is not supported by ftlog",
+                self.kind
+            )));
+        }
+        Ok(self.pattern.as_deref().unwrap_or(DEFAULT_PATTERN))
+    }
+}
+
+/// Logger with appenders resolved along its parents
+struct Resolved<'a> {
+    name: &'a str,
+    level: LevelFilter,
+    appenders: Vec<&'a str>,
+    redirect: bool,
+}
+
+impl Config {
+    /// Create a builder with appenders, format and levels of the configuration
+    pub fn into_builder(self) -> Result<Builder, InitError> {
+        let mut builder = crate::builder();
+
+        let mut appenders = BTreeMap::new();
+        let mut pattern: Option<(&str, &str)> = None;
+        for (name, config) in &self.appenders {
+            if let Some(encoder) = config.encoder() {
+                let current = encoder.pattern()?;
+                match pattern {
+                    Some((other, first)) if first != current => {
+                        return Err(invalid(format!(
+                            "appenders `{}` and `{}` have different patterns, ftlog formats a record once for all appenders",
+                            other, name
+                        )))
+                    }
+                    Some(_) => (),
+                    None => pattern = Some((name, current)),
+                }
+            }
+            let appender = SharedAppender(Arc::new(Mutex::new(config.open()?)));
+            appenders.insert(name.as_str(), appender);
+        }
+        if let Some((_, pattern)) = pattern {
+            builder = builder.formatter(Pattern::parse(pattern).map_err(invalid)?);
+        }
+        let lookup = |name: &str| {
+            appenders
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ConfigError::UnknownAppender(name.to_string()))
+        };
+
+        let root: Vec<&str> = self.root.appenders.iter().map(|x| x.as_str()).collect();
+        match root.split_first() {
+            Some((first, rest)) => {
+                builder = builder.root(lookup(first)?);
+                for name in rest {
+                    builder = builder.attach(leak(name), lookup(name)?, LevelFilter::Trace);
+                }
+            }
+            None => builder = builder.root(sink()),
+        }
+
+        // parents have shorter names, so they are resolved before their children
+        let mut names: Vec<&str> = self.loggers.keys().map(|x| x.as_str()).collect();
+        names.sort_by_key(|name| name.len());
+        let mut loggers: Vec<Resolved> = Vec::new();
+        for name in names {
+            let config = &self.loggers[name];
+            let parent = loggers
+                .iter()
+                .rev()
+                .find(|parent| in_module(name, parent.name));
+            let (parent_level, parent_appenders, parent_redirect) = match parent {
+                Some(p) => (p.level, &p.appenders, p.redirect),
+                None => (self.root.level, &root, false),
+            };
+            let mut resolved: Vec<&str> = Vec::new();
+            let inherited = config.additive.then_some(parent_appenders);
+            for appender in config
+                .appenders
+                .iter()
+                .map(|x| x.as_str())
+                .chain(inherited.into_iter().flatten().copied())
+            {
+                if !resolved.contains(&appender) {
+                    resolved.push(appender);
+                }
+            }
+            let redirect = parent_redirect || resolved != root;
+            loggers.push(Resolved {
+                name,
+                level: config.level.unwrap_or(parent_level),
+                appenders: resolved,
+                redirect,
+            });
+        }
+
+        // records of a logger that writes to other appenders than root are
+        // redirected to a destination named after the logger, and the closest
+        // logger wins as filters are tried in order
+        for logger in loggers.iter().rev().filter(|x| x.redirect) {
+            if appenders.contains_key(logger.name) {
+                return Err(invalid(format!(
+                    "logger `{}` has the same name as an appender",
+                    logger.name
+                )));
+            }
+            let writers = logger
+                .appenders
+                .iter()
+                .map(|name| lookup(name).map(|x| Box::new(x) as Box<dyn Write + Send>))
+                .collect::<Result<_, _>>()?;
+            let name = leak(logger.name);
+            builder = builder
+                .appender(name, ChainAppenders::new(writers))
+                .filter(move |_, _, target| in_module(target, name), name);
+        }
+
+        let max_level = loggers
+            .iter()
+            .map(|x| x.level)
+            .fold(self.root.level, Ord::max);
+        let root_level = self.root.level;
+        let levels: Vec<(String, LevelFilter)> = loggers
+            .into_iter()
+            .rev()
+            .map(|x| (x.name.to_string(), x.level))
+            .collect();
+        Ok(builder
+            .max_log_level(max_level)
+            .root_log_level(LevelFilter::Trace)
+            .filter_fn(move |metadata| {
+                let level = levels
+                    .iter()
+                    .find(|(name, _)| in_module(metadata.target(), name))
+                    .map_or(root_level, |(_, level)| *level);
+                metadata.level() <= level
+            }))
+    }
+}
+
+/// Part of a pattern
+enum Piece {
+    Literal(String),
+    Spec {
+        kind: Kind,
+        right: bool,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+enum Kind {
+    Date(OwnedFormatItem),
+    Level,
+    Target,
+    Message,
+    Newline,
+    Module,
+    File,
+    Line,
+    Thread,
+    ThreadId,
+    Pid,
+    Key(String, String),
+    Highlight(Vec<Piece>),
+}
+
+/// Formatter of log4rs pattern encoder
+struct Pattern(Vec<Piece>);
+
+impl Pattern {
+    fn parse(pattern: &str) -> Result<Pattern, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        Ok(Pattern(parser.pieces(false)?))
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied();
+        self.pos += 1;
+        c
+    }
+
+    /// Consume `c` if it is the next character
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.chars.get(self.pos) == Some(&c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Parse until end of pattern, or the `)` closing an argument
+    fn pieces(&mut self, argument: bool) -> Result<Vec<Piece>, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        loop {
+            let c = match self.next() {
+                Some(c) => c,
+                None if argument => return Err("unclosed `(` in pattern".to_string()),
+                None => break,
+            };
+            match c {
+                '{' | '}' | '(' | ')' | '\\' if self.eat(c) => literal.push(c),
+                '{' => {
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(self.spec()?);
+                }
+                ')' if argument => break,
+                '}' | '(' | ')' => return Err(format!("unescaped `{}` in pattern", c)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(pieces)
+    }
+
+    /// Parse a specifier after `{`
+    fn spec(&mut self) -> Result<Piece, String> {
+        let mut name = String::new();
+        while let Some(c) = self
+            .chars
+            .get(self.pos)
+            .filter(|c| c.is_alphanumeric() || **c == '_')
+        {
+            name.push(*c);
+            self.pos += 1;
+        }
+        let mut args = Vec::new();
+        while self.eat('(') {
+            args.push(self.pieces(true)?);
+        }
+        let (mut right, mut min, mut max) = (false, 0, None);
+        if self.eat(':') {
+            right = self.eat('>');
+            if !right {
+                self.eat('<');
+            }
+            min = self.number().unwrap_or(0);
+            if self.eat('.') {
+                max = Some(self.number().ok_or("missing maximum width in pattern")?);
+            }
+        }
+        if !self.eat('}') {
+            return Err(format!("unclosed specifier `{}` in pattern", name));
+        }
+        let text = |ix: usize, args: &mut Vec<Vec<Piece>>| -> Result<Option<String>, String> {
+            let Some(arg) = args.get_mut(ix) else {
+                return Ok(None);
+            };
+            let mut text = String::new();
+            for piece in arg.drain(..) {
+                match piece {
+                    Piece::Literal(s) => text.push_str(&s),
+                    Piece::Spec { .. } => {
+                        return Err(format!("argument of `{}` must be plain text", name))
+                    }
+                }
+            }
+            Ok(Some(text))
+        };
+        let kind = match name.as_str() {
+            "d" | "date" => {
+                let format = text(0, &mut args)?;
+                let format = format.as_deref().unwrap_or("%Y-%m-%dT%H:%M:%S%.6f%:z");
+                let description = strftime(format)?;
+                Kind::Date(
+                    time::format_description::parse_owned::<1>(&description)
+                        .map_err(|e| format!("invalid date format `{}`: {}", format, e))?,
+                )
+            }
+            "l" | "level" => Kind::Level,
+            "t" | "target" => Kind::Target,
+            "m" | "message" => Kind::Message,
+            "n" => Kind::Newline,
+            "M" | "module" => Kind::Module,
+            "f" | "file" => Kind::File,
+            "L" | "line" => Kind::Line,
+            "T" | "thread" => Kind::Thread,
+            "I" | "thread_id" => Kind::ThreadId,
+            "P" | "pid" => Kind::Pid,
+            "X" | "mdc" => Kind::Key(
+                text(0, &mut args)?.ok_or("missing key of `X` in pattern")?,
+                text(1, &mut args)?.unwrap_or_default(),
+            ),
+            "h" | "highlight" => Kind::Highlight(args.into_iter().next().unwrap_or_default()),
+            _ => return Err(format!("unknown specifier `{}` in pattern", name)),
+        };
+        Ok(Piece::Spec {
+            kind,
+            right,
+            min,
+            max,
+        })
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+}
+
+/// Convert a strftime format to a format description of `time` crate
+fn strftime(format: &str) -> Result<String, String> {
+    let mut description = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            if c == '[' {
+                description.push('[');
+            }
+            description.push(c);
+            continue;
+        }
+        let mut spec: String = chars.next().into_iter().collect();
+        while spec
+            .chars()
+            .last()
+            .is_some_and(|c| c.is_ascii_digit() || c == '.' || c == ':')
+        {
+            match chars.next() {
+                Some(c) => spec.push(c),
+                None => break,
+            }
+        }
+        description.push_str(match spec.as_str() {
+            "Y" => "[year]",
+            "y" => "[year repr:last_two]",
+            "m" => "[month]",
+            "b" | "h" => "[month repr:short]",
+            "B" => "[month repr:long]",
+            "d" => "[day]",
+            "e" => "[day padding:space]",
+            "j" => "[ordinal]",
+            "a" => "[weekday repr:short]",
+            "A" => "[weekday repr:long]",
+            "H" => "[hour]",
+            "I" => "[hour repr:12]",
+            "p" => "[period]",
+            "M" => "[minute]",
+            "S" => "[second]",
+            "f" | "9f" => "[subsecond digits:9]",
+            "3f" => "[subsecond digits:3]",
+            "6f" => "[subsecond digits:6]",
+            ".f" | ".9f" => ".[subsecond digits:9]",
+            ".3f" => ".[subsecond digits:3]",
+            ".6f" => ".[subsecond digits:6]",
+            "z" => "[offset_hour sign:mandatory][offset_minute]",
+            ":z" => "[offset_hour sign:mandatory]:[offset_minute]",
+            "F" => "[year]-[month]-[day]",
+            "T" => "[hour]:[minute]:[second]",
+            "s" => "[unix_timestamp]",
+            "%" => "%",
+            _ => return Err(format!("unsupported date format `%{}`", spec)),
+        });
+    }
+    Ok(description)
+}
+
+fn render(pieces: &[Piece], record: &LogRecord<'_>, out: &mut String) {
+    use std::fmt::Write;
+
+    for piece in pieces {
+        let (kind, right, min, max) = match piece {
+            Piece::Literal(s) => {
+                out.push_str(s);
+                continue;
+            }
+            Piece::Spec {
+                kind,
+                right,
+                min,
+                max,
+            } => (kind, *right, *min, *max),
+        };
+        let mut value = String::new();
+        // writing to a String never fails
+        let _ = match kind {
+            Kind::Date(format) => {
+                let time = record.time();
+                match time.format(format) {
+                    Ok(s) => write!(value, "{}", s),
+                    Err(_) => write!(value, "{}", time),
+                }
+            }
+            Kind::Level => write!(value, "{}", record.level()),
+            Kind::Target => write!(value, "{}", record.target()),
+            Kind::Message => write!(value, "{}", record.args()),
+            Kind::Newline => writeln!(value),
+            Kind::Module => write!(value, "{}", record.module_path().unwrap_or_default()),
+            Kind::File => write!(value, "{}", record.file().unwrap_or_default()),
+            Kind::Line => match record.line() {
+                Some(line) => write!(value, "{}", line),
+                None => Ok(()),
+            },
+            Kind::Thread => write!(value, "{}", record.thread().unwrap_or("unnamed")),
+            Kind::ThreadId => {
+                let id = format!("{:?}", record.thread_id());
+                let id = id.trim_start_matches("ThreadId(").trim_end_matches(')');
+                write!(value, "{}", id)
+            }
+            Kind::Pid => write!(value, "{}", std::process::id()),
+            Kind::Key(key, default) => {
+                let found = record.key_values().find(|(k, _)| k == key);
+                write!(value, "{}", found.map_or(default.as_str(), |(_, v)| v))
+            }
+            Kind::Highlight(inner) => {
+                render(inner, record, &mut value);
+                Ok(())
+            }
+        };
+        if let Some(max) = max {
+            if let Some((ix, _)) = value.char_indices().nth(max) {
+                value.truncate(ix);
+            }
+        }
+        let _ = if right {
+            write!(out, "{:>min$}", value)
+        } else {
+            write!(out, "{:<min$}", value)
+        };
+    }
+}
+
+impl RecordFormatter for Pattern {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let mut line = String::new();
+        render(&self.0, record, &mut line);
+        buf.extend_from_slice(line.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_pattern() {
+        assert!(
+            Pattern::parse("{d(%Y-%m-%d %H:%M:%S%.3f)} {h({l:<5})} [{T}] {t} - {m}{n}").is_ok()
+        );
+        assert!(Pattern::parse("{{literal}} (({X(user)(-)}))").is_ok());
+        assert!(Pattern::parse("{unknown}").is_err());
+        assert!(Pattern::parse("{m").is_err());
+        assert!(Pattern::parse("{h({m}").is_err());
+        assert!(Pattern::parse("{d(%Q)}").is_err());
+    }
+
+    #[test]
+    fn convert_strftime() {
+        assert_eq!(
+            strftime("%Y-%m-%d [%H:%M:%S%.3f]").unwrap(),
+            "[year]-[month]-[day] [[[hour]:[minute]:[second].[subsecond digits:3]]"
+        );
+        assert_eq!(strftime("100%%").unwrap(), "100%");
+    }
+
+    #[test]
+    fn interval() {
+        assert!(matches!(parse_period("1 day"), Ok(Period::Day)));
+        assert!(matches!(parse_period("hours"), Ok(Period::Hour)));
+        assert!(parse_period("6 hours").is_err());
+        assert!(parse_period("1 week").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Per-thread contextual fields
+//!
+//! Fields inserted into the context of a thread are attached to every log record
+//! made by the thread, until the returned guard is dropped. This is handy for ids of
+//! a request or a job, which otherwise have to be passed to every log call.
+//!
+//! ```
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! {
+//!     let _request = ftlog::context::insert("request_id", 42);
+//!     log::info!("handling request");
+//!     // Output:
+//!     // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:4] request_id=42 handling request
+//! }
+//! log::info!("request handled");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:8] request handled
+//! ```
+//!
+//! Context fields are printed by the default format, and come first in
+//! [`LogRecord::key_values`](crate::format::LogRecord::key_values) for
+//! [`RecordFormatter`](crate::format::RecordFormatter).
+//! A custom [`FtLogFormat`](crate::FtLogFormat) can read them with [`for_each`].
+//!
+//! [`span!`](crate::span) inserts fields for a scope and logs its elapsed time at
+//! the end.
+//!
+//! With `otel` feature, `trace_id` and `span_id` of the current OpenTelemetry span
+//! are attached after the fields of the thread, so logs can be correlated with
+//! distributed traces.
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Instant;
+
+use log::Level;
+
+struct Entry {
+    id: u64,
+    key: &'static str,
+    value: Arc<str>,
+}
+
+#[derive(Default)]
+struct Context {
+    next_id: u64,
+    entries: Vec<Entry>,
+}
+
+impl Context {
+    /// Entries not shadowed by a later one with the same key
+    fn visible(&self) -> impl Iterator<Item = &Entry> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(ix, entry)| !self.entries[ix + 1..].iter().any(|x| x.key == entry.key))
+            .map(|(_, entry)| entry)
+    }
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::new(Context::default());
+}
+
+/// Removes the field from context of current thread when dropped
+#[must_use = "the field is removed immediately if the guard is not kept"]
+pub struct ContextGuard {
+    id: u64,
+    // context is thread local, so is the guard
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let _ = CONTEXT.try_with(|context| {
+            let mut context = context.borrow_mut();
+            if let Some(ix) = context.entries.iter().rposition(|x| x.id == self.id) {
+                context.entries.remove(ix);
+            }
+        });
+    }
+}
+
+/// Attach a field to all log records of current thread, until the guard is dropped
+///
+/// A field inserted later shadows the one with the same key.
+pub fn insert(key: &'static str, value: impl Display) -> ContextGuard {
+    CONTEXT.with(|context| {
+        let mut context = context.borrow_mut();
+        let id = context.next_id;
+        context.next_id += 1;
+        context.entries.push(Entry {
+            id,
+            key,
+            value: value.to_string().into(),
+        });
+        ContextGuard {
+            id,
+            _not_send: PhantomData,
+        }
+    })
+}
+
+/// Visit fields in context of current thread, in the order of insertion
+pub fn for_each(mut f: impl FnMut(&'static str, &str)) {
+    let _ = CONTEXT.try_with(|context| {
+        for entry in context.borrow().visible() {
+            f(entry.key, &entry.value);
+        }
+    });
+    #[cfg(feature = "otel")]
+    for (key, value) in otel_ids().into_iter().flatten() {
+        f(key, &value);
+    }
+}
+
+/// Ids of the current OpenTelemetry span, if there is a valid one
+#[cfg(feature = "otel")]
+fn otel_ids() -> Option<[(&'static str, String); 2]> {
+    use opentelemetry::trace::TraceContextExt;
+
+    let context = opentelemetry::Context::current();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context.is_valid().then(|| {
+        [
+            ("trace_id", span_context.trace_id().to_string()),
+            ("span_id", span_context.span_id().to_string()),
+        ]
+    })
+}
+
+/// A scope with context fields, created by [`span!`](crate::span)
+///
+/// Fields of the span are attached to log records of current thread until the span
+/// is dropped, then elapsed time of the span is logged with the fields.
+#[must_use = "the span ends immediately if it is not kept"]
+pub struct Span {
+    name: &'static str,
+    level: Level,
+    start: Instant,
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    // dropped after the elapsed time is logged
+    _fields: Vec<ContextGuard>,
+}
+
+impl Span {
+    #[doc(hidden)]
+    pub fn new(
+        name: &'static str,
+        level: Level,
+        (module_path, file, line): (&'static str, &'static str, u32),
+        fields: Vec<ContextGuard>,
+    ) -> Span {
+        Span {
+            name,
+            level,
+            start: Instant::now(),
+            module_path,
+            file,
+            line,
+            _fields: fields,
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if self.level > log::max_level() {
+            return;
+        }
+        let elapsed = self.start.elapsed();
+        log::logger().log(
+            &log::Record::builder()
+                .level(self.level)
+                .target(self.module_path)
+                .module_path_static(Some(self.module_path))
+                .file_static(Some(self.file))
+                .line(Some(self.line))
+                .args(format_args!("{} finished in {:?}", self.name, elapsed))
+                .build(),
+        );
+    }
+}
+
+/// Whether current thread has no fields in context, see [`fields`]
+pub(crate) fn is_empty() -> bool {
+    let empty = CONTEXT
+        .try_with(|context| context.borrow().entries.is_empty())
+        .unwrap_or(true);
+    #[cfg(feature = "otel")]
+    let empty = empty && otel_ids().is_none();
+    empty
+}
+
+/// Fields in context of current thread, to be sent to log thread along with a record
+pub(crate) fn fields() -> Vec<(&'static str, Arc<str>)> {
+    let mut fields = Vec::new();
+    extend_fields(&mut fields);
+    fields
+}
+
+/// Append fields in context of current thread to `fields`, see [`fields`]
+pub(crate) fn extend_fields(fields: &mut Vec<(&'static str, Arc<str>)>) {
+    let _ = CONTEXT.try_with(|context| {
+        fields.extend(
+            context
+                .borrow()
+                .visible()
+                .map(|entry| (entry.key, entry.value.clone())),
+        );
+    });
+    #[cfg(feature = "otel")]
+    fields.extend(
+        otel_ids()
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key, Arc::from(value))),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn span_fields() {
+        {
+            let _span = crate::span!("outer", id = 7, user = "alice");
+            let _inner = crate::span!(level: Level::Debug, "inner", id = 8);
+            assert_eq!(
+                fields()
+                    .into_iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<_>>(),
+                ["user=alice", "id=8"]
+            );
+        }
+        assert!(fields().is_empty());
+    }
+
+    #[test]
+    fn shadow_and_remove() {
+        let outer = insert("request_id", 1);
+        let user = insert("user", "alice");
+        {
+            let _inner = insert("request_id", 2);
+            let keys = fields()
+                .into_iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>();
+            assert_eq!(keys, ["user=alice", "request_id=2"]);
+        }
+        drop(outer);
+        let mut keys = Vec::new();
+        for_each(|k, v| keys.push(format!("{}={}", k, v)));
+        assert_eq!(keys, ["user=alice"]);
+        drop(user);
+        assert!(fields().is_empty());
+    }
+}
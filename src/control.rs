@@ -0,0 +1,189 @@
+//! Control socket
+//!
+//! [`Builder::control`](crate::Builder::control) starts a small server on a Unix
+//! socket or a loopback TCP port, so orchestration tooling can manage logging of a
+//! running service. Each request is a line of text, each response is zero or more
+//! lines of output followed by `ok` or `error: <reason>`.
+//!
+//! | request                      | effect                                           |
+//! |------------------------------|--------------------------------------------------|
+//! | `level`                      | print the max level                              |
+//! | `level <level>`              | set the max level, see [`set_level`](crate::set_level) |
+//! | `targets`                    | print levels of target prefixes as `<prefix> <level>` |
+//! | `target <prefix> <level>`    | set the level of a target prefix, see [`set_target_level`](crate::set_target_level) |
+//! | `target <prefix> reset`      | remove the level of a target prefix              |
+//! | `rotate`                     | reopen log files, see [`reopen`](crate::appender::file::reopen) |
+//! | `flush`                      | write queued records and flush appenders         |
+//! | `stats`                      | print [statistics](mod@crate::stats) as `<name> <value>` |
+//!
+//! ```no_run
+//! use ftlog::control::Listen;
+//!
+//! let _guard = ftlog::builder()
+//!     .control(Listen::Unix("/run/app/log.sock".into()))
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! ```shell
+//! $ echo "level debug" | socat - UNIX-CONNECT:/run/app/log.sock
+//! ok
+//! $ echo "stats" | socat - UNIX-CONNECT:/run/app/log.sock
+//! dropped 0
+//! queue_depth 0
+//! records.error 2
+//! ...
+//! ok
+//! ```
+//!
+//! There is no authentication: access is limited by permissions of the socket file,
+//! or to local processes for TCP, which only listens on loopback addresses.
+//! Connections are served one at a time by a background thread.
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
+
+use log::LevelFilter;
+
+use crate::Shared;
+
+/// Address of the control server
+#[derive(Debug, Clone)]
+pub enum Listen {
+    /// Unix socket at the path, replacing a stale socket file left by a previous run
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// TCP on a loopback address
+    Tcp(SocketAddr),
+}
+
+/// Start serving `listen` in a background thread
+pub(crate) fn spawn(shared: &Arc<Shared>, listen: Listen) -> std::io::Result<()> {
+    let shared = Arc::downgrade(shared);
+    let thread = std::thread::Builder::new().name("logger-control".to_string());
+    match listen {
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            use std::os::unix::fs::FileTypeExt;
+            if std::fs::symlink_metadata(&path).is_ok_and(|x| x.file_type().is_socket()) {
+                std::fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)?;
+            thread.spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if serve(&shared, &stream, &stream).is_err() && shared.strong_count() == 0 {
+                        return;
+                    }
+                }
+            })?;
+        }
+        Listen::Tcp(addr) => {
+            let listener = TcpListener::bind(addr)?;
+            thread.spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if serve(&shared, &stream, &stream).is_err() && shared.strong_count() == 0 {
+                        return;
+                    }
+                }
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Serve requests of a connection until it is closed
+fn serve<R, W>(shared: &Weak<Shared>, reader: R, mut writer: W) -> std::io::Result<()>
+where
+    R: std::io::Read,
+    W: Write,
+{
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let Some(shared) = shared.upgrade() else {
+            writeln!(writer, "error: logger is dropped")?;
+            return Err(std::io::ErrorKind::BrokenPipe.into());
+        };
+        match execute(&shared, &line, &mut writer)? {
+            Ok(()) => writeln!(writer, "ok")?,
+            Err(reason) => writeln!(writer, "error: {}", reason)?,
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level).map_err(|_| format!("invalid level {}", level))
+}
+
+/// Execute a request, writing its output. The inner error is the reason of a failed
+/// request, while the outer one is an I/O error of the connection.
+fn execute<W: Write>(
+    shared: &Arc<Shared>,
+    request: &str,
+    out: &mut W,
+) -> std::io::Result<Result<(), String>> {
+    let words = request.split_whitespace().collect::<Vec<_>>();
+    let result = match words.as_slice() {
+        ["level"] => {
+            writeln!(out, "{}", shared.level.get().as_str().to_lowercase())?;
+            Ok(())
+        }
+        ["level", level] => parse_level(level).map(|level| shared.set_level(level)),
+        ["targets"] => {
+            for (prefix, level) in shared.targets.load().iter() {
+                writeln!(out, "{} {}", prefix, level.as_str().to_lowercase())?;
+            }
+            Ok(())
+        }
+        ["target", prefix, "reset"] => {
+            shared.set_target_level(prefix, None);
+            Ok(())
+        }
+        ["target", prefix, level] => {
+            parse_level(level).map(|level| shared.set_target_level(prefix, Some(level)))
+        }
+        ["rotate"] => {
+            crate::appender::file::reopen();
+            Ok(())
+        }
+        ["flush"] => {
+            shared.flush();
+            Ok(())
+        }
+        ["stats"] => {
+            let stats = shared.metrics.snapshot(shared.queue.len());
+            writeln!(out, "dropped {}", stats.dropped)?;
+            writeln!(out, "queue_depth {}", stats.queue_depth)?;
+            for (level, count) in stats.records {
+                writeln!(out, "records.{} {}", level.as_str().to_lowercase(), count)?;
+            }
+            for appender in &stats.appenders {
+                writeln!(
+                    out,
+                    "bytes_written.{} {}",
+                    appender.name, appender.bytes_written
+                )?;
+            }
+            let latency = stats.write_latency;
+            for (name, value) in [
+                ("p50", latency.p50),
+                ("p90", latency.p90),
+                ("p99", latency.p99),
+                ("max", latency.max),
+            ] {
+                writeln!(out, "write_latency_us.{} {}", name, value.as_micros())?;
+            }
+            writeln!(out, "rotations {}", stats.rotations)?;
+            Ok(())
+        }
+        [] => Err("empty request".to_string()),
+        [command, ..] => Err(format!("unknown request {}", command)),
+    };
+    Ok(result)
+}
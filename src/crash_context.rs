@@ -0,0 +1,65 @@
+//! Verbose records kept per thread until an error occurs
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use hashbrown::HashMap;
+use log::{Level, LevelFilter};
+
+use crate::worker::LogMsg;
+
+thread_local! {
+    // ring buffers of the calling thread, by id of logger
+    static RINGS: RefCell<HashMap<usize, VecDeque<LogMsg>>> = RefCell::new(HashMap::new());
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) struct CrashContext {
+    // unique among loggers, so a new logger never gets records of a dropped one
+    id: usize,
+    persist: LevelFilter,
+    capacity: usize,
+}
+
+impl CrashContext {
+    pub(crate) fn new(persist: LevelFilter, capacity: usize) -> CrashContext {
+        CrashContext {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            persist,
+            capacity,
+        }
+    }
+
+    /// Whether records of `level` are kept in the ring buffer instead of sent
+    #[inline]
+    pub(crate) fn buffered(&self, level: Level) -> bool {
+        level > self.persist
+    }
+
+    /// Keep `msg` in the ring buffer of the calling thread, evicting the oldest one
+    /// when full
+    pub(crate) fn keep(&self, msg: LogMsg) {
+        if self.capacity == 0 {
+            return;
+        }
+        // the thread local is gone when logging from destructors of other ones
+        let _ = RINGS.try_with(|rings| {
+            let mut rings = rings.borrow_mut();
+            let ring = rings.entry(self.id).or_default();
+            if ring.len() >= self.capacity {
+                ring.pop_front();
+            }
+            ring.push_back(msg);
+        });
+    }
+
+    /// Take records kept by the calling thread, oldest first
+    pub(crate) fn take(&self) -> VecDeque<LogMsg> {
+        RINGS
+            .try_with(|rings| rings.borrow_mut().remove(&self.id))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,120 @@
+//! Fields computed in log thread and appended to every record
+//!
+//! An [`Enricher`] added with [`Builder::enrich`](crate::Builder::enrich) adds
+//! key-values to each record after it reaches log thread, so that values like the
+//! pod name, the build SHA or the region are computed once per record off the hot
+//! path, and can depend on the level or target of the record. Enrichers run in the
+//! order they are added, before redaction, sanitizing and formatting.
+//!
+//! ```
+//! use ftlog::enrich::{Enricher, Fields};
+//!
+//! /// Region of the node, looked up once
+//! struct Region(String);
+//!
+//! impl Enricher for Region {
+//!     fn enrich(&self, fields: &mut Fields<'_>) {
+//!         fields.insert("region", &self.0);
+//!     }
+//! }
+//!
+//! let _guard = ftlog::builder()
+//!     .enrich(Region("eu-west-1".to_string()))
+//!     // closures are enrichers too
+//!     .enrich(|fields: &mut Fields<'_>| {
+//!         if fields.level() <= log::Level::Warn {
+//!             fields.insert("build", env!("CARGO_PKG_VERSION"));
+//!         }
+//!     })
+//!     .try_init()
+//!     .unwrap();
+//! log::warn!("disk almost full");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms WARN main [src/main.rs:26] region=eu-west-1 build=0.2.0 disk almost full
+//! ```
+//!
+//! Fields are appended after key-values of the log call, and a key already set by
+//! the log call or an earlier enricher is kept. Records are routed before they are
+//! enriched, so enriched fields are not seen by
+//! [`Builder::route_field`](crate::Builder::route_field). Records of a custom
+//! [`FtLogFormat`](crate::FtLogFormat) are formatted at the log call and are not
+//! enriched.
+use log::Level;
+
+use crate::format::FieldValue;
+use crate::worker::Payload;
+
+/// A source of fields appended to each record in log thread, see [module doc](self)
+pub trait Enricher: Send + Sync {
+    /// Add fields to a record with [`Fields::insert`]
+    fn enrich(&self, fields: &mut Fields<'_>);
+}
+
+impl<F> Enricher for F
+where
+    F: Fn(&mut Fields<'_>) + Send + Sync,
+{
+    fn enrich(&self, fields: &mut Fields<'_>) {
+        self(fields)
+    }
+}
+
+/// Key-values of a record passed to an [`Enricher`]
+pub struct Fields<'a> {
+    level: Level,
+    target: &'a str,
+    key_values: &'a mut Vec<(String, FieldValue)>,
+}
+
+impl Fields<'_> {
+    #[inline]
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    #[inline]
+    pub fn target(&self) -> &str {
+        self.target
+    }
+
+    /// Value of `key` set by the log call or an earlier enricher
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.key_values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Append `key` with `value`, unless `key` is already set
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        if self.get(&key).is_none() {
+            self.key_values.push((key, FieldValue::Text(value.into())));
+        }
+    }
+}
+
+/// Run `enrichers` on the key-values of a log message in place
+pub(crate) fn apply(
+    payload: &mut Payload,
+    level: Level,
+    target: &str,
+    enrichers: &[Box<dyn Enricher>],
+) {
+    if let Payload::Static(msg) = payload {
+        *payload = Payload::Message(Box::new(msg.to_message()));
+    }
+    let key_values = match payload {
+        Payload::Record(fields) => &mut fields.key_values,
+        Payload::Message(msg) => &mut msg.key_values,
+        _ => return,
+    };
+    let mut fields = Fields {
+        level,
+        target,
+        key_values,
+    };
+    for enricher in enrichers {
+        enricher.enrich(&mut fields);
+    }
+}
@@ -0,0 +1,369 @@
+//! Drop-in replacement of `env_logger::Builder`
+//!
+//! [`env_builder()`](crate::env_builder) returns an [`EnvBuilder`] that reads
+//! `RUST_LOG` and offers the most used methods of `env_logger::Builder`, so a
+//! project can switch to ftlog by replacing the builder and keeping the returned
+//! guard:
+//!
+//! ```
+//! use ftlog::env::TimestampPrecision;
+//!
+//! // was: env_logger::builder()
+//! let _guard = ftlog::env_builder()
+//!     .filter_module("hyper", log::LevelFilter::Warn)
+//!     .format_timestamp(Some(TimestampPrecision::Millis))
+//!     .init();
+//! log::info!("Hello world!");
+//! // Output:
+//! // [2023-06-14T03:13:26.160Z INFO  main] Hello world!
+//! ```
+//!
+//! Log lines follow the default format of env_logger. Records still go through the
+//! async pipeline of ftlog, so the guard must be kept to write queued records when
+//! the program exits. Use [`EnvBuilder::into_builder`] for settings of ftlog that
+//! have no counterpart in env_logger.
+use std::io::{stderr, stdout, Write};
+
+use log::LevelFilter;
+use time::format_description::OwnedFormatItem;
+use time::UtcOffset;
+
+use crate::format::{LogRecord, RecordFormatter};
+use crate::{Builder, InitError, LoggerGuard};
+
+/// Default environment variable of filters
+pub const DEFAULT_FILTER_ENV: &str = "RUST_LOG";
+/// Default environment variable of write style
+pub const DEFAULT_WRITE_STYLE_ENV: &str = "RUST_LOG_STYLE";
+
+/// Precision of timestamp in log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampPrecision {
+    #[default]
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+/// Whether to color log lines
+///
+/// Kept for compatibility, ftlog does not color log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteStyle {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Destination of log lines
+#[derive(Default)]
+pub enum Target {
+    Stdout,
+    #[default]
+    Stderr,
+    Pipe(Box<dyn Write + Send + 'static>),
+}
+
+/// Builder with the interface of `env_logger::Builder`, see [module doc](self)
+pub struct EnvBuilder {
+    // module prefix and level, a later directive for the same module replaces earlier ones
+    directives: Vec<(Option<String>, LevelFilter)>,
+    message_filter: Option<String>,
+    format: EnvFormat,
+    target: Target,
+}
+
+impl Default for EnvBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnvBuilder {
+    /// Create a builder without reading environment variables
+    pub fn new() -> EnvBuilder {
+        EnvBuilder {
+            directives: Vec::new(),
+            message_filter: None,
+            format: EnvFormat {
+                timestamp: Some(TimestampPrecision::Seconds),
+                level: true,
+                module_path: false,
+                target: true,
+            },
+            target: Target::Stderr,
+        }
+    }
+
+    /// Create a builder configured by `RUST_LOG` and `RUST_LOG_STYLE`
+    pub fn from_default_env() -> EnvBuilder {
+        Self::new().parse_default_env()
+    }
+
+    /// Apply filters in `RUST_LOG` and write style in `RUST_LOG_STYLE`
+    pub fn parse_default_env(self) -> EnvBuilder {
+        self.parse_env(DEFAULT_FILTER_ENV)
+            .parse_write_style_env(DEFAULT_WRITE_STYLE_ENV)
+    }
+
+    /// Apply filters in environment variable `name`, if set
+    pub fn parse_env(self, name: &str) -> EnvBuilder {
+        match std::env::var(name) {
+            Ok(filters) => self.parse_filters(&filters),
+            Err(_) => self,
+        }
+    }
+
+    /// Apply write style in environment variable `name`, if set
+    pub fn parse_write_style_env(self, name: &str) -> EnvBuilder {
+        match std::env::var(name) {
+            Ok(style) => self.parse_write_style(&style),
+            Err(_) => self,
+        }
+    }
+
+    /// Apply filters in env_logger syntax, e.g. `info,my_crate::db=debug/query`
+    ///
+    /// Invalid directives are reported to stderr and ignored. The text after `/`
+    /// keeps records whose message contains it, a plain substring rather than a regex.
+    pub fn parse_filters(mut self, filters: &str) -> EnvBuilder {
+        let (directives, message) = match filters.split_once('/') {
+            Some((directives, message)) => (directives, Some(message)),
+            None => (filters, None),
+        };
+        for directive in directives.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            let parsed = match directive.split_once('=') {
+                None => match directive.parse() {
+                    Ok(level) => Some((None, level)),
+                    Err(_) => Some((Some(directive), LevelFilter::Trace)),
+                },
+                Some((module, level)) => level
+                    .trim()
+                    .parse()
+                    .ok()
+                    .map(|level| (Some(module.trim()), level)),
+            };
+            match parsed {
+                Some((module, level)) => self = self.filter(module, level),
+                None => eprintln!("ftlog: invalid logging spec '{}', ignoring it", directive),
+            }
+        }
+        if let Some(message) = message.filter(|x| !x.is_empty()) {
+            self.message_filter = Some(message.to_string());
+        }
+        self
+    }
+
+    /// Set level of records whose target starts with `module`, or of all records when
+    /// `module` is `None`
+    pub fn filter(mut self, module: Option<&str>, level: LevelFilter) -> EnvBuilder {
+        let module = module.map(|x| x.to_string());
+        self.directives.retain(|(x, _)| *x != module);
+        self.directives.push((module, level));
+        self
+    }
+
+    /// Set level of records whose target starts with `module`
+    pub fn filter_module(self, module: &str, level: LevelFilter) -> EnvBuilder {
+        self.filter(Some(module), level)
+    }
+
+    /// Set level of records not matched by module filters
+    pub fn filter_level(self, level: LevelFilter) -> EnvBuilder {
+        self.filter(None, level)
+    }
+
+    /// Set precision of UTC timestamp, or hide it with `None`
+    pub fn format_timestamp(mut self, precision: Option<TimestampPrecision>) -> EnvBuilder {
+        self.format.timestamp = precision;
+        self
+    }
+
+    pub fn format_timestamp_secs(self) -> EnvBuilder {
+        self.format_timestamp(Some(TimestampPrecision::Seconds))
+    }
+
+    pub fn format_timestamp_millis(self) -> EnvBuilder {
+        self.format_timestamp(Some(TimestampPrecision::Millis))
+    }
+
+    pub fn format_timestamp_micros(self) -> EnvBuilder {
+        self.format_timestamp(Some(TimestampPrecision::Micros))
+    }
+
+    pub fn format_timestamp_nanos(self) -> EnvBuilder {
+        self.format_timestamp(Some(TimestampPrecision::Nanos))
+    }
+
+    /// Whether to print level, defaults to `true`
+    pub fn format_level(mut self, write: bool) -> EnvBuilder {
+        self.format.level = write;
+        self
+    }
+
+    /// Whether to print module path, defaults to `false`
+    pub fn format_module_path(mut self, write: bool) -> EnvBuilder {
+        self.format.module_path = write;
+        self
+    }
+
+    /// Whether to print target, defaults to `true`
+    pub fn format_target(mut self, write: bool) -> EnvBuilder {
+        self.format.target = write;
+        self
+    }
+
+    /// Set write style, which has no effect as log lines are not colored
+    pub fn write_style(self, _style: WriteStyle) -> EnvBuilder {
+        self
+    }
+
+    /// Parse write style, which has no effect as log lines are not colored
+    pub fn parse_write_style(self, _style: &str) -> EnvBuilder {
+        self
+    }
+
+    /// Set destination of log lines, defaults to stderr
+    pub fn target(mut self, target: Target) -> EnvBuilder {
+        self.target = target;
+        self
+    }
+
+    /// Convert to a ftlog [`Builder`] with the filters, format and destination
+    pub fn into_builder(self) -> Builder {
+        let mut directives = self.directives;
+        if directives.is_empty() {
+            directives.push((None, LevelFilter::Error));
+        }
+        // the longest matching module wins
+        directives
+            .sort_by_key(|(module, _)| std::cmp::Reverse(module.as_ref().map_or(0, |x| x.len())));
+        let max_level = directives
+            .iter()
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or(LevelFilter::Off);
+
+        let mut builder = crate::builder()
+            .formatter(self.format)
+            .max_log_level(max_level)
+            .filter_fn(move |metadata| {
+                directives
+                    .iter()
+                    .find(|(module, _)| {
+                        module
+                            .as_ref()
+                            .is_none_or(|x| metadata.target().starts_with(x.as_str()))
+                    })
+                    .is_some_and(|(_, level)| metadata.level() <= *level)
+            });
+        builder = match self.target {
+            Target::Stdout => builder.root(stdout()),
+            Target::Stderr => builder.root(stderr()),
+            Target::Pipe(pipe) => builder.root(pipe),
+        };
+        if let Some(message) = self.message_filter {
+            builder = builder
+                .drop_filters(move |record| !record.args().to_string().contains(message.as_str()));
+        }
+        builder
+    }
+
+    /// Build and install as the global logger
+    pub fn try_init(self) -> Result<LoggerGuard, InitError> {
+        self.into_builder().try_init()
+    }
+
+    /// Build and install as the global logger
+    ///
+    /// # Panics
+    ///
+    /// Panics if a global logger is already set or the logger fails to build.
+    pub fn init(self) -> LoggerGuard {
+        self.try_init()
+            .expect("EnvBuilder::init should not be called after logger initialized")
+    }
+}
+
+/// Default format of env_logger, e.g. `[2023-06-14T03:13:26Z INFO  main] message`
+struct EnvFormat {
+    timestamp: Option<TimestampPrecision>,
+    level: bool,
+    module_path: bool,
+    target: bool,
+}
+
+fn timestamp_format(precision: TimestampPrecision) -> OwnedFormatItem {
+    let subsecond = match precision {
+        TimestampPrecision::Seconds => "",
+        TimestampPrecision::Millis => ".[subsecond digits:3]",
+        TimestampPrecision::Micros => ".[subsecond digits:6]",
+        TimestampPrecision::Nanos => ".[subsecond digits:9]",
+    };
+    time::format_description::parse_owned::<1>(&format!(
+        "[year]-[month]-[day]T[hour]:[minute]:[second]{}Z",
+        subsecond
+    ))
+    .unwrap()
+}
+
+impl RecordFormatter for EnvFormat {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let mut header = Vec::new();
+        if let Some(precision) = self.timestamp {
+            let time = record.time().to_offset(UtcOffset::UTC);
+            header.push(
+                time.format(&timestamp_format(precision))
+                    .unwrap_or_else(|_| time.to_string()),
+            );
+        }
+        if self.level {
+            header.push(format!("{:<5}", record.level()));
+        }
+        if self.module_path {
+            header.extend(record.module_path().map(|x| x.to_string()));
+        }
+        if self.target {
+            header.push(record.target().to_string());
+        }
+        if !header.is_empty() {
+            write!(buf, "[{}] ", header.join(" "))?;
+        }
+        write!(buf, "{}", record.args())?;
+        for (key, value) in record.key_values() {
+            write!(buf, " {}={}", key, value)?;
+        }
+        writeln!(buf)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_filters() {
+        let builder = EnvBuilder::new().parse_filters("warn,db=debug, net::tcp ,bad=loud/query");
+        assert_eq!(
+            builder.directives,
+            vec![
+                (None, LevelFilter::Warn),
+                (Some("db".to_string()), LevelFilter::Debug),
+                (Some("net::tcp".to_string()), LevelFilter::Trace),
+            ]
+        );
+        assert_eq!(builder.message_filter.as_deref(), Some("query"));
+
+        let builder = builder.parse_filters("db=off");
+        assert_eq!(
+            builder.directives.last(),
+            Some(&(Some("db".to_string()), LevelFilter::Off))
+        );
+        assert_eq!(builder.directives.len(), 3);
+    }
+}
@@ -0,0 +1,95 @@
+//! Logging errors with their causes
+//!
+//! [`error_chain!`](crate::error_chain) logs an error at `Error` level with the error
+//! and its whole chain of [`source`](std::error::Error::source) as key-values,
+//! instead of relying on its `Debug` output:
+//!
+//! - `error`: the error itself
+//! - `caused_by`: its sources joined with `: `, omitted without a source
+//! - `error_debug`: `Debug` output of the error, e.g. with the kind of an `io::Error`
+//! - `backtrace`: backtrace of the log call, only when enabled by `RUST_BACKTRACE`
+//!   or `RUST_LIB_BACKTRACE`, since errors do not expose their own backtrace on
+//!   stable Rust
+//!
+//! ```
+//! use std::io::{Error, ErrorKind};
+//!
+//! #[derive(Debug)]
+//! struct LoadError(Error);
+//! impl std::fmt::Display for LoadError {
+//!     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+//!         write!(f, "failed to load config")
+//!     }
+//! }
+//! impl std::error::Error for LoadError {
+//!     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+//!         Some(&self.0)
+//!     }
+//! }
+//!
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! let err = LoadError(Error::new(ErrorKind::NotFound, "no such file"));
+//! ftlog::error_chain!(err, "startup aborted");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms ERROR main [src/main.rs:19] error=failed to load config caused_by=no such file error_debug=LoadError(Custom { kind: NotFound, error: "no such file" }) startup aborted
+//!
+//! // boxed errors are dereferenced
+//! let boxed: Box<dyn std::error::Error> = Box::new(err);
+//! ftlog::error_chain!(*boxed, "startup aborted");
+//! ```
+//!
+//! [`error!`](crate::error!) does the same with `err = e;` before the message, e.g.
+//! `ftlog::error!(err = ?e; "startup aborted")`.
+//!
+//! Key-values are only printed with `kv` feature, which is enabled by default.
+//! [`ErrorChain`] renders the same chain in a message.
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::error::Error;
+use std::fmt::{Arguments, Display};
+
+use log::{Level, Record};
+
+/// Display an error followed by its sources, e.g. `failed to load config: no such file`
+pub struct ErrorChain<'a>(pub &'a (dyn Error + 'static));
+
+impl Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(err) = source {
+            write!(f, ": {}", err)?;
+            source = err.source();
+        }
+        Ok(())
+    }
+}
+
+#[doc(hidden)]
+pub fn log_error_chain(
+    err: &(dyn Error + 'static),
+    args: Arguments,
+    target: &str,
+    location: (&'static str, &'static str, u32),
+) {
+    let mut key_values = vec![("error", err.to_string())];
+    if let Some(source) = err.source() {
+        key_values.push(("caused_by", ErrorChain(source).to_string()));
+    }
+    key_values.push(("error_debug", format!("{:?}", err)));
+    let backtrace = Backtrace::capture();
+    if backtrace.status() == BacktraceStatus::Captured {
+        key_values.push(("backtrace", backtrace.to_string()));
+    }
+    let (module_path, file, line) = location;
+    log::logger().log(
+        &Record::builder()
+            .level(Level::Error)
+            .target(target)
+            .module_path_static(Some(module_path))
+            .file_static(Some(file))
+            .line(Some(line))
+            .key_values(&key_values)
+            .args(args)
+            .build(),
+    );
+}
@@ -0,0 +1,629 @@
+//! Custom layout of log lines
+//!
+//! [`FtLogFormat`](crate::FtLogFormat) only controls the message part of a log line,
+//! while timestamp, delay, sequence number and missed count are always prepended by
+//! log thread.
+//! Implement [`RecordFormatter`] and set it with
+//! [`Builder::formatter`](crate::Builder::formatter) to control the whole line.
+//! [`Builder::format_for_target`](crate::Builder::format_for_target) sets it for
+//! records of some targets only, and
+//! [`Builder::appender_formatter`](crate::Builder::appender_formatter) for lines
+//! written to some appenders only.
+//!
+//! Data of log record is collected at the log call and sent to log thread, the
+//! formatter runs in log thread(s).
+//!
+//! ```
+//! use std::io::Write;
+//!
+//! use ftlog::format::{LogRecord, RecordFormatter};
+//!
+//! struct Pipe;
+//! impl RecordFormatter for Pipe {
+//!     fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+//!         write!(
+//!             buf,
+//!             "{}|{}|{}|{}:{}|",
+//!             record.timestamp(),
+//!             record.level(),
+//!             record.thread().unwrap_or("-"),
+//!             record.file().unwrap_or("-"),
+//!             record.line().unwrap_or(0),
+//!         )?;
+//!         for (key, value) in record.key_values() {
+//!             write!(buf, "{}={} ", key, value)?;
+//!         }
+//!         writeln!(buf, "{}", record.args())
+//!     }
+//! }
+//!
+//! let _guard = ftlog::builder().formatter(Pipe).try_init().unwrap();
+//! log::info!(user = "alice"; "logged in");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08|INFO|main|src/main.rs:17|user=alice logged in
+//! ```
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::sync::{Arc, OnceLock};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+#[cfg(feature = "kv")]
+use log::kv::{Error as KvError, Key, Value, VisitSource};
+use log::{Level, Record};
+use time::{Date, Month, OffsetDateTime, Time, UtcOffset};
+
+use crate::intern::Symbol;
+use crate::worker::Renderer;
+
+mod ecs;
+mod pretty;
+mod structured;
+mod syslog;
+mod w3c;
+
+pub use ecs::EcsFormatter;
+pub use pretty::PrettyFormatter;
+pub use syslog::{Facility, Rfc3164Formatter};
+pub use w3c::W3cFormatter;
+
+/// Format a log record into a complete log line
+///
+/// Called in log thread(s), so the implementation should be `Send + Sync`.
+pub trait RecordFormatter: Send + Sync {
+    /// Write a log line to `buf`
+    ///
+    /// A trailing newline is appended if `buf` does not end with one, and nothing is
+    /// written when `buf` is left empty. On error the record is skipped.
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()>;
+
+    /// Whether output is binary, which is written as is without a trailing newline
+    fn binary(&self) -> bool {
+        false
+    }
+}
+
+/// Formats of the whole record
+///
+/// [`Format::Text`] writes the layout of the default format, other formats are
+/// structured.
+/// A record is encoded as a map of `time` formatted by the time format of builder,
+/// `seq`, `level`, `target`, `thread`, `file`, `line`, `message`, and `fields` holding
+/// key-values of the record. `seq`, `thread`, `file`, `line` and `fields` are omitted
+/// when absent or empty.
+///
+/// ```
+/// let _guard = ftlog::builder()
+///     .formatter(ftlog::format::Format::Json)
+///     .try_init()
+///     .unwrap();
+/// log::info!(user = "alice"; "logged in");
+/// // Output:
+/// // {"time":"2023-06-14 11:13:26.160+08","level":"INFO","target":"main","thread":"main","file":"src/main.rs","line":6,"message":"logged in","fields":{"user":"alice"}}
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Line of the default format, e.g. to switch back from JSON with
+    /// [`set_format`](crate::set_format)
+    Text,
+    /// JSON object per line
+    Json,
+    /// [MessagePack](https://msgpack.org) map per record, written back to back
+    /// without separators
+    MsgPack,
+    /// [CBOR](https://cbor.io) map per record, written back to back without separators
+    Cbor,
+}
+
+impl RecordFormatter for Format {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        match self {
+            Format::Text => text(record, buf),
+            format => structured::encode(*format, record, buf),
+        }
+    }
+
+    fn binary(&self) -> bool {
+        matches!(self, Format::MsgPack | Format::Cbor)
+    }
+}
+
+/// Write a record like the default format, without colors and abbreviated paths
+fn text(record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+    use std::io::Write;
+
+    write!(
+        buf,
+        "{} {}ms ",
+        record.timestamp(),
+        record.delay().as_millis()
+    )?;
+    if let Some(seq) = record.seq() {
+        write!(buf, "#{} ", seq)?;
+    }
+    if let Some(missed) = record.missed() {
+        write!(buf, "{} ", missed)?;
+    }
+    write!(buf, "{}", record.level())?;
+    if let Some(thread) = record.thread() {
+        write!(buf, " {}", thread)?;
+    }
+    if let Some(file) = record.file() {
+        write!(buf, " [{}:{}]", file, record.line().unwrap_or(0))?;
+    }
+    for (key, value) in record.key_values() {
+        write!(buf, " {}={}", key, value)?;
+    }
+    writeln!(buf, " {}", record.args())
+}
+
+/// Keys used by ftlog to control log calls, excluded from key-values of a record
+#[cfg(feature = "kv")]
+const RESERVED_KEYS: [&str; 3] = ["limit", "drop", "random_drop"];
+
+/// Data of a log record collected at the log call
+pub(crate) struct RecordFields {
+    pub(crate) module_path: Option<Symbol>,
+    pub(crate) file: Option<Cow<'static, str>>,
+    pub(crate) line: Option<u32>,
+    pub(crate) thread: Option<String>,
+    pub(crate) thread_id: ThreadId,
+    pub(crate) key_values: Vec<(String, FieldValue)>,
+    pub(crate) args: Cow<'static, str>,
+}
+
+/// Value of a key-value
+pub(crate) enum FieldValue {
+    Text(String),
+    /// value captured with serde, rendered as JSON when first read in log thread
+    #[cfg(feature = "serde")]
+    Json(serde_json::Value, OnceLock<String>),
+    /// value of [`Lazy::in_log_thread`], computed when first read in log thread
+    #[cfg_attr(not(feature = "kv"), allow(dead_code))]
+    Deferred(Arc<Thunk>, OnceLock<String>),
+}
+
+impl FieldValue {
+    #[inline]
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            FieldValue::Text(text) => text,
+            #[cfg(feature = "serde")]
+            FieldValue::Json(value, text) => text.get_or_init(|| value.to_string()),
+            FieldValue::Deferred(thunk, text) => text.get_or_init(|| (thunk.0)()),
+        }
+    }
+
+    /// Keep structs, maps and sequences captured with serde structured, and turn
+    /// other values into text
+    #[cfg(feature = "kv")]
+    fn new(value: Value) -> FieldValue {
+        if let Some(deferred) = value
+            .to_borrowed_error()
+            .and_then(|x| x.downcast_ref::<Deferred>())
+        {
+            return FieldValue::Deferred(deferred.0.clone(), OnceLock::new());
+        }
+        #[cfg(feature = "serde")]
+        if value.to_borrowed_str().is_none()
+            && value.to_i64().is_none()
+            && value.to_u64().is_none()
+            && value.to_f64().is_none()
+            && value.to_bool().is_none()
+        {
+            match serde_json::to_value(&value) {
+                Ok(json @ (serde_json::Value::Array(_) | serde_json::Value::Object(_))) => {
+                    return FieldValue::Json(json, OnceLock::new())
+                }
+                Ok(serde_json::Value::String(text)) => return FieldValue::Text(text),
+                _ => (),
+            }
+        }
+        FieldValue::Text(value.to_string())
+    }
+}
+
+impl Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Value of a key-value computed only for records passing level and filters, made
+/// with [`lazy`](crate::lazy)
+///
+/// The closure runs in the calling thread when the record is logged, or in log
+/// thread with [`Lazy::in_log_thread`].
+pub struct Lazy<F>(pub(crate) F);
+
+impl<F, T> Lazy<F>
+where
+    F: Fn() -> T + Send + Sync + 'static,
+    T: Display,
+{
+    /// Run the closure in log thread instead, keeping it off the calling thread
+    ///
+    /// Only values of key-values are deferred, a custom
+    /// [`FtLogFormat`](crate::FtLogFormat) reading the record still runs the closure
+    /// in the calling thread.
+    pub fn in_log_thread(self) -> Deferred {
+        let f = self.0;
+        Deferred(Arc::new(Thunk(Box::new(move || f().to_string()))))
+    }
+}
+
+impl<F, T> Display for Lazy<F>
+where
+    F: Fn() -> T,
+    T: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", (self.0)())
+    }
+}
+
+impl<F, T> log::kv::ToValue for Lazy<F>
+where
+    F: Fn() -> T,
+    T: Display,
+{
+    fn to_value(&self) -> log::kv::Value<'_> {
+        log::kv::Value::from_dyn_display(self)
+    }
+}
+
+/// [`Lazy`] value computed in log thread, see [`Lazy::in_log_thread`]
+#[derive(Clone)]
+pub struct Deferred(Arc<Thunk>);
+
+pub(crate) struct Thunk(Box<dyn Fn() -> String + Send + Sync>);
+
+impl Display for Deferred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&(self.0 .0)())
+    }
+}
+
+impl std::fmt::Debug for Deferred {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Deferred").finish_non_exhaustive()
+    }
+}
+
+// captured as an error, the only kind of value that can be downcast, to be moved
+// to log thread instead of being formatted
+impl std::error::Error for Deferred {}
+
+impl log::kv::ToValue for Deferred {
+    fn to_value(&self) -> log::kv::Value<'_> {
+        log::kv::Value::from_dyn_error(self)
+    }
+}
+
+/// Key-values of a log call, passed to [`Builder::scrub`](crate::Builder::scrub)
+///
+/// Keys are kept in the order of the log call. Values captured with serde keep their
+/// structure unless replaced or borrowed mutably.
+#[derive(Default)]
+pub struct KvMap(Vec<(String, FieldValue)>);
+
+impl KvMap {
+    pub(crate) fn new(#[allow(unused_variables)] record: &Record) -> KvMap {
+        #[allow(unused_mut)]
+        let mut key_values = Vec::new();
+        #[cfg(feature = "kv")]
+        visit_key_values(record, &mut key_values);
+        KvMap(key_values)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Value of `key` to modify in place, a structured value is turned into text
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut String> {
+        let value = &mut self.0.iter_mut().find(|(k, _)| k == key)?.1;
+        if !matches!(value, FieldValue::Text(_)) {
+            *value = FieldValue::Text(value.as_str().to_string());
+        }
+        match value {
+            FieldValue::Text(text) => Some(text),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Set `key` to `value`, replacing the value in place if `key` exists
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        let value = FieldValue::Text(value.into());
+        match self.0.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.0.push((key, value)),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let ix = self.0.iter().position(|(k, _)| k == key)?;
+        Some(self.0.remove(ix).1.as_str().to_string())
+    }
+
+    /// Keep only key-values for which `f` returns true
+    pub fn retain<F: FnMut(&str, &str) -> bool>(&mut self, mut f: F) {
+        self.0.retain(|(k, v)| f(k, v.as_str()));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl log::kv::Source for KvMap {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::VisitSource<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        for (key, value) in &self.0 {
+            let value = match value {
+                FieldValue::Text(text) => log::kv::Value::from(text.as_str()),
+                #[cfg(feature = "serde")]
+                FieldValue::Json(json, _) => log::kv::Value::from_serde(json),
+                FieldValue::Deferred(..) => log::kv::Value::from(value.as_str()),
+            };
+            visitor.visit_pair(log::kv::Key::from_str(key), value)?;
+        }
+        Ok(())
+    }
+}
+
+impl RecordFields {
+    pub(crate) fn new(record: &Record) -> Self {
+        #[allow(unused_mut)]
+        let mut key_values = crate::context::fields()
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), FieldValue::Text(v.to_string())))
+            .collect();
+        #[cfg(feature = "kv")]
+        visit_key_values(record, &mut key_values);
+        let thread = std::thread::current();
+        RecordFields {
+            module_path: record
+                .module_path_static()
+                .map(Symbol::from_static)
+                .or_else(|| record.module_path().map(Symbol::new)),
+            file: record
+                .file_static()
+                .map(Cow::Borrowed)
+                .or_else(|| record.file().map(|s| Cow::Owned(s.to_owned()))),
+            line: record.line(),
+            thread: thread.name().map(|n| n.to_string()),
+            thread_id: thread.id(),
+            key_values,
+            args: record
+                .args()
+                .as_str()
+                .map(Cow::Borrowed)
+                .unwrap_or_else(|| Cow::Owned(record.args().to_string())),
+        }
+    }
+}
+
+impl Display for RecordFields {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.args)
+    }
+}
+
+/// Append key-values of a log call to `key_values`, excluding those used by ftlog
+#[cfg(feature = "kv")]
+pub(crate) fn visit_key_values(record: &Record, key_values: &mut Vec<(String, FieldValue)>) {
+    struct Visitor<'a>(&'a mut Vec<(String, FieldValue)>);
+
+    impl<'kvs> VisitSource<'kvs> for Visitor<'_> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+            if !RESERVED_KEYS.contains(&key.as_str()) {
+                self.0.push((key.to_string(), FieldValue::new(value)));
+            }
+            Ok(())
+        }
+    }
+
+    let _ = record.key_values().visit(&mut Visitor(key_values));
+}
+
+/// A log record as seen by [`RecordFormatter`]
+pub struct LogRecord<'a> {
+    pub(crate) fields: &'a RecordFields,
+    pub(crate) level: Level,
+    pub(crate) target: &'a str,
+    pub(crate) time: OffsetDateTime,
+    pub(crate) renderer: &'a Renderer,
+    pub(crate) delay: Duration,
+    pub(crate) missed: Option<i64>,
+    pub(crate) seq: Option<u64>,
+}
+
+impl<'a> LogRecord<'a> {
+    /// Time of the log call, in timezone configured by builder
+    #[inline]
+    pub fn time(&self) -> OffsetDateTime {
+        self.time
+    }
+
+    /// Time of the log call formatted by the time format configured by builder
+    #[inline]
+    pub fn timestamp(&self) -> String {
+        self.renderer.format_time(&self.time)
+    }
+
+    /// Time between the log call and log thread receiving the record, always zero
+    /// with [`TimestampSource::LogThread`](crate::TimestampSource::LogThread)
+    #[inline]
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Number of records discarded by log interval limit since last written one,
+    /// `None` if the log call has no interval limit
+    #[inline]
+    pub fn missed(&self) -> Option<i64> {
+        self.missed
+    }
+
+    /// Sequence number of the record, `None` unless enabled by
+    /// [`Builder::sequence`](crate::Builder::sequence)
+    #[inline]
+    pub fn seq(&self) -> Option<u64> {
+        self.seq
+    }
+
+    #[inline]
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    #[inline]
+    pub fn target(&self) -> &'a str {
+        self.target
+    }
+
+    #[inline]
+    pub fn module_path(&self) -> Option<&'a str> {
+        self.fields.module_path.as_deref()
+    }
+
+    #[inline]
+    pub fn file(&self) -> Option<&'a str> {
+        self.fields.file.as_deref()
+    }
+
+    #[inline]
+    pub fn line(&self) -> Option<u32> {
+        self.fields.line
+    }
+
+    /// Name of the thread that made the log call
+    #[inline]
+    pub fn thread(&self) -> Option<&'a str> {
+        self.fields.thread.as_deref()
+    }
+
+    /// ID of the thread that made the log call
+    #[inline]
+    pub fn thread_id(&self) -> ThreadId {
+        self.fields.thread_id
+    }
+
+    /// Global fields set by [`Builder::global_field`](crate::Builder::global_field),
+    /// fields in [context](crate::context) of the calling thread, and key-values of
+    /// the log call excluding those used by ftlog like `limit`, in this order
+    ///
+    /// Key-values of log calls are only available with `kv` feature, which is
+    /// enabled by default.
+    /// Structs, maps and sequences captured with serde, like `key:serde = value`, are
+    /// rendered as JSON with `serde` feature.
+    #[inline]
+    pub fn key_values(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.renderer
+            .global_fields
+            .iter()
+            .map(|(k, v)| (*k, v.as_str()))
+            .chain(
+                self.fields
+                    .key_values
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str())),
+            )
+    }
+
+    /// The log message
+    #[inline]
+    pub fn args(&self) -> &'a str {
+        &self.fields.args
+    }
+}
+
+/// Timestamp at the start of `line`, or in the `time` field of a JSON line
+pub(crate) fn parse_time(line: &str, offset: UtcOffset) -> Option<OffsetDateTime> {
+    let text = if line.starts_with('{') {
+        let ix = line.find("\"time\":\"")?;
+        &line[ix + 8..]
+    } else {
+        line
+    };
+    let b = text.as_bytes();
+    let number = |range: std::ops::Range<usize>| -> Option<u32> {
+        let digits = b.get(range)?;
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        std::str::from_utf8(digits).ok()?.parse().ok()
+    };
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    if b.len() < 19
+        || !matches!(b[10], b' ' | b'T')
+        || separators.iter().any(|(ix, x)| b[*ix] != *x)
+    {
+        return None;
+    }
+    let date = Date::from_calendar_date(
+        number(0..4)? as i32,
+        Month::try_from(number(5..7)? as u8).ok()?,
+        number(8..10)? as u8,
+    )
+    .ok()?;
+    let mut ix = 19;
+    let mut nanos = 0;
+    if b.get(ix) == Some(&b'.') {
+        ix += 1;
+        let digits = b[ix..].iter().take_while(|x| x.is_ascii_digit()).count();
+        for (n, digit) in b[ix..ix + digits].iter().enumerate().take(9) {
+            nanos += (digit - b'0') as u32 * 10u32.pow(8 - n as u32);
+        }
+        ix += digits;
+    }
+    let time = Time::from_hms_nano(
+        number(11..13)? as u8,
+        number(14..16)? as u8,
+        number(17..19)? as u8,
+        nanos,
+    )
+    .ok()?;
+    let offset = match b.get(ix) {
+        Some(b'Z') => UtcOffset::UTC,
+        Some(sign @ (b'+' | b'-')) => {
+            let mut negative = *sign == b'-';
+            ix += 1;
+            // the default format prints negative offsets like `+-05`
+            if b.get(ix) == Some(&b'-') {
+                negative = true;
+                ix += 1;
+            }
+            let hours = number(ix..ix + 2)? as i8;
+            ix += 2;
+            if b.get(ix) == Some(&b':') {
+                ix += 1;
+            }
+            let minutes = number(ix..ix + 2).unwrap_or(0) as i8;
+            let offset = UtcOffset::from_hms(hours, minutes, 0).ok()?;
+            if negative {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => offset,
+    };
+    Some(date.with_time(time).assume_offset(offset))
+}
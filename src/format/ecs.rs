@@ -0,0 +1,98 @@
+//! Elastic Common Schema
+use std::io::Write;
+
+use time::format_description::well_known::Rfc3339;
+use time::UtcOffset;
+
+use super::structured::json_str;
+use super::{LogRecord, RecordFormatter};
+
+/// Version of ECS the fields follow
+const ECS_VERSION: &str = "8.11.0";
+
+/// JSON lines with field names of [Elastic Common Schema](https://www.elastic.co/guide/en/ecs/current/index.html)
+///
+/// Records are indexed by Elasticsearch and Kibana without ingest pipelines:
+///
+/// - `@timestamp`: time of the log call in UTC
+/// - `log.level`, `log.logger` (target), `message`
+/// - `log.origin.file.name`, `log.origin.file.line`, `process.thread.name` when known
+/// - `error.message` and `error.stack_trace` from key-values `error`, `caused_by`
+///   and `backtrace` logged by [`error_chain!`](crate::error_chain)
+/// - `labels`: other key-values
+///
+/// ```
+/// let _guard = ftlog::builder()
+///     .formatter(ftlog::format::EcsFormatter)
+///     .try_init()
+///     .unwrap();
+/// log::info!(user = "alice"; "logged in");
+/// // Output:
+/// // {"@timestamp":"2023-06-14T03:13:26.160123Z","log.level":"INFO","message":"logged in","ecs.version":"8.11.0","log.logger":"main","log.origin.file.name":"src/main.rs","log.origin.file.line":6,"process.thread.name":"main","labels":{"user":"alice"}}
+/// ```
+pub struct EcsFormatter;
+
+impl RecordFormatter for EcsFormatter {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let time = record
+            .time()
+            .to_offset(UtcOffset::UTC)
+            .format(&Rfc3339)
+            .map_err(std::io::Error::other)?;
+        buf.extend_from_slice(b"{\"@timestamp\":");
+        json_str(buf, &time)?;
+        buf.extend_from_slice(b",\"log.level\":");
+        json_str(buf, record.level().as_str())?;
+        buf.extend_from_slice(b",\"message\":");
+        json_str(buf, record.args())?;
+        write!(buf, ",\"ecs.version\":\"{}\",\"log.logger\":", ECS_VERSION)?;
+        json_str(buf, record.target())?;
+        if let Some(file) = record.file() {
+            buf.extend_from_slice(b",\"log.origin.file.name\":");
+            json_str(buf, file)?;
+        }
+        if let Some(line) = record.line() {
+            write!(buf, ",\"log.origin.file.line\":{}", line)?;
+        }
+        if let Some(thread) = record.thread() {
+            buf.extend_from_slice(b",\"process.thread.name\":");
+            json_str(buf, thread)?;
+        }
+
+        let (mut error, mut caused_by, mut backtrace) = (None, None, None);
+        let mut labels = Vec::new();
+        for (key, value) in record.key_values() {
+            match key {
+                "error" => error = Some(value),
+                "caused_by" => caused_by = Some(value),
+                "backtrace" => backtrace = Some(value),
+                _ => labels.push((key, value)),
+            }
+        }
+        if let Some(error) = error {
+            buf.extend_from_slice(b",\"error.message\":");
+            match caused_by {
+                Some(caused_by) => json_str(buf, &format!("{}: {}", error, caused_by))?,
+                None => json_str(buf, error)?,
+            }
+        }
+        if let Some(backtrace) = backtrace {
+            buf.extend_from_slice(b",\"error.stack_trace\":");
+            json_str(buf, backtrace)?;
+        }
+        if !labels.is_empty() {
+            buf.extend_from_slice(b",\"labels\":{");
+            for (i, (key, value)) in labels.into_iter().enumerate() {
+                if i > 0 {
+                    buf.push(b',');
+                }
+                json_str(buf, key)?;
+                buf.push(b':');
+                json_str(buf, value)?;
+            }
+            buf.push(b'}');
+        }
+        buf.extend_from_slice(b"}\n");
+        Ok(())
+    }
+}
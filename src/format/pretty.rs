@@ -0,0 +1,130 @@
+//! Multi-line layout for reading logs during development
+use std::io::Write;
+
+use super::{LogRecord, RecordFormatter};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+
+/// Records spread over several indented lines, for local development
+///
+/// The first line holds timestamp, level aligned to 5 characters, target and
+/// message, followed by the source location and thread, then each key-value on its
+/// own line. `caused_by` of [`error_chain!`](crate::error_chain) is split into one
+/// numbered line per source, and values or messages spanning lines are indented
+/// as a whole.
+///
+/// Levels are colored, and colors are removed for appenders which do not accept
+/// them, see [`Appender::colors`](crate::appender::Appender::colors). Use it for all
+/// appenders with [`Builder::formatter`](crate::Builder::formatter), or for the
+/// console only with [`Builder::appender_formatter`](crate::Builder::appender_formatter).
+///
+/// ```
+/// use ftlog::format::PrettyFormatter;
+///
+/// let _guard = ftlog::builder()
+///     .appender_formatter("root", PrettyFormatter::new())
+///     .try_init()
+///     .unwrap();
+/// log::info!(user = "alice", attempts = 3; "logged in");
+/// // Output:
+/// //   2023-06-14 11:13:26.160+08  INFO main: logged in
+/// //     at src/main.rs:8 on main
+/// //     user: alice
+/// //     attempts: 3
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PrettyFormatter {
+    color: bool,
+}
+
+impl Default for PrettyFormatter {
+    fn default() -> Self {
+        PrettyFormatter::new()
+    }
+}
+
+impl PrettyFormatter {
+    pub fn new() -> PrettyFormatter {
+        PrettyFormatter { color: true }
+    }
+
+    /// Write ANSI color codes, `true` by default
+    pub fn color(mut self, color: bool) -> PrettyFormatter {
+        self.color = color;
+        self
+    }
+
+    #[inline]
+    fn paint<'a>(&self, code: &'a str) -> (&'a str, &'static str) {
+        match self.color {
+            true => (code, RESET),
+            false => ("", ""),
+        }
+    }
+}
+
+/// Write `text`, indenting its lines after the first one by `indent` spaces
+fn write_indented(buf: &mut Vec<u8>, text: &str, indent: usize) -> std::io::Result<()> {
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            write!(buf, "\n{:indent$}", "", indent = indent)?;
+        }
+        buf.extend_from_slice(line.as_bytes());
+    }
+    Ok(())
+}
+
+impl RecordFormatter for PrettyFormatter {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let level = record.level();
+        let (level_on, level_off) = self.paint(crate::level_color(level));
+        let (bold, bold_off) = self.paint(BOLD);
+        let (dim, dim_off) = self.paint(DIM);
+        write!(
+            buf,
+            "  {} {}{:>5}{} {}{}:{} ",
+            record.timestamp(),
+            level_on,
+            level.as_str(),
+            level_off,
+            bold,
+            record.target(),
+            bold_off
+        )?;
+        write_indented(buf, record.args(), 4)?;
+        buf.push(b'\n');
+
+        let file = record.file();
+        let thread = record.thread();
+        if file.is_some() || thread.is_some() {
+            write!(buf, "    {}", dim)?;
+            if let Some(file) = file {
+                write!(buf, "at {}:{}", file, record.line().unwrap_or(0))?;
+            }
+            if let Some(thread) = thread {
+                let sep = if file.is_some() { " " } else { "" };
+                write!(buf, "{}on {}", sep, thread)?;
+            }
+            writeln!(buf, "{}", dim_off)?;
+        }
+
+        for (key, value) in record.key_values() {
+            write!(buf, "    {}{}{}:", bold, key, bold_off)?;
+            if key == "caused_by" {
+                for (i, cause) in value.split(": ").enumerate() {
+                    write!(buf, "\n      {:>2}: ", i)?;
+                    write_indented(buf, cause, 10)?;
+                }
+            } else if value.contains('\n') {
+                write!(buf, "\n      ")?;
+                write_indented(buf, value, 6)?;
+            } else {
+                write!(buf, " {}", value)?;
+            }
+            buf.push(b'\n');
+        }
+        Ok(())
+    }
+}
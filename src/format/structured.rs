@@ -0,0 +1,213 @@
+//! Encoders of structured records
+//!
+//! A record is encoded as a map of `time`, `seq`, `level`, `target`, `thread`,
+//! `file`, `line`, `message` and `fields`, where `fields` is a map of key-values.
+//! Absent values like `thread` and empty `fields` are omitted.
+use std::io::Write;
+
+use super::{Format, LogRecord};
+
+/// Value in a structured record
+enum Field<'a> {
+    Str(&'a str),
+    Uint(u64),
+    Map(Vec<(&'a str, &'a str)>),
+}
+
+fn fields<'a>(record: &'a LogRecord<'a>, time: &'a str) -> Vec<(&'static str, Field<'a>)> {
+    let mut fields = vec![("time", Field::Str(time))];
+    if let Some(seq) = record.seq() {
+        fields.push(("seq", Field::Uint(seq)));
+    }
+    fields.push(("level", Field::Str(record.level().as_str())));
+    fields.push(("target", Field::Str(record.target())));
+    if let Some(thread) = record.thread() {
+        fields.push(("thread", Field::Str(thread)));
+    }
+    if let Some(file) = record.file() {
+        fields.push(("file", Field::Str(file)));
+    }
+    if let Some(line) = record.line() {
+        fields.push(("line", Field::Uint(line as u64)));
+    }
+    fields.push(("message", Field::Str(record.args())));
+    let key_values = record.key_values().collect::<Vec<_>>();
+    if !key_values.is_empty() {
+        fields.push(("fields", Field::Map(key_values)));
+    }
+    fields
+}
+
+pub(super) fn encode(
+    format: Format,
+    record: &LogRecord<'_>,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<()> {
+    let time = record.timestamp();
+    let fields = fields(record, &time);
+    match format {
+        Format::Text => unreachable!("text is not a structured format"),
+        Format::Json => json(&fields, buf),
+        Format::MsgPack => {
+            msgpack_map(buf, fields.len());
+            for (key, value) in &fields {
+                msgpack_str(buf, key);
+                match value {
+                    Field::Str(x) => msgpack_str(buf, x),
+                    Field::Uint(x) => {
+                        buf.push(0xcf);
+                        buf.extend_from_slice(&x.to_be_bytes());
+                    }
+                    Field::Map(kvs) => {
+                        msgpack_map(buf, kvs.len());
+                        for (k, v) in kvs {
+                            msgpack_str(buf, k);
+                            msgpack_str(buf, v);
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        Format::Cbor => {
+            cbor_head(buf, 5, fields.len() as u64);
+            for (key, value) in &fields {
+                cbor_str(buf, key);
+                match value {
+                    Field::Str(x) => cbor_str(buf, x),
+                    Field::Uint(x) => cbor_head(buf, 0, *x),
+                    Field::Map(kvs) => {
+                        cbor_head(buf, 5, kvs.len() as u64);
+                        for (k, v) in kvs {
+                            cbor_str(buf, k);
+                            cbor_str(buf, v);
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn json(fields: &[(&str, Field<'_>)], buf: &mut Vec<u8>) -> std::io::Result<()> {
+    buf.push(b'{');
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            buf.push(b',');
+        }
+        json_str(buf, key)?;
+        buf.push(b':');
+        match value {
+            Field::Str(x) => json_str(buf, x)?,
+            Field::Uint(x) => write!(buf, "{}", x)?,
+            Field::Map(kvs) => {
+                buf.push(b'{');
+                for (j, (k, v)) in kvs.iter().enumerate() {
+                    if j > 0 {
+                        buf.push(b',');
+                    }
+                    json_str(buf, k)?;
+                    buf.push(b':');
+                    json_str(buf, v)?;
+                }
+                buf.push(b'}');
+            }
+        }
+    }
+    buf.extend_from_slice(b"}\n");
+    Ok(())
+}
+
+pub(super) fn json_str(buf: &mut Vec<u8>, s: &str) -> std::io::Result<()> {
+    buf.push(b'"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.extend_from_slice(b"\\\""),
+            '\\' => buf.extend_from_slice(b"\\\\"),
+            '\n' => buf.extend_from_slice(b"\\n"),
+            '\r' => buf.extend_from_slice(b"\\r"),
+            '\t' => buf.extend_from_slice(b"\\t"),
+            c if (c as u32) < 0x20 => write!(buf, "\\u{:04x}", c as u32)?,
+            c => {
+                let mut utf8 = [0; 4];
+                buf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            }
+        }
+    }
+    buf.push(b'"');
+    Ok(())
+}
+
+fn msgpack_map(buf: &mut Vec<u8>, len: usize) {
+    if len < 16 {
+        buf.push(0x80 | len as u8);
+    } else if len <= u16::MAX as usize {
+        buf.push(0xde);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(0xdf);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+fn msgpack_str(buf: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    if len < 32 {
+        buf.push(0xa0 | len as u8);
+    } else if len <= u8::MAX as usize {
+        buf.extend_from_slice(&[0xd9, len as u8]);
+    } else if len <= u16::MAX as usize {
+        buf.push(0xda);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(0xdb);
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// Initial bytes of a data item with major type `major` and argument `value`
+fn cbor_head(buf: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        buf.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        buf.extend_from_slice(&[major | 24, value as u8]);
+    } else if value <= u16::MAX as u64 {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn cbor_str(buf: &mut Vec<u8>, s: &str) {
+    cbor_head(buf, 3, s.len() as u64);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encode_lengths() {
+        let mut buf = Vec::new();
+        msgpack_str(&mut buf, &"a".repeat(40));
+        assert_eq!(buf[..2], [0xd9, 40]);
+        buf.clear();
+        msgpack_map(&mut buf, 20);
+        assert_eq!(buf, [0xde, 0, 20]);
+        buf.clear();
+        cbor_head(&mut buf, 3, 500);
+        assert_eq!(buf, [0x79, 0x01, 0xf4]);
+        buf.clear();
+        json_str(&mut buf, "a\"b\n\u{1}").unwrap();
+        assert_eq!(buf, br#""a\"b\n\u0001""#);
+    }
+}
@@ -0,0 +1,165 @@
+//! Classic BSD syslog lines
+use std::io::Write;
+use std::net::IpAddr;
+
+use log::Level;
+use time::format_description::OwnedFormatItem;
+
+use super::{LogRecord, RecordFormatter};
+
+/// Syslog facility, the source of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+/// Old style syslog lines of [RFC 3164](https://www.rfc-editor.org/rfc/rfc3164),
+/// e.g. `<14>Jun 14 11:13:26 web01 billing[4242]: logged in user=alice`
+///
+/// For collectors that cannot parse RFC 5424. The timestamp is in the timezone
+/// configured by builder, without year as the RFC requires. Newlines in messages
+/// are escaped as `#012` like rsyslog does, so a record always takes one line.
+///
+/// ```
+/// use ftlog::format::{Facility, Rfc3164Formatter};
+///
+/// let _guard = ftlog::builder()
+///     .formatter(Rfc3164Formatter::new().facility(Facility::Local0).tag("billing"))
+///     .try_init()
+///     .unwrap();
+/// log::info!("logged in");
+/// // Output:
+/// // <134>Jun 14 11:13:26 web01 billing[4242]: logged in
+/// ```
+pub struct Rfc3164Formatter {
+    facility: Facility,
+    hostname: String,
+    tag: String,
+    pid: u32,
+    timestamp: OwnedFormatItem,
+}
+
+impl Default for Rfc3164Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rfc3164Formatter {
+    /// Facility `user`, hostname of the machine and name of the executable as tag
+    pub fn new() -> Rfc3164Formatter {
+        let tag = std::env::current_exe()
+            .ok()
+            .and_then(|x| x.file_stem().map(|x| x.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "ftlog".to_string());
+        Rfc3164Formatter {
+            facility: Facility::User,
+            hostname: hostname(),
+            tag: String::new(),
+            pid: std::process::id(),
+            timestamp: time::format_description::parse_owned::<1>(
+                "[month repr:short] [day padding:space] [hour]:[minute]:[second]",
+            )
+            .unwrap(),
+        }
+        .tag(&tag)
+    }
+
+    pub fn facility(mut self, facility: Facility) -> Rfc3164Formatter {
+        self.facility = facility;
+        self
+    }
+
+    /// Override the detected hostname
+    pub fn hostname(mut self, hostname: &str) -> Rfc3164Formatter {
+        self.hostname = hostname.to_string();
+        self
+    }
+
+    /// Set program name before pid, only alphanumeric characters and `-_.` of the
+    /// first 32 characters are kept
+    pub fn tag(mut self, tag: &str) -> Rfc3164Formatter {
+        self.tag = tag
+            .chars()
+            .take(32)
+            .filter(|c| c.is_ascii_alphanumeric() || "-_.".contains(*c))
+            .collect();
+        self
+    }
+}
+
+/// Hostname without domain, which RFC 3164 requires
+fn hostname() -> String {
+    let name = crate::hostname().unwrap_or_else(|| "localhost".to_string());
+    if name.parse::<IpAddr>().is_ok() {
+        return name;
+    }
+    match name.split_once('.') {
+        Some((host, _)) => host.to_string(),
+        None => name,
+    }
+}
+
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Write `s` with newlines escaped as `#012`
+fn write_escaped(buf: &mut Vec<u8>, s: &str) {
+    for (i, part) in s.split('\n').enumerate() {
+        if i > 0 {
+            buf.extend_from_slice(b"#012");
+        }
+        buf.extend_from_slice(part.as_bytes());
+    }
+}
+
+impl RecordFormatter for Rfc3164Formatter {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let timestamp = record
+            .time()
+            .format(&self.timestamp)
+            .map_err(std::io::Error::other)?;
+        write!(
+            buf,
+            "<{}>{} {} {}[{}]: ",
+            self.facility as u8 * 8 + severity(record.level()),
+            timestamp,
+            self.hostname,
+            self.tag,
+            self.pid
+        )?;
+        write_escaped(buf, record.args());
+        for (key, value) in record.key_values() {
+            write!(buf, " {}=", key)?;
+            write_escaped(buf, value);
+        }
+        buf.push(b'\n');
+        Ok(())
+    }
+}
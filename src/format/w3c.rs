@@ -0,0 +1,135 @@
+//! W3C Extended Log File Format
+use std::io::Write;
+
+use time::{OffsetDateTime, UtcOffset};
+
+use super::{LogRecord, RecordFormatter};
+
+/// Lines of [W3C Extended Log File Format](https://www.w3.org/TR/WD-logfile.html)
+///
+/// Fields are listed in the `#Fields` directive of the file header, which is
+/// made by [`W3cFormatter::header`] and set with
+/// [`FileAppenderBuilder::header`](crate::appender::file::FileAppenderBuilder) so
+/// that each rotated file starts with it. Fields are filled by:
+///
+/// - `date` and `time`: time of the log call in UTC, as the format requires
+/// - `x-level`, `x-target`, `x-thread` and `x-message`: level, target, thread name
+///   and message of the record
+/// - any other field, like `cs-method` or `sc-status`: the key-value of that name
+///
+/// Missing values are written as `-`. Values with whitespace or quotes are quoted,
+/// with quotes doubled.
+///
+/// ```no_run
+/// use ftlog::appender::{FileAppender, Period};
+/// use ftlog::format::W3cFormatter;
+///
+/// let format = W3cFormatter::new(["date", "time", "cs-method", "cs-uri-stem", "sc-status"]);
+/// let appender = FileAppender::builder()
+///     .path("./access.log")
+///     .rotate(Period::Day)
+///     .header(format.header())
+///     .build();
+/// let _guard = ftlog::builder()
+///     .formatter(format)
+///     .root(appender)
+///     .try_init()
+///     .unwrap();
+/// log::info!(cs_method = "GET", cs_uri_stem = "/index.html", sc_status = 200; "");
+/// // Output:
+/// // #Version: 1.0
+/// // #Date: 2023-06-14 03:13:26
+/// // #Fields: date time cs-method cs-uri-stem sc-status
+/// // 2023-06-14 03:13:26 GET /index.html 200
+/// ```
+///
+/// Keys of key-values can not contain `-`, so `_` in keys matches `-` in field names.
+#[derive(Clone)]
+pub struct W3cFormatter {
+    fields: Vec<String>,
+}
+
+impl W3cFormatter {
+    pub fn new<I, S>(fields: I) -> W3cFormatter
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        W3cFormatter {
+            fields: fields.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Make the `#Version`, `#Date` and `#Fields` directives of a new file
+    pub fn header(&self) -> impl Fn() -> String + Send + Sync + 'static {
+        let fields = self.fields.join(" ");
+        move || {
+            let now = OffsetDateTime::now_utc();
+            format!(
+                "#Version: 1.0\n#Date: {} {}\n#Fields: {}\n",
+                now.date(),
+                time_of(&now),
+                fields
+            )
+        }
+    }
+}
+
+/// `hh:mm:ss` of `datetime`
+fn time_of(datetime: &OffsetDateTime) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        datetime.hour(),
+        datetime.minute(),
+        datetime.second()
+    )
+}
+
+fn write_value(buf: &mut Vec<u8>, value: &str) {
+    if value.is_empty() {
+        buf.push(b'-');
+    } else if value.contains(|c: char| c.is_whitespace() || c == '"') {
+        buf.push(b'"');
+        for c in value.chars() {
+            match c {
+                '"' => buf.extend_from_slice(b"\"\""),
+                c if c.is_control() => buf.push(b' '),
+                c => {
+                    let mut utf8 = [0; 4];
+                    buf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+            }
+        }
+        buf.push(b'"');
+    } else {
+        buf.extend_from_slice(value.as_bytes());
+    }
+}
+
+impl RecordFormatter for W3cFormatter {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        let time = record.time().to_offset(UtcOffset::UTC);
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                buf.push(b' ');
+            }
+            match field.as_str() {
+                "date" => write!(buf, "{}", time.date())?,
+                "time" => buf.extend_from_slice(time_of(&time).as_bytes()),
+                "x-level" => buf.extend_from_slice(record.level().as_str().as_bytes()),
+                "x-target" => write_value(buf, record.target()),
+                "x-thread" => write_value(buf, record.thread().unwrap_or_default()),
+                "x-message" => write_value(buf, record.args()),
+                field => {
+                    let value = record
+                        .key_values()
+                        .find(|(key, _)| key.replace('_', "-") == field)
+                        .map(|(_, value)| value);
+                    write_value(buf, value.unwrap_or_default());
+                }
+            }
+        }
+        buf.push(b'\n');
+        Ok(())
+    }
+}
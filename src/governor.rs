@@ -0,0 +1,166 @@
+//! Adaptive level under backpressure
+//!
+//! When producers outpace log thread for a long time, a bounded channel fills up and
+//! records are dropped indiscriminately, or log calls block. A [`Governor`] set by
+//! [`Builder::governor`](crate::Builder::governor) sheds verbose records first
+//! instead: once the queue stays above a high-water mark for a while, records less
+//! severe than the governor's level are dropped at call site. The level is restored
+//! after the queue stays below a low-water mark for the same duration.
+//!
+//! ```
+//! use std::time::Duration;
+//! use ftlog::governor::Governor;
+//! use ftlog::LevelFilter;
+//!
+//! let _guard = ftlog::builder()
+//!     .max_log_level(LevelFilter::Debug)
+//!     // drop Debug records while more than 10k records are queued for 5 seconds
+//!     .governor(Governor::new(10_000, Duration::from_secs(5), LevelFilter::Info))
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! Each transition is logged with target `ftlog::governor`, a `Warn` record when the
+//! level is raised and an `Info` record when it is restored. The queue is checked by
+//! a background thread, and the record is logged by the next log call after the
+//! transition. Levels set by [`set_level`](crate::set_level) and
+//! [`set_target_level`](crate::set_target_level) are kept, the governor only caps them.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use log::{Level, LevelFilter};
+
+use crate::{AtomicLevel, Shared};
+
+/// Configuration of the adaptive level, see [module](self) docs
+#[derive(Debug, Clone)]
+pub struct Governor {
+    high_water: usize,
+    low_water: usize,
+    sustain: Duration,
+    level: LevelFilter,
+}
+
+impl Governor {
+    /// Drop records less severe than `level` once more than `high_water` records are
+    /// queued for `sustain`
+    ///
+    /// The level is restored once at most half of `high_water` records are queued
+    /// for `sustain`, see [`Governor::low_water`].
+    pub fn new(high_water: usize, sustain: Duration, level: LevelFilter) -> Governor {
+        Governor {
+            high_water,
+            low_water: high_water / 2,
+            sustain,
+            level,
+        }
+    }
+
+    /// Restore the level once at most `low_water` records are queued for the sustain
+    /// duration
+    pub fn low_water(mut self, low_water: usize) -> Governor {
+        self.low_water = low_water.min(self.high_water);
+        self
+    }
+}
+
+/// Cap of levels set by the governor, and transitions waiting to be logged
+pub(crate) struct Throttle {
+    cap: AtomicLevel,
+    pending: AtomicBool,
+    notices: Mutex<Vec<(Level, String)>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Throttle {
+            cap: AtomicLevel::new(LevelFilter::Trace),
+            pending: AtomicBool::new(false),
+            notices: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Throttle {
+    #[inline]
+    pub(crate) fn cap(&self) -> LevelFilter {
+        self.cap.get()
+    }
+
+    /// Whether there are transitions to log
+    #[inline]
+    pub(crate) fn pending(&self) -> bool {
+        self.pending.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn take(&self) -> Vec<(Level, String)> {
+        let mut notices = self.notices.lock().unwrap_or_else(|e| e.into_inner());
+        self.pending.store(false, Ordering::Relaxed);
+        std::mem::take(&mut *notices)
+    }
+
+    fn set(&self, cap: LevelFilter, level: Level, notice: String) {
+        self.cap.0.store(cap as usize, Ordering::Relaxed);
+        let mut notices = self.notices.lock().unwrap_or_else(|e| e.into_inner());
+        notices.push((level, notice));
+        self.pending.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Watch the queue of `shared` in a background thread, switching its cap
+pub(crate) fn spawn(shared: &Arc<Shared>, governor: Governor) -> std::io::Result<()> {
+    let shared: Weak<Shared> = Arc::downgrade(shared);
+    let poll = (governor.sustain / 10).clamp(Duration::from_millis(10), Duration::from_secs(1));
+    std::thread::Builder::new()
+        .name("logger-governor".to_string())
+        .spawn(move || {
+            let mut raised = false;
+            // since when the queue is past the mark of the next transition
+            let mut since: Option<Instant> = None;
+            loop {
+                std::thread::sleep(poll);
+                let Some(shared) = shared.upgrade() else {
+                    return;
+                };
+                if shared.closed.load(Ordering::Relaxed) {
+                    return;
+                }
+                let depth = shared.queue.len();
+                let past = match raised {
+                    false => depth > governor.high_water,
+                    true => depth <= governor.low_water,
+                };
+                if !past {
+                    since = None;
+                    continue;
+                }
+                if since.get_or_insert_with(Instant::now).elapsed() < governor.sustain {
+                    continue;
+                }
+                since = None;
+                raised = !raised;
+                if raised {
+                    shared.throttle.set(
+                        governor.level,
+                        Level::Warn,
+                        format!(
+                            "log queue stayed above {} records for {:?}, dropping records below {}",
+                            governor.high_water, governor.sustain, governor.level
+                        ),
+                    );
+                } else {
+                    shared.throttle.set(
+                        LevelFilter::Trace,
+                        Level::Info,
+                        format!(
+                            "log queue stayed below {} records for {:?}, restoring records below {}",
+                            governor.low_water, governor.sustain, governor.level
+                        ),
+                    );
+                }
+                shared.update_max_level();
+            }
+        })?;
+    Ok(())
+}
@@ -0,0 +1,164 @@
+//! Interned targets and module paths
+//!
+//! Every record carries its target, and its module path for `RecordFormatter`, to log
+//! thread. Most are the same few strings logged millions of times, so instead of
+//! copying them per record, they are interned into small IDs at call site the first
+//! time they are seen and resolved back to text in log thread. Lookups are lock-free;
+//! only the first record of a new string takes a lock.
+//!
+//! Strings that are not `'static`, e.g. targets built at runtime, are leaked when
+//! interned. At most [`CAPACITY`] strings are interned, later ones are copied per
+//! record as before, so a program making up targets endlessly does not leak memory.
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use arc_swap::ArcSwap;
+use hashbrown::HashMap;
+
+/// Maximum number of distinct strings interned
+const CAPACITY: usize = 1024;
+
+/// Interned strings by ID, set once before their ID is published
+static NAMES: [OnceLock<&'static str>; CAPACITY] = [const { OnceLock::new() }; CAPACITY];
+
+thread_local! {
+    // IDs of static strings by address and length, skipping hashing of the text
+    static STATIC_IDS: RefCell<HashMap<(usize, usize), u32>> = RefCell::new(HashMap::new());
+    // ID of the string this thread resolved last, as log calls mostly repeat it
+    static LAST_ID: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// ID of `name` if it is the string this thread resolved last
+#[inline]
+fn last(name: &str) -> Option<u32> {
+    let id = LAST_ID.try_with(Cell::get).ok().flatten()?;
+    (NAMES[id as usize].get().copied() == Some(name)).then_some(id)
+}
+
+/// Symbol of `id`, remembered as the string this thread resolved last
+#[inline]
+fn remember(id: u32) -> Symbol {
+    let _ = LAST_ID.try_with(|x| x.set(Some(id)));
+    Symbol::Interned(id)
+}
+
+struct Interner {
+    ids: ArcSwap<HashMap<&'static str, u32>>,
+    /// serializes insertions, which copy the map
+    insert: Mutex<()>,
+}
+
+fn interner() -> &'static Interner {
+    static INTERNER: OnceLock<Interner> = OnceLock::new();
+    INTERNER.get_or_init(|| Interner {
+        ids: ArcSwap::from_pointee(HashMap::new()),
+        insert: Mutex::new(()),
+    })
+}
+
+/// ID of `name`, interning it with `leak` if new, or `None` once the table is full
+fn intern<F: FnOnce() -> &'static str>(name: &str, leak: F) -> Option<u32> {
+    let interner = interner();
+    if let Some(id) = interner.ids.load().get(name) {
+        return Some(*id);
+    }
+    let _guard = interner.insert.lock().unwrap_or_else(|e| e.into_inner());
+    let ids = interner.ids.load();
+    if let Some(id) = ids.get(name) {
+        return Some(*id);
+    }
+    if ids.len() >= CAPACITY {
+        return None;
+    }
+    let mut ids = HashMap::clone(&ids);
+    let name = leak();
+    let id = ids.len() as u32;
+    let _ = NAMES[id as usize].set(name);
+    ids.insert(name, id);
+    interner.ids.store(Arc::new(ids));
+    Some(id)
+}
+
+/// A target or module path sent to log thread, dereferencing to the string
+pub(crate) enum Symbol {
+    Interned(u32),
+    /// copied, as too many strings are interned already
+    Owned(Box<str>),
+}
+
+impl Symbol {
+    #[inline]
+    pub(crate) fn from_static(name: &'static str) -> Symbol {
+        if let Some(id) = last(name) {
+            return Symbol::Interned(id);
+        }
+        let key = (name.as_ptr() as usize, name.len());
+        let cached = STATIC_IDS
+            .try_with(|ids| ids.borrow().get(&key).copied())
+            .ok()
+            .flatten();
+        if let Some(id) = cached {
+            return remember(id);
+        }
+        match intern(name, || name) {
+            Some(id) => {
+                let _ = STATIC_IDS.try_with(|ids| ids.borrow_mut().insert(key, id));
+                remember(id)
+            }
+            None => Symbol::Owned(name.into()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn new(name: &str) -> Symbol {
+        if let Some(id) = last(name) {
+            return Symbol::Interned(id);
+        }
+        match intern(name, || Box::leak(name.into())) {
+            Some(id) => remember(id),
+            None => Symbol::Owned(name.into()),
+        }
+    }
+}
+
+impl Symbol {
+    /// The string, copied unless interned
+    pub(crate) fn to_cow(&self) -> Cow<'static, str> {
+        match self {
+            Symbol::Interned(id) => {
+                Cow::Borrowed(NAMES[*id as usize].get().copied().unwrap_or_default())
+            }
+            Symbol::Owned(name) => Cow::Owned(name.to_string()),
+        }
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        match self {
+            Symbol::Interned(id) => NAMES[*id as usize].get().copied().unwrap_or_default(),
+            Symbol::Owned(name) => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intern_once() {
+        let dynamic = String::from("app::intern::dynamic");
+        let a = Symbol::new(&dynamic);
+        let b = Symbol::new("app::intern::dynamic");
+        let c = Symbol::from_static("app::intern::static");
+        assert!(matches!((&a, &b), (Symbol::Interned(x), Symbol::Interned(y)) if x == y));
+        assert_eq!(&*a, "app::intern::dynamic");
+        assert_eq!(&*c, "app::intern::static");
+    }
+}
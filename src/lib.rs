@@ -31,7 +31,7 @@
 //!
 //! // minimal configuration with default setting
 //!
-//! // When drops, the guard calls and waits `flush` to logger.
+//! // When drops, the guard calls and waits `flush` to logger, then stops the log thread.
 //! // With guard that share the lifetime of `main` fn, there is no need to manually call flush at the end of `main` fn.
 //! let _guard = ftlog::builder().try_init().unwrap();
 //!
@@ -110,6 +110,26 @@
 //! Only shown if the frequency of logging for a single log call is limited (e.g.
 //! `log::info!(limit=3000i64;"msg")`).
 //!
+//! Fields attached to current thread by [`context::insert`] are printed right before the
+//! message, like `request_id=42 My log message`.
+//!
+//! To control the layout of the whole line, including timestamp and key-values
+//! of log calls, implement [`format::RecordFormatter`] and set it with
+//! [`Builder::formatter`].
+//!
+//! [`format::Format`] sets structured output for log collectors: JSON lines,
+//! or MessagePack and CBOR maps that skip the cost of parsing JSON.
+//! [`format::EcsFormatter`] writes JSON lines with field names of Elastic Common Schema.
+//! [`format::Rfc3164Formatter`] writes old style syslog lines for legacy collectors.
+//! [`format::W3cFormatter`] writes W3C Extended Log File Format, with a header for each file.
+//! [`access_log!`] with [`access::CombinedLogFormatter`] writes access logs of Apache and Nginx.
+//!
+//! For `no_std` firmware, the `ftlog-core` crate encodes records of the same binary format
+//! at call site and streams them to a pluggable sink, like UART or RTT.
+//!
+//! For extreme volume of logs, [`binary::BinaryFormat`] writes compact binary records
+//! instead of text lines, which are converted back to text by the `ftlog-decode` binary.
+//!
 //! ## Randomly drop log
 //!
 //! Use `random_drop` or `drop` to specify the probability of randomly discarding logs.
@@ -130,14 +150,33 @@
 //! );
 //! ```
 //!
+//! To keep only a fraction of verbose records, use `Builder::sample`. Sampling is
+//! decided at call site, so dropped records are never formatted or sent to log thread:
+//! ```rust
+//! # #[cfg(feature = "random_drop")]
+//! let _builder = ftlog::builder()
+//!     // keeps 1% of Debug and Trace records
+//!     .sample(log::LevelFilter::Debug, 0.01);
+//! ```
+//!
 //! ## Custom timestamp format
 //!
 //! `ftlog` relies on the `time` crate for the formatting of timestamp. To use custom time format,
 //! first construct a valid time format description,
 //! and then pass it to ftlog builder by `ftlog::time_format(&mut self)`.
+//! RFC3339 and time since unix epoch are also available through [`TimeFormat`].
+//!
+//! Timestamps have millisecond resolution by default. When millions of messages are
+//! logged per second, use [`Builder::time_precision`] to print microseconds or
+//! nanoseconds instead. The precision applies to the default format, RFC3339 and
+//! unix epoch, while custom format description decides its own subsecond digits.
 //!
 //! In case an error occurs when formatting timestamp, `ftlog` will fallback to RFC3339 time format.
 //!
+//! Timestamps are taken when log macros are called by default. With
+//! [`TimestampSource::LogThread`], they are taken by log thread instead, which saves
+//! reading the clock at call site but lags behind the event under load.
+//!
 //! ### Example
 //! ```rust
 //! let format = time::format_description::parse_owned::<1>(
@@ -150,6 +189,15 @@
 //! // 2023/06/14 11:13:26.160840 0ms INFO main [main.rs:3] Log with custom timestamp format
 //! ```
 //!
+//! Format description can also be checked at compile time with `time` crate's
+//! `macros` feature:
+//! ```rust
+//! # use ftlog::TimeFormat;
+//! let _builder = ftlog::builder()
+//!     .time_format(time::macros::format_description!("[hour]:[minute]:[second]"));
+//! let _builder = ftlog::builder().time_format(TimeFormat::UnixEpoch);
+//! ```
+//!
 //! ## Log with interval
 //!
 //! `ftlog` allows to limit the write frequency for individual log calls.
@@ -175,6 +223,10 @@
 //! ```
 //! The number **2** above shows how many log messages is discarded since last log.
 //!
+//! Interval limit is checked in log thread. To skip records at call site so that hot
+//! loops do not flood the channel, use `log_every_n!`, `log_at_most_every!` or
+//! `Builder::default_rate_limit`, see [`rate_limit`].
+//!
 //! ## Log rotation
 //! `ftlog` supports log rotation in local timezone. The available rotation
 //! periods are:
@@ -253,6 +305,41 @@
 //! let _guard = logger.init().unwrap();
 //! ```
 //!
+//! ## Standalone logger
+//!
+//! A `Logger` that is not installed as the global logger runs its own log thread and
+//! appenders, e.g. for an access log beside the application log. Log to it with
+//! `log_to!`, or use it as any other `log::Log` implementation. Queued records are
+//! written and appenders are flushed when the logger is dropped.
+//!
+//! ```rust
+//! use ftlog::{appender::FileAppender, log_to, Level};
+//!
+//! let access_log = ftlog::builder()
+//!     .with_thread(false)
+//!     .root(FileAppender::new("./access.log"))
+//!     .build()
+//!     .unwrap();
+//! log_to!(access_log, Level::Info, "GET /index.html 200");
+//! log_to!(access_log, target: "access", Level::Warn, "GET /missing 404");
+//! # drop(access_log);
+//! # std::fs::remove_file("./access.log").unwrap();
+//! ```
+//!
+//! ## Migrating from env_logger
+//!
+//! `ftlog::env_builder()` reads `RUST_LOG` and has the most used methods of
+//! `env_logger::Builder`, e.g. `filter_module`, `format_timestamp` and `write_style`.
+//! Log lines keep the format of env_logger. Keep the returned guard so queued
+//! records are written when the program exits.
+//!
+//! ```rust
+//! // was: env_logger::builder().filter_module("hyper", log::LevelFilter::Warn).init();
+//! let _guard = ftlog::env_builder()
+//!     .filter_module("hyper", log::LevelFilter::Warn)
+//!     .init();
+//! ```
+//!
 //! # Features
 //! - **tsc**
 //!   Use [TSC](https://en.wikipedia.org/wiki/Time_Stamp_Counter) for clock source for higher performance without
@@ -261,16 +348,89 @@
 //!   TSC offers the most accurate and cheapest way to access current time under certain condition:
 //!   1. the CPU frequency must be constant
 //!   1. must with CPU of x86/x86_64 architecture, since TSC is an x86/x86_64 specific register.
-//!   1. never suspend
 //!
-//!   The current feature further requires that the build target **MUST BE LINUX**. Otherwise it will fall back to
-//!   a fast but much less accurate implementation.
+//!   The current feature further requires that the build target **MUST BE LINUX**.
+//!   TSC is checked when the first logger is built, and ftlog falls back to
+//!   `std::time::Instant` where it is unreliable, see [`time_backend`].
+//!
+//! - **kv** (enabled by default)
+//!   Capture key-values of log calls like `info!(user_id = 42; "logged in")`. They are
+//!   printed by the default format as `user_id=42 logged in`, and passed to
+//!   [`format::RecordFormatter`]. Keys used by ftlog (`limit`, `drop` and `random_drop`)
+//!   are excluded. Expensive values can be computed only for logged records with
+//!   [`lazy`].
+//!
+//! - **tracing**
+//!   Forward events of `tracing` crate to ftlog with `ftlog::tracing::layer()`, or
+//!   `Builder::capture_tracing(true)` to set it up when installing ftlog.
+//!
+//! - **slog**
+//!   Use ftlog as backend of `slog` crate with `ftlog::slog::FtLogDrain`.
+//!
+//! - **config**
+//!   Load log4rs-style YAML configuration with `ftlog::config::load`.
+//!
+//! - **prometheus**
+//!   Export statistics of the log pipeline with `ftlog::prometheus::Collector`.
+//!
+//! - **metrics**
+//!   Emit counters of records, drops and rotations through the `metrics` facade.
+//!
+//! - **audit**
+//!   Write tamper-evident logs with `ftlog::appender::audit::AuditFileAppender`, whose
+//!   records are chained by SHA-256 with checkpoints signed by HMAC.
+//!
+//! - **redact**
+//!   Mask text matching regex patterns in messages and key-values with
+//!   `Builder::redact`, before any appender sees the record.
+//!
+//! - **message_filter**
+//!   Drop records or lower their level when their message matches regex patterns,
+//!   with `Builder::deny_message`, `Builder::allow_message` and
+//!   `Builder::downgrade_message`, in log thread.
+//!
+//! - **signal**
+//!   Toggle the max level of a live process with a Unix signal, e.g. `kill -USR1`,
+//!   by `Builder::toggle_on_signal`.
+//!
+//! - **control**
+//!   Serve a Unix or loopback TCP socket to change levels, reopen files, flush and read
+//!   statistics of a running service, with `Builder::control`.
+//!
+//! - **admin**
+//!   Handle `/loglevel` to read and change levels and `/logtail` to stream written lines
+//!   as server-sent events over HTTP, with `ftlog::admin::Admin`.
+//!
+//! - **query**
+//!   Search records of a time range across rotated files, including gzipped ones, with
+//!   `ftlog::query::Search`.
+//!
+//! - **tls**
+//!   Encrypt connections of `ftlog::appender::tcp::TcpAppender` with rustls, with
+//!   optional client certificates.
+//!
+//! - **otel**
+//!   Attach `trace_id` and `span_id` of the current OpenTelemetry span to log records.
+//!
+//! - **serde**
+//!   Log structs, maps and sequences implementing `serde::Serialize` as key-values,
+//!   like `info!(user:serde = user; "logged in")`. They are captured without formatting
+//!   at call site and printed as JSON by log thread. Implies `kv`.
+//!
+//! - **tui**
+//!   Show the last written lines with level filtering and search in a ratatui
+//!   interface, with `ftlog::tui::LogView`.
+//!
+//! - **encoding**
+//!   Write files of `FileAppender` in a legacy encoding like GBK or Shift_JIS, with
+//!   `ftlog::appender::Encoding::Legacy`.
 //!   
 //! # Timezone
 //!
-//! For performance, timezone is detected once at logger buildup, and use it later in every
-//! log message. This is partly due to timezone detetion is expensive, and partly to the unsafe
-//! nature of underlying system call in multi-thread program in Linux.
+//! For performance, the local offset is detected once a minute by log thread, and used
+//! for every log message of the minute. This is partly due to timezone detection is
+//! expensive, and partly to the unsafe nature of underlying system call in multi-thread
+//! program in Linux.
 //!
 //! It's also recommended to use UTC instead to further avoid timestamp convertion to timezone for every log message.
 //!
@@ -288,75 +448,179 @@
 //! | `env_logger` <br/> output to file with `BufWriter`| with i32      | 278 ns/iter     | 565 ns/iter     |
 
 use arc_swap::ArcSwap;
+#[doc(hidden)]
+pub use log as __log;
 pub use log::{
-    debug, error, info, log, log_enabled, logger, trace, warn, Level, LevelFilter, Record,
+    debug, info, log, log_enabled, logger, trace, warn, Level, LevelFilter, Log, Metadata, Record,
 };
-use time::format_description::OwnedFormatItem;
+use time::format_description::{BorrowedFormatItem, OwnedFormatItem};
 use time::{OffsetDateTime, UtcOffset};
 
+use std::any::TypeId;
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::fmt::Display;
 use std::hash::{BuildHasher, Hash, Hasher};
-use std::io::{stderr, Error as IoError, Write};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::io::{stderr, Error as IoError};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
-use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, SendTimeoutError, TrySendError};
 use hashbrown::HashMap;
-use log::{kv::Key, set_boxed_logger, set_max_level, Log, Metadata, SetLoggerError};
+use log::{kv::Key, set_logger, set_max_level, SetLoggerError};
 
+pub mod access;
+#[cfg(feature = "admin")]
+pub mod admin;
 pub mod appender;
+mod batch;
+pub mod bench;
+pub mod binary;
+pub mod clock;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod context;
+#[cfg(feature = "control")]
+pub mod control;
+mod crash_context;
+pub mod enrich;
+pub mod env;
+pub mod error;
+pub mod format;
+pub mod governor;
+mod intern;
+mod macros;
+#[cfg(feature = "message_filter")]
+mod message_filter;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod middleware;
+mod multiline;
+mod pool;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "query")]
+pub mod query;
+mod queue;
+pub mod rate_limit;
+#[cfg(feature = "redact")]
+pub mod redact;
+mod sanitize;
+#[cfg(all(unix, feature = "signal"))]
+pub mod signal;
+#[cfg(feature = "slog")]
+pub mod slog;
+pub mod stats;
+pub mod tap;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+mod truncate;
+#[cfg(feature = "tui")]
+pub mod tui;
+mod worker;
 
-use tm::{duration, now, to_utc, Time};
+use appender::Sink;
+use clock::{Clock, Stamp};
+use format::{Format, KvMap, RecordFields, RecordFormatter};
+use intern::Symbol;
+use rate_limit::CallsiteLimiter;
+use stats::{Metrics, SelfTest, SelfTestError, StatsSnapshot};
+use tap::FormattedRecord;
+use worker::{
+    Destination, LogMsg, LogWorker, LoggerInput, LoggerOutput, Payload, Route, Rule, WorkerConfig,
+};
 
 #[cfg(not(feature = "tsc"))]
 mod tm {
     use super::*;
 
-    pub type Time = std::time::SystemTime;
+    pub type Time = Instant;
     #[inline]
     pub fn now() -> Time {
-        std::time::SystemTime::now()
+        Instant::now()
     }
     #[inline]
     pub fn to_utc(time: Time) -> OffsetDateTime {
-        time.into()
+        clock::wall_time(time)
     }
 
     #[inline]
     pub fn duration(from: Time, to: Time) -> Duration {
-        to.duration_since(from).unwrap_or_default()
+        to.saturating_duration_since(from)
     }
 }
 
 #[cfg(feature = "tsc")]
 mod tm {
     use super::*;
-    pub type Time = minstant::Instant;
+
+    /// Instant of TSC, or of `std` where TSC is unreliable
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Time {
+        Tsc(minstant::Instant),
+        Std(Instant),
+    }
+
+    static TSC: OnceLock<bool> = OnceLock::new();
+
+    /// Whether TSC is calibrated and agrees with the monotonic clock of the OS, checked
+    /// once in the process
+    pub fn tsc() -> bool {
+        *TSC.get_or_init(|| {
+            if !minstant::is_tsc_available() {
+                return false;
+            }
+            // some VMs pass calibration but run TSC at another pace
+            let (tsc, std) = (minstant::Instant::now(), Instant::now());
+            std::thread::sleep(Duration::from_millis(2));
+            let (tsc, std) = (tsc.elapsed().as_secs_f64(), std.elapsed().as_secs_f64());
+            (tsc - std).abs() <= std * 0.05
+        })
+    }
+
     #[inline]
     pub fn now() -> Time {
-        minstant::Instant::now()
+        match tsc() {
+            true => Time::Tsc(minstant::Instant::now()),
+            false => Time::Std(Instant::now()),
+        }
     }
     #[inline]
     pub fn to_utc(time: Time) -> OffsetDateTime {
-        static ANCHOR: once_cell::sync::Lazy<minstant::Anchor> =
-            once_cell::sync::Lazy::new(|| minstant::Anchor::new());
-        OffsetDateTime::from_unix_timestamp_nanos(time.as_unix_nanos(&ANCHOR) as i128).unwrap()
+        clock::wall_time(time)
     }
     #[inline]
     pub fn duration(from: Time, to: Time) -> Duration {
-        to.duration_since(from)
+        match (from, to) {
+            (Time::Tsc(from), Time::Tsc(to)) => to.duration_since(from),
+            (Time::Std(from), Time::Std(to)) => to.saturating_duration_since(from),
+            _ => Duration::ZERO,
+        }
+    }
+
+    impl std::ops::Add<Duration> for Time {
+        type Output = Time;
+
+        fn add(self, duration: Duration) -> Time {
+            match self {
+                Time::Tsc(time) => Time::Tsc(time + duration),
+                Time::Std(time) => Time::Std(time + duration),
+            }
+        }
     }
 }
 
 #[cfg(target_family = "unix")]
 fn local_timezone() -> UtcOffset {
     UtcOffset::current_local_offset().unwrap_or_else(|_| {
-        let tz = tz::TimeZone::local().unwrap();
-        let current_local_time_type = tz.find_current_local_time_type().unwrap();
-        let diff_secs = current_local_time_type.ut_offset();
-        UtcOffset::from_whole_seconds(diff_secs).unwrap()
+        // fall back to UTC without a readable local timezone
+        tz::TimeZone::local()
+            .ok()
+            .and_then(|tz| Some(tz.find_current_local_time_type().ok()?.ut_offset()))
+            .and_then(|diff_secs| UtcOffset::from_whole_seconds(diff_secs).ok())
+            .unwrap_or(UtcOffset::UTC)
     })
 }
 #[cfg(not(target_family = "unix"))]
@@ -364,108 +628,67 @@ fn local_timezone() -> UtcOffset {
     UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)
 }
 
-struct LogMsg {
-    time: Time,
-    msg: Box<dyn Sync + Send + Display>,
-    level: Level,
-    target: String,
-    limit: u32,
-    limit_key: u64,
+/// Name of the machine, from `HOSTNAME` or the system
+pub(crate) fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
 }
 
-impl LogMsg {
-    fn write(
-        self,
-        filters: &[Directive],
-        appenders: &mut HashMap<&'static str, Box<dyn Write + Send>>,
-        root: &mut Box<dyn Write + Send>,
-        root_level: LevelFilter,
-        missed_log: &mut HashMap<u64, i64, nohash_hasher::BuildNoHashHasher<u64>>,
-        last_log: &mut HashMap<u64, Time, nohash_hasher::BuildNoHashHasher<u64>>,
-        offset: Option<UtcOffset>,
-        time_format: &time::format_description::OwnedFormatItem,
-    ) {
-        let msg = self.msg.to_string();
-        if msg.is_empty() {
-            return;
-        }
-
-        let now = now();
+/// Offset of timestamps written by log thread
+///
+/// The local offset is looked up again once the minute of records moves forward, so that
+/// timestamps of a long-running process follow DST transitions.
+pub(crate) enum Offset {
+    Utc,
+    Fixed(UtcOffset),
+    /// minute since epoch the offset was looked up in, plus 1 so that 0 is never
+    /// looked up, in the upper half, and offset seconds in the lower half, so that
+    /// render threads read both together
+    Local(AtomicU64),
+}
 
-        // Find an appender filter if one exists
-        let writer = if let Some(filter) = filters
-            .iter()
-            .find(|x| (*x.filter)(&self.msg, self.level, &self.target))
-        {
-            filter
-                .appender
-                .and_then(|n| appenders.get_mut(n))
-                .unwrap_or(root)
-        } else {
-            if root_level < self.level {
-                return;
-            }
-            root
-        };
+impl Offset {
+    fn new(timezone: &LogTimezone) -> Offset {
+        match timezone {
+            // looked up by the first record
+            LogTimezone::Local => Offset::Local(AtomicU64::new(0)),
+            LogTimezone::Utc => Offset::Utc,
+            LogTimezone::Fixed(offset) => Offset::Fixed(*offset),
+        }
+    }
 
-        let delay = duration(self.time, now);
-        let utc_datetime = to_utc(self.time);
-
-        let offset_datetime = offset
-            .map(|o| utc_datetime.to_offset(o))
-            .unwrap_or(utc_datetime);
-        let s: String;
-        if self.limit > 0 {
-            let missed_entry = missed_log.entry(self.limit_key).or_insert_with(|| 0);
-            if let Some(last) = last_log.get(&self.limit_key) {
-                if duration(*last, now) < Duration::from_millis(self.limit as u64) {
-                    *missed_entry += 1;
-                    return;
+    /// `utc` in the offset at that time
+    #[inline]
+    pub(crate) fn apply(&self, utc: OffsetDateTime) -> OffsetDateTime {
+        match self {
+            Offset::Utc => utc,
+            Offset::Fixed(offset) => utc.to_offset(*offset),
+            Offset::Local(cached) => {
+                let minute = utc
+                    .unix_timestamp()
+                    .div_euclid(60)
+                    .clamp(0, u32::MAX as i64 - 1) as u64
+                    + 1;
+                let mut packed = cached.load(Ordering::Relaxed);
+                // refreshed only when the minute moves forward, so that records of an
+                // earlier minute from another render thread reuse the offset
+                if minute > packed >> 32 {
+                    let seconds = local_timezone().whole_seconds();
+                    packed = minute << 32 | seconds as u32 as u64;
+                    // keep the offset of a later minute stored by a racing render thread
+                    cached.fetch_max(packed, Ordering::Relaxed);
                 }
+                let offset = UtcOffset::from_whole_seconds(packed as u32 as i32);
+                utc.to_offset(offset.unwrap_or(UtcOffset::UTC))
             }
-            last_log.insert(self.limit_key, now);
-
-            s = format!(
-                "{} {}ms {} {}\n",
-                offset_datetime
-                    .format(&time_format)
-                    .unwrap_or_else(|_| offset_datetime
-                        .format(&time::format_description::well_known::Rfc3339)
-                        .unwrap()),
-                delay.as_millis(),
-                *missed_entry,
-                msg
-            );
-            *missed_entry = 0;
-        } else {
-            s = format!(
-                "{} {}ms {}\n",
-                offset_datetime
-                    .format(&time_format)
-                    .unwrap_or_else(|_| offset_datetime
-                        .format(&time::format_description::well_known::Rfc3339)
-                        .unwrap()),
-                delay.as_millis(),
-                msg
-            );
         }
-        if let Err(e) = writer.write_all(s.as_bytes()) {
-            eprintln!("logger write message failed: {}", e);
-        };
     }
 }
 
-enum LoggerInput {
-    LogMsg(LogMsg),
-    Flush,
-}
-
-#[derive(Debug)]
-enum LoggerOutput {
-    Flushed,
-    FlushError(std::io::Error),
-}
-
 /// Shared by ftlog formatter
 ///
 /// To further reduce time spent on log macro calls, ftlog saves required data
@@ -509,7 +732,7 @@ enum LoggerOutput {
 ///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 ///         f.write_str(&format!(
 ///             "{}@{}||{}:{}[{}] {}",
-///             self.thread.as_ref().map(|x| x.as_str()).unwrap_or(""),
+///             self.thread.as_deref().unwrap_or(""),
 ///             self.module_path.unwrap_or(""),
 ///             self.file.unwrap_or(""),
 ///             self.line.unwrap_or(0),
@@ -537,48 +760,288 @@ pub trait FtLogFormat: Send + Sync {
 /// ```text
 /// 2022-11-22 17:02:12.574+08 0ms INFO main [examples/ftlog.rs:27] Hello, world!
 /// ```
+///
+/// Thread name and source location can be omitted with [`Builder::with_thread`]
+/// and [`Builder::with_source_location`].
 pub struct FtLogFormatter;
 impl FtLogFormat for FtLogFormatter {
     /// Return a box object that contains required data (e.g. thread name, line of code, etc.) for later formatting into string
     #[inline]
     fn msg(&self, record: &Record) -> Box<dyn Send + Sync + Display> {
-        Box::new(Message {
+        DefaultFormat::default().msg(record)
+    }
+}
+
+/// `FtLogFormatter` with options set by builder
+#[derive(Clone)]
+struct DefaultFormat {
+    thread: bool,
+    location: bool,
+    style: Style,
+    global_fields: Option<GlobalFields>,
+}
+
+impl Default for DefaultFormat {
+    fn default() -> Self {
+        DefaultFormat {
+            thread: true,
+            location: true,
+            style: Style::default(),
+            global_fields: None,
+        }
+    }
+}
+
+thread_local! {
+    // name of current thread, or its ID if unnamed, shared by its records
+    static THREAD_NAME: Arc<str> = {
+        let thread = std::thread::current();
+        match thread.name() {
+            Some(name) => name.into(),
+            None => format!("{:?}", thread.id()).into(),
+        }
+    };
+}
+
+impl DefaultFormat {
+    #[inline]
+    fn thread(&self) -> Option<Arc<str>> {
+        self.thread.then(|| THREAD_NAME.with(|x| x.clone()))
+    }
+
+    /// Message of a record without formatting arguments, key-values and context,
+    /// which is sent to log thread without allocating
+    #[inline]
+    fn static_msg(format: &Arc<DefaultFormat>, record: &Record) -> Option<StaticMessage> {
+        let args = record.args().as_str()?;
+        if record.key_values().count() > 0 || !context::is_empty() {
+            return None;
+        }
+        let file = match format.location {
+            true => record.file_static()?,
+            false => "",
+        };
+        Some(StaticMessage {
+            format: format.clone(),
             level: record.level(),
-            thread: std::thread::current().name().map(|n| n.to_string()),
-            file: record
+            thread: format.thread(),
+            file,
+            line: record.line().unwrap_or(0),
+            args,
+        })
+    }
+}
+
+impl DefaultFormat {
+    /// Fill `msg` with `record`, reusing buffers of `msg`
+    #[inline]
+    fn fill(&self, msg: &mut Message, record: &Record) {
+        msg.level = record.level();
+        msg.thread = self.thread();
+        msg.location = self.location.then(|| {
+            let file = record
                 .file_static()
-                .map(|s| Cow::Borrowed(s))
+                .map(Cow::Borrowed)
                 .or_else(|| record.file().map(|s| Cow::Owned(s.to_owned())))
-                .unwrap_or(Cow::Borrowed("")),
-            line: record.line(),
-            args: record
-                .args()
-                .as_str()
-                .map(|s| Cow::Borrowed(s))
-                .unwrap_or_else(|| Cow::Owned(format!("{}", record.args()))),
-        })
+                .unwrap_or(Cow::Borrowed(""));
+            (file, record.line().unwrap_or(0))
+        });
+        msg.style = self.style;
+        msg.global_fields = self.global_fields.clone();
+        context::extend_fields(&mut msg.context);
+        #[cfg(feature = "kv")]
+        format::visit_key_values(record, &mut msg.key_values);
+        match record.args().as_str() {
+            Some(args) => msg.args.push_str(args),
+            None => {
+                let _ = std::fmt::Write::write_fmt(&mut msg.args, *record.args());
+            }
+        }
+    }
+}
+
+impl FtLogFormat for DefaultFormat {
+    #[inline]
+    fn msg(&self, record: &Record) -> Box<dyn Send + Sync + Display> {
+        let mut msg = Message::default();
+        self.fill(&mut msg, record);
+        Box::new(msg)
+    }
+}
+
+pub(crate) struct Message {
+    level: Level,
+    thread: Option<Arc<str>>,
+    location: Option<(Cow<'static, str>, u32)>,
+    style: Style,
+    global_fields: Option<GlobalFields>,
+    context: Vec<(&'static str, Arc<str>)>,
+    key_values: Vec<(String, format::FieldValue)>,
+    args: String,
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Message {
+            level: Level::Info,
+            thread: None,
+            location: None,
+            style: Style::default(),
+            global_fields: None,
+            context: Vec::new(),
+            key_values: Vec::new(),
+            args: String::new(),
+        }
+    }
+}
+
+impl Message {
+    /// Drop fields of the last record, keeping capacity of buffers
+    pub(crate) fn clear(&mut self) {
+        self.thread = None;
+        self.location = None;
+        self.global_fields = None;
+        self.context.clear();
+        self.key_values.clear();
+        self.args.clear();
+    }
+}
+
+/// Options of the default format applied while writing a message
+#[derive(Clone, Copy, Default)]
+struct Style {
+    abbreviate: bool,
+    color: bool,
+}
+
+/// ANSI color code of a level
+pub(crate) fn level_color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[31m",
+        Level::Warn => "\x1b[33m",
+        Level::Info => "\x1b[32m",
+        Level::Debug => "\x1b[34m",
+        Level::Trace => "\x1b[35m",
     }
 }
 
-struct Message {
+/// Write level, thread, location, global fields and context of the default format
+fn write_prefix(
+    f: &mut std::fmt::Formatter<'_>,
     level: Level,
-    thread: Option<String>,
-    file: Cow<'static, str>,
-    line: Option<u32>,
-    args: Cow<'static, str>,
+    thread: Option<&str>,
+    location: Option<(&str, u32)>,
+    style: Style,
+    global_fields: Option<&GlobalFields>,
+    context: &[(&'static str, Arc<str>)],
+) -> std::fmt::Result {
+    // plain strings are written as is, which is much cheaper than `write!`
+    if style.color {
+        f.write_str(level_color(level))?;
+        f.write_str(level.as_str())?;
+        f.write_str("\x1b[0m")?;
+    } else {
+        f.write_str(level.as_str())?;
+    }
+    if let Some(thread) = thread {
+        f.write_str(" ")?;
+        f.write_str(thread)?;
+    }
+    if let Some((file, line)) = location {
+        if style.abbreviate {
+            write!(f, " [{}:{}]", abbreviate_path(file), line)?;
+        } else {
+            write!(f, " [{}:{}]", file, line)?;
+        }
+    }
+    for (key, value) in global_fields.iter().flat_map(|x| x.iter()) {
+        write!(f, " {}={}", key, value)?;
+    }
+    for (key, value) in context {
+        write!(f, " {}={}", key, value)?;
+    }
+    Ok(())
 }
 
 impl Display for Message {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&format!(
-            "{} {} [{}:{}] {}",
+        write_prefix(
+            f,
+            self.level,
+            self.thread.as_deref(),
+            self.location
+                .as_ref()
+                .map(|(file, line)| (file.as_ref(), *line)),
+            self.style,
+            self.global_fields.as_ref(),
+            &self.context,
+        )?;
+        for (key, value) in &self.key_values {
+            write!(f, " {}={}", key, value)?;
+        }
+        f.write_str(" ")?;
+        f.write_str(&self.args)
+    }
+}
+
+/// [`Message`] of a record without formatting arguments, key-values and context
+pub(crate) struct StaticMessage {
+    format: Arc<DefaultFormat>,
+    level: Level,
+    thread: Option<Arc<str>>,
+    file: &'static str,
+    line: u32,
+    args: &'static str,
+}
+
+impl Display for StaticMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_prefix(
+            f,
             self.level,
-            self.thread.as_deref().unwrap_or(""),
-            self.file,
-            self.line.unwrap_or(0),
-            self.args
-        ))
+            self.thread.as_deref(),
+            self.format.location.then_some((self.file, self.line)),
+            self.format.style,
+            self.format.global_fields.as_ref(),
+            &[],
+        )?;
+        f.write_str(" ")?;
+        f.write_str(self.args)
+    }
+}
+
+impl StaticMessage {
+    /// The same message with room for key-values added in log thread
+    pub(crate) fn to_message(&self) -> Message {
+        Message {
+            level: self.level,
+            thread: self.thread.clone(),
+            location: self
+                .format
+                .location
+                .then_some((Cow::Borrowed(self.file), self.line)),
+            style: self.format.style,
+            global_fields: self.format.global_fields.clone(),
+            args: self.args.to_string(),
+            ..Message::default()
+        }
+    }
+}
+
+/// Shorten each directory of a path to its first character, e.g. `s/a/file.rs`
+/// for `src/appender/file.rs`
+fn abbreviate_path(path: &str) -> String {
+    let (dirs, file) = match path.rfind(['/', '\\']) {
+        Some(ix) => (&path[..ix], &path[ix + 1..]),
+        None => return path.to_string(),
+    };
+    let mut abbreviated = String::with_capacity(path.len());
+    for dir in dirs.split(['/', '\\']) {
+        abbreviated.extend(dir.chars().next());
+        abbreviated.push('/');
     }
+    abbreviated.push_str(file);
+    abbreviated
 }
 
 struct DiscardState {
@@ -586,298 +1049,2263 @@ struct DiscardState {
     count: AtomicUsize,
 }
 
-/// A guard that flushes logs associated to a Logger on a drop
-///
-/// With this guard, you can ensure all logs are written to destination
-/// when the application exits.
-pub struct LoggerGuard {
-    queue: Sender<LoggerInput>,
-    notification: Receiver<LoggerOutput>,
+type OverflowCallback = Box<dyn Fn(usize) + Send + Sync>;
+
+/// Minimal interval between calls of the callback set by `Builder::on_overflow`
+const OVERFLOW_NOTICE_PERIOD: Duration = Duration::from_secs(1);
+
+struct OverflowNotice {
+    callback: OverflowCallback,
+    // records dropped since the callback was last called
+    pending: AtomicUsize,
+    limit: rate_limit::AtMostEvery,
 }
-impl Drop for LoggerGuard {
-    fn drop(&mut self) {
-        self.queue
-            .send(LoggerInput::Flush)
-            .expect("logger queue closed when flushing, this is a bug");
-        self.notification
-            .recv()
-            .expect("logger notification closed, this is a bug");
+
+/// Max level of a logger, changeable at runtime
+struct AtomicLevel(AtomicUsize);
+
+impl AtomicLevel {
+    fn new(level: LevelFilter) -> AtomicLevel {
+        AtomicLevel(AtomicUsize::new(level as usize))
+    }
+
+    #[inline]
+    fn get(&self) -> LevelFilter {
+        match self.0.load(Ordering::Relaxed) {
+            0 => LevelFilter::Off,
+            1 => LevelFilter::Error,
+            2 => LevelFilter::Warn,
+            3 => LevelFilter::Info,
+            4 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
     }
-}
-/// ftlog global logger
-pub struct Logger {
-    format: Box<dyn FtLogFormat>,
-    level: LevelFilter,
-    filters: Vec<Box<dyn Fn(&Record) -> bool + Send + Sync>>,
-    queue: Sender<LoggerInput>,
-    notification: Receiver<LoggerOutput>,
-    block: bool,
-    discard_state: Option<DiscardState>,
-    stopped: AtomicBool,
 }
 
-impl Logger {
-    pub fn init(self) -> Result<LoggerGuard, SetLoggerError> {
-        let guard = LoggerGuard {
-            queue: self.queue.clone(),
-            notification: self.notification.clone(),
-        };
+/// State shared by a logger, its guard and the global handle
+struct Shared {
+    queue: queue::Sender<LoggerInput>,
+    metrics: Arc<Metrics>,
+    tap: Arc<tap::Tap>,
+    closed: AtomicBool,
+    /// shutdown as seen by log thread
+    progress: Arc<worker::Progress>,
+    /// handler of [`Builder::on_internal_error`]
+    on_internal_error: Option<InternalErrorHandler>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    shutdown_timeout: Option<Duration>,
+    once_summary: bool,
+    level: AtomicLevel,
+    /// levels of target prefixes overriding `level`, longest prefix first
+    targets: ArcSwap<Vec<(String, LevelFilter)>>,
+    /// `targets` is not empty, so that log calls skip loading it otherwise
+    targeted: AtomicBool,
+    /// cap of all levels set by the governor under backpressure
+    throttle: governor::Throttle,
+    /// files written by log calls instead of log thread, see [`Builder::per_thread`]
+    per_thread: Option<appender::per_thread::PerThread>,
+    /// records batched per thread, see [`Builder::low_contention`]
+    batches: Option<batch::Batches>,
+    /// buffers of records returned by log thread
+    pool: Arc<pool::Pool>,
+    /// records dropped since the last marker, see [`Builder::drop_markers`]
+    dropped: Option<AtomicUsize>,
+    /// formatter of the whole line, shared with log thread
+    formatter: Arc<worker::SharedFormatter>,
+    /// collect record data for `RecordFormatter` instead of calling `format`
+    record_fields: AtomicBool,
+}
 
-        set_max_level(self.level);
-        let boxed = Box::new(self);
-        set_boxed_logger(boxed).map(|_| guard)
+impl Shared {
+    /// Pass `error` to the handler of this logger
+    fn internal_error(&self, error: InternalError) {
+        with_error_handler(&self.on_internal_error, || internal_error(error));
     }
-}
 
-impl Log for Logger {
+    /// Max level of records of `target`
     #[inline]
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        // already checked in log macros
-        self.level >= metadata.level()
+    fn level_of(&self, target: &str) -> LevelFilter {
+        let level = self.level.get();
+        let level = match self.targeted.load(Ordering::Relaxed) {
+            false => level,
+            true => self
+                .targets
+                .load()
+                .iter()
+                .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+                .map_or(level, |(_, level)| *level),
+        };
+        level.min(self.throttle.cap())
     }
 
-    fn log(&self, record: &Record) {
-        #[cfg(feature = "random_drop")]
-        {
-            let random_drop = record
-                .key_values()
-                .get(Key::from_str("random_drop"))
-                .or_else(|| record.key_values().get(Key::from_str("drop")))
-                .and_then(|x| x.to_f64())
-                .unwrap_or(1.) as f32;
-            if random_drop < 1. && fastrand::f32() < random_drop {
-                return;
-            }
-        }
+    /// The most verbose level of any target, used as the max level of `log` crate
+    fn max_level(&self) -> LevelFilter {
+        self.targets
+            .load()
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level.get(), Ord::max)
+            .min(self.throttle.cap())
+    }
 
-        let limit = record
-            .key_values()
-            .get(Key::from_str("limit"))
-            .and_then(|x| x.to_u64())
-            .unwrap_or(0) as u32;
+    fn set_level(self: &Arc<Self>, level: LevelFilter) {
+        self.level.0.store(level as usize, Ordering::Relaxed);
+        self.update_max_level();
+    }
 
-        // This will short circuit if any of the filters return false, meaning don't keep this record.
-        if !self.filters.is_empty() {
-            if self.filters.iter().all(|filter| filter(record)) {
-                // Drop this log record
-                println!("Dropping this record {:?}", record);
-                return;
+    fn set_target_level(self: &Arc<Self>, prefix: &str, level: Option<LevelFilter>) {
+        self.targets.rcu(|targets| {
+            let mut targets = targets
+                .iter()
+                .filter(|(x, _)| x != prefix)
+                .cloned()
+                .collect::<Vec<_>>();
+            if let Some(level) = level {
+                targets.push((prefix.to_string(), level));
+                targets.sort_by_key(|(x, _)| std::cmp::Reverse(x.len()));
             }
+            targets
+        });
+        self.targeted
+            .store(!self.targets.load().is_empty(), Ordering::Relaxed);
+        self.update_max_level();
+    }
+
+    fn update_max_level(self: &Arc<Self>) {
+        if GLOBAL_PIPELINE.get().is_some_and(|x| Arc::ptr_eq(x, self)) {
+            set_max_level(self.max_level());
         }
+    }
 
-        let limit_key = if limit == 0 {
-            0
-        } else {
-            let mut b = hashbrown::hash_map::DefaultHashBuilder::default().build_hasher();
-            if let Some(p) = record.module_path() {
-                p.as_bytes().hash(&mut b);
-            } else {
-                record.file().unwrap_or("").as_bytes().hash(&mut b);
-            }
-            record.line().unwrap_or(0).hash(&mut b);
-            b.finish()
+    /// Send a marker for records dropped since the last one, unless the queue is full
+    fn send_dropped(&self) {
+        let Some(dropped) = &self.dropped else {
+            return;
         };
-        let msg = self.format.msg(record);
-        let msg = LoggerInput::LogMsg(LogMsg {
-            time: now(),
-            msg,
-            target: record.target().to_owned(),
-            level: record.level(),
-            limit,
-            limit_key,
-        });
-        if self.block {
-            if self.queue.send(msg).is_err() {
-                let stop = self.stopped.load(Ordering::SeqCst);
-                if !stop {
-                    eprintln!("logger queue closed when logging, this is a bug");
-                    self.stopped.store(true, Ordering::SeqCst)
-                }
-            }
-        } else {
-            match self.queue.try_send(msg) {
-                Err(TrySendError::Full(_)) => {
-                    if let Some(s) = &self.discard_state {
-                        let count = s.count.fetch_add(1, Ordering::SeqCst);
-                        if s.last.load().elapsed().as_secs() >= 5 {
-                            eprintln!("Excessive log messages. Log omitted: {}", count);
-                            s.last.store(Arc::new(Instant::now()));
-                        }
-                    }
-                }
-                Err(TrySendError::Disconnected(_)) => {
-                    let stop = self.stopped.load(Ordering::SeqCst);
-                    if !stop {
-                        eprintln!("logger queue closed when logging, this is a bug");
-                        self.stopped.store(true, Ordering::SeqCst)
-                    }
-                }
-                _ => (),
-            }
+        if dropped.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let count = dropped.swap(0, Ordering::Relaxed);
+        if count > 0 && self.queue.try_send(LoggerInput::Dropped(count)).is_err() {
+            dropped.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// Send batched records of all threads to log thread
+    fn send_batches(&self) {
+        if let Some(batches) = &self.batches {
+            batches.flush(false, |batch| {
+                let _ = self.queue.send(LoggerInput::Batch(batch));
+            });
         }
     }
 
     fn flush(&self) {
-        self.queue
-            .send(LoggerInput::Flush)
-            .expect("logger queue closed when flushing, this is a bug");
-        if let LoggerOutput::FlushError(err) = self
-            .notification
-            .recv()
-            .expect("logger notification closed, this is a bug")
-        {
-            eprintln!("Fail to flush: {}", err);
+        if self.closed.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(Err(source)) = self.per_thread.as_ref().map(|x| x.flush()) {
+            self.internal_error(InternalError::Flush {
+                appender: "per-thread".to_string(),
+                source,
+            });
+        }
+        self.send_batches();
+        self.send_dropped();
+        // a reply channel per call, so that concurrent callers each wait for the
+        // records they sent before flushing
+        let (reply, done) = bounded(1);
+        if self.queue.send(LoggerInput::Flush(reply)).is_err() {
+            return;
+        }
+        if let Ok(LoggerOutput::FlushError(appender, source)) = done.recv() {
+            self.internal_error(InternalError::Flush { appender, source });
         }
     }
-}
 
-struct BoundedChannelOption {
-    size: usize,
-    block: bool,
-    print: bool,
+    fn set_format(&self, format: Format) {
+        self.formatter.store(Some(Arc::new(Box::new(format))));
+        self.record_fields.store(true, Ordering::Relaxed);
+    }
+
+    /// Send a marker through the log pipeline and wait for at most `timeout` until
+    /// it is written and flushed by all appenders
+    fn selftest(&self, timeout: Duration) -> Result<SelfTest, SelfTestError> {
+        if self.closed.load(Ordering::Relaxed) {
+            return Err(SelfTestError::Stopped);
+        }
+        let start = Instant::now();
+        self.send_batches();
+        self.send_dropped();
+        let (reply, done) = bounded(1);
+        let probe = LoggerInput::Probe(worker::Probe::new(reply));
+        match self.queue.send_timeout(probe, timeout) {
+            Ok(()) => (),
+            Err(SendTimeoutError::Timeout(_)) => return Err(SelfTestError::Timeout),
+            Err(SendTimeoutError::Disconnected(_)) => return Err(SelfTestError::Stopped),
+        }
+        let report = match done.recv_timeout(timeout.saturating_sub(start.elapsed())) {
+            Ok(report) => report,
+            Err(RecvTimeoutError::Timeout) => return Err(SelfTestError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => return Err(SelfTestError::Stopped),
+        };
+        Ok(SelfTest {
+            queued: report.queued,
+            rendered: report.rendered,
+            appenders: report.appenders,
+            total: start.elapsed(),
+            stats: self.metrics.snapshot(self.queue.len()),
+        })
+    }
+
+    /// Stop accepting log messages, write queued messages, flush appenders and
+    /// join log thread, waiting for at most `timeout`.
+    fn shutdown(&self, timeout: Option<Duration>) -> ShutdownReport {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return ShutdownReport::default();
+        }
+        if let Some(Err(source)) = self.per_thread.as_ref().map(|x| x.flush()) {
+            self.internal_error(InternalError::Flush {
+                appender: "per-thread".to_string(),
+                source,
+            });
+        }
+        self.send_batches();
+        self.send_dropped();
+        let start = Instant::now();
+        // leave some time for flushing appenders after draining the queue
+        let deadline = timeout.map(|x| start + x.mul_f32(0.9));
+        let (reply, done) = bounded(1);
+        // log thread drains messages queued from now on, before the quit request
+        self.progress.begin(deadline);
+        if self.queue.send(LoggerInput::Quit(reply)).is_err() {
+            return ShutdownReport::default();
+        }
+        let report = match timeout {
+            Some(timeout) => done
+                .recv_timeout(timeout.saturating_sub(start.elapsed()))
+                .ok(),
+            None => done.recv().ok(),
+        };
+        match report {
+            Some(report) => {
+                let handle = self.worker.lock().unwrap_or_else(|e| e.into_inner()).take();
+                if let Some(handle) = handle {
+                    let _ = handle.join();
+                }
+                report
+            }
+            None => {
+                // messages still queued are not handled before the deadline either
+                let report = self.progress.report();
+                let abandoned = report.abandoned + self.queue.len();
+                self.internal_error(InternalError::ShutdownTimeout { abandoned });
+                ShutdownReport {
+                    abandoned,
+                    joined: false,
+                    ..report
+                }
+            }
+        }
+    }
 }
 
-/// Ftlog builder
+/// Error returned by [`Builder::try_init`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InitError {
+    /// a global logger has already been set, by ftlog or another crate
+    AlreadySet(SetLoggerError),
+    /// I/O error when setting up the logger, e.g. failing to spawn log thread
+    Io(IoError),
+    /// invalid configuration of the builder or of a configuration file, e.g. a
+    /// filter referring to an appender that is not configured
+    InvalidConfig(ConfigError),
+}
+
+impl Display for InitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InitError::AlreadySet(_) => write!(f, "global logger already set"),
+            InitError::Io(e) => write!(f, "failed to set up logger: {}", e),
+            InitError::InvalidConfig(e) => write!(f, "invalid logger config: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitError::AlreadySet(e) => Some(e),
+            InitError::Io(e) => Some(e),
+            InitError::InvalidConfig(e) => Some(e),
+        }
+    }
+}
+
+impl From<IoError> for InitError {
+    fn from(e: IoError) -> Self {
+        InitError::Io(e)
+    }
+}
+
+impl From<ConfigError> for InitError {
+    fn from(e: ConfigError) -> Self {
+        InitError::InvalidConfig(e)
+    }
+}
+
+impl From<AppenderError> for InitError {
+    fn from(e: AppenderError) -> Self {
+        InitError::InvalidConfig(ConfigError::Appender(e))
+    }
+}
+
+/// Invalid configuration, see [`InitError::InvalidConfig`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// an appender referred to by name, e.g. by a filter, is not configured
+    UnknownAppender(String),
+    /// a setting is invalid or not supported, e.g. a redaction pattern that does not
+    /// compile
+    Invalid(String),
+    /// a configuration file is malformed, at `location` when known
+    Parse {
+        location: Option<Location>,
+        message: String,
+    },
+    /// an appender could not be set up
+    Appender(AppenderError),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::UnknownAppender(name) => write!(f, "appender {} not configured", name),
+            ConfigError::Invalid(msg) => f.write_str(msg),
+            ConfigError::Parse {
+                location: Some(location),
+                message,
+            } => write!(f, "{}: {}", location, message),
+            ConfigError::Parse {
+                location: None,
+                message,
+            } => f.write_str(message),
+            ConfigError::Appender(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Appender(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<AppenderError> for ConfigError {
+    fn from(e: AppenderError) -> Self {
+        ConfigError::Appender(e)
+    }
+}
+
+/// Position in a configuration file, lines and columns counted from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Location {
+    /// the file, unless the configuration is parsed from a string
+    pub path: Option<std::path::PathBuf>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}:{}:{}", path.display(), self.line, self.column),
+            None => write!(f, "line {} column {}", self.line, self.column),
+        }
+    }
+}
+
+/// Failure to set up an appender
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AppenderError {
+    /// the log file at `path` could not be opened or created
+    Open {
+        path: std::path::PathBuf,
+        source: IoError,
+    },
+    /// the placeholder `${name}` in `path` has no value
+    Placeholder {
+        path: std::path::PathBuf,
+        name: String,
+    },
+}
+
+impl Display for AppenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppenderError::Open { path, source } => {
+                write!(f, "fail to open log file {}: {}", path.display(), source)
+            }
+            AppenderError::Placeholder { path, name } => write!(
+                f,
+                "environment variable {} of log file {} is not set",
+                name,
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AppenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppenderError::Open { source, .. } => Some(source),
+            AppenderError::Placeholder { .. } => None,
+        }
+    }
+}
+
+/// Failure inside the logger, passed to the handler of
+/// [`Builder::on_internal_error`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InternalError {
+    /// writing to appender `appender` failed, e.g. a rotated file could not be
+    /// opened, and the line is lost
+    Write { appender: String, source: IoError },
+    /// flushing appender `appender` failed
+    Flush { appender: String, source: IoError },
+    /// formatting a record of `target` failed or panicked, and the record is dropped
+    Format { target: String, message: String },
+    /// `path`, a directory of log files or an outdated log file, could not be listed
+    /// or deleted
+    Cleanup {
+        path: std::path::PathBuf,
+        source: IoError,
+    },
+    /// the spill buffer at `dir` is full, and its oldest segment of `bytes` bytes is
+    /// deleted with the lines in it
+    SpillDropped { dir: std::path::PathBuf, bytes: u64 },
+    /// log thread did not finish before the shutdown deadline, and `abandoned` log
+    /// messages may be lost
+    ShutdownTimeout { abandoned: usize },
+}
+
+impl Display for InternalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InternalError::Write { appender, source } => {
+                write!(f, "write to appender {} failed: {}", appender, source)
+            }
+            InternalError::Flush { appender, source } => {
+                write!(f, "flush of appender {} failed: {}", appender, source)
+            }
+            InternalError::Format { target, message } => {
+                write!(f, "format of a record of {} failed: {}", target, message)
+            }
+            InternalError::Cleanup { path, source } => write!(
+                f,
+                "fail to clean outdated logs at {}: {}",
+                path.display(),
+                source
+            ),
+            InternalError::SpillDropped { dir, bytes } => write!(
+                f,
+                "spill buffer at {} full, oldest segment of {} bytes dropped",
+                dir.display(),
+                bytes
+            ),
+            InternalError::ShutdownTimeout { abandoned } => write!(
+                f,
+                "log thread does not finish in time, {} log messages may be lost",
+                abandoned
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InternalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InternalError::Write { source, .. }
+            | InternalError::Flush { source, .. }
+            | InternalError::Cleanup { source, .. } => Some(source),
+            InternalError::Format { .. }
+            | InternalError::SpillDropped { .. }
+            | InternalError::ShutdownTimeout { .. } => None,
+        }
+    }
+}
+
+pub(crate) type InternalErrorHandler = Arc<dyn Fn(&InternalError) + Send + Sync>;
+
+thread_local! {
+    /// Handler of [`Builder::on_internal_error`] of the logger working in this thread,
+    /// as appenders report errors without knowing which logger they belong to
+    static ON_INTERNAL_ERROR: RefCell<Option<InternalErrorHandler>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with internal errors of this thread going to `handler`
+pub(crate) fn with_error_handler<R>(
+    handler: &Option<InternalErrorHandler>,
+    f: impl FnOnce() -> R,
+) -> R {
+    /// Restores previous handler, even if `f` panics
+    struct Restore(Option<InternalErrorHandler>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let _ = ON_INTERNAL_ERROR.try_with(|x| *x.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(ON_INTERNAL_ERROR.with(|x| x.replace(handler.clone())));
+    f()
+}
+
+/// Handler receiving internal errors of this thread, for threads spawned on its behalf
+pub(crate) fn error_handler() -> Option<InternalErrorHandler> {
+    ON_INTERNAL_ERROR
+        .try_with(|x| x.borrow().clone())
+        .ok()
+        .flatten()
+}
+
+/// Pass `error` to the handler of this thread, or print it to stderr without one
+pub(crate) fn internal_error(error: InternalError) {
+    match error_handler() {
+        Some(handler) => {
+            // a panicking handler must not take log thread down
+            let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| handler(&error)));
+        }
+        None => eprintln!("ftlog: {}", error),
+    }
+}
+
+/// Outcome of shutting down a logger
+///
+/// See [`shutdown`] for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// number of log messages queued when shutdown began, or sent by log calls racing
+    /// with it, that log thread handled before the deadline
+    pub flushed: usize,
+    /// number of queued log messages discarded because the deadline was reached
+    pub abandoned: usize,
+    /// whether log thread finished and was joined within the deadline
+    pub joined: bool,
+}
+
+impl Default for ShutdownReport {
+    fn default() -> Self {
+        ShutdownReport {
+            flushed: 0,
+            abandoned: 0,
+            joined: true,
+        }
+    }
+}
+
+/// Gracefully shut down the global logger within a deadline
+///
+/// The logger stops accepting new log messages, writes queued messages, flushes
+/// appenders and joins log thread. Queued messages that cannot be written before
+/// the deadline are abandoned. Log calls after shutdown are ignored.
+///
+/// Returns `None` if ftlog is not installed as the global logger. Calling this more
+/// than once, or after `LoggerGuard` is dropped, has no effect.
 ///
 /// ```
-/// # use ftlog::appender::{FileAppender, Duration, Period};
-/// # use log::LevelFilter;
-/// let logger = ftlog::builder()
-///     // use our own format
-///     .format(ftlog::FtLogFormatter)
-///     // global max log level
-///     .max_log_level(LevelFilter::Info)
-///     // define root appender, pass anything that is Write and Send
-///     // omit `Builder::root` to write to stderr
-///     .root(FileAppender::rotate_with_expire(
-///         "./current.log",
-///         Period::Day,
-///         Duration::days(7),
-///     ))
-///     // ---------- configure additional filter ----------
-///     // write to "ftlog-appender" appender, with different level filter
-///     .filter(|msg, level, target| target == "ftlog::appender"&& level == LevelFilter::Error, "ftlog-appender")
-///     // write to root appender, but with different level filter
-///     .filter(|msg, level, target| target == "ftlog" && level == LevelFilter::Trace, None)
-///     // write to "ftlog" appender, with default level filter
-///     .filter(|msg, level, target| target == "ftlog::appender::file", "ftlog")
-///     // ----------  configure additional appender ----------
-///     // new appender
-///     .appender("ftlog-appender", FileAppender::new("ftlog-appender.log"))
-///     // new appender, rotate to new file every Day
-///     .appender("ftlog", FileAppender::rotate("ftlog.log", Period::Day))
-///     .build()
-///     .expect("logger build failed");
+/// # use std::time::Duration;
+/// let _guard = ftlog::builder().try_init().unwrap();
+/// log::info!("Hello world!");
+/// let report = ftlog::shutdown(Duration::from_secs(3)).unwrap();
+/// assert_eq!(report.abandoned, 0);
 /// ```
+pub fn shutdown(timeout: Duration) -> Option<ShutdownReport> {
+    GLOBAL_LOGGER.get().map(|x| x.shutdown(Some(timeout)))
+}
+
+/// A guard that flushes logs associated to a Logger on a drop
 ///
-/// # Local timezone
-/// For performance reason, `ftlog` only retrieve timezone info once and use this
-/// local timezone offset forever. Thus timestamp in log does not aware of timezone
-/// change by OS.
-pub struct Builder {
+/// With this guard, you can ensure all logs are written to destination
+/// when the application exits.
+///
+/// When dropped, the logger stops accepting new log messages, writes all queued
+/// messages, flushes appenders and joins log thread. Log calls after the guard is
+/// dropped are ignored. To avoid hanging at exit when an appender is stuck, see
+/// `Builder::shutdown_timeout`.
+pub struct LoggerGuard {
+    shared: Arc<Shared>,
+}
+impl Drop for LoggerGuard {
+    fn drop(&mut self) {
+        let timeout = self.shared.shutdown_timeout;
+        match GLOBAL_LOGGER.get() {
+            Some(logger) if Arc::ptr_eq(&logger.shared, &self.shared) => {
+                logger.shutdown(timeout);
+            }
+            // the logger failed to be installed
+            _ => {
+                self.shared.shutdown(timeout);
+            }
+        }
+    }
+}
+/// ftlog logger
+///
+/// Installed as the global logger by [`Logger::init`], or used standalone with
+/// [`log_to!`]. Dropping a standalone logger writes queued records and joins its log
+/// thread.
+pub struct Logger {
     format: Box<dyn FtLogFormat>,
-    time_format: Option<OwnedFormatItem>,
-    level: Option<LevelFilter>,
-    root_level: Option<LevelFilter>,
-    root: Box<dyn Write + Send>,
-    appenders: HashMap<&'static str, Box<dyn Write + Send + 'static>>,
-    filters: Vec<Directive>,
-    drop_filters: Vec<Box<dyn Fn(&Record) -> bool + Send + Sync>>,
-    bounded_channel_option: Option<BoundedChannelOption>,
-    timezone: LogTimezone,
+    // target prefixes with their own `RecordFormatter`
+    format_targets: Vec<&'static str>,
+    // the default format, to send static messages without allocating
+    fast_path: Option<Arc<DefaultFormat>>,
+    rate_limit: Option<CallsiteLimiter>,
+    // take timestamp at call site, or leave it to log thread
+    call_site_time: bool,
+    // next sequence number of records, see `Builder::sequence`
+    sequence: Option<AtomicU64>,
+    clock: Option<Arc<dyn Clock>>,
+    // fraction of records kept for each level, indexed by `Level as usize`
+    #[cfg(feature = "random_drop")]
+    sample: [f32; 6],
+    filters: Vec<DropFilter>,
+    metadata_filters: Vec<MetadataFilter>,
+    scrubs: Vec<Scrub>,
+    middlewares: Vec<Box<dyn middleware::Middleware>>,
+    shared: Arc<Shared>,
+    overflow: OverflowPolicy,
+    // only kept with `OverflowPolicy::DropOldest` to evict queued records
+    receiver: Option<queue::Receiver<LoggerInput>>,
+    // queue length from which only `Error` records are accepted, when part of the
+    // channel is reserved for them
+    reserve_from: Option<usize>,
+    // records at least as severe are written and flushed before log call returns
+    sync_level: LevelFilter,
+    crash_context: Option<crash_context::CrashContext>,
+    #[cfg(feature = "tracing")]
+    capture_tracing: bool,
+    discard_state: Option<DiscardState>,
+    overflow_notice: Option<OverflowNotice>,
+    stopped: AtomicBool,
+}
+
+static GLOBAL_PIPELINE: OnceLock<Arc<Shared>> = OnceLock::new();
+/// the installed logger, owning `GLOBAL_PIPELINE`
+static GLOBAL_LOGGER: OnceLock<&'static Logger> = OnceLock::new();
+
+/// Change the max log level of the global logger at runtime
+///
+/// Unlike [`log::set_max_level`], this also lets the root appender write records of
+/// the new level, unless its level is set by [`Builder::root_log_level`]. Appenders
+/// with their own level keep it.
+///
+/// ```
+/// # use ftlog::LevelFilter;
+/// let _guard = ftlog::builder().try_init().unwrap();
+/// log::debug!("dropped");
+/// ftlog::set_level(LevelFilter::Debug);
+/// log::debug!("written");
+/// ```
+pub fn set_level(level: LevelFilter) {
+    match GLOBAL_PIPELINE.get() {
+        Some(pipeline) => pipeline.set_level(level),
+        None => set_max_level(level),
+    }
+}
+
+/// Override the max log level of records of the global logger whose target starts
+/// with `prefix`, or remove the override with `None`
+///
+/// The longest matching prefix wins, other records follow the level of
+/// [`set_level`]. Does nothing if ftlog is not installed as the global logger.
+///
+/// ```
+/// # use ftlog::LevelFilter;
+/// let _guard = ftlog::builder().try_init().unwrap();
+/// ftlog::set_target_level("hyper", Some(LevelFilter::Warn));
+/// ftlog::set_target_level("app::db", Some(LevelFilter::Debug));
+/// ```
+pub fn set_target_level(prefix: &str, level: Option<LevelFilter>) {
+    if let Some(pipeline) = GLOBAL_PIPELINE.get() {
+        pipeline.set_target_level(prefix, level);
+    }
+}
+
+/// Switch the format of the whole line of the global logger at runtime, e.g. from
+/// JSON to text while a developer is watching a running service
+///
+/// This overrides [`Builder::formatter`] and [`Builder::format`], but not
+/// [`Builder::format_for_target`]. Records queued before the switch may still be
+/// written in the previous format. Does nothing if ftlog is not installed as the
+/// global logger.
+///
+/// ```
+/// use ftlog::format::Format;
+///
+/// let _guard = ftlog::builder().formatter(Format::Json).try_init().unwrap();
+/// log::info!("as JSON");
+/// ftlog::set_format(Format::Text);
+/// log::info!("as text");
+/// ```
+pub fn set_format(format: Format) {
+    if let Some(pipeline) = GLOBAL_PIPELINE.get() {
+        pipeline.set_format(format);
+    }
+}
+
+/// Source of timestamps, see [`time_backend`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeBackend {
+    /// TSC register of the CPU, with `tsc` feature
+    Tsc,
+    /// monotonic clock of the OS, `std::time::Instant`
+    Monotonic,
+}
+
+/// Source of timestamps of loggers without a custom [`Clock`]
+///
+/// With `tsc` feature, TSC is checked the first time it is needed, at the latest when a
+/// logger is built. Where it is not calibrated, e.g. without invariant TSC on some
+/// VMs, or its pace does not agree with the monotonic clock of the OS, ftlog falls back
+/// to `std::time::Instant`.
+///
+/// ```
+/// let backend = ftlog::time_backend();
+/// if cfg!(not(feature = "tsc")) {
+///     assert_eq!(backend, ftlog::TimeBackend::Monotonic);
+/// }
+/// ```
+pub fn time_backend() -> TimeBackend {
+    #[cfg(feature = "tsc")]
+    if tm::tsc() {
+        return TimeBackend::Tsc;
+    }
+    TimeBackend::Monotonic
+}
+
+/// Statistics of the log pipeline of the global logger
+///
+/// Returns `None` if ftlog is not installed as the global logger. See [`stats`](mod@stats)
+/// module for details.
+pub fn stats() -> Option<StatsSnapshot> {
+    GLOBAL_PIPELINE
+        .get()
+        .map(|p| p.metrics.snapshot(p.queue.len()))
+}
+
+/// Check that the log pipeline of the global logger works, e.g. in a readiness probe
+///
+/// A marker line `-- selftest --` is sent through the pipeline like a record, and
+/// written and flushed by every appender, regardless of their level. Appenders with
+/// a binary format are only flushed. Returns the latency of each stage and
+/// [`stats()`] once all appenders are done, or an error if it takes longer than
+/// `timeout`.
+///
+/// ```
+/// # use std::time::Duration;
+/// let _guard = ftlog::builder().try_init().unwrap();
+/// let report = ftlog::selftest(Duration::from_secs(1)).unwrap();
+/// assert!(report.healthy());
+/// println!("queued for {:?}, written in {:?}", report.queued, report.total);
+/// ```
+pub fn selftest(timeout: Duration) -> Result<SelfTest, SelfTestError> {
+    GLOBAL_PIPELINE
+        .get()
+        .ok_or(SelfTestError::NotInstalled)?
+        .selftest(timeout)
+}
+
+/// Receive lines written by the global logger, see [`tap`](mod@tap) module
+///
+/// The receiver is disconnected if ftlog is not installed as the global logger.
+pub fn subscribe() -> Receiver<Arc<FormattedRecord>> {
+    match GLOBAL_PIPELINE.get() {
+        Some(pipeline) => pipeline.tap.subscribe(),
+        None => bounded(0).1,
+    }
+}
+
+/// Value of a key-value computed only if the record is logged
+///
+/// The closure is not called for records dropped by level, filters or sampling.
+/// With [`Lazy::in_log_thread`](format::Lazy::in_log_thread), it is called in log
+/// thread, keeping expensive diagnostics off the hot path.
+///
+/// ```
+/// use ftlog::lazy;
+/// # let _guard = ftlog::builder().try_init().unwrap();
+/// let buf = vec![0u8; 4096];
+/// log::debug!(payload = lazy(|| format!("{:?}", buf)); "sent");
+/// let summary = format!("{} bytes", buf.len());
+/// log::info!(payload = lazy(move || summary.clone()).in_log_thread(); "sent");
+/// // Output:
+/// // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:6] payload=4096 bytes sent
+/// ```
+pub fn lazy<F, T>(f: F) -> format::Lazy<F>
+where
+    F: Fn() -> T,
+    T: Display,
+{
+    format::Lazy(f)
+}
+
+impl Logger {
+    pub fn init(self) -> Result<LoggerGuard, SetLoggerError> {
+        let guard = LoggerGuard {
+            shared: self.shared.clone(),
+        };
+
+        set_max_level(self.shared.max_level());
+        let pipeline = self.shared.clone();
+        #[cfg(feature = "tracing")]
+        let capture_tracing = self.capture_tracing;
+        // the global logger is never dropped, kept to write the summary of
+        // `Builder::once_summary` through it at shutdown; if another logger is set,
+        // it is left behind with its pipeline shut down as `guard` drops
+        let logger: &'static Logger = Box::leak(Box::new(self));
+        set_logger(logger).map(|_| {
+            let _ = GLOBAL_PIPELINE.set(pipeline);
+            let _ = GLOBAL_LOGGER.set(logger);
+            #[cfg(feature = "tracing")]
+            if capture_tracing {
+                if let Err(e) = tracing::install() {
+                    eprintln!("ftlog failed to capture tracing events: {}", e);
+                }
+            }
+            guard
+        })
+    }
+
+    /// Change the max log level of this logger at runtime
+    ///
+    /// The root appender follows the new level unless its level is set by
+    /// [`Builder::root_log_level`]. For the global logger, use [`set_level`].
+    pub fn set_level(&self, level: LevelFilter) {
+        self.shared.set_level(level);
+    }
+
+    /// Override the max log level of records whose target starts with `prefix`, or
+    /// remove the override with `None`
+    ///
+    /// The longest matching prefix wins. For the global logger, use
+    /// [`set_target_level`].
+    pub fn set_target_level(&self, prefix: &str, level: Option<LevelFilter>) {
+        self.shared.set_target_level(prefix, level);
+    }
+
+    /// Switch the format of the whole line of this logger at runtime, see
+    /// [`set_format`]
+    pub fn set_format(&self, format: Format) {
+        self.shared.set_format(format);
+    }
+
+    /// Receive lines written by this logger, see [`tap`](mod@tap) module
+    pub fn subscribe(&self) -> Receiver<Arc<FormattedRecord>> {
+        self.shared.tap.subscribe()
+    }
+
+    /// Statistics of the log pipeline of this logger
+    pub fn stats(&self) -> StatsSnapshot {
+        self.shared.metrics.snapshot(self.shared.queue.len())
+    }
+
+    /// Check that the log pipeline of this logger works, see [`selftest`]
+    pub fn selftest(&self, timeout: Duration) -> Result<SelfTest, SelfTestError> {
+        self.shared.selftest(timeout)
+    }
+}
+
+impl Drop for Logger {
+    fn drop(&mut self) {
+        // only reachable for standalone loggers, as the global one is never dropped
+        self.shutdown(self.shared.shutdown_timeout);
+    }
 }
 
-/// Handy function to get ftlog builder
-#[inline]
-pub fn builder() -> Builder {
-    Builder::new()
-}
+impl Logger {
+    /// Log the summary of `Builder::once_summary` through this logger, then shut down
+    /// its pipeline
+    fn shutdown(&self, timeout: Option<Duration>) -> ShutdownReport {
+        if self.shared.once_summary && !self.shared.closed.load(Ordering::SeqCst) {
+            rate_limit::log_once_summary(self);
+        }
+        self.shared.shutdown(timeout)
+    }
+
+    fn closed(&self) {
+        let stop = self.stopped.load(Ordering::SeqCst);
+        if !stop {
+            eprintln!("logger queue closed when logging, this is a bug");
+            self.stopped.store(true, Ordering::SeqCst)
+        }
+    }
+
+    /// Send a record to log thread, following the overflow policy
+    fn send(&self, msg: LogMsg) {
+        let level = msg.level;
+        if let Some(per_thread) = &self.shared.per_thread {
+            let shared = &self.shared;
+            if let Some(line) =
+                with_error_handler(&shared.on_internal_error, || per_thread.write(msg))
+            {
+                self.shared.tap.send(level, &line);
+                self.shared.metrics.count(level);
+            }
+            return;
+        }
+        match &self.shared.batches {
+            Some(batches) => batches.push(msg, level == Level::Error, |batch| {
+                self.enqueue(LoggerInput::Batch(batch), level)
+            }),
+            None => self.enqueue(LoggerInput::LogMsg(msg), level),
+        }
+    }
+
+    /// Send a message carrying records to log thread, `level` being the most severe
+    /// level of the records
+    fn enqueue(&self, msg: LoggerInput, level: Level) {
+        // `Error` records may use the reserved part of the channel, others are treated
+        // as overflowing once the rest is full
+        self.shared.send_dropped();
+        let priority = level == Level::Error && self.reserve_from.is_some();
+        let reserved = self
+            .reserve_from
+            .is_some_and(|x| !priority && self.shared.queue.len() >= x);
+        match self.overflow {
+            OverflowPolicy::Block => {
+                if self.shared.queue.send(msg).is_err() {
+                    self.closed();
+                }
+            }
+            // wait for log thread instead of discarding `Error` records
+            _ if priority => {
+                if self.shared.queue.send(msg).is_err() {
+                    self.closed();
+                }
+            }
+            OverflowPolicy::DropNewest if reserved => self.discard(msg.records()),
+            OverflowPolicy::DropNewest => match self.shared.queue.try_send(msg) {
+                Err(TrySendError::Full(msg)) => self.discard(msg.records()),
+                Err(TrySendError::Disconnected(_)) => self.closed(),
+                _ => (),
+            },
+            OverflowPolicy::DropOldest if reserved => self.drop_oldest(msg),
+            OverflowPolicy::DropOldest => match self.shared.queue.try_send(msg) {
+                Err(TrySendError::Full(msg)) => self.drop_oldest(msg),
+                Err(TrySendError::Disconnected(_)) => self.closed(),
+                _ => (),
+            },
+        }
+    }
+
+    fn discard(&self, records: usize) {
+        for _ in 0..records {
+            self.shared.metrics.count_dropped();
+        }
+        if let Some(dropped) = &self.shared.dropped {
+            dropped.fetch_add(records, Ordering::Relaxed);
+        }
+        if let Some(s) = &self.discard_state {
+            let count = s.count.fetch_add(records, Ordering::SeqCst);
+            if s.last.load().elapsed().as_secs() >= 5 {
+                eprintln!("Excessive log messages. Log omitted: {}", count);
+                s.last.store(Arc::new(Instant::now()));
+            }
+        }
+        if let Some(notice) = &self.overflow_notice {
+            notice.pending.fetch_add(records, Ordering::Relaxed);
+            if notice.limit.allow(OVERFLOW_NOTICE_PERIOD) {
+                let dropped = notice.pending.swap(0, Ordering::Relaxed);
+                if dropped > 0 {
+                    (notice.callback)(dropped);
+                }
+            }
+        }
+    }
+
+    fn drop_oldest(&self, msg: LoggerInput) {
+        if let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Ok(LoggerInput::LogMsg(queued))
+                    if self.reserve_from.is_none() || queued.level != Level::Error =>
+                {
+                    self.discard(1)
+                }
+                Ok(LoggerInput::Batch(queued))
+                    if self.reserve_from.is_none()
+                        || queued.iter().all(|x| x.level != Level::Error) =>
+                {
+                    self.discard(queued.len())
+                }
+                // control messages and reserved `Error` records are not evicted,
+                // requeue it into the slot just freed, never blocking the log call;
+                // if another log call takes the slot first, it is dropped, and the
+                // sender of a control message sees the log thread hang up
+                Ok(input) => match self.shared.queue.try_send(input) {
+                    Err(TrySendError::Full(input)) => self.discard(input.records()),
+                    Err(TrySendError::Disconnected(_)) => {
+                        self.closed();
+                        return;
+                    }
+                    _ => (),
+                },
+                Err(_) => (),
+            }
+        }
+        match self.shared.queue.try_send(msg) {
+            Err(TrySendError::Full(msg)) => self.discard(msg.records()),
+            Err(TrySendError::Disconnected(_)) => self.closed(),
+            _ => (),
+        }
+    }
+}
+
+impl Logger {
+    /// Run scrub hooks and middlewares, `None` to drop the record, `Some(None)` to
+    /// keep it as is
+    fn rewrite(&self, record: &Record) -> Option<Option<middleware::OwnedRecord>> {
+        if self.scrubs.is_empty() && self.middlewares.is_empty() {
+            return Some(None);
+        }
+        let mut owned = middleware::OwnedRecord::new(record);
+        for scrub in &self.scrubs {
+            scrub(&mut owned.message, &mut owned.key_values);
+        }
+        let mut modified = !self.scrubs.is_empty();
+        for middleware in &self.middlewares {
+            match middleware.process(&mut owned) {
+                middleware::Action::Keep => (),
+                middleware::Action::Modify => modified = true,
+                middleware::Action::Drop => return None,
+            }
+        }
+        Some(modified.then_some(owned))
+    }
+
+    #[inline]
+    fn payload(&self, record: &Record) -> Payload {
+        if self.shared.record_fields.load(Ordering::Relaxed)
+            || self
+                .format_targets
+                .iter()
+                .any(|x| record.target().starts_with(x))
+        {
+            Payload::Record(Box::new(RecordFields::new(record)))
+        } else if let Some(format) = &self.fast_path {
+            if let Some(msg) = DefaultFormat::static_msg(format, record) {
+                return Payload::Static(msg);
+            }
+            let mut msg = self.shared.pool.take();
+            format.fill(&mut msg, record);
+            Payload::Message(msg)
+        } else {
+            Payload::Display(self.format.msg(record))
+        }
+    }
+}
+
+impl Log for Logger {
+    #[inline]
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // level is already checked in log macros
+        self.shared.level_of(metadata.target()) >= metadata.level()
+            && self.metadata_filters.iter().all(|f| f(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.shared.throttle.pending() {
+            for (level, notice) in self.shared.throttle.take() {
+                self.log(
+                    &Record::builder()
+                        .level(level)
+                        .target("ftlog::governor")
+                        .module_path_static(Some("ftlog::governor"))
+                        .file_static(Some(file!()))
+                        .line(Some(line!()))
+                        .args(format_args!("{}", notice))
+                        .build(),
+                );
+            }
+        }
+        if self.shared.closed.load(Ordering::Relaxed)
+            || record.level() > self.shared.level_of(record.target())
+        {
+            return;
+        }
+        if !self.metadata_filters.iter().all(|f| f(record.metadata())) {
+            return;
+        }
+        #[cfg(feature = "random_drop")]
+        {
+            let keep = self.sample[record.level() as usize];
+            if keep < 1. && fastrand::f32() >= keep {
+                return;
+            }
+            let random_drop = record
+                .key_values()
+                .get(Key::from_str("random_drop"))
+                .or_else(|| record.key_values().get(Key::from_str("drop")))
+                .and_then(|x| x.to_f64())
+                .unwrap_or(1.) as f32;
+            if random_drop < 1. && fastrand::f32() < random_drop {
+                return;
+            }
+        }
+
+        let limit = record
+            .key_values()
+            .get(Key::from_str("limit"))
+            .and_then(|x| x.to_u64())
+            .unwrap_or(0) as u32;
+
+        // This will short circuit if any of the filters return false, meaning don't keep this record.
+        if !self.filters.is_empty() && self.filters.iter().all(|filter| filter(record)) {
+            // Drop this log record
+            return;
+        }
+
+        let limit_key = if limit == 0 && self.rate_limit.is_none() {
+            0
+        } else {
+            let mut b = hashbrown::hash_map::DefaultHashBuilder::default().build_hasher();
+            if let Some(p) = record.module_path() {
+                p.as_bytes().hash(&mut b);
+            } else {
+                record.file().unwrap_or("").as_bytes().hash(&mut b);
+            }
+            record.line().unwrap_or(0).hash(&mut b);
+            b.finish()
+        };
+        // default rate limit applies to log calls without their own `limit`
+        if let (0, Some(rate_limit)) = (limit, &self.rate_limit) {
+            if !rate_limit.allow(limit_key) {
+                return;
+            }
+        }
+        let Some(rewritten) = self.rewrite(record) else {
+            return;
+        };
+        let (msg, level, target) = match rewritten {
+            None => (
+                self.payload(record),
+                record.level(),
+                match record.module_path_static() {
+                    // target defaults to module path, which is static
+                    Some(path) if path == record.target() => Symbol::from_static(path),
+                    _ => Symbol::new(record.target()),
+                },
+            ),
+            Some(owned) => (
+                self.payload(
+                    &record
+                        .to_builder()
+                        .level(owned.level)
+                        .target(&owned.target)
+                        .args(format_args!("{}", owned.message))
+                        .key_values(&owned.key_values)
+                        .build(),
+                ),
+                owned.level,
+                Symbol::new(&owned.target),
+            ),
+        };
+        let msg = LogMsg {
+            time: self
+                .call_site_time
+                .then(|| Stamp::now(self.clock.as_deref())),
+            seq: self
+                .sequence
+                .as_ref()
+                .map(|x| x.fetch_add(1, Ordering::Relaxed)),
+            msg,
+            target,
+            level,
+            limit,
+            limit_key,
+        };
+        if let Some(context) = &self.crash_context {
+            if context.buffered(level) {
+                context.keep(msg);
+                return;
+            }
+            if level == Level::Error {
+                for kept in context.take() {
+                    self.send(kept);
+                }
+            }
+        }
+        self.send(msg);
+        if level <= self.sync_level {
+            self.flush();
+        }
+    }
+
+    /// Block until records logged before the call are written and appenders are
+    /// flushed, e.g. with `log::logger().flush()` before the process exits or forks
+    ///
+    /// Records of other threads logged meanwhile may be written too. Returns at once
+    /// after shutdown.
+    fn flush(&self) {
+        self.shared.flush();
+    }
+}
+
+/// Behavior of log calls when the bounded channel to log thread is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// block the thread calling log macros until log thread catches up
+    Block,
+    /// discard the record being logged
+    DropNewest,
+    /// discard the oldest queued record to make room for the record being logged
+    DropOldest,
+}
+
+/// Handling of line breaks in messages, see [`Builder::multiline`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Multiline {
+    /// write line breaks as they are
+    #[default]
+    AsIs,
+    /// write line breaks as `\n` and `\r`, keeping a record on a single line
+    Escape,
+    /// start each continuation line with the prefix, e.g. `"\t"` which many
+    /// shippers join to the previous line
+    Indent(&'static str),
+}
+
+/// Queue carrying records from log calls to log thread, see [`Builder::queue`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueKind {
+    /// a crossbeam channel, bounded by [`Builder::channel_capacity`] or unbounded
+    Channel,
+    /// a lock-free ring buffer of the given capacity, allocated upfront
+    Ring(usize),
+}
+
+struct BoundedChannelOption {
+    size: usize,
+    policy: OverflowPolicy,
+    print: bool,
+    reserve: usize,
+}
+
+impl Default for BoundedChannelOption {
+    fn default() -> Self {
+        BoundedChannelOption {
+            size: 100_000,
+            policy: OverflowPolicy::DropNewest,
+            print: true,
+            reserve: 0,
+        }
+    }
+}
+
+/// Ftlog builder
+///
+/// ```
+/// # use ftlog::appender::{FileAppender, Duration, Period};
+/// # use log::LevelFilter;
+/// let logger = ftlog::builder()
+///     // use our own format
+///     .format(ftlog::FtLogFormatter)
+///     // global max log level
+///     .max_log_level(LevelFilter::Info)
+///     // define root appender, pass anything that is Write and Send
+///     // omit `Builder::root` to write to stderr
+///     .root(FileAppender::rotate_with_expire(
+///         "./current.log",
+///         Period::Day,
+///         Duration::days(7),
+///     ))
+///     // ---------- configure additional filter ----------
+///     // write to "ftlog-appender" appender, with different level filter
+///     .filter(|msg, level, target| target == "ftlog::appender"&& level == LevelFilter::Error, "ftlog-appender")
+///     // write to root appender, but with different level filter
+///     .filter(|msg, level, target| target == "ftlog" && level == LevelFilter::Trace, None)
+///     // write to "ftlog" appender, with default level filter
+///     .filter(|msg, level, target| target == "ftlog::appender::file", "ftlog")
+///     // ----------  configure additional appender ----------
+///     // new appender
+///     .appender("ftlog-appender", FileAppender::new("ftlog-appender.log"))
+///     // new appender, rotate to new file every Day
+///     .appender("ftlog", FileAppender::rotate("ftlog.log", Period::Day))
+///     .build()
+///     .expect("logger build failed");
+/// ```
+///
+/// # Local timezone
+/// For performance reason, `ftlog` retrieves the local timezone offset at most once a
+/// minute in log thread, and uses it for every record of that minute. Thus timestamps
+/// follow a timezone change by OS, like a DST transition, within a minute.
+pub struct Builder {
+    // `None` for `FtLogFormatter`, which is configurable by builder
+    format: Option<Box<dyn FtLogFormat>>,
+    with_thread: bool,
+    with_source_location: bool,
+    abbreviate_source_path: bool,
+    color: bool,
+    global_fields: Vec<(&'static str, String)>,
+    time_format: Option<TimeFormat>,
+    formatter: Option<Box<dyn RecordFormatter>>,
+    target_formatters: Vec<(&'static str, Box<dyn RecordFormatter>)>,
+    appender_formatters: Vec<(&'static str, Box<dyn RecordFormatter>)>,
+    time_precision: TimePrecision,
+    timestamp_source: TimestampSource,
+    clock: Option<Arc<dyn Clock>>,
+    level: Option<LevelFilter>,
+    /// levels of target prefixes, see [`Builder::silence_below`]
+    target_levels: Vec<(String, LevelFilter)>,
+    renames: Vec<(&'static str, &'static str)>,
+    root_level: Option<LevelFilter>,
+    root: Box<dyn appender::Appender>,
+    appenders: HashMap<&'static str, Destination>,
+    attached: Vec<&'static str>,
+    routes: Vec<Route>,
+    filters: Vec<Directive>,
+    drop_filters: Vec<DropFilter>,
+    metadata_filters: Vec<MetadataFilter>,
+    scrubs: Vec<Scrub>,
+    middlewares: Vec<Box<dyn middleware::Middleware>>,
+    enrichers: Vec<Box<dyn enrich::Enricher>>,
+    on_overflow: Option<OverflowCallback>,
+    on_internal_error: Option<InternalErrorHandler>,
+    bounded_channel_option: Option<BoundedChannelOption>,
+    timezone: LogTimezone,
+    shutdown_timeout: Option<Duration>,
+    workers: usize,
+    flush_interval: Duration,
+    appender_flush_intervals: Vec<(&'static str, Duration)>,
+    default_rate_limit: Option<Duration>,
+    once_summary: bool,
+    sync_level: LevelFilter,
+    crash_context: Option<(LevelFilter, usize)>,
+    #[cfg(feature = "redact")]
+    redactions: Vec<(String, String)>,
+    #[cfg(feature = "message_filter")]
+    message_rules: Vec<(String, message_filter::Action)>,
+    sanitize: bool,
+    max_message_len: Option<usize>,
+    multiline: Multiline,
+    also_stderr: Option<LevelFilter>,
+    governor: Option<governor::Governor>,
+    per_thread: Option<std::path::PathBuf>,
+    low_contention: bool,
+    queue: QueueKind,
+    reorder_window: Option<Duration>,
+    sequence: bool,
+    drop_markers: bool,
+    #[cfg(all(unix, feature = "signal"))]
+    signal_toggles: Vec<(i32, LevelFilter)>,
+    #[cfg(feature = "control")]
+    control: Option<control::Listen>,
+    #[cfg(feature = "random_drop")]
+    sample: Vec<(LevelFilter, f32)>,
+    #[cfg(feature = "tracing")]
+    capture_tracing: bool,
+}
+
+/// Handy function to get ftlog builder
+#[inline]
+pub fn builder() -> Builder {
+    Builder::new()
+}
+
+/// Builder with the interface of `env_logger::builder()`, configured by `RUST_LOG`,
+/// see [`env`](mod@env)
+#[inline]
+pub fn env_builder() -> env::EnvBuilder {
+    env::EnvBuilder::from_default_env()
+}
+
+type GlobalFields = Arc<[(&'static str, String)]>;
+type DropFilter = Box<dyn Fn(&Record) -> bool + Send + Sync>;
+type MetadataFilter = Box<dyn Fn(&Metadata) -> bool + Send + Sync>;
+type Scrub = Box<dyn Fn(&mut String, &mut KvMap) + Send + Sync>;
+type DirectiveFilter = Box<dyn Fn(&dyn Display, Level, &str) -> bool + Send>;
+
+struct Directive {
+    pub(crate) filter: DirectiveFilter,
+    pub(crate) appender: Option<&'static str>,
+}
+/// Timestamp format of log messages
+///
+/// Any format description of `time` crate converts into `TimeFormat`, including
+/// the one made by `time::macros::format_description!` at compile time:
+///
+/// ```
+/// # use ftlog::TimeFormat;
+/// let _guard = ftlog::builder()
+///     .time_format(time::macros::format_description!(
+///         "[hour]:[minute]:[second].[subsecond digits:3]"
+///     ))
+///     .try_init()
+///     .unwrap();
+/// ```
+pub enum TimeFormat {
+    /// custom format description, see
+    /// [time crate book](https://time-rs.github.io/book/api/format-description.html)
+    /// for syntax
+    Custom(OwnedFormatItem),
+    /// RFC3339, e.g. `2023-06-14T11:13:26.160+08:00`
+    Rfc3339,
+    /// time since unix epoch, in unit of [`TimePrecision`], e.g. `1686712406160`
+    /// in milliseconds
+    UnixEpoch,
+}
+
+impl TimeFormat {
+    /// Replace built-in formats with format description of given precision
+    fn with_precision(self, precision: TimePrecision) -> TimeFormat {
+        match self {
+            TimeFormat::Rfc3339 => time::format_description::parse_owned::<1>(&format!(
+                "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:{}][offset_hour sign:mandatory]:[offset_minute]",
+                precision.digits()
+            ))
+            .unwrap()
+            .into(),
+            x => x,
+        }
+    }
+}
+
+/// Resolution of timestamp in log messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimePrecision {
+    /// 3 digits of subsecond, the default
+    #[default]
+    Millis,
+    /// 6 digits of subsecond
+    Micros,
+    /// 9 digits of subsecond
+    Nanos,
+}
+
+impl TimePrecision {
+    fn digits(self) -> u8 {
+        match self {
+            TimePrecision::Millis => 3,
+            TimePrecision::Micros => 6,
+            TimePrecision::Nanos => 9,
+        }
+    }
+
+    /// Nanoseconds of a unit
+    pub(crate) fn unit_nanos(self) -> i128 {
+        match self {
+            TimePrecision::Millis => 1_000_000,
+            TimePrecision::Micros => 1_000,
+            TimePrecision::Nanos => 1,
+        }
+    }
+}
+
+/// When the timestamp of a log message is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampSource {
+    /// when the log macro is called, the default
+    ///
+    /// This is the true time of the event, and delay between the log call and log
+    /// thread is printed after the timestamp.
+    #[default]
+    CallSite,
+    /// when log thread handles the message
+    ///
+    /// This saves reading the clock at call site. Under load, the timestamp lags behind
+    /// the event by the time the message waits in the channel, and the printed delay
+    /// is always 0ms.
+    LogThread,
+}
+
+impl From<OwnedFormatItem> for TimeFormat {
+    fn from(format: OwnedFormatItem) -> Self {
+        TimeFormat::Custom(format)
+    }
+}
+
+impl From<&[BorrowedFormatItem<'_>]> for TimeFormat {
+    fn from(format: &[BorrowedFormatItem<'_>]) -> Self {
+        TimeFormat::Custom(format.into())
+    }
+}
+
+impl From<Vec<BorrowedFormatItem<'_>>> for TimeFormat {
+    fn from(format: Vec<BorrowedFormatItem<'_>>) -> Self {
+        TimeFormat::Custom(format.as_slice().into())
+    }
+}
+
+/// timezone for log
+pub enum LogTimezone {
+    /// local timezone
+    ///
+    /// Only *unix OS is supported for now
+    Local,
+    /// UTC timezone
+    Utc,
+    /// fixed timezone
+    Fixed(UtcOffset),
+}
+
+impl Builder {
+    #[inline]
+    /// Create a ftlog builder with default settings:
+    /// - global log level: INFO
+    /// - root log level: INFO
+    /// - no drop filters
+    /// - default formatter: `FtLogFormatter`
+    /// - output to stderr
+    /// - bounded channel between worker thread and log thread, with a size limit of 100_000
+    /// - discard excessive log messages
+    /// - log with timestamp of local timezone
+    pub fn new() -> Builder {
+        Builder {
+            format: None,
+            with_thread: true,
+            with_source_location: true,
+            abbreviate_source_path: false,
+            color: false,
+            global_fields: Vec::new(),
+            level: None,
+            target_levels: Vec::new(),
+            renames: Vec::new(),
+            root_level: None,
+            root: Sink::from(stderr()).0,
+            appenders: HashMap::new(),
+            attached: Vec::new(),
+            routes: Vec::new(),
+            filters: Vec::new(),
+            drop_filters: Vec::new(),
+            metadata_filters: Vec::new(),
+            scrubs: Vec::new(),
+            middlewares: Vec::new(),
+            enrichers: Vec::new(),
+            on_overflow: None,
+            on_internal_error: None,
+            bounded_channel_option: Some(BoundedChannelOption::default()),
+            timezone: LogTimezone::Local,
+            time_format: None,
+            formatter: None,
+            target_formatters: Vec::new(),
+            appender_formatters: Vec::new(),
+            time_precision: TimePrecision::Millis,
+            timestamp_source: TimestampSource::CallSite,
+            clock: None,
+            shutdown_timeout: None,
+            workers: 1,
+            flush_interval: Duration::from_secs(1),
+            appender_flush_intervals: Vec::new(),
+            default_rate_limit: None,
+            once_summary: false,
+            sync_level: LevelFilter::Off,
+            crash_context: None,
+            #[cfg(feature = "redact")]
+            redactions: Vec::new(),
+            #[cfg(feature = "message_filter")]
+            message_rules: Vec::new(),
+            sanitize: false,
+            max_message_len: None,
+            multiline: Multiline::AsIs,
+            also_stderr: None,
+            governor: None,
+            per_thread: None,
+            low_contention: false,
+            queue: QueueKind::Channel,
+            reorder_window: None,
+            sequence: false,
+            drop_markers: false,
+            #[cfg(all(unix, feature = "signal"))]
+            signal_toggles: Vec::new(),
+            #[cfg(feature = "control")]
+            control: None,
+            #[cfg(feature = "tracing")]
+            capture_tracing: false,
+            #[cfg(feature = "random_drop")]
+            sample: Vec::new(),
+        }
+    }
+
+    /// Set custom formatter
+    #[inline]
+    pub fn format<F: FtLogFormat + 'static>(mut self, format: F) -> Builder {
+        self.format = if TypeId::of::<F>() == TypeId::of::<FtLogFormatter>() {
+            None
+        } else {
+            Some(Box::new(format))
+        };
+        self
+    }
+
+    /// Include name of the thread making log call in default format, defaults to `true`
+    ///
+    /// ID of the thread is printed instead (e.g. `ThreadId(2)`) if the thread is not named.
+    /// This only affects [`FtLogFormatter`], thread info is always available to
+    /// [`RecordFormatter`] by [`LogRecord::thread`](format::LogRecord::thread) and
+    /// [`LogRecord::thread_id`](format::LogRecord::thread_id).
+    #[inline]
+    pub fn with_thread(mut self, with_thread: bool) -> Builder {
+        self.with_thread = with_thread;
+        self
+    }
+
+    /// Include source file and line of log call (e.g. `[src/main.rs:12]`) in default
+    /// format, defaults to `true`
+    ///
+    /// This only affects [`FtLogFormatter`].
+    #[inline]
+    pub fn with_source_location(mut self, with_source_location: bool) -> Builder {
+        self.with_source_location = with_source_location;
+        self
+    }
+
+    /// Shorten directories of source file in default format to their first character,
+    /// e.g. `[s/a/file.rs:12]` for `src/appender/file.rs`, defaults to `false`
+    ///
+    /// This only affects [`FtLogFormatter`].
+    #[inline]
+    pub fn abbreviate_source_path(mut self, abbreviate: bool) -> Builder {
+        self.abbreviate_source_path = abbreviate;
+        self
+    }
+
+    /// Color levels in default format, defaults to `false`
+    ///
+    /// Color codes are only kept for appenders writing to a terminal, see
+    /// [`Appender::colors`](appender::Appender::colors), so a file appender still
+    /// gets plain lines.
+    ///
+    /// This only affects [`FtLogFormatter`].
+    #[inline]
+    pub fn color(mut self, color: bool) -> Builder {
+        self.color = color;
+        self
+    }
+
+    /// Attach a field with fixed value to all log records, e.g. name and version
+    /// of the service
+    ///
+    /// ```
+    /// let _builder = ftlog::builder()
+    ///     .global_field("service", "billing")
+    ///     .global_field("version", env!("CARGO_PKG_VERSION"));
+    /// ```
+    ///
+    /// Global fields are printed by default format before [`context`]
+    /// fields, and come first in [`LogRecord::key_values`](format::LogRecord::key_values)
+    /// for [`RecordFormatter`].
+    pub fn global_field(mut self, key: &'static str, value: impl Display) -> Builder {
+        self.global_fields.push((key, value.to_string()));
+        self
+    }
+
+    /// Set formatter of the whole log line, see [`format`](mod@format) module
+    ///
+    /// This takes precedence over [`Builder::format`], [`Builder::time_format`] is
+    /// still available to the formatter with [`LogRecord::timestamp`](format::LogRecord::timestamp).
+    #[inline]
+    pub fn formatter<F: RecordFormatter + 'static>(mut self, formatter: F) -> Builder {
+        self.formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// Set formatter of the whole log line for records whose target starts with
+    /// `prefix`, overriding [`Builder::formatter`] and [`Builder::format`]
+    ///
+    /// The longest matching prefix wins. Lines of all formats go to the same
+    /// appenders, so this is mostly useful with [`Builder::route`].
+    ///
+    /// ```
+    /// use ftlog::appender::FileAppender;
+    /// use ftlog::format::Format;
+    ///
+    /// let _guard = ftlog::builder()
+    ///     .format_for_target("access", Format::Json)
+    ///     .route("access", FileAppender::new("./access.log"))
+    ///     .try_init()
+    ///     .unwrap();
+    /// log::info!(target: "access", method = "GET"; "/index.html");
+    /// log::info!("human-readable");
+    /// // access.log:
+    /// // {"time":"2023-06-14 11:13:26.160+08","level":"INFO","target":"access","thread":"main","file":"src/main.rs","line":10,"message":"/index.html","fields":{"method":"GET"}}
+    /// # drop(_guard);
+    /// # std::fs::remove_file("./access.log").unwrap();
+    /// ```
+    #[inline]
+    pub fn format_for_target<F: RecordFormatter + 'static>(
+        mut self,
+        prefix: &'static str,
+        formatter: F,
+    ) -> Builder {
+        self.target_formatters.push((prefix, Box::new(formatter)));
+        self
+    }
+
+    /// Set formatter of lines written to appender `name`, e.g. a readable layout on
+    /// the console next to JSON in files
+    ///
+    /// `name` is `root`, the name of an appender, or of a route as in
+    /// [`Builder::appender_flush_interval`]. Other appenders keep the lines of
+    /// [`Builder::formatter`], or of [`Format::Text`] when no
+    /// formatter is set, since records are then formatted from their fields.
+    ///
+    /// ```
+    /// use ftlog::appender::FileAppender;
+    /// use ftlog::format::{Format, PrettyFormatter};
+    ///
+    /// let _guard = ftlog::builder()
+    ///     .formatter(Format::Json)
+    ///     .root(std::io::stderr())
+    ///     .appender_formatter("root", PrettyFormatter::new())
+    ///     .attach("file", FileAppender::new("./app.log"), log::LevelFilter::Info)
+    ///     .try_init()
+    ///     .unwrap();
+    /// log::info!(user = "alice"; "logged in");
+    /// # drop(_guard);
+    /// # std::fs::remove_file("./app.log").unwrap();
+    /// ```
+    #[inline]
+    pub fn appender_formatter<F: RecordFormatter + 'static>(
+        mut self,
+        name: &'static str,
+        formatter: F,
+    ) -> Builder {
+        self.appender_formatters.push((name, Box::new(formatter)));
+        self
+    }
+
+    /// Set timestamp format, see [`TimeFormat`]
+    ///
+    /// In case an error occurs when formatting timestamp with a custom format,
+    /// `ftlog` will fallback to RFC3339.
+    #[inline]
+    pub fn time_format(mut self, format: impl Into<TimeFormat>) -> Builder {
+        self.time_format = Some(format.into());
+        self
+    }
+
+    /// Set subsecond precision of timestamp, defaults to milliseconds
+    ///
+    /// This applies to the default timestamp format, [`TimeFormat::Rfc3339`]
+    /// and [`TimeFormat::UnixEpoch`].
+    #[inline]
+    pub fn time_precision(mut self, precision: TimePrecision) -> Builder {
+        self.time_precision = precision;
+        self
+    }
+
+    /// Set when timestamps are taken, see [`TimestampSource`]
+    ///
+    /// ```
+    /// use ftlog::TimestampSource;
+    /// let logger = ftlog::builder()
+    ///     .timestamp_source(TimestampSource::LogThread)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn timestamp_source(mut self, source: TimestampSource) -> Builder {
+        self.timestamp_source = source;
+        self
+    }
+
+    /// Read time from `clock` instead of the system clock, see [`clock`](mod@clock)
+    ///
+    /// The clock is used for timestamps, delays and log interval limits. File
+    /// appenders take their own clock, see `FileAppender::builder().clock()`.
+    #[inline]
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Builder {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Read wall-clock time from `now` instead of the system clock, a shorthand for
+    /// [`clock`](Builder::clock) with [`FnClock`](clock::FnClock)
+    ///
+    /// Handy in tests to move time across a day or month boundary without sleeping.
+    #[inline]
+    pub fn with_now<F>(self, now: F) -> Builder
+    where
+        F: Fn() -> OffsetDateTime + Send + Sync + 'static,
+    {
+        self.clock(clock::FnClock::new(now))
+    }
+
+    /// Only keep log records whose metadata passes `filter`, evaluated at call site
+    ///
+    /// Records rejected by the filter are neither formatted nor sent to log thread.
+    /// The filter is consulted on every log call, so rules can be changed at runtime,
+    /// e.g. through an atomic flag. With multiple filters, a record is kept only when
+    /// all of them return `true`.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// static VERBOSE_DB: AtomicBool = AtomicBool::new(false);
+    ///
+    /// let logger = ftlog::builder()
+    ///     .filter_fn(|metadata| {
+    ///         !metadata.target().starts_with("db") || VERBOSE_DB.load(Ordering::Relaxed)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn filter_fn<F>(mut self, filter: F) -> Builder
+    where
+        F: Fn(&Metadata) -> bool + Send + Sync + 'static,
+    {
+        self.metadata_filters.push(Box::new(filter));
+        self
+    }
+
+    /// This will drop log records before they are sent into the channel.
+    #[inline]
+    pub fn drop_filters<F>(mut self, filter: F) -> Builder
+    where
+        F: Fn(&Record) -> bool + Send + Sync + 'static,
+    {
+        self.drop_filters.push(Box::new(filter));
+        self
+    }
+
+    /// Rewrite the message and key-values of each record at the log call, e.g. to
+    /// remove personal data in one place instead of at every call site
+    ///
+    /// Hooks run in the order they are added, after filters and before the record is
+    /// queued, so they should be cheap. Key-values of [`context`](mod@context) and
+    /// global fields are not passed to hooks, and key-values are only kept with `kv`
+    /// feature, which is enabled by default.
+    ///
+    /// ```
+    /// use std::hash::{DefaultHasher, Hash, Hasher};
+    ///
+    /// let _guard = ftlog::builder()
+    ///     .scrub(|msg, kvs| {
+    ///         if let Some(user) = kvs.get_mut("user_id") {
+    ///             let mut hasher = DefaultHasher::new();
+    ///             user.hash(&mut hasher);
+    ///             *user = format!("{:x}", hasher.finish());
+    ///         }
+    ///         kvs.remove("password");
+    ///         if msg.len() > 1024 {
+    ///             *msg = msg.chars().take(1024).collect();
+    ///         }
+    ///     })
+    ///     .try_init()
+    ///     .unwrap();
+    /// log::info!(user_id = "alice", password = "hunter2"; "logged in");
+    /// // Output:
+    /// // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:19] user_id=9a4ae1bd0d3bb2e9 logged in
+    /// ```
+    #[inline]
+    pub fn scrub<F>(mut self, scrub: F) -> Builder
+    where
+        F: Fn(&mut String, &mut KvMap) + Send + Sync + 'static,
+    {
+        self.scrubs.push(Box::new(scrub));
+        self
+    }
+
+    /// Add a stage rewriting or dropping records at the log call, run after
+    /// [`Builder::scrub`] hooks, see [`middleware`] module
+    #[inline]
+    pub fn middleware<M: middleware::Middleware + 'static>(mut self, middleware: M) -> Builder {
+        self.middlewares.push(Box::new(middleware));
+        self
+    }
+
+    /// Append fields computed in log thread to every record, see [`enrich`] module
+    #[inline]
+    pub fn enrich<E: enrich::Enricher + 'static>(mut self, enricher: E) -> Builder {
+        self.enrichers.push(Box::new(enricher));
+        self
+    }
+
+    /// bound channel between worker thread and log thread
+    ///
+    /// When `block_when_full` is true, it will block current thread where
+    /// log macro (e.g. `log::info`) is called until log thread is able to handle new message.
+    /// Otherwises, excessive log messages will be discarded.
+    ///
+    /// By default, excessive log messages is discarded silently. To show how many log
+    /// messages have been dropped, see `Builder::print_omitted_count()` and
+    /// `Builder::on_overflow()`.
+    #[inline]
+    pub fn bounded(mut self, size: usize, block_when_full: bool) -> Builder {
+        let reserve = self
+            .bounded_channel_option
+            .as_ref()
+            .map_or(0, |x| x.reserve);
+        self.bounded_channel_option = Some(BoundedChannelOption {
+            size,
+            policy: if block_when_full {
+                OverflowPolicy::Block
+            } else {
+                OverflowPolicy::DropNewest
+            },
+            print: false,
+            reserve,
+        });
+        self
+    }
+
+    /// Set capacity of the bounded channel between worker thread and log thread
+    ///
+    /// Memory consumed by queued log messages is bounded by the capacity. What
+    /// happens when the channel is full is decided by `Builder::overflow_policy()`,
+    /// which discards the newest log message by default.
+    ///
+    /// Calling this after `Builder::unbounded()` makes the channel bounded again.
+    #[inline]
+    pub fn channel_capacity(mut self, size: usize) -> Builder {
+        self.bounded_channel_option
+            .get_or_insert_with(BoundedChannelOption::default)
+            .size = size;
+        self
+    }
+
+    /// Set the behavior of log calls when the bounded channel is full
+    ///
+    /// - `OverflowPolicy::Block` blocks the thread calling log macros until log thread
+    ///   is able to handle new message
+    /// - `OverflowPolicy::DropNewest` discards the log message being logged
+    /// - `OverflowPolicy::DropOldest` evicts the oldest queued log message, so that the
+    ///   most recent messages are kept
+    ///
+    /// Calling this after `Builder::unbounded()` makes the channel bounded again.
+    ///
+    /// ```
+    /// use ftlog::OverflowPolicy;
+    /// let logger = ftlog::builder()
+    ///     .channel_capacity(10_000)
+    ///     .overflow_policy(OverflowPolicy::DropOldest)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Builder {
+        self.bounded_channel_option
+            .get_or_insert_with(BoundedChannelOption::default)
+            .policy = policy;
+        self
+    }
+
+    /// Reserve capacity of the bounded channel for `Error` records
+    ///
+    /// When the channel is saturated, other records are discarded once the free
+    /// capacity drops to `size`, leaving room for `Error` records. If even the reserve
+    /// is used up, log calls of `Error` records wait for log thread instead of being
+    /// discarded. Nothing is reserved by default.
+    ///
+    /// Only takes effect with `OverflowPolicy::DropNewest` or `OverflowPolicy::DropOldest`,
+    /// as no record is discarded with `OverflowPolicy::Block`. Calling this after
+    /// `Builder::unbounded()` makes the channel bounded again.
+    ///
+    /// ```
+    /// let logger = ftlog::builder()
+    ///     .channel_capacity(100_000)
+    ///     // 1_000 more slots for `Error` records only
+    ///     .priority_reserve(1_000)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn priority_reserve(mut self, size: usize) -> Builder {
+        self.bounded_channel_option
+            .get_or_insert_with(BoundedChannelOption::default)
+            .reserve = size;
+        self
+    }
+
+    /// Set how often log thread flushes buffered appenders when idle, defaults to 1
+    /// second
+    ///
+    /// Appenders are flushed when no message arrives for a while and the interval has
+    /// elapsed since their last flush. A shorter interval makes records visible sooner,
+    /// at the cost of more write syscalls. Buffers are also flushed when they are full
+    /// and on `log::logger().flush()`.
+    #[inline]
+    pub fn flush_interval(mut self, interval: Duration) -> Builder {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Override the flush interval for an appender, see [`Builder::flush_interval`]
+    ///
+    /// `name` is the name of an appender added by [`Builder::appender`] or
+    /// [`Builder::attach`], a prefix of [`Builder::route`], `key=value` of
+    /// [`Builder::route_field`], or `"root"` for the root appender.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// let logger = ftlog::builder()
+    ///     .flush_interval(Duration::from_secs(5))
+    ///     .attach("alert", std::io::stdout(), log::LevelFilter::Error)
+    ///     .appender_flush_interval("alert", Duration::from_millis(50))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn appender_flush_interval(mut self, name: &'static str, interval: Duration) -> Builder {
+        self.appender_flush_intervals.push((name, interval));
+        self
+    }
+
+    /// Keep records more verbose than `persist` only in memory, writing them when an
+    /// error occurs
+    ///
+    /// Each thread keeps its last `capacity` verbose records in a ring buffer. When the
+    /// thread logs an `Error` record, the kept records are written right before it,
+    /// so failures come with the debug context leading to them, without the cost of
+    /// writing verbose logs all the time. Kept records show the time of their log
+    /// call, unless timestamps are taken by log thread. Verbose records still need
+    /// to pass [`Builder::max_log_level`] to be kept.
+    ///
+    /// ```
+    /// # use log::LevelFilter;
+    /// let _guard = ftlog::builder()
+    ///     .max_log_level(LevelFilter::Trace)
+    ///     // write `Info` and more severe records, keep the last 200 `Debug` and
+    ///     // `Trace` records of each thread until an error
+    ///     .crash_context(LevelFilter::Info, 200)
+    ///     .try_init()
+    ///     .unwrap();
+    /// log::debug!("connecting to db");
+    /// log::error!("connection refused");
+    /// ```
+    #[inline]
+    pub fn crash_context(mut self, persist: LevelFilter, capacity: usize) -> Builder {
+        self.crash_context = Some((persist, capacity));
+        self
+    }
+
+    /// Strip ANSI escape sequences and escape other control characters in messages,
+    /// key-values and thread names, defaults to `false`
+    ///
+    /// Sanitizing runs in log thread before records are formatted, so that a message
+    /// can neither forge log lines with line breaks nor play terminal tricks on
+    /// someone viewing the log file, e.g. by `cat`. Line feeds and carriage returns
+    /// are written as `\n` and `\r`, other control characters but tab like `\x07`.
+    ///
+    /// ```
+    /// let _guard = ftlog::builder().sanitize(true).try_init().unwrap();
+    /// log::info!("user {}", "alice\n2023-06-14 11:13:26.160+08 0ms INFO main forged");
+    /// // Output:
+    /// // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:2] user alice\n2023-06-14 11:13:26.160+08 0ms INFO main forged
+    /// ```
+    #[inline]
+    pub fn sanitize(mut self, sanitize: bool) -> Builder {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Truncate messages longer than `bytes`, for sinks with a hard limit of message
+    /// size like UDP or journald
+    ///
+    /// Truncated messages end with `…(truncated N bytes)` and are cut at a UTF-8
+    /// boundary, so that they take at most `bytes` with the note. The message part of
+    /// the default format, or the whole output of a custom [`FtLogFormat`], is
+    /// truncated in log thread after [`Builder::sanitize`]. Key-values are kept.
+    ///
+    /// ```
+    /// let _guard = ftlog::builder().max_message_len(64).try_init().unwrap();
+    /// log::info!("{}", "x".repeat(100));
+    /// // Output:
+    /// // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:2] xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx…(truncated 60 bytes)
+    /// ```
+    #[inline]
+    pub fn max_message_len(mut self, bytes: usize) -> Builder {
+        self.max_message_len = Some(bytes);
+        self
+    }
+
+    /// Set how line breaks in messages are written, defaults to [`Multiline::AsIs`]
+    ///
+    /// Multi-line messages like stack traces or SQL statements break line-based log
+    /// shippers, which take each line as a record. Like [`Builder::max_message_len`],
+    /// this applies to the message part of the default format, or the whole output
+    /// of a custom [`FtLogFormat`], in log thread before truncating.
+    ///
+    /// ```
+    /// use ftlog::Multiline;
+    ///
+    /// let _guard = ftlog::builder()
+    ///     .multiline(Multiline::Indent("    "))
+    ///     .try_init()
+    ///     .unwrap();
+    /// log::info!("query failed:\nSELECT *\nFROM users");
+    /// // Output:
+    /// // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:7] query failed:
+    /// //     SELECT *
+    /// //     FROM users
+    /// ```
+    #[inline]
+    pub fn multiline(mut self, policy: Multiline) -> Builder {
+        self.multiline = policy;
+        self
+    }
+
+    /// Replace text matching `pattern` in messages and key-values with `replacement`
+    /// before records are formatted, see [`redact`](mod@redact)
+    ///
+    /// An invalid pattern is reported as [`InitError::InvalidConfig`] when building.
+    #[cfg(feature = "redact")]
+    #[inline]
+    pub fn redact(mut self, pattern: &str, replacement: impl Into<String>) -> Builder {
+        self.redactions
+            .push((pattern.to_string(), replacement.into()));
+        self
+    }
 
-struct Directive {
-    filter: Box<dyn Fn(&dyn Display, Level, &str) -> bool + Send>,
-    appender: Option<&'static str>,
-}
-/// timezone for log
-pub enum LogTimezone {
-    /// local timezone
+    /// Drop records whose message matches `pattern`, e.g. known noisy lines of a
+    /// dependency
     ///
-    /// Only *unix OS is supported for now
-    Local,
-    /// UTC timezone
-    Utc,
-    /// fixed timezone
-    Fixed(UtcOffset),
-}
+    /// Message rules are checked in log thread before routing, so log calls stay cheap,
+    /// but records are still queued. They are tried in the order they are added and
+    /// the first matching rule of [`allow_message`](Builder::allow_message),
+    /// [`deny_message`](Builder::deny_message) and
+    /// [`downgrade_message`](Builder::downgrade_message) wins. Patterns match the
+    /// message only, without level, location and key-values, or the whole output of
+    /// a custom [`FtLogFormat`]. Records written to
+    /// [per-thread files](Builder::per_thread) are not checked.
+    ///
+    /// An invalid pattern is reported as [`InitError::InvalidConfig`] when building.
+    ///
+    /// ```
+    /// use ftlog::{Level, LevelFilter};
+    ///
+    /// let _guard = ftlog::builder()
+    ///     .root_log_level(LevelFilter::Info)
+    ///     .allow_message("connection reset by database")
+    ///     .deny_message("^connection reset")
+    ///     .downgrade_message(r"retrying in \d+ms", Level::Debug)
+    ///     .try_init()
+    ///     .unwrap();
+    /// log::warn!("connection reset by peer"); // dropped
+    /// log::warn!("connection reset by database"); // written
+    /// log::warn!("retrying in 100ms"); // not written by root appender, as a debug record
+    /// ```
+    #[cfg(feature = "message_filter")]
+    #[inline]
+    pub fn deny_message(mut self, pattern: &str) -> Builder {
+        self.message_rules
+            .push((pattern.to_string(), message_filter::Action::Deny));
+        self
+    }
 
-impl Builder {
+    /// Keep records whose message matches `pattern` unchanged, skipping later message
+    /// rules, see [`Builder::deny_message`]
+    #[cfg(feature = "message_filter")]
     #[inline]
-    /// Create a ftlog builder with default settings:
-    /// - global log level: INFO
-    /// - root log level: INFO
-    /// - no drop filters
-    /// - default formatter: `FtLogFormatter`
-    /// - output to stderr
-    /// - bounded channel between worker thread and log thread, with a size limit of 100_000
-    /// - discard excessive log messages
-    /// - log with timestamp of local timezone
-    pub fn new() -> Builder {
-        Builder {
-            format: Box::new(FtLogFormatter),
-            level: None,
-            root_level: None,
-            root: Box::new(stderr()) as Box<dyn Write + Send>,
-            appenders: HashMap::new(),
-            filters: Vec::new(),
-            drop_filters: Vec::new(),
-            bounded_channel_option: Some(BoundedChannelOption {
-                size: 100_000,
-                block: false,
-                print: true,
-            }),
-            timezone: LogTimezone::Local,
-            time_format: None,
-        }
+    pub fn allow_message(mut self, pattern: &str) -> Builder {
+        self.message_rules
+            .push((pattern.to_string(), message_filter::Action::Allow));
+        self
     }
 
-    /// Set custom formatter
+    /// Lower the level of records whose message matches `pattern` to `level`, see
+    /// [`Builder::deny_message`]
+    ///
+    /// Records of a less severe level are kept unchanged. Appenders then filter the
+    /// record by its new level, while the max level of the logger is only checked at
+    /// log call.
+    #[cfg(feature = "message_filter")]
     #[inline]
-    pub fn format<F: FtLogFormat + 'static>(mut self, format: F) -> Builder {
-        self.format = Box::new(format);
+    pub fn downgrade_message(mut self, pattern: &str, level: Level) -> Builder {
+        self.message_rules.push((
+            pattern.to_string(),
+            message_filter::Action::Downgrade(level),
+        ));
         self
     }
 
-    /// Set custom datetime formatter
+    /// Write records to a file of each thread at log call, bypassing log thread, see
+    /// [`per_thread`](appender::per_thread)
     #[inline]
-    pub fn time_format(mut self, format: OwnedFormatItem) -> Builder {
-        self.time_format = Some(format);
+    pub fn per_thread(mut self, path: impl AsRef<std::path::Path>) -> Builder {
+        self.per_thread = Some(path.as_ref().to_path_buf());
         self
     }
 
-    /// This will drop log records before they are sent into the channel.
+    /// Drop verbose records while log thread falls behind for a long time, see
+    /// [`governor`](mod@governor)
     #[inline]
-    pub fn drop_filters<F>(mut self, filter: F) -> Builder
-    where
-        F: Fn(&Record) -> bool + Send + Sync + 'static,
-    {
-        self.drop_filters.push(Box::new(filter));
+    pub fn governor(mut self, governor: governor::Governor) -> Builder {
+        self.governor = Some(governor);
         self
     }
 
-    /// bound channel between worker thread and log thread
+    /// Raise the max log level to `level` on `signal`, and restore it on the next one,
+    /// see [`signal`](mod@signal)
     ///
-    /// When `block_when_full` is true, it will block current thread where
-    /// log macro (e.g. `log::info`) is called until log thread is able to handle new message.
-    /// Otherwises, excessive log messages will be discarded.
+    /// The default action of `signal`, e.g. terminating on `SIGUSR1`, is replaced
+    /// once the logger is built.
+    #[cfg(all(unix, feature = "signal"))]
+    #[inline]
+    pub fn toggle_on_signal(mut self, signal: i32, level: LevelFilter) -> Builder {
+        self.signal_toggles.push((signal, level));
+        self
+    }
+
+    /// Serve requests to change levels, reopen files, flush and read statistics on a
+    /// local socket, see [`control`](mod@control)
     ///
-    /// By default, excessive log messages is discarded silently. To show how many log
-    /// messages have been dropped, see `Builder::print_omitted_count()`.
+    /// A TCP address that is not a loopback address is reported as
+    /// [`InitError::InvalidConfig`] when building.
+    #[cfg(feature = "control")]
     #[inline]
-    pub fn bounded(mut self, size: usize, block_when_full: bool) -> Builder {
-        self.bounded_channel_option = Some(BoundedChannelOption {
-            size,
-            block: block_when_full,
-            print: false,
-        });
+    pub fn control(mut self, listen: control::Listen) -> Builder {
+        self.control = Some(listen);
+        self
+    }
+
+    /// Write records of `level` and more severe levels synchronously
+    ///
+    /// Log calls of these records wait until the record is written by log thread and
+    /// appenders are flushed, so that it is not lost if the process aborts right after
+    /// logging. Records are still written by log thread, in the same order as others.
+    /// No record is written synchronously by default.
+    ///
+    /// ```
+    /// # use log::LevelFilter;
+    /// let logger = ftlog::builder()
+    ///     .sync_level(LevelFilter::Error)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn sync_level(mut self, level: LevelFilter) -> Builder {
+        self.sync_level = level;
+        self
+    }
+
+    /// Forward events of `tracing` crate to ftlog when installed as the global logger
+    ///
+    /// Requires `tracing` feature. [`Logger::init`] sets a global tracing subscriber
+    /// with [`tracing::layer()`], so events of dependencies using tracing are written
+    /// along with log records. A message is printed to stderr if another global
+    /// subscriber is already set, and ftlog is installed regardless.
+    ///
+    /// ```
+    /// let _guard = ftlog::builder().capture_tracing(true).try_init().unwrap();
+    /// tracing::info!(user = "alice", "logged in");
+    /// ```
+    #[cfg(feature = "tracing")]
+    #[inline]
+    pub fn capture_tracing(mut self, capture: bool) -> Builder {
+        self.capture_tracing = capture;
         self
     }
 
@@ -885,9 +3313,135 @@ impl Builder {
     /// thread is bounded, and set to discard excessive log messages
     #[inline]
     pub fn print_omitted_count(mut self, print: bool) -> Builder {
-        self.bounded_channel_option
-            .as_mut()
-            .map(|o| o.print = print);
+        if let Some(o) = self.bounded_channel_option.as_mut() {
+            o.print = print;
+        }
+        self
+    }
+
+    /// call `callback` with the number of log messages discarded because the channel
+    /// to log thread was full
+    ///
+    /// The callback runs in the log call that discards a message, at most once a
+    /// second, and is passed the count since its last call. Keep it cheap, e.g. bump
+    /// a metric or raise a flag, since the queue is still full when it is called.
+    /// It is never called if log calls block when the channel is full.
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let dropped = Arc::new(AtomicUsize::new(0));
+    /// let counter = dropped.clone();
+    /// let logger = ftlog::builder()
+    ///     .bounded(1000, false)
+    ///     .on_overflow(move |n| {
+    ///         counter.fetch_add(n, Ordering::Relaxed);
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn on_overflow<F: Fn(usize) + Send + Sync + 'static>(mut self, callback: F) -> Builder {
+        self.on_overflow = Some(Box::new(callback));
+        self
+    }
+
+    /// Call `handler` with failures inside the logger instead of printing them to
+    /// stderr
+    ///
+    /// Failed writes and flushes of appenders, e.g. a rotated file that cannot be
+    /// opened, formatters failing or panicking on a record, outdated log files
+    /// that cannot be deleted, spill buffers dropping lines and shutdowns missing
+    /// their deadline are reported as [`InternalError`]. The logger keeps
+    /// going after each of them, dropping the record or line concerned. The handler
+    /// may run in log thread, so it must not block for long, and a panic in it is
+    /// ignored.
+    ///
+    /// Each logger reports to its own handler, including errors of its appenders in
+    /// log thread, in threads writing [per-thread files](Builder::per_thread)
+    /// and in threads cleaning up outdated files. Loggers built without a handler
+    /// print to stderr.
+    ///
+    /// ```
+    /// let logger = ftlog::builder()
+    ///     .on_internal_error(|e| eprintln!("logging is degraded: {}", e))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn on_internal_error<F>(mut self, handler: F) -> Builder
+    where
+        F: Fn(&InternalError) + Send + Sync + 'static,
+    {
+        self.on_internal_error = Some(Arc::new(handler));
+        self
+    }
+
+    /// Number records in the order of log calls, starting from 0, defaults to `false`
+    ///
+    /// The number follows the delay in the default layout, like
+    /// `2023-06-14 11:13:26.160+08 0ms #42 INFO main [src/main.rs:6] logged in`, is
+    /// a `seq` field of [`Format`]s and is available to formatters as
+    /// [`LogRecord::seq`](format::LogRecord::seq). Numbers are assigned before records
+    /// are queued, so records dropped when the queue is full leave gaps, and records of
+    /// different threads may be written slightly out of order unless
+    /// [`Builder::reorder_window`] is set.
+    ///
+    /// ```
+    /// let _guard = ftlog::builder().sequence(true).try_init().unwrap();
+    /// ```
+    #[inline]
+    pub fn sequence(mut self, enable: bool) -> Builder {
+        self.sequence = enable;
+        self
+    }
+
+    /// Write a `-- N records dropped --` line to all appenders where records were
+    /// dropped because the queue to log thread was full, defaults to `false`
+    ///
+    /// The marker is queued by the next log call that finds room in the queue, so it
+    /// takes the place of the dropped records, or follows records queued at that time
+    /// with [`OverflowPolicy::DropOldest`]. Binary formats, like
+    /// [`Format::MsgPack`], get no marker.
+    ///
+    /// ```
+    /// let _guard = ftlog::builder()
+    ///     .bounded(1000, false)
+    ///     .drop_markers(true)
+    ///     .try_init()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn drop_markers(mut self, enable: bool) -> Builder {
+        self.drop_markers = enable;
+        self
+    }
+
+    /// Select the queue between log calls and log thread, defaults to
+    /// [`QueueKind::Channel`]
+    ///
+    /// [`QueueKind::Ring`] is a bounded ring buffer allocated once at startup, so log
+    /// calls never allocate for queueing and their latency stays predictable under
+    /// load. Its capacity replaces [`Builder::channel_capacity`], and the overflow
+    /// policy and priority reserve apply as for a bounded channel. Building fails with
+    /// [`InitError::InvalidConfig`] if [`Builder::unbounded`] is called afterwards.
+    ///
+    /// ```
+    /// use ftlog::QueueKind;
+    /// let logger = ftlog::builder()
+    ///     .queue(QueueKind::Ring(1 << 16))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn queue(mut self, kind: QueueKind) -> Builder {
+        if let QueueKind::Ring(size) = kind {
+            self.bounded_channel_option
+                .get_or_insert_with(BoundedChannelOption::default)
+                .size = size;
+        }
+        self.queue = kind;
         self
     }
 
@@ -908,12 +3462,150 @@ impl Builder {
     /// Combine with `Builder::filter()`, ftlog can output log in different module
     /// path to different output target.
     #[inline]
-    pub fn appender(
+    pub fn appender(mut self, name: &'static str, appender: impl Into<Sink>) -> Builder {
+        self.appenders.insert(
+            name,
+            Destination::new(name, appender.into().0, LevelFilter::Trace),
+        );
+        self
+    }
+
+    /// Attach an additional appender with its own level threshold
+    ///
+    /// Records that are not redirected by `Builder::filter()` are written to the root
+    /// appender and every attached appender whose level allows them, so the same
+    /// record can go to multiple destinations with different verbosity. The record is
+    /// formatted only once in log thread no matter how many appenders accept it.
+    ///
+    /// Attached appenders are also named appenders: `Builder::filter()` can still
+    /// redirect records exclusively to them, subject to the same level threshold.
+    ///
+    /// ```no_run
+    /// # use ftlog::appender::FileAppender;
+    /// # use ftlog::LevelFilter;
+    /// let logger = ftlog::builder()
+    ///     .max_log_level(LevelFilter::Debug)
+    ///     // console at Info
+    ///     .root(std::io::stderr())
+    ///     .root_log_level(LevelFilter::Info)
+    ///     // file at Debug
+    ///     .attach("file", FileAppender::new("./debug.log"), LevelFilter::Debug)
+    ///     // another file at Warn
+    ///     .attach("warn", FileAppender::new("./warn.log"), LevelFilter::Warn)
+    ///     .build()
+    ///     .expect("logger build failed");
+    /// ```
+    #[inline]
+    pub fn attach(
         mut self,
         name: &'static str,
-        appender: impl Write + Send + 'static,
+        appender: impl Into<Sink>,
+        level: LevelFilter,
     ) -> Builder {
-        self.appenders.insert(name, Box::new(appender));
+        self.appenders
+            .insert(name, Destination::new(name, appender.into().0, level));
+        if !self.attached.contains(&name) {
+            self.attached.push(name);
+        }
+        self
+    }
+
+    /// Also write records at or above `level` to stderr, in addition to the
+    /// configured appenders
+    ///
+    /// Unlike [`Builder::attach`], records routed by [`Builder::route`] or redirected
+    /// by `Builder::filter()` are mirrored too, so that local runs and
+    /// `kubectl logs` show output while the primary sink is a file. Colors are kept
+    /// when stderr is a terminal. The mirror is named `stderr` in stats and
+    /// [`Builder::appender_formatter`].
+    ///
+    /// ```
+    /// # use ftlog::appender::FileAppender;
+    /// # use ftlog::LevelFilter;
+    /// let logger = ftlog::builder()
+    ///     .root(FileAppender::new("./app.log"))
+    ///     .route("audit::", FileAppender::new("./audit.log"))
+    ///     .also_stderr(LevelFilter::Info)
+    ///     .build()
+    ///     .expect("logger build failed");
+    /// # drop(logger);
+    /// # std::fs::remove_file("./app.log").unwrap();
+    /// # std::fs::remove_file("./audit.log").unwrap();
+    /// ```
+    #[inline]
+    pub fn also_stderr(mut self, level: LevelFilter) -> Builder {
+        self.also_stderr = Some(level);
+        self
+    }
+
+    /// Route records whose target starts with `prefix` to an appender
+    ///
+    /// Routed records are written only to the appenders of the route, they never
+    /// reach the root appender, attached appenders or appenders selected by
+    /// `Builder::filter()`. This keeps a dedicated file (e.g. an audit log) free
+    /// from ordinary application logs, and vice versa.
+    ///
+    /// Calling `route` multiple times with the same prefix writes routed records to
+    /// all of the given appenders. When several prefixes match, the longest one wins.
+    ///
+    /// ```no_run
+    /// # use ftlog::appender::FileAppender;
+    /// let logger = ftlog::builder()
+    ///     .route("audit::", FileAppender::new("./audit.log"))
+    ///     .build()
+    ///     .expect("logger build failed");
+    /// ```
+    #[inline]
+    pub fn route(self, prefix: &'static str, appender: impl Into<Sink>) -> Builder {
+        self.add_route(Rule::Prefix(prefix), appender.into())
+    }
+
+    /// Route records with key-value `key` to an appender, if its value is `value`
+    /// when given
+    ///
+    /// Key-values of the record and fields of [`context`] are matched, records of a
+    /// custom [`FtLogFormat`] have none. Like [`Builder::route`], routed records are
+    /// written only to the appenders of the route, and calling `route_field` with
+    /// the same key and value adds appenders to the route. Field routes are checked
+    /// in the order they are added, before target-prefix routes. Their appenders are
+    /// named `key=value`, or `key` without value, in stats and
+    /// [`Builder::appender_flush_interval`].
+    ///
+    /// ```no_run
+    /// # use ftlog::appender::FileAppender;
+    /// let logger = ftlog::builder()
+    ///     .route_field("audit", "true", FileAppender::new("./audit.log"))
+    ///     .route_field("tenant", "acme", FileAppender::new("./acme.log"))
+    ///     .route_field("tenant", "globex", FileAppender::new("./globex.log"))
+    ///     .build()
+    ///     .expect("logger build failed");
+    /// ```
+    #[inline]
+    pub fn route_field<V: Into<Option<&'static str>>>(
+        self,
+        key: &'static str,
+        value: V,
+        appender: impl Into<Sink>,
+    ) -> Builder {
+        self.add_route(Rule::Field(key, value.into()), appender.into())
+    }
+
+    fn add_route(mut self, rule: Rule, appender: Sink) -> Builder {
+        match self.routes.iter_mut().find(|r| r.rule == rule) {
+            Some(route) => {
+                let name = format!("{}#{}", rule.name(), route.appenders.len());
+                let dest = Destination::new(name, appender.0, LevelFilter::Trace);
+                route.appenders.push(dest)
+            }
+            None => self.routes.push(Route {
+                rule,
+                appenders: vec![Destination::new(
+                    rule.name(),
+                    appender.0,
+                    LevelFilter::Trace,
+                )],
+            }),
+        }
         self
     }
 
@@ -943,9 +3635,11 @@ impl Builder {
     #[inline]
     /// Configure the default log output target.
     ///
-    /// Omit this method will output to stderr.
-    pub fn root(mut self, writer: impl Write + Send + 'static) -> Builder {
-        self.root = Box::new(writer);
+    /// Omit this method will output to stderr. Like other appenders, it is any
+    /// [`Write`](std::io::Write), or an [`Appender`](appender::Appender) wrapped in
+    /// [`Sink`].
+    pub fn root(mut self, writer: impl Into<Sink>) -> Builder {
+        self.root = writer.into().0;
         self
     }
 
@@ -958,6 +3652,62 @@ impl Builder {
         self
     }
 
+    /// Drop all records whose target starts with `prefix`, e.g. of a chatty
+    /// dependency
+    ///
+    /// Same as [`Builder::silence_below`] with [`LevelFilter::Off`].
+    ///
+    /// ```
+    /// # use ftlog::LevelFilter;
+    /// let _guard = ftlog::builder()
+    ///     .silence("h2")
+    ///     .silence_below("sqlx::query", LevelFilter::Warn)
+    ///     .try_init()
+    ///     .unwrap();
+    /// ```
+    #[inline]
+    pub fn silence(self, prefix: &str) -> Builder {
+        self.silence_below(prefix, LevelFilter::Off)
+    }
+
+    /// Set the max log level of records whose target starts with `prefix`,
+    /// overriding [`Builder::max_log_level`]
+    ///
+    /// Records are checked at log call, so silenced records cost no more than a level
+    /// check. The longest matching prefix wins, and levels can be changed later with
+    /// [`set_target_level`]. Setting the same prefix again replaces its level.
+    #[inline]
+    pub fn silence_below(mut self, prefix: &str, level: LevelFilter) -> Builder {
+        self.target_levels.retain(|(x, _)| x != prefix);
+        self.target_levels.push((prefix.to_string(), level));
+        self
+    }
+
+    /// Replace the `prefix` of targets with `to`, so that written targets are short
+    /// and stable names rather than module paths which change with refactors
+    ///
+    /// The rest of the target is kept, e.g. `my_app::internal::db::pool::conn` is
+    /// written as `db::conn` below. Targets are renamed in log thread before routing
+    /// and formatting, so [`Builder::route`], [`Builder::filter`] and
+    /// [`Builder::format_for_target`] see the new target, while levels are checked
+    /// at log call by the original target. The longest matching prefix wins.
+    ///
+    /// ```
+    /// # use ftlog::appender::FileAppender;
+    /// let logger = ftlog::builder()
+    ///     .rename_target("my_app::internal::db::pool", "db")
+    ///     .route("db", FileAppender::new("./db.log"))
+    ///     .build()
+    ///     .expect("logger build failed");
+    /// # drop(logger);
+    /// # std::fs::remove_file("./db.log").unwrap();
+    /// ```
+    #[inline]
+    pub fn rename_target(mut self, prefix: &'static str, to: &'static str) -> Builder {
+        self.renames.push((prefix, to));
+        self
+    }
+
     #[inline]
     /// Set max log level
     ///
@@ -970,11 +3720,11 @@ impl Builder {
     #[inline]
     /// Log with timestamp of local timezone
     ///
-    /// Timezone is fixed after logger setup for the following reasons:
-    /// 1. `time` v0.3 currently do not allow access to local offset for multithread process
-    /// in unix-like OS.
-    /// 1. timezone retrieval from OS is quite slow (around several microsecond) compare with
-    /// utc timestamp retrieval (around tens of nanoseconds)
+    /// Timezone retrieval from OS is quite slow (around several microsecond) compared
+    /// with utc timestamp retrieval (around tens of nanoseconds), so log thread looks up
+    /// the local offset once a minute, when the minute of records changes. Timestamps
+    /// follow DST transitions from the first record of the minute after them, and
+    /// rotated files from the next rotation.
     pub fn local_timezone(mut self) -> Builder {
         self.timezone = LogTimezone::Local;
         self
@@ -1001,139 +3751,401 @@ impl Builder {
         self
     }
 
+    #[inline]
+    /// Limit how long `LoggerGuard` waits for log thread when dropped
+    ///
+    /// By default, dropping the guard blocks until all queued log messages are
+    /// written and appenders are flushed. With a timeout, the guard gives up waiting
+    /// after the given duration, and the remaining messages may be lost. This prevents
+    /// a stuck appender (e.g. a full disk or an unresponsive network sink) from hanging
+    /// the process at exit.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Builder {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// Log each call site at most once per `period`, unless the log call sets its
+    /// own `limit`
+    ///
+    /// Unlike `limit`, this is decided at call site, so suppressed records are not
+    /// sent to log thread and not counted as missed. Call sites are told apart by
+    /// a hash of module path and line in a fixed size table, rarely two call sites
+    /// may share a limit. See also [`rate_limit`] module.
+    pub fn default_rate_limit(mut self, period: Duration) -> Builder {
+        self.default_rate_limit = Some(period);
+        self
+    }
+
+    /// Keep only `rate` (from 0 to 1) of records at `level` and more verbose levels,
+    /// e.g. `sample(LevelFilter::Debug, 0.01)` keeps 1% of `Debug` and `Trace` records
+    ///
+    /// Records are dropped randomly at call site before formatting, so dropped ones
+    /// cost almost nothing. When multiple samplings apply to a level, the smallest
+    /// rate is used:
+    ///
+    /// ```
+    /// # use ftlog::LevelFilter;
+    /// let _builder = ftlog::builder()
+    ///     .max_log_level(LevelFilter::Trace)
+    ///     // keep 10% of Debug records and 1% of Trace records
+    ///     .sample(LevelFilter::Debug, 0.1)
+    ///     .sample(LevelFilter::Trace, 0.01);
+    /// ```
+    ///
+    /// Requires `random_drop` feature.
+    #[cfg(feature = "random_drop")]
+    pub fn sample(mut self, level: LevelFilter, rate: f32) -> Builder {
+        self.sample.push((level, rate.clamp(0., 1.)));
+        self
+    }
+
+    /// Log number of suppressed calls of each [`log_once!`] call site at shutdown,
+    /// defaults to `false`
+    ///
+    /// The summary is logged through this logger when the guard returned by
+    /// [`Logger::init`] is dropped, by [`shutdown`], or when a standalone logger is
+    /// dropped, like `suppressed 12 subsequent occurrences`.
+    pub fn once_summary(mut self, once_summary: bool) -> Builder {
+        self.once_summary = once_summary;
+        self
+    }
+
+    /// Hold records in log thread for `window`, writing them in order of timestamp
+    ///
+    /// Records taken at about the same time by different threads can reach log thread
+    /// slightly out of order. With a window, log thread sorts records by timestamp
+    /// before writing them, so files are strictly ordered, at the cost of delaying
+    /// each record by `window`. A record arriving later than the window is written
+    /// with the timestamp of the last written record, to keep the order. Held records
+    /// are written on [`Log::flush`] and shutdown.
+    ///
+    /// Timestamps must be taken at call site, see [`Builder::timestamp_source`].
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// let logger = ftlog::builder()
+    ///     .reorder_window(Duration::from_millis(5))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn reorder_window(mut self, window: Duration) -> Builder {
+        self.reorder_window = Some(window);
+        self
+    }
+
+    /// Set number of threads formatting log messages, defaults to 1
+    ///
+    /// With `n > 1`, log messages are formatted in parallel by `n` threads, while
+    /// filtering, log interval limit and writing to appenders still happen in a
+    /// single thread each, so log messages are written in the same order as before.
+    /// Consider this when a single log thread can not keep up with the log volume,
+    /// and formatting (e.g. an expensive `Display` implementation) is the bottleneck.
+    ///
+    /// `0` is treated as `1`.
+    pub fn workers(mut self, n: usize) -> Builder {
+        self.workers = n;
+        self
+    }
+
+    /// Batch records per thread before sending them to log thread, defaults to `false`
+    ///
+    /// With many threads logging at a high rate, they contend on the channel to log
+    /// thread. When enabled, each thread accumulates up to 32 records and sends them
+    /// as a single message, once the batch is full or a few hundred microseconds
+    /// after its first record. `Error` records are sent at once along with the batch,
+    /// and [`Log::flush`] sends batches of all threads first. Records of a thread keep
+    /// their order, while records of different threads may be written out of order
+    /// by up to the batching delay.
+    ///
+    /// Timestamps are taken at call site, regardless of [`Builder::timestamp_source`].
+    /// Under [`OverflowPolicy::DropNewest`] and [`OverflowPolicy::DropOldest`], a full
+    /// channel drops a whole batch.
+    pub fn low_contention(mut self, enabled: bool) -> Builder {
+        self.low_contention = enabled;
+        self
+    }
+
     /// Finish building ftlog logger
     ///
     /// The call spawns a log thread to formatting log message into string,
     /// and write to output target.
+    ///
+    /// A filter referring to an appender that is not configured is reported as an
+    /// error of kind `InvalidInput`.
     pub fn build(self) -> Result<Logger, IoError> {
-        let offset = match self.timezone {
-            LogTimezone::Local => Some(local_timezone()),
-            LogTimezone::Utc => None,
-            LogTimezone::Fixed(offset) => Some(offset),
-        };
-        let time_format = self.time_format.unwrap_or_else(|| {
-            time::format_description::parse_owned::<1>(
-                "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:3]+[offset_hour]",
-            )
-            .unwrap()
+        self.build_logger().map_err(|e| match e {
+            InitError::Io(e) => e,
+            e => IoError::new(std::io::ErrorKind::InvalidInput, e.to_string()),
+        })
+    }
+
+    fn build_logger(mut self) -> Result<Logger, InitError> {
+        // check TSC now rather than in the first log call
+        let _ = time_backend();
+        let offset = Offset::new(&self.timezone);
+        let global_fields: GlobalFields = self.global_fields.into();
+        let precision = self.time_precision;
+        let record_fields =
+            AtomicBool::new(self.formatter.is_some() || !self.appender_formatters.is_empty());
+        let formatter = Arc::new(worker::SharedFormatter::from(self.formatter.map(Arc::new)));
+        // stable, so that the first one added wins among equal prefixes
+        self.target_formatters
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        // stable, so that the first one added wins among equal prefixes
+        self.renames
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        // records are formatted by target formatters of their new target, so that
+        // their fields are needed when renamed from an original target
+        let renamed = self.renames.iter().filter(|(_, to)| {
+            self.target_formatters
+                .iter()
+                .any(|(prefix, _)| to.starts_with(prefix) || prefix.starts_with(to))
         });
+        let format_targets = self
+            .target_formatters
+            .iter()
+            .map(|(prefix, _)| *prefix)
+            .chain(renamed.map(|(from, _)| *from))
+            .collect();
+        let cache_time = !matches!(self.time_format, Some(TimeFormat::Custom(_)));
+        let time_format = match self.time_format {
+            Some(format) => format.with_precision(precision),
+            None => time::format_description::parse_owned::<1>(&format!(
+                "[year]-[month]-[day] [hour]:[minute]:[second].[subsecond digits:{}]+[offset_hour]",
+                precision.digits()
+            ))
+            .unwrap()
+            .into(),
+        };
         let filters = self.filters;
+        let mut routes = self.routes;
         // check appender name in filters are all valid
         for appender_name in filters.iter().filter_map(|x| x.appender) {
             if !self.appenders.contains_key(appender_name) {
-                panic!("Appender {} not configured", appender_name);
+                return Err(ConfigError::UnknownAppender(appender_name.to_string()).into());
+            }
+        }
+        #[cfg(feature = "control")]
+        if let Some(control::Listen::Tcp(addr)) = &self.control {
+            if !addr.ip().is_loopback() {
+                return Err(ConfigError::Invalid(format!(
+                    "control address {} is not a loopback address",
+                    addr
+                ))
+                .into());
             }
         }
+        #[cfg(feature = "redact")]
+        let redactions = redact::Redactions::new(self.redactions)
+            .map_err(|e| ConfigError::Invalid(format!("invalid redaction pattern: {}", e)))?;
+        #[cfg(feature = "message_filter")]
+        let message_rules = message_filter::MessageRules::new(self.message_rules)
+            .map_err(|e| ConfigError::Invalid(format!("invalid message pattern: {}", e)))?;
         let global_level = self.level.unwrap_or(LevelFilter::Info);
-        let root_level = self.root_level.unwrap_or(global_level);
-        if global_level < root_level {
+        if self.root_level.is_some_and(|x| global_level < x) {
             warn!(
                 "Logs with level more verbose than {} will be ignored",
                 global_level,
             );
         }
 
-        let (sync_sender, receiver) = match &self.bounded_channel_option {
-            None => unbounded(),
-            Some(option) => bounded(option.size),
+        let level = AtomicLevel::new(global_level);
+        let mut target_levels = self.target_levels;
+        target_levels.sort_by_key(|(x, _)| std::cmp::Reverse(x.len()));
+        // records are checked against the max level at log call, which root appender
+        // follows unless configured otherwise
+        let root_level = self.root_level.unwrap_or(LevelFilter::Trace);
+        let mut root = Destination::new("root", self.root, root_level);
+        let mut mirror = self.also_stderr.map(|level| {
+            let stderr = appender::Sink::new(appender::console::ConsoleAppender::stderr());
+            Destination::new("stderr", stderr.0, level)
+        });
+        for (name, interval) in self.appender_flush_intervals {
+            for dest in destinations(
+                name,
+                &mut root,
+                &mut self.appenders,
+                mirror.as_mut(),
+                &mut routes,
+            )? {
+                dest.flush_interval = Some(interval);
+            }
+        }
+        let mut appender_formatters = Vec::with_capacity(self.appender_formatters.len());
+        for (ix, (name, formatter)) in self.appender_formatters.into_iter().enumerate() {
+            for dest in destinations(
+                name,
+                &mut root,
+                &mut self.appenders,
+                mirror.as_mut(),
+                &mut routes,
+            )? {
+                dest.formatter = Some(ix);
+            }
+            appender_formatters.push(formatter);
+        }
+        let metrics = Arc::new(Metrics::new());
+        metrics.register(root.counter.clone());
+        if let Some(mirror) = &mirror {
+            metrics.register(mirror.counter.clone());
+        }
+        let mut names = self.appenders.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            metrics.register(self.appenders[name].counter.clone());
+        }
+        for dest in routes.iter().flat_map(|r| r.appenders.iter()) {
+            metrics.register(dest.counter.clone());
+        }
+
+        let (sync_sender, receiver) = match (&self.bounded_channel_option, self.queue) {
+            (Some(option), QueueKind::Ring(_)) => queue::ring(option.size + option.reserve),
+            (None, QueueKind::Ring(_)) => {
+                return Err(
+                    ConfigError::Invalid("ring queue can not be unbounded".to_string()).into(),
+                )
+            }
+            (option, QueueKind::Channel) => {
+                queue::channel(option.as_ref().map(|x| x.size + x.reserve))
+            }
         };
-        let (notification_sender, notification_receiver) = bounded(1);
-        std::thread::Builder::new()
-            .name("logger".to_string())
-            .spawn(move || {
-                let mut appenders = self.appenders;
-                let filters = filters;
-
-                let mut root = self.root;
-                let mut last_log = HashMap::default();
-                let mut missed_log = HashMap::default();
-                let mut last_flush = Instant::now();
-                let timeout = Duration::from_millis(200);
-                loop {
-                    match receiver.recv_timeout(timeout) {
-                        Ok(LoggerInput::LogMsg(log_msg)) => {
-                            log_msg.write(
-                                &filters,
-                                &mut appenders,
-                                &mut root,
-                                root_level,
-                                &mut missed_log,
-                                &mut last_log,
-                                offset,
-                                &time_format,
-                            );
-                        }
-                        Ok(LoggerInput::Flush) => {
-                            let max = receiver.len();
-                            'queue: for _ in 1..=max {
-                                if let Ok(LoggerInput::LogMsg(msg)) = receiver.try_recv() {
-                                    msg.write(
-                                        &filters,
-                                        &mut appenders,
-                                        &mut root,
-                                        root_level,
-                                        &mut missed_log,
-                                        &mut last_log,
-                                        offset,
-                                        &time_format,
-                                    )
-                                } else {
-                                    break 'queue;
-                                }
-                            }
-                            let flush_result = appenders
-                                .values_mut()
-                                .chain([&mut root])
-                                .find_map(|w| w.flush().err());
-                            if let Some(error) = flush_result {
-                                notification_sender
-                                    .send(LoggerOutput::FlushError(error))
-                                    .expect("logger notification failed");
-                            } else {
-                                notification_sender
-                                    .send(LoggerOutput::Flushed)
-                                    .expect("logger notification failed");
-                            }
-                        }
-                        Err(RecvTimeoutError::Timeout) => {
-                            if last_flush.elapsed() > Duration::from_millis(1000) {
-                                let flush_errors = appenders
-                                    .values_mut()
-                                    .chain([&mut root])
-                                    .filter_map(|w| w.flush().err());
-                                for err in flush_errors {
-                                    log::warn!("Ftlog flush error: {}", err);
-                                }
-                                last_flush = Instant::now();
-                            };
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "sender closed without sending a Quit first, this is a bug, {}",
-                                e
-                            );
-                        }
-                    }
-                }
-            })?;
-        let block = self
+        let overflow = self
             .bounded_channel_option
             .as_ref()
-            .map(|x| x.block)
-            .unwrap_or(false);
+            .map(|x| x.policy)
+            .unwrap_or(OverflowPolicy::Block);
+        let evict_receiver = (overflow == OverflowPolicy::DropOldest).then(|| receiver.clone());
+        let reserve_from = self
+            .bounded_channel_option
+            .as_ref()
+            .filter(|x| x.reserve > 0 && overflow != OverflowPolicy::Block)
+            .map(|x| x.size);
+        let tap = Arc::new(tap::Tap::default());
+        let progress = Arc::<worker::Progress>::default();
+        let pool = Arc::new(pool::Pool::default());
+        let worker = LogWorker::new(WorkerConfig {
+            routes,
+            filters,
+            root,
+            appenders: self.appenders,
+            attached: self.attached,
+            mirror,
+            offset,
+            time_format,
+            precision,
+            cache_time,
+            formatter: formatter.clone(),
+            target_formatters: self.target_formatters,
+            appender_formatters,
+            global_fields: global_fields.clone(),
+            metrics: metrics.clone(),
+            tap: tap.clone(),
+            progress: progress.clone(),
+            on_internal_error: self.on_internal_error.clone(),
+            workers: self.workers,
+            flush_interval: self.flush_interval,
+            clock: self.clock.clone(),
+            renames: self.renames,
+            reorder_window: self.reorder_window,
+            pool: pool.clone(),
+            sanitize: self.sanitize,
+            max_message_len: self.max_message_len,
+            multiline: self.multiline,
+            enrichers: self.enrichers,
+            #[cfg(feature = "redact")]
+            redactions,
+            #[cfg(feature = "message_filter")]
+            message_rules,
+        });
+        let shared = Arc::new(Shared {
+            queue: sync_sender,
+            metrics: metrics.clone(),
+            tap: tap.clone(),
+            closed: AtomicBool::new(false),
+            progress,
+            on_internal_error: self.on_internal_error,
+            worker: Mutex::new(None),
+            shutdown_timeout: self.shutdown_timeout,
+            once_summary: self.once_summary,
+            level,
+            targeted: AtomicBool::new(!target_levels.is_empty()),
+            targets: ArcSwap::new(Arc::new(target_levels)),
+            throttle: governor::Throttle::default(),
+            per_thread: self.per_thread.map(|path| {
+                appender::per_thread::PerThread::new(path, worker.renderer(), self.clock.clone())
+            }),
+            batches: self.low_contention.then(batch::Batches::new),
+            pool,
+            dropped: self.drop_markers.then(|| AtomicUsize::new(0)),
+            formatter,
+            record_fields,
+        });
+        if self.low_contention {
+            batch::spawn(&shared)?;
+        }
+        if let Some(governor) = self.governor {
+            governor::spawn(&shared, governor)?;
+        }
+        #[cfg(all(unix, feature = "signal"))]
+        if !self.signal_toggles.is_empty() {
+            signal::spawn(&shared, self.signal_toggles)?;
+        }
+        #[cfg(feature = "control")]
+        if let Some(listen) = self.control {
+            control::spawn(&shared, listen)?;
+        }
+        let handle = worker.spawn(receiver)?;
+        *shared.worker.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
         let print = self
             .bounded_channel_option
             .as_ref()
             .map(|x| x.print)
             .unwrap_or(false);
+        let default_format = DefaultFormat {
+            thread: self.with_thread,
+            location: self.with_source_location,
+            style: Style {
+                abbreviate: self.abbreviate_source_path,
+                color: self.color,
+            },
+            global_fields: (!global_fields.is_empty()).then(|| global_fields.clone()),
+        };
+        let fast_path = self
+            .format
+            .is_none()
+            .then(|| Arc::new(default_format.clone()));
         Ok(Logger {
-            format: self.format,
+            format: self.format.unwrap_or_else(|| Box::new(default_format)),
+            format_targets,
+            fast_path,
+            rate_limit: self.default_rate_limit.map(CallsiteLimiter::new),
+            call_site_time: self.timestamp_source == TimestampSource::CallSite
+                || self.low_contention,
+            sequence: self.sequence.then(|| AtomicU64::new(0)),
+            clock: self.clock,
+            #[cfg(feature = "random_drop")]
+            sample: std::array::from_fn(|ix| {
+                self.sample
+                    .iter()
+                    .filter(|(level, _)| *level != LevelFilter::Off && ix >= *level as usize)
+                    .map(|(_, rate)| *rate)
+                    .fold(1f32, f32::min)
+            }),
             filters: self.drop_filters,
-            level: global_level,
-            queue: sync_sender,
-            notification: notification_receiver,
-            block,
-            discard_state: if block || !print {
+            metadata_filters: self.metadata_filters,
+            scrubs: self.scrubs,
+            middlewares: self.middlewares,
+            shared,
+            overflow,
+            receiver: evict_receiver,
+            reserve_from,
+            sync_level: self.sync_level,
+            crash_context: self
+                .crash_context
+                .map(|(persist, capacity)| crash_context::CrashContext::new(persist, capacity)),
+            #[cfg(feature = "tracing")]
+            capture_tracing: self.capture_tracing,
+            discard_state: if overflow == OverflowPolicy::Block || !print {
                 None
             } else {
                 Some(DiscardState {
@@ -1141,14 +4153,33 @@ impl Builder {
                     count: AtomicUsize::new(0),
                 })
             },
+            overflow_notice: self
+                .on_overflow
+                .filter(|_| overflow != OverflowPolicy::Block)
+                .map(|callback| OverflowNotice {
+                    callback,
+                    pending: AtomicUsize::new(0),
+                    limit: rate_limit::AtMostEvery::new(),
+                }),
             stopped: AtomicBool::new(false),
         })
     }
 
     /// try building and setting as global logger
-    pub fn try_init(self) -> Result<LoggerGuard, Box<dyn std::error::Error>> {
-        let logger = self.build()?;
-        Ok(logger.init()?)
+    ///
+    /// Unlike [`Builder::build`] followed by [`Logger::init`], errors are told apart by
+    /// [`InitError`], so that a library or a test can initialize ftlog opportunistically:
+    ///
+    /// ```
+    /// match ftlog::builder().try_init() {
+    ///     Ok(_guard) => log::info!("logger installed"),
+    ///     Err(ftlog::InitError::AlreadySet(_)) => (),
+    ///     Err(e) => panic!("{}", e),
+    /// }
+    /// ```
+    pub fn try_init(self) -> Result<LoggerGuard, InitError> {
+        let logger = self.build_logger()?;
+        logger.init().map_err(InitError::AlreadySet)
     }
 }
 
@@ -1158,3 +4189,58 @@ impl Default for Builder {
         Builder::new()
     }
 }
+
+/// Appenders named `name`: root, a named appender, the stderr mirror, or all
+/// appenders of a route
+fn destinations<'a>(
+    name: &str,
+    root: &'a mut Destination,
+    appenders: &'a mut HashMap<&'static str, Destination>,
+    mirror: Option<&'a mut Destination>,
+    routes: &'a mut [Route],
+) -> Result<Vec<&'a mut Destination>, InitError> {
+    let dests = match name {
+        "root" => vec![root],
+        _ => match (appenders.get_mut(name), mirror) {
+            (Some(dest), _) => vec![dest],
+            (None, Some(mirror)) if name == "stderr" => vec![mirror],
+            _ => routes
+                .iter_mut()
+                .filter(|r| r.rule.name() == name)
+                .flat_map(|r| r.appenders.iter_mut())
+                .collect(),
+        },
+    };
+    if dests.is_empty() {
+        return Err(ConfigError::UnknownAppender(name.to_string()).into());
+    }
+    Ok(dests)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn local_offset_refreshed_every_minute() {
+        let offset = Offset::new(&LogTimezone::Local);
+        let utc = OffsetDateTime::from_unix_timestamp(1_666_627_230).unwrap();
+        let local = offset.apply(utc);
+        assert_eq!(local, utc);
+        assert_eq!(local.offset(), local_timezone());
+
+        let Offset::Local(cached) = &offset else {
+            unreachable!()
+        };
+        let minute = 1_666_627_230 / 60 + 1;
+        assert_eq!(cached.load(Ordering::Relaxed) >> 32, minute);
+        // a stale offset is kept within the minute and for earlier minutes, and
+        // replaced in the next one
+        cached.store(minute << 32 | 3600, Ordering::Relaxed);
+        assert_eq!(offset.apply(utc).offset().whole_seconds(), 3600);
+        let earlier = offset.apply(utc - Duration::from_secs(60));
+        assert_eq!(earlier.offset().whole_seconds(), 3600);
+        let next = offset.apply(utc + Duration::from_secs(30));
+        assert_eq!(next.offset(), local_timezone());
+    }
+}
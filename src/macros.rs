@@ -0,0 +1,289 @@
+/// Create a [`Span`](crate::context::Span) with context fields, logging its elapsed
+/// time when dropped
+///
+/// The span is logged at `Info` level by default, use `level: ` to specify another one.
+///
+/// ```
+/// # let _guard = ftlog::builder().try_init().unwrap();
+/// fn handle_request(id: u64) {
+///     let _span = ftlog::span!("handle_request", id = id);
+///     log::info!("start");
+///     // Output:
+///     // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:3] id=7 start
+/// }
+/// handle_request(7);
+/// // Output:
+/// // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:2] id=7 handle_request finished in 35.2µs
+///
+/// let _span = ftlog::span!(level: log::Level::Debug, "debug_only");
+/// ```
+#[macro_export]
+macro_rules! span {
+    (level: $level:expr, $name:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::context::Span::new(
+            $name,
+            $level,
+            (module_path!(), file!(), line!()),
+            vec![$($crate::context::insert(stringify!($key), $value)),*],
+        )
+    };
+    ($name:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::span!(level: $crate::Level::Info, $name $(, $key = $value)*)
+    };
+}
+
+/// Log the 1st, (n+1)th, (2n+1)th... call of the call site
+///
+/// Skipped calls are decided at call site and never sent to log thread.
+///
+/// ```
+/// use ftlog::{log_every_n, Level};
+/// for i in 0..1000 {
+///     // logged 10 times
+///     log_every_n!(100, Level::Warn, "slow response #{}", i);
+/// }
+/// ```
+#[macro_export]
+macro_rules! log_every_n {
+    ($n:expr, $level:expr, $($arg:tt)+) => {{
+        static EVERY_N: $crate::rate_limit::EveryN = $crate::rate_limit::EveryN::new();
+        let level = $level;
+        if $crate::log_enabled!(level) && EVERY_N.tick($n as u64) {
+            $crate::log!(level, $($arg)+);
+        }
+    }};
+}
+
+/// Log at most once per period for the call site
+///
+/// The period is either `std::time::Duration` or `time::Duration`. Skipped calls are
+/// decided at call site and never sent to log thread.
+///
+/// ```
+/// use ftlog::{appender::Duration, log_at_most_every, Level};
+/// for _ in 0..1000 {
+///     // logged once
+///     log_at_most_every!(Duration::seconds(5), Level::Info, "queue is full");
+/// }
+/// ```
+#[macro_export]
+macro_rules! log_at_most_every {
+    ($period:expr, $level:expr, $($arg:tt)+) => {{
+        static AT_MOST_EVERY: $crate::rate_limit::AtMostEvery =
+            $crate::rate_limit::AtMostEvery::new();
+        let level = $level;
+        if $crate::log_enabled!(level) && AT_MOST_EVERY.allow($period) {
+            $crate::log!(level, $($arg)+);
+        }
+    }};
+}
+
+/// Log only the first call of the call site
+///
+/// Useful for deprecation and configuration warnings. Enable
+/// [`Builder::once_summary`](crate::Builder::once_summary) to log how many calls are
+/// suppressed at shutdown.
+///
+/// ```
+/// use ftlog::{log_once, Level};
+/// for _ in 0..10 {
+///     // logged once
+///     log_once!(Level::Warn, "`foo` is deprecated, use `bar` instead");
+/// }
+/// ```
+#[macro_export]
+macro_rules! log_once {
+    ($level:expr, $($arg:tt)+) => {{
+        static ONCE: $crate::rate_limit::Once =
+            $crate::rate_limit::Once::new(module_path!(), file!(), line!());
+        let level = $level;
+        if $crate::log_enabled!(level) && ONCE.first() {
+            $crate::log!(level, $($arg)+);
+        }
+    }};
+}
+
+/// [`log_once!`](crate::log_once) at `Error` level
+#[macro_export]
+macro_rules! error_once {
+    ($($arg:tt)+) => { $crate::log_once!($crate::Level::Error, $($arg)+) };
+}
+
+/// [`log_once!`](crate::log_once) at `Warn` level
+#[macro_export]
+macro_rules! warn_once {
+    ($($arg:tt)+) => { $crate::log_once!($crate::Level::Warn, $($arg)+) };
+}
+
+/// [`log_once!`](crate::log_once) at `Info` level
+#[macro_export]
+macro_rules! info_once {
+    ($($arg:tt)+) => { $crate::log_once!($crate::Level::Info, $($arg)+) };
+}
+
+/// [`log_once!`](crate::log_once) at `Debug` level
+#[macro_export]
+macro_rules! debug_once {
+    ($($arg:tt)+) => { $crate::log_once!($crate::Level::Debug, $($arg)+) };
+}
+
+/// [`log_once!`](crate::log_once) at `Trace` level
+#[macro_export]
+macro_rules! trace_once {
+    ($($arg:tt)+) => { $crate::log_once!($crate::Level::Trace, $($arg)+) };
+}
+
+/// Log to a standalone [`Logger`](crate::Logger), or anything implementing
+/// [`Log`](crate::Log)
+///
+/// Unlike `log::log!(logger: ...)`, records are filtered by the level of the given
+/// logger instead of the global max level, so it works without a global logger.
+///
+/// ```
+/// use ftlog::{log_to, Level};
+///
+/// let logger = ftlog::builder().build().unwrap();
+/// log_to!(logger, Level::Info, "user {} logged in", "alice");
+/// log_to!(logger, target: "access", Level::Info, "GET /index.html 200");
+/// ```
+#[macro_export]
+macro_rules! log_to {
+    ($logger:expr, target: $target:expr, $level:expr, $($arg:tt)+) => {{
+        let logger = &$logger;
+        let metadata = $crate::Metadata::builder()
+            .level($level)
+            .target($target)
+            .build();
+        if $crate::Log::enabled(logger, &metadata) {
+            $crate::Log::log(
+                logger,
+                &$crate::Record::builder()
+                    .metadata(metadata)
+                    .module_path_static(Some(module_path!()))
+                    .file_static(Some(file!()))
+                    .line(Some(line!()))
+                    .args(format_args!($($arg)+))
+                    .build(),
+            );
+        }
+    }};
+    ($logger:expr, $level:expr, $($arg:tt)+) => {
+        $crate::log_to!($logger, target: module_path!(), $level, $($arg)+)
+    };
+}
+
+/// Log an error with its chain of sources at `Error` level, see [`error`](mod@crate::error)
+///
+/// ```
+/// # let _guard = ftlog::builder().try_init().unwrap();
+/// let err = std::fs::read("/no/such/file").unwrap_err();
+/// ftlog::error_chain!(err, "failed to read {}", "/no/such/file");
+/// ftlog::error_chain!(target: "config", err, "failed to read config");
+/// ```
+#[macro_export]
+macro_rules! error_chain {
+    (target: $target:expr, $err:expr, $($arg:tt)+) => {{
+        let target = $target;
+        if $crate::log_enabled!(target: target, $crate::Level::Error) {
+            $crate::error::log_error_chain(
+                &$err,
+                format_args!($($arg)+),
+                target,
+                (module_path!(), file!(), line!()),
+            );
+        }
+    }};
+    ($err:expr, $($arg:tt)+) => {
+        $crate::error_chain!(target: module_path!(), $err, $($arg)+)
+    };
+}
+
+/// Log at `Error` level, like `log::error!`, with a shorthand for errors
+///
+/// `err = e;` or `error = e;` before the message logs `e` with its chain of sources
+/// like [`error_chain!`](crate::error_chain), so JSON output gets them as separate
+/// fields. A `?` before the error is accepted too, and the message may be omitted.
+/// Anything else is passed to `log::error!`.
+///
+/// ```
+/// # let _guard = ftlog::builder().try_init().unwrap();
+/// let e = std::fs::read("/no/such/file").unwrap_err();
+/// ftlog::error!(err = ?e; "failed to read {}", "/no/such/file");
+/// ftlog::error!(target: "config", error = e);
+/// ftlog::error!("plain {}", "message");
+/// ```
+#[macro_export]
+macro_rules! error {
+    (target: $target:expr, err = $(?)? $err:expr; $($arg:tt)+) => {
+        $crate::error_chain!(target: $target, $err, $($arg)+)
+    };
+    (target: $target:expr, err = $(?)? $err:expr $(;)?) => {
+        $crate::error_chain!(target: $target, $err, "")
+    };
+    (target: $target:expr, error = $(?)? $err:expr $(; $($arg:tt)+)?) => {
+        $crate::error!(target: $target, err = $err $(; $($arg)+)?)
+    };
+    (err = $(?)? $err:expr $(; $($arg:tt)+)?) => {
+        $crate::error!(target: module_path!(), err = $err $(; $($arg)+)?)
+    };
+    (error = $(?)? $err:expr $(; $($arg:tt)+)?) => {
+        $crate::error!(target: module_path!(), err = $err $(; $($arg)+)?)
+    };
+    ($($arg:tt)+) => {
+        $crate::__log::error!($($arg)+)
+    };
+}
+
+/// Log an HTTP request for access logs, see [`access`](mod@crate::access)
+///
+/// Takes method, path, status, latency as `std::time::Duration`, bytes sent and user
+/// agent, followed by optional key-values after `;`. Records have `Info` level and
+/// target `access` unless `target: ` is given, and go to the global logger unless
+/// `logger: ` is given.
+///
+/// ```
+/// # use std::time::Duration;
+/// # let _guard = ftlog::builder().try_init().unwrap();
+/// let latency = Duration::from_millis(3);
+/// ftlog::access_log!("GET", "/index.html", 200, latency, 2326, "curl/8.0");
+/// ftlog::access_log!(target: "api", "GET", "/v1/users", 200, latency, 512, "curl/8.0";
+///     client = "10.0.0.7", protocol = "HTTP/2.0");
+/// ```
+#[macro_export]
+macro_rules! access_log {
+    (logger: $logger:expr, target: $target:expr, $method:expr, $path:expr, $status:expr,
+        $latency:expr, $bytes:expr, $user_agent:expr $(; $($key:ident = $value:expr),+ $(,)?)?) => {{
+        let logger = &$logger;
+        let metadata = $crate::Metadata::builder()
+            .level($crate::Level::Info)
+            .target($target)
+            .build();
+        if $crate::Log::enabled(logger, &metadata) {
+            $crate::access::log_access(
+                logger,
+                metadata,
+                $crate::access::Request {
+                    method: &$method,
+                    path: &$path,
+                    status: $status,
+                    latency: $latency,
+                    bytes: $bytes,
+                    user_agent: &$user_agent,
+                },
+                &[$($((stringify!($key), &$value as &dyn ::std::fmt::Display)),+)?],
+                (module_path!(), file!(), line!()),
+            );
+        }
+    }};
+    (logger: $logger:expr, $($rest:tt)+) => {
+        $crate::access_log!(logger: $logger, target: "access", $($rest)+)
+    };
+    (target: $target:expr, $($rest:tt)+) => {{
+        if $crate::log_enabled!(target: $target, $crate::Level::Info) {
+            $crate::access_log!(logger: $crate::logger(), target: $target, $($rest)+);
+        }
+    }};
+    ($($rest:tt)+) => {
+        $crate::access_log!(target: "access", $($rest)+)
+    };
+}
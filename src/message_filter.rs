@@ -0,0 +1,41 @@
+//! Rules on the text of messages, applied in log thread before routing, see
+//! [`Builder::deny_message`](crate::Builder::deny_message)
+use log::Level;
+use regex::Regex;
+
+/// What to do with a record whose message matches a rule
+#[derive(Clone, Copy)]
+pub(crate) enum Action {
+    /// keep the record as is, skipping later rules
+    Allow,
+    Deny,
+    /// lower the level of the record to at most this one
+    Downgrade(Level),
+}
+
+/// Compiled patterns in the order they are added
+#[derive(Default)]
+pub(crate) struct MessageRules(Vec<(Regex, Action)>);
+
+impl MessageRules {
+    pub(crate) fn new(rules: Vec<(String, Action)>) -> Result<MessageRules, regex::Error> {
+        rules
+            .into_iter()
+            .map(|(pattern, action)| Ok((Regex::new(&pattern)?, action)))
+            .collect::<Result<_, _>>()
+            .map(MessageRules)
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Action of the first rule matching `message`
+    pub(crate) fn action(&self, message: &str) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(regex, _)| regex.is_match(message))
+            .map(|(_, action)| *action)
+    }
+}
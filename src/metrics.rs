@@ -0,0 +1,51 @@
+//! Telemetry through the `metrics` facade
+//!
+//! Requires `metrics` feature. Counters are emitted to the recorder installed with
+//! the `metrics` crate, e.g. by `metrics-exporter-prometheus`, as they change:
+//!
+//! - `ftlog_records_total{level}`: records handled by log thread
+//! - `ftlog_dropped_total`: records discarded because the channel to log thread was full
+//! - `ftlog_rotations_total`: rotations of `FileAppender`s
+//!
+//! Nothing needs to be set up in ftlog, records handled before a recorder is
+//! installed are not counted. `prometheus` feature exports the same numbers to a
+//! `prometheus` registry instead.
+use ::metrics::{counter, describe_counter, Unit};
+use log::Level;
+
+const RECORDS: &str = "ftlog_records_total";
+const DROPPED: &str = "ftlog_dropped_total";
+const ROTATIONS: &str = "ftlog_rotations_total";
+
+/// Describe counters to the installed recorder
+pub(crate) fn describe() {
+    describe_counter!(RECORDS, Unit::Count, "Records handled by log thread");
+    describe_counter!(
+        DROPPED,
+        Unit::Count,
+        "Records discarded because the channel to log thread was full"
+    );
+    describe_counter!(ROTATIONS, Unit::Count, "Rotations of file appenders");
+}
+
+#[inline]
+pub(crate) fn record(level: Level) {
+    let level = match level {
+        Level::Error => "error",
+        Level::Warn => "warn",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    };
+    counter!(RECORDS, "level" => level).increment(1);
+}
+
+#[inline]
+pub(crate) fn dropped() {
+    counter!(DROPPED).increment(1);
+}
+
+#[inline]
+pub(crate) fn rotation() {
+    counter!(ROTATIONS).increment(1);
+}
@@ -0,0 +1,98 @@
+//! Reusable stages rewriting or dropping records at the log call
+//!
+//! A [`Middleware`] sees each record as an [`OwnedRecord`] it may change, and
+//! decides whether the record is kept as it was, kept with changes, or dropped.
+//! Middlewares added with [`Builder::middleware`](crate::Builder::middleware) run in
+//! the order they are added, after filters and [`Builder::scrub`](crate::Builder::scrub)
+//! hooks, so that cross-cutting concerns like redaction, enrichment or routing hints
+//! can be packaged once and shared between services.
+//!
+//! ```
+//! use ftlog::middleware::{Action, Middleware, OwnedRecord};
+//!
+//! /// Tag records of the payment service so that they are routed to their own file
+//! struct Payments;
+//!
+//! impl Middleware for Payments {
+//!     fn process(&self, record: &mut OwnedRecord) -> Action {
+//!         if !record.target.starts_with("payments") {
+//!             return Action::Keep;
+//!         }
+//!         record.key_values.insert("team", "payments");
+//!         Action::Modify
+//!     }
+//! }
+//!
+//! let _guard = ftlog::builder()
+//!     .middleware(Payments)
+//!     // closures are middlewares too
+//!     .middleware(|record: &mut OwnedRecord| match record.message.contains("healthz") {
+//!         true => Action::Drop,
+//!         false => Action::Keep,
+//!     })
+//!     .try_init()
+//!     .unwrap();
+//! log::info!(target: "payments::stripe", "charged");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:27] team=payments charged
+//! ```
+//!
+//! Like `scrub` hooks, middlewares run on the thread of the log call and should be
+//! cheap. Key-values of [`context`](mod@crate::context) and global fields are not
+//! passed to them. A changed level is not checked against the max log level again.
+use log::{Level, Record};
+
+use crate::format::KvMap;
+
+/// What to do with a record after a [`Middleware`] processed it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// keep the record, which was not changed
+    Keep,
+    /// drop the record, skipping later middlewares
+    Drop,
+    /// keep the record with the changes made to it
+    Modify,
+}
+
+/// A stage processing each record at the log call, see [module doc](self)
+pub trait Middleware: Send + Sync {
+    /// Change `record` in place and tell what to do with it
+    ///
+    /// Changes of all middlewares are applied once any of them returns
+    /// [`Action::Modify`], and discarded otherwise.
+    fn process(&self, record: &mut OwnedRecord) -> Action;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(&mut OwnedRecord) -> Action + Send + Sync,
+{
+    fn process(&self, record: &mut OwnedRecord) -> Action {
+        self(record)
+    }
+}
+
+/// A record of a log call passed to a [`Middleware`]
+#[non_exhaustive]
+pub struct OwnedRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// key-values of the log call, kept only with `kv` feature
+    pub key_values: KvMap,
+}
+
+impl OwnedRecord {
+    pub(crate) fn new(record: &Record) -> OwnedRecord {
+        OwnedRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: match record.args().as_str() {
+                Some(args) => args.to_string(),
+                None => record.args().to_string(),
+            },
+            key_values: KvMap::new(record),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Line breaks in messages, see [`Builder::multiline`](crate::Builder::multiline)
+use std::borrow::Cow;
+
+use crate::worker::Payload;
+use crate::Multiline;
+
+/// Rewrite line breaks of `text` by `policy`, or `None` if it is a single line
+pub(crate) fn convert(text: &str, policy: Multiline) -> Option<String> {
+    if !text.contains(['\n', '\r']) {
+        return None;
+    }
+    match policy {
+        Multiline::AsIs => None,
+        Multiline::Escape => Some(text.replace('\r', "\\r").replace('\n', "\\n")),
+        Multiline::Indent(prefix) => {
+            let mut indented = String::with_capacity(text.len() + 16);
+            for (i, line) in text.lines().enumerate() {
+                if i > 0 {
+                    indented.push('\n');
+                    indented.push_str(prefix);
+                }
+                indented.push_str(line);
+            }
+            Some(indented)
+        }
+    }
+}
+
+/// Rewrite line breaks in the message of a log message in place, or in the whole
+/// output of a custom `FtLogFormat`
+pub(crate) fn apply(payload: &mut Payload, policy: Multiline) {
+    match payload {
+        Payload::Record(fields) => {
+            if let Some(text) = convert(&fields.args, policy) {
+                fields.args = Cow::Owned(text);
+            }
+        }
+        Payload::Message(msg) => {
+            if let Some(text) = convert(&msg.args, policy) {
+                msg.args = text;
+            }
+        }
+        Payload::Static(msg) => {
+            if let Some(args) = convert(msg.args, policy) {
+                // the message ends the default format
+                let mut text = msg.to_string();
+                text.truncate(text.len() - msg.args.len());
+                text.push_str(&args);
+                *payload = Payload::Display(Box::new(text));
+            }
+        }
+        Payload::Display(msg) => {
+            if let Some(text) = convert(&msg.to_string(), policy) {
+                *payload = Payload::Display(Box::new(text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn convert_line_breaks() {
+        let text = "SELECT *\r\nFROM users\nWHERE id = 1";
+        assert_eq!(convert("one line", Multiline::Escape), None);
+        assert_eq!(convert(text, Multiline::AsIs), None);
+        assert_eq!(
+            convert(text, Multiline::Escape).unwrap(),
+            "SELECT *\\r\\nFROM users\\nWHERE id = 1"
+        );
+        assert_eq!(
+            convert(text, Multiline::Indent("  | ")).unwrap(),
+            "SELECT *\n  | FROM users\n  | WHERE id = 1"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Buffers of records recycled from log thread back to log calls
+//!
+//! A record in the default format with formatting arguments is boxed along with its
+//! formatted text, key-values and context. At a million records per second, the
+//! allocation at call site and the free in log thread make up a sizable part of
+//! logging cost, so log thread returns the boxes to a pool once they are rendered, and
+//! log calls reuse them, keeping the capacity of their buffers.
+use crossbeam_queue::ArrayQueue;
+
+use crate::Message;
+
+/// Maximum number of buffers kept in the pool
+const CAPACITY: usize = 1024;
+/// Buffers with a larger formatted text are freed instead of kept
+const MAX_TEXT: usize = 1024;
+
+pub(crate) struct Pool {
+    buffers: ArrayQueue<Box<Message>>,
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Pool {
+            buffers: ArrayQueue::new(CAPACITY),
+        }
+    }
+}
+
+impl Pool {
+    /// A recycled buffer, or a new one if the pool is empty
+    #[inline]
+    pub(crate) fn take(&self) -> Box<Message> {
+        self.buffers
+            .pop()
+            .unwrap_or_else(|| Box::new(Message::default()))
+    }
+
+    /// Return a buffer, releasing what it refers to but keeping its capacity
+    #[inline]
+    pub(crate) fn give(&self, mut msg: Box<Message>) {
+        if msg.args.capacity() > MAX_TEXT {
+            return;
+        }
+        msg.clear();
+        let _ = self.buffers.push(msg);
+    }
+}
@@ -0,0 +1,147 @@
+//! Prometheus metrics of the log pipeline
+//!
+//! Requires `prometheus` feature. [`Collector`] exports [statistics](mod@crate::stats)
+//! of a logger to a `prometheus` registry, so that dashboards can tell when logs
+//! are dropped or log thread falls behind:
+//!
+//! - `ftlog_records_total{level}`: records handled by log thread
+//! - `ftlog_dropped_total`: records discarded because the channel to log thread was full
+//! - `ftlog_queue_depth`: records waiting in the channel to log thread
+//! - `ftlog_bytes_written_total{appender}`: bytes written to each appender
+//! - `ftlog_rotations_total`: rotations of all `FileAppender`s in the process
+//!
+//! ```
+//! let registry = prometheus::Registry::new();
+//! registry
+//!     .register(Box::new(ftlog::prometheus::Collector::new()))
+//!     .unwrap();
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! log::info!("Hello world!");
+//! let families = registry.gather();
+//! ```
+use std::sync::{Arc, Mutex};
+
+use ::prometheus::core::{Collector as PrometheusCollector, Desc};
+use ::prometheus::proto::MetricFamily;
+use ::prometheus::{IntCounter, IntCounterVec, IntGauge, Opts};
+
+use crate::stats::StatsSnapshot;
+use crate::{Logger, Shared, GLOBAL_PIPELINE};
+
+/// A `prometheus` collector reporting statistics of a logger
+pub struct Collector {
+    // `None` for the global logger, which may be installed after the collector is created
+    shared: Option<Arc<Shared>>,
+    records: IntCounterVec,
+    dropped: IntCounter,
+    queue_depth: IntGauge,
+    bytes_written: IntCounterVec,
+    rotations: IntCounter,
+    // counters only go up, so concurrent collects must not both add the same increase
+    collecting: Mutex<()>,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Collector {
+    /// Report the global logger, metrics are empty until ftlog is installed
+    pub fn new() -> Collector {
+        Self::with_shared(None)
+    }
+
+    /// Report a logger that is not installed as the global logger
+    pub fn for_logger(logger: &Logger) -> Collector {
+        Self::with_shared(Some(logger.shared.clone()))
+    }
+
+    fn with_shared(shared: Option<Arc<Shared>>) -> Collector {
+        Collector {
+            shared,
+            records: IntCounterVec::new(
+                Opts::new("ftlog_records_total", "Records handled by log thread"),
+                &["level"],
+            )
+            .unwrap(),
+            dropped: IntCounter::new(
+                "ftlog_dropped_total",
+                "Records discarded because the channel to log thread was full",
+            )
+            .unwrap(),
+            queue_depth: IntGauge::new(
+                "ftlog_queue_depth",
+                "Records waiting in the channel to log thread",
+            )
+            .unwrap(),
+            bytes_written: IntCounterVec::new(
+                Opts::new(
+                    "ftlog_bytes_written_total",
+                    "Bytes written to each appender",
+                ),
+                &["appender"],
+            )
+            .unwrap(),
+            rotations: IntCounter::new(
+                "ftlog_rotations_total",
+                "Rotations of all file appenders in the process",
+            )
+            .unwrap(),
+            collecting: Mutex::new(()),
+        }
+    }
+
+    fn snapshot(&self) -> Option<StatsSnapshot> {
+        self.shared
+            .as_ref()
+            .or_else(|| GLOBAL_PIPELINE.get())
+            .map(|x| x.metrics.snapshot(x.queue.len()))
+    }
+}
+
+/// Raise `counter` to `value`
+fn advance(counter: &IntCounter, value: u64) {
+    counter.inc_by(value.saturating_sub(counter.get()));
+}
+
+impl PrometheusCollector for Collector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.records
+            .desc()
+            .into_iter()
+            .chain(self.dropped.desc())
+            .chain(self.queue_depth.desc())
+            .chain(self.bytes_written.desc())
+            .chain(self.rotations.desc())
+            .collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let _collecting = self.collecting.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(stats) = self.snapshot() {
+            for (level, count) in stats.records {
+                let level = level.as_str().to_ascii_lowercase();
+                advance(&self.records.with_label_values(&[level.as_str()]), count);
+            }
+            advance(&self.dropped, stats.dropped);
+            self.queue_depth.set(stats.queue_depth as i64);
+            for appender in stats.appenders {
+                let counter = self
+                    .bytes_written
+                    .with_label_values(&[appender.name.as_str()]);
+                advance(&counter, appender.bytes_written);
+            }
+            advance(&self.rotations, stats.rotations);
+        }
+        self.records
+            .collect()
+            .into_iter()
+            .chain(self.dropped.collect())
+            .chain(self.queue_depth.collect())
+            .chain(self.bytes_written.collect())
+            .chain(self.rotations.collect())
+            .collect()
+    }
+}
@@ -0,0 +1,229 @@
+//! Search records in rotated log files
+//!
+//! [`Search`] finds files created by a rotating [`FileAppender`] from the configured
+//! path, and iterates records of a time range across them. Files compressed by gzip
+//! after rotation, like `app-20240101.log.gz`, are decompressed on the fly.
+//!
+//! ```no_run
+//! use ftlog::query::Search;
+//! use time::macros::datetime;
+//!
+//! // files of `FileAppender::rotate("/var/log/app/app.log", Period::Hour)`
+//! let search = Search::new("/var/log/app", "app");
+//! for record in search
+//!     .records(datetime!(2024-01-01 10:00 +8)..datetime!(2024-01-01 10:30 +8))
+//!     .unwrap()
+//! {
+//!     let record = record.unwrap();
+//!     println!("{}", record.text);
+//! }
+//! ```
+//!
+//! Only files whose period overlaps the range are read. A record starts with a
+//! timestamp in the default format or RFC3339, or is a JSON line with a `time` field
+//! like those of [`Format::Json`](crate::format::Format::Json). Lines without a
+//! timestamp, e.g. a multi-line message or a backtrace, belong to the record before
+//! them. Timestamps without an offset are in the timezone of the search, local by
+//! default.
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::ops::{Bound, RangeBounds};
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+use time::{OffsetDateTime, UtcOffset};
+
+use crate::appender::file::parse_rotated;
+use crate::appender::FileAppender;
+use crate::format::parse_time;
+use crate::LogTimezone;
+
+/// Files created by rotation of a log file, see [module](self) docs
+pub struct Search {
+    dir: PathBuf,
+    stem: String,
+    timezone: LogTimezone,
+}
+
+/// A record found by [`Search`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Entry {
+    /// timestamp of the record
+    pub time: OffsetDateTime,
+    /// lines of the record, without the trailing newline
+    pub text: String,
+    /// the file containing the record
+    pub path: PathBuf,
+}
+
+/// A file created by rotation, covering `[start, end)`
+struct Rotated {
+    path: PathBuf,
+    start: OffsetDateTime,
+    end: OffsetDateTime,
+}
+
+impl Search {
+    /// Files in `dir` rotated from a path whose file stem is `stem`, e.g. `app` for
+    /// `app.log` rotated into `app-20240101.log`
+    pub fn new(dir: impl AsRef<Path>, stem: impl Into<String>) -> Search {
+        Search {
+            dir: dir.as_ref().to_path_buf(),
+            stem: stem.into(),
+            timezone: LogTimezone::Local,
+        }
+    }
+
+    /// Timezone of rotation, which should be the one of the appender, local by default
+    pub fn timezone(mut self, timezone: LogTimezone) -> Search {
+        self.timezone = timezone;
+        self
+    }
+
+    fn rotated(&self) -> std::io::Result<Vec<Rotated>> {
+        let offset = FileAppender::offset_from_timezone(&self.timezone);
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let name = entry.file_name();
+            let Some((stem, period, start)) = name.to_str().and_then(parse_rotated) else {
+                continue;
+            };
+            if stem != self.stem {
+                continue;
+            }
+            let start = start.assume_offset(offset);
+            files.push(Rotated {
+                path: entry.path(),
+                start,
+                end: FileAppender::next(&start, period),
+            });
+        }
+        files.sort_by(|a, b| (a.start, &a.path).cmp(&(b.start, &b.path)));
+        Ok(files)
+    }
+
+    /// Rotated files, oldest first
+    pub fn files(&self) -> std::io::Result<Vec<PathBuf>> {
+        Ok(self.rotated()?.into_iter().map(|x| x.path).collect())
+    }
+
+    /// Records whose timestamp is in `range`, oldest file first
+    ///
+    /// Records are yielded in the order of each file. An I/O error while reading a
+    /// file is yielded once, then the search continues with the next file.
+    pub fn records<R: RangeBounds<OffsetDateTime>>(&self, range: R) -> std::io::Result<Records> {
+        let from = range.start_bound().cloned();
+        let to = range.end_bound().cloned();
+        let files = self
+            .rotated()?
+            .into_iter()
+            .filter(|file| {
+                let after_from = match from {
+                    Bound::Included(from) | Bound::Excluded(from) => file.end > from,
+                    Bound::Unbounded => true,
+                };
+                let before_to = match to {
+                    Bound::Included(to) => file.start <= to,
+                    Bound::Excluded(to) => file.start < to,
+                    Bound::Unbounded => true,
+                };
+                after_from && before_to
+            })
+            .map(|x| x.path)
+            .collect();
+        Ok(Records {
+            files,
+            reader: None,
+            pending: None,
+            range: (from, to),
+            offset: FileAppender::offset_from_timezone(&self.timezone),
+            line: Vec::new(),
+        })
+    }
+}
+
+/// Iterator of records returned by [`Search::records`]
+pub struct Records {
+    files: VecDeque<PathBuf>,
+    reader: Option<(PathBuf, Box<dyn BufRead>)>,
+    /// record whose following lines are not read yet
+    pending: Option<Entry>,
+    range: (Bound<OffsetDateTime>, Bound<OffsetDateTime>),
+    offset: UtcOffset,
+    line: Vec<u8>,
+}
+
+impl Records {
+    /// Replace the pending record, returning the previous one if it is in range
+    fn replace(&mut self, entry: Option<Entry>) -> Option<Entry> {
+        std::mem::replace(&mut self.pending, entry).filter(|x| self.range.contains(&x.time))
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|x| x == "gz") {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+impl Iterator for Records {
+    type Item = std::io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((path, reader)) = &mut self.reader else {
+                let path = self.files.pop_front()?;
+                match open(&path) {
+                    Ok(reader) => self.reader = Some((path, reader)),
+                    Err(e) => return Some(Err(e)),
+                }
+                continue;
+            };
+            self.line.clear();
+            match reader.read_until(b'\n', &mut self.line) {
+                Ok(0) => {
+                    self.reader = None;
+                    if let Some(entry) = self.replace(None) {
+                        return Some(Ok(entry));
+                    }
+                }
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&self.line);
+                    let line = line.trim_end_matches(['\n', '\r']);
+                    match parse_time(line, self.offset) {
+                        Some(time) => {
+                            let entry = Entry {
+                                time,
+                                text: line.to_string(),
+                                path: path.clone(),
+                            };
+                            if let Some(entry) = self.replace(Some(entry)) {
+                                return Some(Ok(entry));
+                            }
+                        }
+                        None => {
+                            if let Some(pending) = &mut self.pending {
+                                pending.text.push('\n');
+                                pending.text.push_str(line);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    self.reader = None;
+                    self.pending = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Queue between log calls and log thread
+//!
+//! Either a crossbeam channel, or a ring buffer of fixed capacity selected by
+//! [`QueueKind::Ring`](crate::QueueKind::Ring). Both expose the subset of the channel
+//! API used by the logger, so that log calls and log thread do not care which one
+//! is in use.
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{
+    RecvError, RecvTimeoutError, SendError, SendTimeoutError, TryRecvError, TrySendError,
+};
+use crossbeam_queue::ArrayQueue;
+
+/// Lock-free ring buffer, with producers and consumers parked only when it is full or
+/// empty
+struct Ring<T> {
+    buffer: ArrayQueue<T>,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    /// threads parked on `parked`, either waiting for a value or for room
+    waiting: AtomicUsize,
+    lock: Mutex<()>,
+    parked: Condvar,
+}
+
+impl<T> Ring<T> {
+    /// Wake parked threads, after a push, a pop or a disconnection
+    #[inline]
+    fn wake(&self) {
+        fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::SeqCst) > 0 {
+            let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+            self.parked.notify_all();
+        }
+    }
+
+    /// Park until `ready` or `deadline`, returning `false` on timeout
+    fn park<F: Fn() -> bool>(&self, deadline: Option<Instant>, ready: F) -> bool {
+        let guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+        let result = if ready() {
+            true
+        } else {
+            match deadline.map(|x| x.checked_duration_since(Instant::now())) {
+                None => {
+                    let _guard = self.parked.wait(guard);
+                    true
+                }
+                Some(Some(timeout)) => {
+                    let _guard = self.parked.wait_timeout(guard, timeout);
+                    true
+                }
+                Some(None) => false,
+            }
+        };
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    fn try_push(&self, value: T) -> Result<(), TrySendError<T>> {
+        if self.receivers.load(Ordering::Acquire) == 0 {
+            return Err(TrySendError::Disconnected(value));
+        }
+        match self.buffer.push(value) {
+            Ok(()) => {
+                self.wake();
+                Ok(())
+            }
+            Err(value) => Err(TrySendError::Full(value)),
+        }
+    }
+
+    fn try_pop(&self) -> Result<T, TryRecvError> {
+        match self.buffer.pop() {
+            Some(value) => {
+                self.wake();
+                Ok(value)
+            }
+            None if self.senders.load(Ordering::Acquire) == 0 => {
+                // values pushed right before the last sender left
+                self.buffer.pop().ok_or(TryRecvError::Disconnected)
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+/// Sending half of the queue
+pub(crate) enum Sender<T> {
+    Channel(crossbeam_channel::Sender<T>),
+    Ring(RingSender<T>),
+}
+
+/// Receiving half of the queue
+pub(crate) enum Receiver<T> {
+    Channel(crossbeam_channel::Receiver<T>),
+    Ring(RingReceiver<T>),
+}
+
+pub(crate) struct RingSender<T>(Arc<Ring<T>>);
+
+pub(crate) struct RingReceiver<T>(Arc<Ring<T>>);
+
+impl<T> Drop for RingSender<T> {
+    fn drop(&mut self) {
+        self.0.senders.fetch_sub(1, Ordering::AcqRel);
+        self.0.wake();
+    }
+}
+
+impl<T> Drop for RingReceiver<T> {
+    fn drop(&mut self) {
+        self.0.receivers.fetch_sub(1, Ordering::AcqRel);
+        self.0.wake();
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Receiver::Channel(x) => Receiver::Channel(x.clone()),
+            Receiver::Ring(x) => {
+                x.0.receivers.fetch_add(1, Ordering::AcqRel);
+                Receiver::Ring(RingReceiver(x.0.clone()))
+            }
+        }
+    }
+}
+
+/// Channel holding at most `capacity` values, or any number with `None`
+pub(crate) fn channel<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let (sender, receiver) = match capacity {
+        Some(capacity) => crossbeam_channel::bounded(capacity),
+        None => crossbeam_channel::unbounded(),
+    };
+    (Sender::Channel(sender), Receiver::Channel(receiver))
+}
+
+/// Ring buffer holding at most `capacity` values, allocated upfront
+pub(crate) fn ring<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let ring = Arc::new(Ring {
+        buffer: ArrayQueue::new(capacity.max(1)),
+        senders: AtomicUsize::new(1),
+        receivers: AtomicUsize::new(1),
+        waiting: AtomicUsize::new(0),
+        lock: Mutex::new(()),
+        parked: Condvar::new(),
+    });
+    (
+        Sender::Ring(RingSender(ring.clone())),
+        Receiver::Ring(RingReceiver(ring)),
+    )
+}
+
+impl<T> Sender<T> {
+    /// Send `value`, waiting for room while the queue is full
+    pub(crate) fn send(&self, value: T) -> Result<(), SendError<T>> {
+        match self {
+            Sender::Channel(x) => x.send(value),
+            Sender::Ring(RingSender(ring)) => {
+                let mut value = value;
+                loop {
+                    match ring.try_push(value) {
+                        Ok(()) => return Ok(()),
+                        Err(TrySendError::Disconnected(x)) => return Err(SendError(x)),
+                        Err(TrySendError::Full(x)) => value = x,
+                    }
+                    ring.park(None, || {
+                        !ring.buffer.is_full() || ring.receivers.load(Ordering::Acquire) == 0
+                    });
+                }
+            }
+        }
+    }
+
+    /// Send `value`, waiting for room while the queue is full for at most `timeout`
+    pub(crate) fn send_timeout(
+        &self,
+        value: T,
+        timeout: Duration,
+    ) -> Result<(), SendTimeoutError<T>> {
+        match self {
+            Sender::Channel(x) => x.send_timeout(value, timeout),
+            Sender::Ring(RingSender(ring)) => {
+                let deadline = Instant::now() + timeout;
+                let mut value = value;
+                loop {
+                    match ring.try_push(value) {
+                        Ok(()) => return Ok(()),
+                        Err(TrySendError::Disconnected(x)) => {
+                            return Err(SendTimeoutError::Disconnected(x))
+                        }
+                        Err(TrySendError::Full(x)) => value = x,
+                    }
+                    let ready =
+                        || !ring.buffer.is_full() || ring.receivers.load(Ordering::Acquire) == 0;
+                    if !ring.park(Some(deadline), ready) {
+                        return Err(SendTimeoutError::Timeout(value));
+                    }
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub(crate) fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        match self {
+            Sender::Channel(x) => x.try_send(value),
+            Sender::Ring(RingSender(ring)) => ring.try_push(value),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Sender::Channel(x) => x.len(),
+            Sender::Ring(RingSender(ring)) => ring.buffer.len(),
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self {
+            Receiver::Channel(x) => x.try_recv(),
+            Receiver::Ring(RingReceiver(ring)) => ring.try_pop(),
+        }
+    }
+
+    pub(crate) fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        match self {
+            Receiver::Channel(x) => x.recv_timeout(timeout),
+            Receiver::Ring(RingReceiver(ring)) => {
+                let deadline = Instant::now() + timeout;
+                loop {
+                    match ring.try_pop() {
+                        Ok(value) => return Ok(value),
+                        Err(TryRecvError::Disconnected) => {
+                            return Err(RecvTimeoutError::Disconnected)
+                        }
+                        Err(TryRecvError::Empty) => (),
+                    }
+                    let ready =
+                        || !ring.buffer.is_empty() || ring.senders.load(Ordering::Acquire) == 0;
+                    if !ring.park(Some(deadline), ready) {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn recv(&self) -> Result<T, RecvError> {
+        match self {
+            Receiver::Channel(x) => x.recv(),
+            Receiver::Ring(RingReceiver(ring)) => loop {
+                match ring.try_pop() {
+                    Ok(value) => return Ok(value),
+                    Err(TryRecvError::Disconnected) => return Err(RecvError),
+                    Err(TryRecvError::Empty) => (),
+                }
+                ring.park(None, || {
+                    !ring.buffer.is_empty() || ring.senders.load(Ordering::Acquire) == 0
+                });
+            },
+        }
+    }
+}
@@ -0,0 +1,241 @@
+//! Rate limiting of log calls at call site
+//!
+//! Unlike `limit` of log calls (e.g. `info!(limit=3000; "msg")`), which is applied
+//! in log thread, records suppressed here are never sent to log thread, so a hot loop
+//! does not flood the channel.
+//!
+//! - [`log_every_n!`](crate::log_every_n) logs the 1st, (n+1)th, (2n+1)th... call
+//! - [`log_at_most_every!`](crate::log_at_most_every) logs at most once per period
+//! - [`Builder::default_rate_limit`](crate::Builder::default_rate_limit) applies a
+//!   period to all log calls
+//! - [`log_once!`](crate::log_once) and [`info_once!`](crate::info_once) etc. log only
+//!   the first call
+//!
+//! ```
+//! use ftlog::{appender::Duration, log_at_most_every, log_every_n, Level};
+//!
+//! for i in 0..1000 {
+//!     log_every_n!(100, Level::Warn, "processed {} items", i);
+//!     log_at_most_every!(Duration::seconds(5), Level::Info, "still running");
+//! }
+//! ```
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// Nanoseconds since first use, never 0
+fn now_nanos() -> u64 {
+    static ANCHOR: OnceLock<Instant> = OnceLock::new();
+    ANCHOR.get_or_init(Instant::now).elapsed().as_nanos() as u64 + 1
+}
+
+/// Check if an event is allowed, given the time of last allowed one
+///
+/// `0` for `last` means never.
+#[inline]
+fn try_acquire(last: &AtomicU64, period_nanos: u64) -> bool {
+    let prev = last.load(Ordering::Relaxed);
+    let now = now_nanos();
+    if prev != 0 && now.saturating_sub(prev) < period_nanos {
+        return false;
+    }
+    last.compare_exchange(prev, now, Ordering::Relaxed, Ordering::Relaxed)
+        .is_ok()
+}
+
+/// Counter of a call site for [`log_every_n!`](crate::log_every_n)
+pub struct EveryN {
+    count: AtomicU64,
+}
+
+impl Default for EveryN {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EveryN {
+    pub const fn new() -> Self {
+        EveryN {
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a call, return `true` for the 1st, (n+1)th, (2n+1)th... call
+    #[inline]
+    pub fn tick(&self, n: u64) -> bool {
+        self.count
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(n.max(1))
+    }
+}
+
+/// Last log time of a call site for [`log_at_most_every!`](crate::log_at_most_every)
+pub struct AtMostEvery {
+    last: AtomicU64,
+}
+
+impl Default for AtMostEvery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AtMostEvery {
+    pub const fn new() -> Self {
+        AtMostEvery {
+            last: AtomicU64::new(0),
+        }
+    }
+
+    /// Return `true` if no call was allowed within `period`
+    #[inline]
+    pub fn allow(&self, period: impl Period) -> bool {
+        try_acquire(&self.last, period.as_nanos())
+    }
+}
+
+/// State of a call site for [`log_once!`](crate::log_once)
+pub struct Once {
+    module_path: &'static str,
+    file: &'static str,
+    line: u32,
+    logged: AtomicBool,
+    suppressed: AtomicU64,
+}
+
+/// Call sites of `log_once!` that have been logged
+static ONCE_SITES: Mutex<Vec<&'static Once>> = Mutex::new(Vec::new());
+
+impl Once {
+    pub const fn new(module_path: &'static str, file: &'static str, line: u32) -> Self {
+        Once {
+            module_path,
+            file,
+            line,
+            logged: AtomicBool::new(false),
+            suppressed: AtomicU64::new(0),
+        }
+    }
+
+    /// Return `true` for the first call only, count the following calls
+    #[inline]
+    pub fn first(&'static self) -> bool {
+        if self.logged.load(Ordering::Relaxed) || self.logged.swap(true, Ordering::Relaxed) {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        ONCE_SITES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(self);
+        true
+    }
+}
+
+/// Log number of suppressed calls for each `log_once!` call site to `logger`
+pub(crate) fn log_once_summary(logger: &dyn log::Log) {
+    let sites = ONCE_SITES.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    for site in sites {
+        let suppressed = site.suppressed.swap(0, Ordering::Relaxed);
+        let metadata = log::Metadata::builder()
+            .level(log::Level::Info)
+            .target(site.module_path)
+            .build();
+        if suppressed == 0 || !logger.enabled(&metadata) {
+            continue;
+        }
+        logger.log(
+            &log::Record::builder()
+                .metadata(metadata)
+                .module_path_static(Some(site.module_path))
+                .file_static(Some(site.file))
+                .line(Some(site.line))
+                .args(format_args!(
+                    "suppressed {} subsequent occurrences",
+                    suppressed
+                ))
+                .build(),
+        );
+    }
+}
+
+/// Durations accepted by [`log_at_most_every!`](crate::log_at_most_every), both
+/// `std::time::Duration` and `time::Duration`
+pub trait Period {
+    /// Length of the period in nanoseconds, negative periods are treated as zero
+    fn as_nanos(&self) -> u64;
+}
+
+impl Period for std::time::Duration {
+    fn as_nanos(&self) -> u64 {
+        std::time::Duration::as_nanos(self).min(u64::MAX as u128) as u64
+    }
+}
+
+impl Period for time::Duration {
+    fn as_nanos(&self) -> u64 {
+        self.whole_nanoseconds().clamp(0, u64::MAX as i128) as u64
+    }
+}
+
+/// Per call site limit applied to all log calls, indexed by hash of call site
+///
+/// Call sites colliding in the table share a limit.
+pub(crate) struct CallsiteLimiter {
+    period_nanos: u64,
+    slots: Box<[AtomicU64]>,
+}
+
+const CALLSITE_SLOTS: usize = 4096;
+
+impl CallsiteLimiter {
+    pub(crate) fn new(period: std::time::Duration) -> Self {
+        CallsiteLimiter {
+            period_nanos: Period::as_nanos(&period),
+            slots: (0..CALLSITE_SLOTS).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn allow(&self, callsite: u64) -> bool {
+        try_acquire(
+            &self.slots[callsite as usize % CALLSITE_SLOTS],
+            self.period_nanos,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_n() {
+        let every = EveryN::new();
+        let allowed = (0..10).filter(|_| every.tick(3)).count();
+        assert_eq!(allowed, 4);
+    }
+
+    #[test]
+    fn once() {
+        static ONCE: Once = Once::new(module_path!(), file!(), line!());
+        let allowed = (0..10).filter(|_| ONCE.first()).count();
+        assert_eq!(allowed, 1);
+        assert_eq!(ONCE.suppressed.load(Ordering::Relaxed), 9);
+    }
+
+    #[test]
+    fn at_most_every() {
+        let limit = AtMostEvery::new();
+        assert!(limit.allow(std::time::Duration::from_secs(60)));
+        assert!(!limit.allow(time::Duration::seconds(60)));
+        assert!(limit.allow(std::time::Duration::ZERO));
+
+        let limiter = CallsiteLimiter::new(std::time::Duration::from_secs(60));
+        assert!(limiter.allow(1));
+        assert!(limiter.allow(2));
+        assert!(!limiter.allow(1));
+    }
+}
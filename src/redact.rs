@@ -0,0 +1,117 @@
+//! Masking sensitive data
+//!
+//! [`Builder::redact`](crate::Builder::redact) replaces text matching a regex in
+//! messages and key-value values with a replacement, which may refer to capture
+//! groups like `$1`. Redaction runs in log thread before records are formatted, so
+//! no appender sees the original text, whatever the format. Patterns are applied in
+//! the order they are added.
+//!
+//! ```
+//! use ftlog::redact::{BEARER_TOKEN, CREDIT_CARD, EMAIL};
+//!
+//! let _guard = ftlog::builder()
+//!     .redact(CREDIT_CARD, "****")
+//!     .redact(EMAIL, "<email>")
+//!     .redact(BEARER_TOKEN, "Bearer ****")
+//!     .redact(r"password=\S+", "password=****")
+//!     .try_init()
+//!     .unwrap();
+//! log::info!(contact = "alice@example.com"; "paid with 4111 1111 1111 1111");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:11] contact=<email> paid with ****
+//! ```
+//!
+//! Keys, targets and file names are not redacted. Routing filters of
+//! [`Builder::filter`](crate::Builder::filter) see messages before redaction.
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::format::{FieldValue, RecordFields};
+use crate::worker::Payload;
+
+/// Credit card numbers of 13 to 19 digits, optionally grouped by spaces or dashes
+pub const CREDIT_CARD: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+/// Email addresses
+pub const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}";
+/// Bearer tokens of HTTP `Authorization` header, including the `Bearer` scheme
+pub const BEARER_TOKEN: &str = r"(?i)\bbearer\s+[A-Za-z0-9\-._~+/]+=*";
+
+/// Compiled patterns and their replacements
+#[derive(Default)]
+pub(crate) struct Redactions(Vec<(Regex, String)>);
+
+impl Redactions {
+    pub(crate) fn new(patterns: Vec<(String, String)>) -> Result<Redactions, regex::Error> {
+        patterns
+            .into_iter()
+            .map(|(pattern, replacement)| Ok((Regex::new(&pattern)?, replacement)))
+            .collect::<Result<_, _>>()
+            .map(Redactions)
+    }
+
+    fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for (regex, replacement) in &self.0 {
+            if let Cow::Owned(redacted) = regex.replace_all(&text, replacement.as_str()) {
+                text = Cow::Owned(redacted);
+            }
+        }
+        text
+    }
+
+    /// Redact message and key-values of a log message in place
+    pub(crate) fn apply(&self, payload: &mut Payload) {
+        if self.0.is_empty() {
+            return;
+        }
+        let text = match payload {
+            Payload::Record(fields) => return self.apply_fields(fields),
+            Payload::Display(msg) => msg.to_string(),
+            Payload::Static(msg) => msg.to_string(),
+            Payload::Message(msg) => msg.to_string(),
+        };
+        if let Cow::Owned(redacted) = self.redact(&text) {
+            *payload = Payload::Display(Box::new(redacted));
+        }
+    }
+
+    fn apply_fields(&self, fields: &mut RecordFields) {
+        if let Cow::Owned(redacted) = self.redact(&fields.args) {
+            fields.args = Cow::Owned(redacted);
+        }
+        for (_, value) in &mut fields.key_values {
+            match value {
+                FieldValue::Text(text) => {
+                    if let Cow::Owned(redacted) = self.redact(text) {
+                        *text = redacted;
+                    }
+                }
+                #[cfg(feature = "serde")]
+                FieldValue::Json(json, text) => {
+                    self.apply_json(json);
+                    *text = Default::default();
+                }
+                FieldValue::Deferred(..) => {
+                    let text = self.redact(value.as_str()).into_owned();
+                    *value = FieldValue::Text(text);
+                }
+            }
+        }
+    }
+
+    /// Redact strings in a structured value, keeping its structure
+    #[cfg(feature = "serde")]
+    fn apply_json(&self, json: &mut serde_json::Value) {
+        match json {
+            serde_json::Value::String(text) => {
+                if let Cow::Owned(redacted) = self.redact(text) {
+                    *text = redacted;
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|x| self.apply_json(x)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|x| self.apply_json(x)),
+            _ => {}
+        }
+    }
+}
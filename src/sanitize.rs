@@ -0,0 +1,228 @@
+//! Escaping control characters of messages
+//!
+//! With [`Builder::sanitize`](crate::Builder::sanitize), log thread strips ANSI escape
+//! sequences and escapes other control characters in messages, key-values and thread
+//! names before records are formatted. A message can then neither forge log lines
+//! with line breaks nor move the cursor or recolor the terminal of someone viewing a
+//! log file with `cat` or `tail`.
+//!
+//! Line feeds and carriage returns are written as `\n` and `\r`, other control
+//! characters but tab as `\x1f` for C0 and DEL or `\u{9b}` for C1.
+//!
+//! Color codes are also stripped from whole lines written to appenders that do not
+//! keep colors, see [`Appender::colors`](crate::appender::Appender::colors).
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::format::{FieldValue, RecordFields};
+use crate::worker::Payload;
+
+#[inline]
+fn is_control(c: char) -> bool {
+    c != '\t' && c.is_control()
+}
+
+/// Whether `text` has anything to strip or escape
+#[inline]
+fn dirty(text: &str) -> bool {
+    text.chars().any(is_control)
+}
+
+/// Strip ANSI escape sequences of `text` and escape other control characters
+pub(crate) fn sanitize(text: &str) -> Cow<'_, str> {
+    if !dirty(text) {
+        return Cow::Borrowed(text);
+    }
+    let mut clean = String::with_capacity(text.len() + 8);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.peek() {
+                // CSI: parameters and intermediates up to a final byte
+                Some('[') => {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC, DCS and the like: up to BEL or ST
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    chars.next();
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                // two-character sequences, like `ESC c` resetting the terminal
+                Some(c) if ('\x20'..='\x7e').contains(c) => {
+                    chars.next();
+                }
+                _ => clean.push_str("\\x1b"),
+            },
+            '\n' => clean.push_str("\\n"),
+            '\r' => clean.push_str("\\r"),
+            c if is_control(c) && (c as u32) < 0x80 => {
+                let _ = write!(clean, "\\x{:02x}", c as u32);
+            }
+            c if is_control(c) => {
+                let _ = write!(clean, "\\u{{{:x}}}", c as u32);
+            }
+            c => clean.push(c),
+        }
+    }
+    Cow::Owned(clean)
+}
+
+/// Strip ANSI escape sequences of a formatted line, keeping anything else
+pub(crate) fn strip_ansi(line: &[u8]) -> Cow<'_, [u8]> {
+    if !line.contains(&0x1b) {
+        return Cow::Borrowed(line);
+    }
+    let mut clean = Vec::with_capacity(line.len());
+    let mut bytes = line.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        if b != 0x1b {
+            clean.push(b);
+            continue;
+        }
+        match bytes.peek() {
+            Some(b'[') => {
+                bytes.next();
+                for b in bytes.by_ref() {
+                    if (0x40..=0x7e).contains(&b) {
+                        break;
+                    }
+                }
+            }
+            Some(b']' | b'P' | b'X' | b'^' | b'_') => {
+                bytes.next();
+                while let Some(b) = bytes.next() {
+                    if b == 0x07 || (b == 0x1b && bytes.next_if_eq(&b'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            Some(0x20..=0x7e) => {
+                bytes.next();
+            }
+            _ => (),
+        }
+    }
+    Cow::Owned(clean)
+}
+
+fn sanitize_string(text: &mut String) {
+    if let Cow::Owned(clean) = sanitize(text) {
+        *text = clean;
+    }
+}
+
+fn sanitize_shared(text: &mut Arc<str>) {
+    if let Cow::Owned(clean) = sanitize(text) {
+        *text = clean.into();
+    }
+}
+
+fn sanitize_value(value: &mut FieldValue) {
+    match value {
+        FieldValue::Text(text) => sanitize_string(text),
+        #[cfg(feature = "serde")]
+        FieldValue::Json(json, text) => {
+            sanitize_json(json);
+            *text = Default::default();
+        }
+        // computed here in log thread
+        FieldValue::Deferred(..) => {
+            let mut text = value.as_str().to_string();
+            sanitize_string(&mut text);
+            *value = FieldValue::Text(text);
+        }
+    }
+}
+
+/// Sanitize strings in a structured value, keeping its structure
+#[cfg(feature = "serde")]
+fn sanitize_json(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::String(text) => sanitize_string(text),
+        serde_json::Value::Array(values) => values.iter_mut().for_each(sanitize_json),
+        serde_json::Value::Object(map) => map.values_mut().for_each(sanitize_json),
+        _ => {}
+    }
+}
+
+/// Sanitize message, key-values and thread name of a log message in place
+pub(crate) fn apply(payload: &mut Payload) {
+    match payload {
+        Payload::Record(fields) => apply_fields(fields),
+        Payload::Message(msg) => {
+            sanitize_string(&mut msg.args);
+            msg.key_values
+                .iter_mut()
+                .for_each(|(_, value)| sanitize_value(value));
+            msg.context
+                .iter_mut()
+                .for_each(|(_, value)| sanitize_shared(value));
+            if let Some(thread) = &mut msg.thread {
+                sanitize_shared(thread);
+            }
+        }
+        Payload::Static(msg) => {
+            if dirty(msg.args) || msg.thread.as_deref().is_some_and(dirty) {
+                let text = sanitize(&msg.to_string()).into_owned();
+                *payload = Payload::Display(Box::new(text));
+            }
+        }
+        Payload::Display(msg) => {
+            let text = msg.to_string();
+            if let Cow::Owned(clean) = sanitize(&text) {
+                *payload = Payload::Display(Box::new(clean));
+            }
+        }
+    }
+}
+
+fn apply_fields(fields: &mut RecordFields) {
+    if let Cow::Owned(clean) = sanitize(&fields.args) {
+        fields.args = Cow::Owned(clean);
+    }
+    fields
+        .key_values
+        .iter_mut()
+        .for_each(|(_, value)| sanitize_value(value));
+    if let Some(thread) = &mut fields.thread {
+        sanitize_string(thread);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strip_and_escape() {
+        assert!(matches!(sanitize("plain\ttext"), Cow::Borrowed(_)));
+        assert_eq!(
+            sanitize("\x1b[31mred\x1b[0m line\nforged\r\x07"),
+            "red line\\nforged\\r\\x07"
+        );
+        assert_eq!(
+            sanitize("\x1b]0;title\x07ok \x1b]8;;x\x1b\\link"),
+            "ok link"
+        );
+        assert_eq!(sanitize("\x1bcreset \u{9b}2J\x1b"), "reset \\u{9b}2J\\x1b");
+    }
+
+    #[test]
+    fn strip_colors() {
+        assert_eq!(
+            &*strip_ansi(b"\x1b[1;31mERROR\x1b[0m main failed\n"),
+            b"ERROR main failed\n"
+        );
+        assert!(matches!(strip_ansi(b"INFO plain\n"), Cow::Borrowed(_)));
+    }
+}
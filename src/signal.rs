@@ -0,0 +1,61 @@
+//! Toggling verbosity by signals
+//!
+//! [`Builder::toggle_on_signal`](crate::Builder::toggle_on_signal) lets an operator
+//! get verbose logs from a live process without restarting it. The first signal
+//! raises the max level of the logger, the next one restores the level it had
+//! before, and so on.
+//!
+//! ```no_run
+//! use ftlog::signal::SIGUSR1;
+//! use ftlog::LevelFilter;
+//!
+//! let _guard = ftlog::builder()
+//!     .toggle_on_signal(SIGUSR1, LevelFilter::Debug)
+//!     .try_init()
+//!     .unwrap();
+//! ```
+//!
+//! ```shell
+//! $ kill -USR1 <pid>  # debug logs on
+//! $ kill -USR1 <pid>  # back to the previous level
+//! ```
+//!
+//! Signals are received by a background thread, so the level changes shortly after
+//! the signal is delivered. The root appender follows the max level unless its
+//! level is set by [`Builder::root_log_level`](crate::Builder::root_log_level).
+use std::sync::{Arc, Weak};
+
+use log::LevelFilter;
+use signal_hook::iterator::Signals;
+
+pub use signal_hook::consts::signal::{SIGHUP, SIGUSR1, SIGUSR2};
+
+use crate::Shared;
+
+/// Listen to `toggles` in a background thread, switching the level of `shared`
+pub(crate) fn spawn(shared: &Arc<Shared>, toggles: Vec<(i32, LevelFilter)>) -> std::io::Result<()> {
+    let mut signals = Signals::new(toggles.iter().map(|(signal, _)| *signal))?;
+    let shared: Weak<Shared> = Arc::downgrade(shared);
+    std::thread::Builder::new()
+        .name("logger-signal".to_string())
+        .spawn(move || {
+            // level before the toggle of each signal, `None` when not raised
+            let mut restore: Vec<Option<LevelFilter>> = vec![None; toggles.len()];
+            for signal in signals.forever() {
+                let Some(shared) = shared.upgrade() else {
+                    return;
+                };
+                let Some(ix) = toggles.iter().position(|(x, _)| *x == signal) else {
+                    continue;
+                };
+                match restore[ix].take() {
+                    Some(level) => shared.set_level(level),
+                    None => {
+                        restore[ix] = Some(shared.level.get());
+                        shared.set_level(toggles[ix].1);
+                    }
+                }
+            }
+        })?;
+    Ok(())
+}
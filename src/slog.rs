@@ -0,0 +1,84 @@
+//! Bridge from `slog` to ftlog
+//!
+//! Requires `slog` feature. [`FtLogDrain`] is a `slog::Drain` sending records to the
+//! global logger, so services instrumented with slog can use ftlog as backend.
+//!
+//! ```
+//! use slog::{o, Drain};
+//!
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! let root = slog::Logger::root(ftlog::slog::FtLogDrain.fuse(), o!("service" => "billing"));
+//! slog::info!(root, "logged in"; "user" => "alice");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:5] service=billing user=alice logged in
+//! ```
+//!
+//! Key-values of slog loggers and records are sent as key-values of log records,
+//! which are printed with `kv` feature enabled.
+use std::fmt::Arguments;
+
+use ::slog::{Drain, FlushError, Key, Never, OwnedKVList, Record, Serializer, KV};
+
+/// A `slog::Drain` sending records to ftlog
+pub struct FtLogDrain;
+
+fn to_log_level(level: ::slog::Level) -> log::Level {
+    match level {
+        ::slog::Level::Critical | ::slog::Level::Error => log::Level::Error,
+        ::slog::Level::Warning => log::Level::Warn,
+        ::slog::Level::Info => log::Level::Info,
+        ::slog::Level::Debug => log::Level::Debug,
+        ::slog::Level::Trace => log::Level::Trace,
+    }
+}
+
+/// Collect slog key-values as strings
+struct KeyValues(Vec<(String, String)>);
+
+impl Serializer for KeyValues {
+    fn emit_arguments(&mut self, key: Key, val: &Arguments<'_>) -> ::slog::Result {
+        self.0.push((key.to_string(), val.to_string()));
+        Ok(())
+    }
+}
+
+impl Drain for FtLogDrain {
+    type Ok = ();
+    type Err = Never;
+
+    fn log(&self, record: &Record<'_>, values: &OwnedKVList) -> Result<(), Never> {
+        let level = to_log_level(record.level());
+        if level > log::max_level() {
+            return Ok(());
+        }
+        let target = match record.tag() {
+            "" => record.module(),
+            tag => tag,
+        };
+        let logger = log::logger();
+        let metadata = log::Metadata::builder().level(level).target(target).build();
+        if !logger.enabled(&metadata) {
+            return Ok(());
+        }
+
+        let mut key_values = KeyValues(Vec::new());
+        let _ = values.serialize(record, &mut key_values);
+        let _ = record.kv().serialize(record, &mut key_values);
+        logger.log(
+            &log::Record::builder()
+                .metadata(metadata)
+                .module_path_static(Some(record.module()))
+                .file_static(Some(record.file()))
+                .line(Some(record.line()))
+                .key_values(&key_values.0)
+                .args(*record.msg())
+                .build(),
+        );
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        log::logger().flush();
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+//! Logging pipeline statistics
+//!
+//! `ftlog` keeps a few cheap counters about the log pipeline, which can be read at
+//! any time with [`stats()`](crate::stats()) for the global logger, or
+//! [`Logger::stats`](crate::Logger::stats) for a logger that is not installed yet.
+//!
+//! ```
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! log::info!("Hello world!");
+//! if let Some(stats) = ftlog::stats() {
+//!     println!(
+//!         "dropped: {}, queued: {}, p99 write latency: {:?}",
+//!         stats.dropped,
+//!         stats.queue_depth,
+//!         stats.write_latency.p99
+//!     );
+//!     for appender in stats.appenders {
+//!         println!("{}: {} bytes", appender.name, appender.bytes_written);
+//!     }
+//! }
+//! ```
+//!
+//! [`selftest()`](crate::selftest()) goes further, sending a marker through the
+//! pipeline to check that every appender actually writes it.
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::Level;
+
+/// Number of rotations of all file appenders in the process
+static ROTATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Count a rotation of a file appender
+pub(crate) fn count_rotation() {
+    ROTATIONS.fetch_add(1, Ordering::Relaxed);
+    #[cfg(feature = "metrics")]
+    crate::metrics::rotation();
+}
+
+const LEVELS: [Level; 5] = [
+    Level::Error,
+    Level::Warn,
+    Level::Info,
+    Level::Debug,
+    Level::Trace,
+];
+
+/// Counters of a single appender, updated by log thread
+pub(crate) struct AppenderCounter {
+    name: Cow<'static, str>,
+    bytes: AtomicU64,
+    /// error of the last failed healthcheck, `None` once it passes
+    error: Mutex<Option<String>>,
+}
+
+impl AppenderCounter {
+    pub(crate) fn new(name: impl Into<Cow<'static, str>>) -> Arc<Self> {
+        Arc::new(AppenderCounter {
+            name: name.into(),
+            bytes: AtomicU64::new(0),
+            error: Mutex::new(None),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn add_bytes(&self, bytes: usize) {
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Record the result of a healthcheck
+    pub(crate) fn set_health(&self, result: std::io::Result<()>) {
+        *self.error.lock().unwrap_or_else(|e| e.into_inner()) = result.err().map(|e| e.to_string());
+    }
+}
+
+const BUCKETS: usize = 64;
+
+/// Histogram of durations in nanoseconds, with power-of-two buckets
+pub(crate) struct Histogram {
+    buckets: [AtomicU64; BUCKETS],
+    max: AtomicU64,
+}
+
+impl Histogram {
+    pub(crate) fn new() -> Self {
+        Histogram {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn record(&self, elapsed: Duration) {
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        let ix = (u64::BITS - nanos.leading_zeros()) as usize;
+        self.buckets[ix.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Upper bound of the bucket containing the given quantile
+    fn quantile(counts: &[u64; BUCKETS], q: f64) -> Duration {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Duration::ZERO;
+        }
+        let rank = ((total as f64 * q).ceil() as u64).max(1);
+        let mut acc = 0;
+        for (ix, count) in counts.iter().enumerate() {
+            acc += count;
+            if acc >= rank {
+                return Duration::from_nanos(1u64.checked_shl(ix as u32).unwrap_or(u64::MAX));
+            }
+        }
+        Duration::from_nanos(u64::MAX)
+    }
+
+    pub(crate) fn snapshot(&self) -> LatencyPercentiles {
+        let counts: [u64; BUCKETS] =
+            std::array::from_fn(|ix| self.buckets[ix].load(Ordering::Relaxed));
+        let max = Duration::from_nanos(self.max.load(Ordering::Relaxed));
+        LatencyPercentiles {
+            p50: Self::quantile(&counts, 0.5).min(max),
+            p90: Self::quantile(&counts, 0.9).min(max),
+            p99: Self::quantile(&counts, 0.99).min(max),
+            max,
+            count: counts.iter().sum(),
+        }
+    }
+}
+
+/// Counters shared by log calls and log thread
+pub(crate) struct Metrics {
+    pub(crate) dropped: AtomicU64,
+    // indexed by `Level as usize - 1`
+    records: [AtomicU64; 5],
+    pub(crate) write_latency: Histogram,
+    appenders: Mutex<Vec<Arc<AppenderCounter>>>,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        #[cfg(feature = "metrics")]
+        crate::metrics::describe();
+        Metrics {
+            dropped: AtomicU64::new(0),
+            records: Default::default(),
+            write_latency: Histogram::new(),
+            appenders: Default::default(),
+        }
+    }
+
+    /// Count a record handled by log thread
+    #[inline]
+    pub(crate) fn count(&self, level: Level) {
+        self.records[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record(level);
+    }
+
+    /// Count a record discarded because the channel to log thread was full
+    #[inline]
+    pub(crate) fn count_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        crate::metrics::dropped();
+    }
+
+    /// Register counter of an appender, so it is included in snapshots
+    pub(crate) fn register(&self, counter: Arc<AppenderCounter>) {
+        self.appenders
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(counter);
+    }
+
+    pub(crate) fn snapshot(&self, queue_depth: usize) -> StatsSnapshot {
+        StatsSnapshot {
+            dropped: self.dropped.load(Ordering::Relaxed),
+            records: LEVELS.map(|level| {
+                (
+                    level,
+                    self.records[level as usize - 1].load(Ordering::Relaxed),
+                )
+            }),
+            queue_depth,
+            appenders: self
+                .appenders
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .iter()
+                .map(|x| {
+                    let error = x.error.lock().unwrap_or_else(|e| e.into_inner()).clone();
+                    AppenderStats {
+                        name: x.name.to_string(),
+                        bytes_written: x.bytes.load(Ordering::Relaxed),
+                        healthy: error.is_none(),
+                        error,
+                    }
+                })
+                .collect(),
+            write_latency: self.write_latency.snapshot(),
+            rotations: ROTATIONS.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Point-in-time statistics of a logger
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct StatsSnapshot {
+    /// number of log messages discarded because the channel to log thread was full
+    pub dropped: u64,
+    /// number of records handled by log thread for each level, from `Error` to `Trace`
+    pub records: [(Level, u64); 5],
+    /// number of messages waiting in the channel to log thread
+    pub queue_depth: usize,
+    /// bytes written to each appender
+    pub appenders: Vec<AppenderStats>,
+    /// time spent by log thread to format and write a single log message
+    pub write_latency: LatencyPercentiles,
+    /// number of rotations of all `FileAppender`s in the process
+    pub rotations: u64,
+}
+
+/// Statistics of a single appender
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AppenderStats {
+    /// `"root"` for the root appender, appender name for named appenders and
+    /// the prefix for appenders added by `Builder::route`, `key=value` for those
+    /// added by `Builder::route_field`, `"stderr"` for `Builder::also_stderr`
+    pub name: String,
+    /// total bytes written to the appender
+    pub bytes_written: u64,
+    /// whether the last [`Appender::healthcheck`](crate::appender::Appender::healthcheck)
+    /// passed, `true` before the first one
+    pub healthy: bool,
+    /// error of the last failed healthcheck
+    pub error: Option<String>,
+}
+
+/// Latency distribution
+///
+/// Percentiles are estimated with power-of-two buckets, so they are upper bounds
+/// that may exceed the actual value by up to 2x.
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct LatencyPercentiles {
+    /// median
+    pub p50: Duration,
+    /// 90th percentile
+    pub p90: Duration,
+    /// 99th percentile
+    pub p99: Duration,
+    /// maximum observed
+    pub max: Duration,
+    /// number of samples
+    pub count: u64,
+}
+
+/// Outcome of a self-test, see [`selftest`](crate::selftest)
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SelfTest {
+    /// time the marker waited in the channel to log thread
+    pub queued: Duration,
+    /// time from leaving the channel to reaching the writer stage, including the
+    /// hop through formatting threads of [`Builder::workers`](crate::Builder::workers)
+    pub rendered: Duration,
+    /// time to write and flush the marker, for each appender
+    pub appenders: Vec<AppenderProbe>,
+    /// time from the call until all appenders were flushed
+    pub total: Duration,
+    /// statistics of the log pipeline right after the self-test
+    pub stats: StatsSnapshot,
+}
+
+impl SelfTest {
+    /// Whether the marker was written and flushed by all appenders
+    pub fn healthy(&self) -> bool {
+        self.appenders.iter().all(|x| x.error.is_none())
+    }
+}
+
+/// Marker of a self-test written to a single appender
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AppenderProbe {
+    /// name of the appender, as in [`AppenderStats::name`]
+    pub name: String,
+    /// time to write and flush the marker
+    pub latency: Duration,
+    /// error writing or flushing the marker, or quarantine of the appender
+    pub error: Option<String>,
+}
+
+/// Failure of a self-test, see [`selftest`](crate::selftest)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SelfTestError {
+    /// ftlog is not installed as the global logger
+    NotInstalled,
+    /// the marker did not reach all appenders before the timeout
+    Timeout,
+    /// the logger is shut down
+    Stopped,
+}
+
+impl std::fmt::Display for SelfTestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelfTestError::NotInstalled => write!(f, "ftlog is not installed"),
+            SelfTestError::Timeout => write!(f, "log pipeline timed out"),
+            SelfTestError::Stopped => write!(f, "logger is shut down"),
+        }
+    }
+}
+
+impl std::error::Error for SelfTestError {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let histogram = Histogram::new();
+        assert_eq!(histogram.snapshot().p99, Duration::ZERO);
+
+        for _ in 0..90 {
+            histogram.record(Duration::from_nanos(100));
+        }
+        for _ in 0..9 {
+            histogram.record(Duration::from_nanos(1000));
+        }
+        histogram.record(Duration::from_micros(50));
+
+        let latency = histogram.snapshot();
+        assert_eq!(latency.count, 100);
+        assert_eq!(latency.p50, Duration::from_nanos(128));
+        assert_eq!(latency.p90, Duration::from_nanos(128));
+        assert_eq!(latency.p99, Duration::from_nanos(1024));
+        assert_eq!(latency.max, Duration::from_micros(50));
+    }
+}
@@ -0,0 +1,83 @@
+//! Live subscription to written lines
+//!
+//! [`subscribe`](crate::subscribe) returns a channel receiving every line written by
+//! log thread, so an in-process diagnostics console or TUI can follow the log stream
+//! alongside the regular appenders.
+//!
+//! ```
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! let records = ftlog::subscribe();
+//! log::warn!("disk almost full");
+//! let record = records.recv().unwrap();
+//! assert_eq!(record.level, log::Level::Warn);
+//! assert!(record.line.ends_with("disk almost full\n"));
+//! ```
+//!
+//! Lines are copied after they are written, and only while there are subscribers.
+//! Each subscriber buffers up to 1024 lines; a subscriber falling further behind
+//! misses lines rather than slowing down logging. Dropping the receiver unsubscribes,
+//! and the receiver is disconnected once the logger is dropped.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use log::Level;
+
+/// Lines an observer can fall behind before further lines are dropped for it
+const CAPACITY: usize = 1024;
+
+/// A line written by log thread
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct FormattedRecord {
+    /// level of the record
+    pub level: Level,
+    /// the formatted line, as written to appenders
+    pub line: String,
+}
+
+/// Observers of lines written by log thread
+#[derive(Default)]
+pub(crate) struct Tap {
+    senders: Mutex<Vec<Sender<Arc<FormattedRecord>>>>,
+    // skips locking `senders` when nobody observes
+    active: AtomicBool,
+}
+
+impl Tap {
+    pub(crate) fn subscribe(&self) -> Receiver<Arc<FormattedRecord>> {
+        let (sender, receiver) = bounded(CAPACITY);
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.push(sender);
+        self.active.store(true, Ordering::Relaxed);
+        receiver
+    }
+
+    /// Whether anybody observes lines, so that log thread skips preparing them
+    #[inline]
+    pub(crate) fn active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Pass a line to observers, never blocking log thread
+    #[inline]
+    pub(crate) fn send(&self, level: Level, line: &[u8]) {
+        if !self.active() {
+            return;
+        }
+        let record = Arc::new(FormattedRecord {
+            level,
+            line: String::from_utf8_lossy(line).into_owned(),
+        });
+        let mut senders = self.senders.lock().unwrap_or_else(|e| e.into_inner());
+        senders.retain(|sender| {
+            !matches!(
+                sender.try_send(record.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+        if senders.is_empty() {
+            self.active.store(false, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Bridge from `tracing` to ftlog
+//!
+//! Requires `tracing` feature. [`layer()`] creates a `tracing_subscriber` layer that
+//! forwards tracing events to the global logger, so logs from `log` and `tracing`
+//! share the same async pipeline and appenders of ftlog.
+//!
+//! ```
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! let subscriber = tracing_subscriber::registry().with(ftlog::tracing::layer());
+//! tracing::subscriber::set_global_default(subscriber).unwrap();
+//!
+//! let span = tracing::info_span!("request", id = 7);
+//! let _enter = span.enter();
+//! tracing::info!(user = "alice", "logged in");
+//! // Output:
+//! // 2023-06-14 11:13:26.160+08 0ms INFO main [src/main.rs:9] id=7 user=alice logged in
+//! ```
+//!
+//! Fields of events and their enclosing spans are sent as key-values of log
+//! records, which are printed with `kv` feature enabled.
+use std::fmt::{Debug, Write};
+
+use ::tracing::field::{Field, Visit};
+use ::tracing::span::{Attributes, Id, Record};
+use ::tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Create a layer forwarding tracing events to ftlog
+#[inline]
+pub fn layer() -> FtLogLayer {
+    FtLogLayer { spans: false }
+}
+
+/// Set a global tracing subscriber with [`layer()`], for `Builder::capture_tracing`
+pub(crate) fn install() -> Result<(), ::tracing::subscriber::SetGlobalDefaultError> {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    ::tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer()))
+}
+
+/// A `tracing_subscriber` layer that forwards tracing events to ftlog
+///
+/// Created by [`layer()`].
+pub struct FtLogLayer {
+    spans: bool,
+}
+
+impl FtLogLayer {
+    /// Also log entering and exiting of spans, defaults to `false`
+    #[inline]
+    pub fn with_spans(mut self, spans: bool) -> FtLogLayer {
+        self.spans = spans;
+        self
+    }
+}
+
+/// Fields of a span, kept in span extensions
+struct SpanFields(Vec<(String, String)>);
+
+/// Collect message and other fields of events and spans
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields
+                .push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            self.fields
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+}
+
+fn to_log_level(level: &::tracing::Level) -> log::Level {
+    match *level {
+        ::tracing::Level::ERROR => log::Level::Error,
+        ::tracing::Level::WARN => log::Level::Warn,
+        ::tracing::Level::INFO => log::Level::Info,
+        ::tracing::Level::DEBUG => log::Level::Debug,
+        ::tracing::Level::TRACE => log::Level::Trace,
+    }
+}
+
+fn forward(metadata: &'static Metadata<'static>, message: &str, key_values: &[(String, String)]) {
+    let level = to_log_level(metadata.level());
+    if level > log::max_level() {
+        return;
+    }
+    let logger = log::logger();
+    let log_metadata = log::Metadata::builder()
+        .level(level)
+        .target(metadata.target())
+        .build();
+    if !logger.enabled(&log_metadata) {
+        return;
+    }
+    logger.log(
+        &log::Record::builder()
+            .metadata(log_metadata)
+            .module_path_static(metadata.module_path())
+            .file_static(metadata.file())
+            .line(metadata.line())
+            .key_values(&key_values)
+            .args(format_args!("{}", message))
+            .build(),
+    );
+}
+
+impl<S> Layer<S> for FtLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(visitor.fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                fields.0.extend(visitor.fields);
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut key_values = Vec::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    key_values.extend(fields.0.iter().cloned());
+                }
+            }
+        }
+        key_values.extend(visitor.fields);
+        forward(event.metadata(), &visitor.message, &key_values);
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if !self.spans {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            let extensions = span.extensions();
+            let fields = extensions
+                .get::<SpanFields>()
+                .map(|x| x.0.as_slice())
+                .unwrap_or_default();
+            forward(span.metadata(), &format!("enter {}", span.name()), fields);
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if !self.spans {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            forward(span.metadata(), &format!("exit {}", span.name()), &[]);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Limit of message length, see [`Builder::max_message_len`](crate::Builder::max_message_len)
+use std::borrow::Cow;
+
+use crate::worker::Payload;
+
+/// Cut `text` to at most `max` bytes including the note of truncated bytes, or
+/// `None` if it fits
+///
+/// The cut is at a char boundary, so the note may leave a few bytes unused. With
+/// `max` shorter than the note, only the note is kept.
+pub(crate) fn truncate(text: &str, max: usize) -> Option<String> {
+    if text.len() <= max {
+        return None;
+    }
+    // the note is the longest when nothing is kept
+    let longest = note(text.len()).len();
+    let mut cut = max.saturating_sub(longest);
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let mut truncated = String::with_capacity(max.max(longest));
+    truncated.push_str(&text[..cut]);
+    truncated.push_str(&note(text.len() - cut));
+    Some(truncated)
+}
+
+#[inline]
+fn note(bytes: usize) -> String {
+    format!("…(truncated {} bytes)", bytes)
+}
+
+/// Truncate the message of a log message in place, or the whole output of a custom
+/// `FtLogFormat`
+pub(crate) fn apply(payload: &mut Payload, max: usize) {
+    match payload {
+        Payload::Record(fields) => {
+            if let Some(text) = truncate(&fields.args, max) {
+                fields.args = Cow::Owned(text);
+            }
+        }
+        Payload::Message(msg) => {
+            if let Some(text) = truncate(&msg.args, max) {
+                msg.args = text;
+            }
+        }
+        Payload::Static(msg) => {
+            if msg.args.len() > max {
+                // the message ends the default format
+                let mut text = msg.to_string();
+                let args = text.split_off(text.len() - msg.args.len());
+                text.push_str(&truncate(&args, max).unwrap_or(args));
+                *payload = Payload::Display(Box::new(text));
+            }
+        }
+        Payload::Display(msg) => {
+            if let Some(text) = truncate(&msg.to_string(), max) {
+                *payload = Payload::Display(Box::new(text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cut_at_char_boundary() {
+        assert_eq!(truncate("short", 5), None);
+        let text = "é".repeat(40);
+        let truncated = truncate(&text, 40).unwrap();
+        assert!(truncated.len() <= 40, "{}", truncated);
+        assert_eq!(truncated, format!("{}…(truncated 64 bytes)", "é".repeat(8)));
+        assert_eq!(truncate(&text, 3).unwrap(), "…(truncated 80 bytes)");
+    }
+}
@@ -0,0 +1,126 @@
+//! Log viewer widget for terminal user interfaces
+//!
+//! [`LogView`] keeps the last lines received from [`subscribe`](crate::subscribe) and
+//! renders them with [ratatui](https://docs.rs/ratatui), filtered by level and by a
+//! search string, so an admin console built on ratatui can show logs without tailing
+//! files.
+//!
+//! ```
+//! use ratatui::backend::TestBackend;
+//! use ratatui::Terminal;
+//!
+//! let _guard = ftlog::builder().try_init().unwrap();
+//! let mut view = ftlog::tui::LogView::subscribe(1000);
+//! log::warn!("disk almost full");
+//! log::logger().flush();
+//!
+//! // in the event loop of the application
+//! view.poll();
+//! view.set_level(log::LevelFilter::Warn);
+//! view.set_search("disk");
+//! let mut terminal = Terminal::new(TestBackend::new(80, 10)).unwrap();
+//! terminal.draw(|frame| frame.render_widget(&view, frame.area())).unwrap();
+//! ```
+//!
+//! The newest line is drawn at the bottom. Lines are shown as written to appenders,
+//! colored by level.
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crossbeam_channel::Receiver;
+use log::{Level, LevelFilter};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::Widget;
+
+use crate::tap::FormattedRecord;
+
+/// The last lines of a live subscription, see [module](self) docs
+pub struct LogView {
+    receiver: Receiver<Arc<FormattedRecord>>,
+    records: VecDeque<Arc<FormattedRecord>>,
+    capacity: usize,
+    level: LevelFilter,
+    search: Option<String>,
+}
+
+impl LogView {
+    /// Keep the last `capacity` lines received by `receiver`, e.g. of
+    /// [`Logger::subscribe`](crate::Logger::subscribe)
+    pub fn new(receiver: Receiver<Arc<FormattedRecord>>, capacity: usize) -> LogView {
+        LogView {
+            receiver,
+            records: VecDeque::with_capacity(capacity.min(4096)),
+            capacity,
+            level: LevelFilter::Trace,
+            search: None,
+        }
+    }
+
+    /// Keep the last `capacity` lines of the global logger
+    pub fn subscribe(capacity: usize) -> LogView {
+        LogView::new(crate::subscribe(), capacity)
+    }
+
+    /// Take lines received since the last call, returning how many there were
+    ///
+    /// Call it before drawing, it never blocks.
+    pub fn poll(&mut self) -> usize {
+        let mut received = 0;
+        for record in self.receiver.try_iter() {
+            if self.records.len() == self.capacity {
+                self.records.pop_front();
+            }
+            if self.capacity > 0 {
+                self.records.push_back(record);
+            }
+            received += 1;
+        }
+        received
+    }
+
+    /// Only show lines of `level` or more severe, defaults to all
+    pub fn set_level(&mut self, level: LevelFilter) {
+        self.level = level;
+    }
+
+    /// Only show lines containing `search`, or all lines if it is empty
+    pub fn set_search(&mut self, search: impl Into<String>) {
+        let search = search.into();
+        self.search = (!search.is_empty()).then_some(search);
+    }
+
+    /// Lines shown with the current level and search, oldest first
+    pub fn records(&self) -> impl DoubleEndedIterator<Item = &FormattedRecord> {
+        self.records
+            .iter()
+            .map(|x| &**x)
+            .filter(|x| x.level <= self.level)
+            .filter(|x| self.search.as_ref().is_none_or(|s| x.line.contains(s)))
+    }
+}
+
+fn style(level: Level) -> Style {
+    let color = match level {
+        Level::Error => Color::Red,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::Green,
+        Level::Debug => Color::Blue,
+        Level::Trace => Color::Magenta,
+    };
+    Style::default().fg(color)
+}
+
+impl Widget for &LogView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let lines = self.records().rev().take(area.height as usize);
+        for (ix, record) in lines.enumerate() {
+            let y = area.bottom() - 1 - ix as u16;
+            let text = record.line.trim_end_matches(['\r', '\n']);
+            let line = Line::styled(text, style(record.level));
+            buf.set_line(area.x, y, &line, area.width);
+        }
+    }
+}
@@ -0,0 +1,1643 @@
+//! Log thread(s)
+//!
+//! A log message goes through three stages after it is received from the channel:
+//!
+//! 1. route: find destinations of the message, and apply log interval limit
+//! 1. render: format the message into a line
+//! 1. write: write the line to destinations
+//!
+//! Routing and writing are stateful and must see messages in order, while rendering
+//! is stateless. By default all stages run in a single log thread. With multiple
+//! workers, a router thread dispatches messages to formatter threads in round-robin,
+//! and a writer thread collects rendered lines from formatter threads in the same
+//! order, so the output keeps the order of the channel.
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{Display, Write as _};
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwapOption;
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
+use hashbrown::HashMap;
+use log::{Level, LevelFilter};
+use time::OffsetDateTime;
+
+use crate::appender::Appender;
+use crate::clock::{Clock, Stamp};
+use crate::enrich::Enricher;
+use crate::format::{Format, LogRecord, RecordFields, RecordFormatter};
+use crate::intern::Symbol;
+use crate::pool::Pool;
+use crate::queue;
+use crate::stats::{AppenderCounter, AppenderProbe, Metrics};
+use crate::tap::Tap;
+use crate::{
+    with_error_handler, Directive, GlobalFields, InternalError, InternalErrorHandler, Message,
+    Multiline, Offset, ShutdownReport, StaticMessage, TimeFormat, TimePrecision,
+};
+
+/// Content of a log message
+pub(crate) enum Payload {
+    /// message made by `FtLogFormat`, prefixed with timestamp when rendered
+    Display(Box<dyn Sync + Send + Display>),
+    /// record data for `RecordFormatter`, which makes the whole line
+    Record(Box<RecordFields>),
+    /// message of the default format without formatting arguments, not allocated
+    Static(StaticMessage),
+    /// message of the default format, returned to the pool once rendered
+    Message(Box<Message>),
+}
+
+impl Payload {
+    /// Text of the message, or the whole output of a custom `FtLogFormat`
+    #[cfg(feature = "message_filter")]
+    fn args(&self) -> Cow<'_, str> {
+        match self {
+            Payload::Display(msg) => Cow::Owned(msg.to_string()),
+            Payload::Record(fields) => Cow::Borrowed(&fields.args),
+            Payload::Static(msg) => Cow::Borrowed(msg.args),
+            Payload::Message(msg) => Cow::Borrowed(&msg.args),
+        }
+    }
+
+    /// Change the level printed by the default format
+    #[cfg(feature = "message_filter")]
+    fn set_level(&mut self, level: Level) {
+        match self {
+            Payload::Static(msg) => msg.level = level,
+            Payload::Message(msg) => msg.level = level,
+            Payload::Display(_) | Payload::Record(_) => (),
+        }
+    }
+
+    #[inline]
+    fn as_display(&self) -> &dyn Display {
+        match self {
+            Payload::Display(msg) => msg,
+            Payload::Record(fields) => fields,
+            Payload::Static(msg) => msg,
+            Payload::Message(msg) => msg,
+        }
+    }
+
+    /// Value of key-value or context field `key`, if the payload carries fields
+    fn field(&self, key: &str) -> Option<&str> {
+        match self {
+            Payload::Record(fields) => fields
+                .key_values
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str()),
+            Payload::Message(msg) => msg
+                .key_values
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.as_str())
+                .or_else(|| {
+                    msg.context
+                        .iter()
+                        .find(|(k, _)| *k == key)
+                        .map(|(_, v)| &**v)
+                }),
+            Payload::Display(_) | Payload::Static(_) => None,
+        }
+    }
+}
+
+pub(crate) struct LogMsg {
+    /// time of the log call, `None` if timestamp is taken by log thread
+    pub(crate) time: Option<Stamp>,
+    /// sequence number, see [`Builder::sequence`](crate::Builder::sequence)
+    pub(crate) seq: Option<u64>,
+    pub(crate) msg: Payload,
+    pub(crate) level: Level,
+    pub(crate) target: Symbol,
+    pub(crate) limit: u32,
+    pub(crate) limit_key: u64,
+}
+
+pub(crate) enum LoggerInput {
+    LogMsg(LogMsg),
+    /// records of a thread batched at call site, in order
+    Batch(Vec<LogMsg>),
+    /// number of records dropped before the following ones
+    Dropped(usize),
+    /// write queued messages, flush appenders and reply once done
+    Flush(Sender<LoggerOutput>),
+    /// write remaining messages before deadline of [`Progress`], flush appenders and
+    /// stop log thread
+    Quit(Sender<ShutdownReport>),
+    /// marker of a self-test
+    Probe(Probe),
+}
+
+/// Marker of [`selftest`](crate::selftest), timestamped by each stage
+pub(crate) struct Probe {
+    sent: Instant,
+    routed: Option<Instant>,
+    rendered: Option<Instant>,
+    reply: Sender<ProbeReport>,
+}
+
+impl Probe {
+    pub(crate) fn new(reply: Sender<ProbeReport>) -> Probe {
+        Probe {
+            sent: Instant::now(),
+            routed: None,
+            rendered: None,
+            reply,
+        }
+    }
+}
+
+/// Latencies of a self-test measured by log thread
+pub(crate) struct ProbeReport {
+    pub(crate) queued: Duration,
+    pub(crate) rendered: Duration,
+    pub(crate) appenders: Vec<AppenderProbe>,
+}
+
+impl LoggerInput {
+    /// Number of records carried
+    pub(crate) fn records(&self) -> usize {
+        match self {
+            LoggerInput::LogMsg(_) => 1,
+            LoggerInput::Batch(batch) => batch.len(),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum LoggerOutput {
+    Flushed,
+    /// name of the first appender failing to flush, and its error
+    FlushError(String, std::io::Error),
+}
+
+/// An output target of the log thread along with its level threshold
+pub(crate) struct Destination {
+    writer: Box<dyn Appender>,
+    level: LevelFilter,
+    pub(crate) counter: Arc<AppenderCounter>,
+    /// overrides the flush interval of log thread
+    pub(crate) flush_interval: Option<Duration>,
+    last_flush: Instant,
+    /// consecutive failed writes
+    failures: u32,
+    /// when the appender was quarantined or last probed, while it is unhealthy
+    quarantined: Option<Instant>,
+    /// change of health to announce through other appenders
+    notice: Option<String>,
+    /// lines keep color codes, see [`Appender::colors`]
+    colors: bool,
+    /// index of its own formatter among `Renderer::appender_formatters`
+    pub(crate) formatter: Option<usize>,
+}
+
+impl Destination {
+    pub(crate) fn new(
+        name: impl Into<Cow<'static, str>>,
+        writer: Box<dyn Appender>,
+        level: LevelFilter,
+    ) -> Self {
+        Destination {
+            colors: writer.colors(),
+            writer,
+            level,
+            counter: AppenderCounter::new(name),
+            flush_interval: None,
+            last_flush: Instant::now(),
+            failures: 0,
+            quarantined: None,
+            notice: None,
+            formatter: None,
+        }
+    }
+
+    #[inline]
+    fn accept(&self, level: Level) -> bool {
+        self.level >= level
+    }
+
+    /// Write a line, unless the appender is quarantined and not due for a probe,
+    /// returning whether a change of health is to be announced
+    #[inline]
+    fn write(&mut self, s: &[u8]) -> bool {
+        if s.is_empty() || self.quarantined.is_some() && !self.probe() {
+            return self.notice.is_some();
+        }
+        match self.writer.write_all(s) {
+            Ok(_) => {
+                self.failures = 0;
+                self.counter.add_bytes(s.len())
+            }
+            Err(e) => {
+                self.failures += 1;
+                if self.failures >= QUARANTINE_AFTER {
+                    let reason = format!("{} failed writes, {}", self.failures, e);
+                    self.quarantine(reason, std::io::Error::new(e.kind(), e.to_string()));
+                }
+                crate::internal_error(InternalError::Write {
+                    appender: self.counter.name().to_string(),
+                    source: e,
+                });
+            }
+        };
+        self.notice.is_some()
+    }
+
+    /// Stop writing to the appender until a probe succeeds
+    fn quarantine(&mut self, reason: String, error: std::io::Error) {
+        self.counter.set_health(Err(error));
+        self.quarantined = Some(Instant::now());
+        self.notice = Some(format!(
+            "appender `{}` quarantined after {}",
+            self.counter.name(),
+            reason
+        ));
+    }
+
+    /// Reopen a quarantined appender if a probe is due, returning whether it is
+    /// healthy again
+    fn probe(&mut self) -> bool {
+        if self
+            .quarantined
+            .is_some_and(|x| x.elapsed() < PROBE_INTERVAL)
+        {
+            return false;
+        }
+        match self.writer.reopen().and_then(|_| self.writer.healthcheck()) {
+            Ok(()) => {
+                self.counter.set_health(Ok(()));
+                self.failures = 0;
+                self.quarantined = None;
+                self.notice = Some(format!("appender `{}` restored", self.counter.name()));
+                true
+            }
+            Err(e) => {
+                self.counter.set_health(Err(e));
+                self.quarantined = Some(Instant::now());
+                false
+            }
+        }
+    }
+}
+
+/// Which records a route receives
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rule {
+    /// records whose target starts with the prefix
+    Prefix(&'static str),
+    /// records with the key-value, of the value if any
+    Field(&'static str, Option<&'static str>),
+}
+
+impl Rule {
+    fn matches(&self, log_msg: &LogMsg) -> bool {
+        match self {
+            Rule::Prefix(prefix) => log_msg.target.starts_with(prefix),
+            Rule::Field(key, value) => log_msg
+                .msg
+                .field(key)
+                .is_some_and(|x| value.is_none_or(|value| x == value)),
+        }
+    }
+
+    /// Name of the first appender of the route, in stats and flush intervals
+    pub(crate) fn name(&self) -> Cow<'static, str> {
+        match self {
+            Rule::Prefix(prefix) => Cow::Borrowed(prefix),
+            Rule::Field(key, None) => Cow::Borrowed(key),
+            Rule::Field(key, Some(value)) => Cow::Owned(format!("{}={}", key, value)),
+        }
+    }
+}
+
+/// Appenders that exclusively receive records matching `rule`
+pub(crate) struct Route {
+    pub(crate) rule: Rule,
+    pub(crate) appenders: Vec<Destination>,
+}
+
+/// Where a record is written to in log thread
+#[derive(Clone, Copy)]
+enum Dispatch {
+    /// appenders of a route
+    Route(usize),
+    /// named appender redirected by a filter
+    Appender(&'static str),
+    /// root appender and attached appenders
+    Default,
+}
+
+/// A log message with destinations decided
+struct Prepared {
+    msg: LogMsg,
+    dispatch: Dispatch,
+    missed: Option<i64>,
+    now: Stamp,
+    start: Instant,
+}
+
+/// A log message formatted into a line
+struct Rendered {
+    line: Vec<u8>,
+    dispatch: Dispatch,
+    level: Level,
+    start: Instant,
+    /// made by a binary format
+    binary: bool,
+    /// lines of appender formatters and whether they are binary, empty unless the
+    /// record carries its fields
+    extra: Vec<(Vec<u8>, bool)>,
+}
+
+/// Formatter of the whole line, swapped by [`set_format`](crate::set_format)
+pub(crate) type SharedFormatter = ArcSwapOption<Box<dyn RecordFormatter>>;
+
+/// Job passed between stages, in the order of log messages
+enum Job<T> {
+    Record(T),
+    /// number of records dropped in place of the job
+    Dropped(usize),
+    /// flush appenders and reply to the caller
+    Flush(Sender<LoggerOutput>),
+    Probe(Probe),
+    /// stop after flushing appenders before the deadline
+    Quit(Option<Instant>, ShutdownReport, Sender<ShutdownReport>),
+}
+
+/// Route stage of log messages, passing prepared ones on
+type RouteMsg<'a> = dyn FnMut(LogMsg, &mut dyn FnMut(Job<Prepared>)) + 'a;
+
+/// A log message held for reordering, ordered by time then arrival
+struct Held {
+    time: Stamp,
+    seq: u64,
+    msg: LogMsg,
+}
+
+impl PartialEq for Held {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.seq) == (other.time, other.seq)
+    }
+}
+
+impl Eq for Held {}
+
+impl PartialOrd for Held {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Held {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.time, self.seq).cmp(&(other.time, other.seq))
+    }
+}
+
+/// Reorder stage: hold log messages for a window, releasing them by timestamp
+struct Reorder {
+    window: Duration,
+    held: BinaryHeap<Reverse<Held>>,
+    seq: u64,
+    /// time of the last released message, later messages are never released before it
+    last: Option<Stamp>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl Reorder {
+    fn new(window: Duration, clock: Option<Arc<dyn Clock>>) -> Reorder {
+        Reorder {
+            window,
+            held: BinaryHeap::new(),
+            seq: 0,
+            last: None,
+            clock,
+        }
+    }
+
+    fn push(&mut self, mut msg: LogMsg) {
+        let time = match (msg.time, self.last) {
+            // arrived after its window, keep the output sorted
+            (Some(time), Some(last)) if time < last => {
+                msg.time = Some(last);
+                last
+            }
+            (Some(time), _) => time,
+            // timestamp is taken when rendered, in the order of release
+            (None, _) => Stamp::now(self.clock.as_deref()),
+        };
+        self.seq += 1;
+        self.held.push(Reverse(Held {
+            time,
+            seq: self.seq,
+            msg,
+        }));
+    }
+
+    /// Release the earliest message held for the window, or any if `all`
+    fn pop(&mut self, all: bool) -> Option<LogMsg> {
+        let Reverse(head) = self.held.peek()?;
+        if !all && Stamp::now(self.clock.as_deref()).since(head.time) < self.window {
+            return None;
+        }
+        let Reverse(head) = self.held.pop()?;
+        self.last = Some(head.time);
+        Some(head.msg)
+    }
+
+    /// Pass `msg` to `next` without reordering, otherwise hold it and pass released
+    /// messages, all of them if `all`
+    fn handle<F: FnMut(LogMsg)>(
+        reorder: &mut Option<Reorder>,
+        msg: Option<LogMsg>,
+        all: bool,
+        mut next: F,
+    ) {
+        let Some(reorder) = reorder else {
+            msg.into_iter().for_each(next);
+            return;
+        };
+        if let Some(msg) = msg {
+            reorder.push(msg);
+        }
+        while let Some(msg) = reorder.pop(all) {
+            next(msg);
+        }
+    }
+
+    /// How long to wait for messages before releasing held ones
+    fn timeout(&self, tick: Duration) -> Duration {
+        match self.held.is_empty() {
+            true => tick,
+            false => tick.min(self.window),
+        }
+    }
+}
+
+/// Route stage: decide destinations and apply log interval limit
+struct Router {
+    /// rule and the most verbose level of route appenders, field rules first, then
+    /// longest prefix first
+    routes: Vec<(Rule, LevelFilter)>,
+    /// filters and the level of their appender, `Off` for unknown appenders
+    filters: Vec<(Directive, LevelFilter)>,
+    /// the most verbose level of root appender and attached appenders
+    default_level: LevelFilter,
+    /// level of the stderr mirror, which takes records of any dispatch
+    mirror_level: LevelFilter,
+    missed_log: HashMap<u64, i64, nohash_hasher::BuildNoHashHasher<u64>>,
+    last_log: HashMap<u64, Stamp, nohash_hasher::BuildNoHashHasher<u64>>,
+    clock: Option<Arc<dyn Clock>>,
+    /// target prefixes and their new names, longest prefix first, see
+    /// [`Builder::rename_target`](crate::Builder::rename_target)
+    renames: Vec<(&'static str, &'static str)>,
+    #[cfg(feature = "message_filter")]
+    message_rules: crate::message_filter::MessageRules,
+}
+
+impl Router {
+    fn prepare(&mut self, mut log_msg: LogMsg) -> Option<Prepared> {
+        let start = Instant::now();
+        if let Some(target) = self.rename(&log_msg.target) {
+            log_msg.target = target;
+        }
+        let now = Stamp::now(self.clock.as_deref());
+
+        #[cfg(feature = "message_filter")]
+        if !self.message_rules.is_empty() {
+            use crate::message_filter::Action;
+            match self.message_rules.action(&log_msg.msg.args()) {
+                Some(Action::Deny) => return None,
+                Some(Action::Downgrade(level)) if level > log_msg.level => {
+                    log_msg.level = level;
+                    log_msg.msg.set_level(level);
+                }
+                _ => (),
+            }
+        }
+
+        let (dispatch, level) = self.dispatch(&log_msg);
+        if level.max(self.mirror_level) < log_msg.level {
+            return None;
+        }
+
+        let missed = if log_msg.limit > 0 {
+            let missed_entry = self
+                .missed_log
+                .entry(log_msg.limit_key)
+                .or_insert_with(|| 0);
+            if let Some(last) = self.last_log.get(&log_msg.limit_key) {
+                if now.since(*last) < Duration::from_millis(log_msg.limit as u64) {
+                    *missed_entry += 1;
+                    return None;
+                }
+            }
+            self.last_log.insert(log_msg.limit_key, now);
+            Some(std::mem::take(missed_entry))
+        } else {
+            None
+        };
+        Some(Prepared {
+            msg: log_msg,
+            dispatch,
+            missed,
+            now,
+            start,
+        })
+    }
+
+    /// New target of a renamed target prefix, keeping the rest of the target
+    fn rename(&self, target: &str) -> Option<Symbol> {
+        let (prefix, to) = self
+            .renames
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix))?;
+        Some(match &target[prefix.len()..] {
+            "" => Symbol::from_static(to),
+            rest => Symbol::new(&format!("{}{}", to, rest)),
+        })
+    }
+
+    /// Routes take precedence over filters, routes are sorted so that the first
+    /// matching field rule wins, then the longest matching prefix. Returns the most
+    /// verbose level of the destinations too.
+    fn dispatch(&self, log_msg: &LogMsg) -> (Dispatch, LevelFilter) {
+        if let Some(ix) = self
+            .routes
+            .iter()
+            .position(|(rule, _)| rule.matches(log_msg))
+        {
+            return (Dispatch::Route(ix), self.routes[ix].1);
+        }
+        // Find an appender filter if one exists
+        self.filters
+            .iter()
+            .find(|(x, _)| (*x.filter)(log_msg.msg.as_display(), log_msg.level, &log_msg.target))
+            .and_then(|(filter, level)| Some((Dispatch::Appender(filter.appender?), *level)))
+            .unwrap_or((Dispatch::Default, self.default_level))
+    }
+}
+
+/// Number of renderers made, giving each an id
+static RENDERERS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The last timestamp this thread formatted, truncated to the precision of its
+    /// renderer, with the id of the renderer and the text, reused by records of the
+    /// same tick; kept per thread so that parallel render threads do not contend
+    static LAST_TIME: RefCell<Option<(usize, OffsetDateTime, String)>> =
+        const { RefCell::new(None) };
+}
+
+/// Render stage: format log messages into lines
+pub(crate) struct Renderer {
+    offset: Offset,
+    time_format: TimeFormat,
+    precision: TimePrecision,
+    /// `time_format` is built in, so that it shows nothing finer than `precision`
+    cache_time: bool,
+    /// tells apart the timestamps cached by renderers of different loggers
+    id: usize,
+    /// changed at runtime by [`set_format`](crate::set_format)
+    formatter: Arc<SharedFormatter>,
+    /// formatters of target prefixes, longest prefix first
+    target_formatters: Vec<(&'static str, Box<dyn RecordFormatter>)>,
+    /// formatters of some appenders, see [`Destination::formatter`]
+    appender_formatters: Vec<Box<dyn RecordFormatter>>,
+    pub(crate) global_fields: GlobalFields,
+    pool: Arc<Pool>,
+    #[cfg(feature = "redact")]
+    redactions: crate::redact::Redactions,
+    /// escape control characters, see [`Builder::sanitize`](crate::Builder::sanitize)
+    sanitize: bool,
+    /// see [`Builder::max_message_len`](crate::Builder::max_message_len)
+    max_message_len: Option<usize>,
+    /// see [`Builder::multiline`](crate::Builder::multiline)
+    multiline: Multiline,
+    /// see [`Builder::enrich`](crate::Builder::enrich)
+    enrichers: Vec<Box<dyn Enricher>>,
+}
+
+impl Renderer {
+    /// Render a log message, dropping it if formatting panics, so that log thread
+    /// keeps going
+    fn render(&self, prepared: Prepared) -> Option<Rendered> {
+        let target = prepared.msg.target.to_cow();
+        std::panic::catch_unwind(AssertUnwindSafe(|| self.render_inner(prepared))).unwrap_or_else(
+            |panic| {
+                let message = match panic.downcast::<String>() {
+                    Ok(message) => *message,
+                    Err(panic) => match panic.downcast::<&str>() {
+                        Ok(message) => message.to_string(),
+                        Err(_) => "panicked".to_string(),
+                    },
+                };
+                crate::internal_error(InternalError::Format {
+                    target: target.into_owned(),
+                    message,
+                });
+                None
+            },
+        )
+    }
+
+    fn render_inner(&self, prepared: Prepared) -> Option<Rendered> {
+        #[allow(unused_mut)]
+        let Prepared {
+            msg: mut log_msg,
+            dispatch,
+            missed,
+            now,
+            start,
+        } = prepared;
+        if !self.enrichers.is_empty() {
+            let target = &log_msg.target;
+            crate::enrich::apply(&mut log_msg.msg, log_msg.level, target, &self.enrichers);
+        }
+        #[cfg(feature = "redact")]
+        self.redactions.apply(&mut log_msg.msg);
+        if self.sanitize {
+            crate::sanitize::apply(&mut log_msg.msg);
+        }
+        if self.multiline != Multiline::AsIs {
+            crate::multiline::apply(&mut log_msg.msg, self.multiline);
+        }
+        if let Some(max) = self.max_message_len {
+            crate::truncate::apply(&mut log_msg.msg, max);
+        }
+        let delay = log_msg.time.map(|time| now.since(time)).unwrap_or_default();
+        let utc_datetime = log_msg.time.unwrap_or(now).to_utc();
+
+        let offset_datetime = self.offset.apply(utc_datetime);
+
+        if let Payload::Record(fields) = &log_msg.msg {
+            let formatter = self
+                .target_formatters
+                .iter()
+                .find(|(prefix, _)| log_msg.target.starts_with(prefix))
+                .map(|(_, formatter)| formatter);
+            let current = self.formatter.load();
+            let formatter = formatter.or(current.as_deref());
+            let record = LogRecord {
+                fields,
+                level: log_msg.level,
+                target: &log_msg.target,
+                time: offset_datetime,
+                renderer: self,
+                delay,
+                missed,
+                seq: log_msg.seq,
+            };
+            // records carry their fields for appender formatters alone, written
+            // like the default format elsewhere
+            let formatter = formatter.map_or(&Format::Text as &dyn RecordFormatter, |x| &**x);
+            let line = format_line(formatter, &record)?;
+            let extra = self
+                .appender_formatters
+                .iter()
+                .map(|x| (format_line(&**x, &record).unwrap_or_default(), x.binary()))
+                .collect::<Vec<_>>();
+            if line.is_empty() && extra.iter().all(|(x, _)| x.is_empty()) {
+                return None;
+            }
+            return Some(Rendered {
+                line,
+                dispatch,
+                level: log_msg.level,
+                start,
+                binary: formatter.binary(),
+                extra,
+            });
+        }
+        let mut line = self.format_time(&offset_datetime);
+        let _ = write!(line, " {}ms ", delay.as_millis());
+        if let Some(seq) = log_msg.seq {
+            let _ = write!(line, "#{} ", seq);
+        }
+        if let Some(missed) = missed {
+            let _ = write!(line, "{} ", missed);
+        }
+        let prefix = line.len();
+        let _ = write!(line, "{}", log_msg.msg.as_display());
+        if let Payload::Message(msg) = log_msg.msg {
+            self.pool.give(msg);
+        }
+        if line.len() == prefix {
+            return None;
+        }
+        line.push('\n');
+        Some(Rendered {
+            line: line.into_bytes(),
+            dispatch,
+            level: log_msg.level,
+            start,
+            binary: false,
+            extra: Vec::new(),
+        })
+    }
+
+    /// Whether lines are written by a binary format
+    pub(crate) fn binary(&self) -> bool {
+        self.formatter
+            .load()
+            .iter()
+            .map(|x| &**x)
+            .chain(self.target_formatters.iter().map(|(_, x)| x))
+            .any(|x| x.binary())
+    }
+
+    /// Render a log message at log call, as if it is written to root appender
+    pub(crate) fn render_now(&self, msg: LogMsg, now: Stamp) -> Option<Vec<u8>> {
+        let prepared = Prepared {
+            msg,
+            dispatch: Dispatch::Default,
+            missed: None,
+            now,
+            start: Instant::now(),
+        };
+        self.render(prepared).map(|x| x.line)
+    }
+
+    #[inline]
+    pub(crate) fn format_time(&self, datetime: &OffsetDateTime) -> String {
+        if !self.cache_time {
+            return self.format_time_uncached(datetime);
+        }
+        // records of the same millisecond, at the default precision, share the text
+        let unit = self.precision.unit_nanos() as u32;
+        let tick = datetime
+            .replace_nanosecond(datetime.nanosecond() / unit * unit)
+            .unwrap_or(*datetime);
+        let cached = LAST_TIME
+            .try_with(|last| match &*last.borrow() {
+                Some((id, time, text))
+                    if *id == self.id && *time == tick && time.offset() == tick.offset() =>
+                {
+                    Some(text.clone())
+                }
+                _ => None,
+            })
+            .ok()
+            .flatten();
+        if let Some(text) = cached {
+            return text;
+        }
+        let text = self.format_time_uncached(datetime);
+        let _ = LAST_TIME.try_with(|last| *last.borrow_mut() = Some((self.id, tick, text.clone())));
+        text
+    }
+
+    fn format_time_uncached(&self, datetime: &OffsetDateTime) -> String {
+        let rfc3339 = || {
+            datetime
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap()
+        };
+        match &self.time_format {
+            TimeFormat::Custom(format) => datetime.format(format).unwrap_or_else(|_| rfc3339()),
+            TimeFormat::Rfc3339 => rfc3339(),
+            TimeFormat::UnixEpoch => {
+                (datetime.unix_timestamp_nanos() / self.precision.unit_nanos()).to_string()
+            }
+        }
+    }
+}
+
+/// Format a record into a line ending with a newline unless binary, skipping the
+/// record on error
+fn format_line(formatter: &dyn RecordFormatter, record: &LogRecord<'_>) -> Option<Vec<u8>> {
+    let mut line = Vec::with_capacity(128);
+    if let Err(e) = formatter.format(record, &mut line) {
+        crate::internal_error(InternalError::Format {
+            target: record.target().to_string(),
+            message: e.to_string(),
+        });
+        return None;
+    }
+    if !line.is_empty() && !formatter.binary() && !line.ends_with(b"\n") {
+        line.push(b'\n');
+    }
+    Some(line)
+}
+
+/// Write stage: write lines to appenders
+struct Writers {
+    /// appenders of each route, in the same order of `Router::routes`
+    routes: Vec<Vec<Destination>>,
+    root: Destination,
+    appenders: HashMap<&'static str, Destination>,
+    attached: Vec<&'static str>,
+    /// see [`Builder::also_stderr`](crate::Builder::also_stderr)
+    mirror: Option<Destination>,
+    metrics: Arc<Metrics>,
+    tap: Arc<Tap>,
+    flush_interval: Duration,
+    /// how long to wait for incoming messages before flushing idle appenders
+    tick: Duration,
+    /// when appenders were last checked for health, first checked once idle
+    last_check: Option<Instant>,
+    /// lines are written by a binary format, where markers of dropped records are
+    /// left out
+    binary: bool,
+    /// some appender drops color codes, see [`Appender::colors`]
+    strip: bool,
+}
+
+impl Writers {
+    fn write(&mut self, rendered: Rendered) {
+        let Rendered {
+            line,
+            dispatch,
+            level,
+            start,
+            binary,
+            extra,
+        } = rendered;
+        // markers follow the format of the last line
+        self.binary = binary;
+        let tap = self.tap.active();
+        let plain = match binary || !(self.strip || tap) {
+            true => Cow::Borrowed(&line[..]),
+            false => crate::sanitize::strip_ansi(&line),
+        };
+        let line_for = |dest: &Destination| match dest.formatter.and_then(|ix| extra.get(ix)) {
+            Some((line, binary)) if *binary || dest.colors => Cow::Borrowed(&line[..]),
+            Some((line, _)) => crate::sanitize::strip_ansi(line),
+            None if dest.colors => Cow::Borrowed(&line[..]),
+            None => Cow::Borrowed(&plain[..]),
+        };
+        let mut notice = false;
+        match dispatch {
+            Dispatch::Route(ix) => {
+                for dest in &mut self.routes[ix] {
+                    if dest.accept(level) {
+                        notice |= dest.write(&line_for(dest));
+                    }
+                }
+            }
+            Dispatch::Appender(name) => {
+                if let Some(dest) = self.appenders.get_mut(name) {
+                    notice |= dest.write(&line_for(dest));
+                }
+            }
+            Dispatch::Default => {
+                if self.root.accept(level) {
+                    notice |= self.root.write(&line_for(&self.root));
+                }
+                for name in &self.attached {
+                    if let Some(dest) = self.appenders.get_mut(name) {
+                        if dest.accept(level) {
+                            notice |= dest.write(&line_for(dest));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(dest) = &mut self.mirror {
+            if dest.accept(level) {
+                notice |= dest.write(&line_for(dest));
+            }
+        }
+        if notice {
+            self.announce();
+        }
+        self.tap.send(level, &plain);
+        self.metrics.count(level);
+        self.metrics.write_latency.record(start.elapsed());
+    }
+
+    /// Write changes of health of appenders to the other healthy appenders
+    fn announce(&mut self) {
+        // announcing may fail and quarantine another appender, at most once each
+        for _ in 0..=self.appenders.len() + self.routes.len() {
+            let notices = self
+                .destinations()
+                .filter_map(|x| x.notice.take())
+                .collect::<Vec<_>>();
+            if notices.is_empty() || self.binary {
+                return;
+            }
+            for notice in notices {
+                let line = format!("-- {} --\n", notice);
+                for dest in self.destinations().filter(|x| x.quarantined.is_none()) {
+                    dest.write(line.as_bytes());
+                }
+            }
+        }
+    }
+
+    /// Write a marker of `count` dropped records to all appenders, regardless of
+    /// their level
+    fn dropped(&mut self, count: usize) {
+        if self.binary {
+            return;
+        }
+        let line = format!("-- {} records dropped --\n", count);
+        for dest in self.destinations() {
+            dest.write(line.as_bytes());
+        }
+        self.announce();
+    }
+
+    fn flush(&mut self) -> LoggerOutput {
+        let now = Instant::now();
+        match self
+            .destinations()
+            .filter(|x| x.quarantined.is_none())
+            .find_map(|w| {
+                w.last_flush = now;
+                let error = w.writer.flush().err()?;
+                Some((w.counter.name().to_string(), error))
+            }) {
+            Some((name, error)) => LoggerOutput::FlushError(name, error),
+            None => LoggerOutput::Flushed,
+        }
+    }
+
+    /// Write the marker of a self-test to all appenders regardless of their level,
+    /// flushing them, and reply with the latency of each stage
+    fn probe(&mut self, probe: Probe) {
+        let written = Instant::now();
+        let line = b"-- selftest --\n";
+        let binary = self.binary;
+        let appenders = self
+            .destinations()
+            .map(|dest| {
+                let start = Instant::now();
+                let result = match dest.quarantined {
+                    Some(_) => Err("quarantined".to_string()),
+                    None => match binary {
+                        true => Ok(()),
+                        false => dest.writer.write_all(line),
+                    }
+                    .and_then(|_| dest.writer.flush())
+                    .map_err(|e| e.to_string()),
+                };
+                if result.is_ok() {
+                    dest.last_flush = Instant::now();
+                    if !binary {
+                        dest.counter.add_bytes(line.len());
+                    }
+                }
+                AppenderProbe {
+                    name: dest.counter.name().to_string(),
+                    latency: start.elapsed(),
+                    error: result.err(),
+                }
+            })
+            .collect();
+        let routed = probe.routed.unwrap_or(written);
+        let _ = probe.reply.send(ProbeReport {
+            queued: routed.saturating_duration_since(probe.sent),
+            rendered: probe
+                .rendered
+                .unwrap_or(written)
+                .saturating_duration_since(routed),
+            appenders,
+        });
+    }
+
+    /// flush appenders periodically when there is no incoming log messages
+    fn idle(&mut self) {
+        let interval = self.flush_interval;
+        for dest in self.destinations() {
+            if dest.quarantined.is_some() {
+                dest.probe();
+                continue;
+            }
+            if dest.last_flush.elapsed() > dest.flush_interval.unwrap_or(interval) {
+                if let Err(source) = dest.writer.flush() {
+                    crate::internal_error(InternalError::Flush {
+                        appender: dest.counter.name().to_string(),
+                        source,
+                    });
+                }
+                dest.last_flush = Instant::now();
+            }
+        }
+        if self
+            .last_check
+            .is_none_or(|x| x.elapsed() >= HEALTH_INTERVAL)
+        {
+            for dest in self.destinations().filter(|x| x.quarantined.is_none()) {
+                match dest.writer.healthcheck() {
+                    Ok(()) => dest.counter.set_health(Ok(())),
+                    Err(e) => dest.quarantine(format!("failed healthcheck, {}", e), e),
+                }
+            }
+            self.last_check = Some(Instant::now());
+        }
+        self.announce();
+    }
+
+    /// Flush appenders within `deadline` and let them shut down
+    fn stop(&mut self, deadline: Option<Instant>) {
+        for dest in self.destinations() {
+            let flushed = match deadline {
+                Some(deadline) => dest
+                    .writer
+                    .flush_with_deadline(deadline.saturating_duration_since(Instant::now())),
+                None => dest.writer.flush(),
+            };
+            if let Err(source) = flushed.and_then(|_| dest.writer.on_shutdown()) {
+                crate::internal_error(InternalError::Flush {
+                    appender: dest.counter.name().to_string(),
+                    source,
+                });
+            }
+        }
+    }
+
+    fn destinations(&mut self) -> impl Iterator<Item = &mut Destination> {
+        self.appenders
+            .values_mut()
+            .chain(self.routes.iter_mut().flatten())
+            .chain([&mut self.root])
+            .chain(self.mirror.as_mut())
+    }
+}
+
+/// Progress of shutdown, published as it changes so that shutdown reports it even
+/// when log thread misses the deadline
+#[derive(Default)]
+pub(crate) struct Progress {
+    /// deadline of shutdown, set once it begins
+    deadline: OnceLock<Option<Instant>>,
+    /// log messages handled by [`drain`]
+    flushed: AtomicUsize,
+    /// log messages abandoned by [`drain`] as the deadline passed
+    abandoned: AtomicUsize,
+}
+
+impl Progress {
+    /// Begin shutdown, so that log thread drains queued messages before `deadline`
+    pub(crate) fn begin(&self, deadline: Option<Instant>) {
+        let _ = self.deadline.set(deadline);
+    }
+
+    #[inline]
+    fn begun(&self) -> bool {
+        self.deadline.get().is_some()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline.get().copied().flatten()
+    }
+
+    /// Log messages handled and abandoned so far
+    pub(crate) fn report(&self) -> ShutdownReport {
+        ShutdownReport {
+            flushed: self.flushed.load(Ordering::Relaxed),
+            abandoned: self.abandoned.load(Ordering::Relaxed),
+            ..ShutdownReport::default()
+        }
+    }
+}
+
+/// Drain the channel once shutdown begins, handling log messages until deadline
+///
+/// `first` is the message received when log thread noticed shutdown. `handle` is
+/// called with log messages and flush requests in order, waiting for the quit
+/// request if it is not queued yet, and the reply to the quit request is returned.
+fn drain<F>(
+    receiver: &queue::Receiver<LoggerInput>,
+    first: LoggerInput,
+    progress: &Progress,
+    mut handle: F,
+) -> Option<Sender<ShutdownReport>>
+where
+    F: FnMut(Job<LogMsg>),
+{
+    let deadline = progress.deadline();
+    let record = |log_msg, handle: &mut F| {
+        if deadline.map(|x| Instant::now() < x).unwrap_or(true) {
+            handle(Job::Record(log_msg));
+            progress.flushed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            progress.abandoned.fetch_add(1, Ordering::Relaxed);
+        }
+    };
+    let mut quit = None;
+    let mut input = Some(first);
+    while let Some(next) = input {
+        match next {
+            LoggerInput::LogMsg(log_msg) => record(log_msg, &mut handle),
+            LoggerInput::Batch(batch) => {
+                for log_msg in batch {
+                    record(log_msg, &mut handle);
+                }
+            }
+            LoggerInput::Dropped(count) => handle(Job::Dropped(count)),
+            LoggerInput::Flush(reply) => handle(Job::Flush(reply)),
+            LoggerInput::Quit(reply) => quit = Some(reply),
+            // dropping the reply tells the caller that the logger is stopped
+            LoggerInput::Probe(_) => (),
+        }
+        // messages sent after the quit request by racing log calls are drained too
+        input = match quit {
+            Some(_) => receiver.try_recv().ok(),
+            None => receiver.recv().ok(),
+        };
+    }
+    quit
+}
+
+/// Configuration of log thread(s)
+pub(crate) struct LogWorker {
+    router: Router,
+    renderer: Arc<Renderer>,
+    writers: Writers,
+    workers: usize,
+    progress: Arc<Progress>,
+    on_internal_error: Option<InternalErrorHandler>,
+    reorder: Option<Reorder>,
+}
+
+const IDLE_TIMEOUT: Duration = Duration::from_millis(200);
+/// Interval of healthchecks of appenders
+const HEALTH_INTERVAL: Duration = Duration::from_secs(5);
+/// Consecutive failed writes after which an appender is quarantined
+const QUARANTINE_AFTER: u32 = 3;
+/// Interval of attempts to reopen a quarantined appender
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Parts of log thread(s) built by [`Builder`](crate::Builder)
+pub(crate) struct WorkerConfig {
+    /// target-prefix and field routes, see [`Builder::route`](crate::Builder::route)
+    pub(crate) routes: Vec<Route>,
+    /// see [`Builder::filter`](crate::Builder::filter)
+    pub(crate) filters: Vec<Directive>,
+    pub(crate) root: Destination,
+    pub(crate) appenders: HashMap<&'static str, Destination>,
+    /// appenders written by records that are not routed or redirected, see
+    /// [`Builder::attach`](crate::Builder::attach)
+    pub(crate) attached: Vec<&'static str>,
+    /// see [`Builder::also_stderr`](crate::Builder::also_stderr)
+    pub(crate) mirror: Option<Destination>,
+    pub(crate) offset: Offset,
+    pub(crate) time_format: TimeFormat,
+    pub(crate) precision: TimePrecision,
+    /// `time_format` is one of built-in formats of `precision`
+    pub(crate) cache_time: bool,
+    pub(crate) formatter: Arc<SharedFormatter>,
+    pub(crate) target_formatters: Vec<(&'static str, Box<dyn RecordFormatter>)>,
+    pub(crate) appender_formatters: Vec<Box<dyn RecordFormatter>>,
+    pub(crate) global_fields: GlobalFields,
+    pub(crate) metrics: Arc<Metrics>,
+    pub(crate) tap: Arc<Tap>,
+    /// progress of shutdown, begun by [`shutdown`](crate::shutdown)
+    pub(crate) progress: Arc<Progress>,
+    /// handler of [`Builder::on_internal_error`](crate::Builder::on_internal_error)
+    pub(crate) on_internal_error: Option<InternalErrorHandler>,
+    /// number of render threads, 1 to do everything in a single log thread
+    pub(crate) workers: usize,
+    pub(crate) flush_interval: Duration,
+    pub(crate) clock: Option<Arc<dyn Clock>>,
+    /// target prefixes and their new names, longest first
+    pub(crate) renames: Vec<(&'static str, &'static str)>,
+    /// see [`Builder::reorder_window`](crate::Builder::reorder_window)
+    pub(crate) reorder_window: Option<Duration>,
+    pub(crate) pool: Arc<Pool>,
+    /// see [`Builder::sanitize`](crate::Builder::sanitize)
+    pub(crate) sanitize: bool,
+    /// see [`Builder::max_message_len`](crate::Builder::max_message_len)
+    pub(crate) max_message_len: Option<usize>,
+    /// see [`Builder::multiline`](crate::Builder::multiline)
+    pub(crate) multiline: Multiline,
+    /// see [`Builder::enrich`](crate::Builder::enrich)
+    pub(crate) enrichers: Vec<Box<dyn Enricher>>,
+    #[cfg(feature = "redact")]
+    pub(crate) redactions: crate::redact::Redactions,
+    #[cfg(feature = "message_filter")]
+    pub(crate) message_rules: crate::message_filter::MessageRules,
+}
+
+impl LogWorker {
+    pub(crate) fn new(config: WorkerConfig) -> Self {
+        let WorkerConfig {
+            mut routes,
+            filters,
+            root,
+            appenders,
+            attached,
+            mirror,
+            offset,
+            time_format,
+            precision,
+            cache_time,
+            formatter,
+            target_formatters,
+            appender_formatters,
+            global_fields,
+            metrics,
+            tap,
+            progress,
+            on_internal_error,
+            workers,
+            flush_interval,
+            clock,
+            renames,
+            reorder_window,
+            pool,
+            sanitize,
+            max_message_len,
+            multiline,
+            enrichers,
+            #[cfg(feature = "redact")]
+            redactions,
+            #[cfg(feature = "message_filter")]
+            message_rules,
+        } = config;
+        // stable, so that field rules keep the order they are added in
+        routes.sort_by_key(|r| match r.rule {
+            Rule::Field(..) => (0, std::cmp::Reverse(0)),
+            Rule::Prefix(prefix) => (1, std::cmp::Reverse(prefix.len())),
+        });
+        let max_level = |dests: &[Destination]| {
+            dests
+                .iter()
+                .map(|x| x.level)
+                .max()
+                .unwrap_or(LevelFilter::Off)
+        };
+        let default_level = attached
+            .iter()
+            .filter_map(|name| appenders.get(name))
+            .map(|x| x.level)
+            .chain([root.level])
+            .max()
+            .unwrap_or(LevelFilter::Off);
+        let tick = routes
+            .iter()
+            .flat_map(|r| r.appenders.iter())
+            .chain(appenders.values())
+            .chain([&root])
+            .chain(mirror.as_ref())
+            .filter_map(|x| x.flush_interval)
+            .chain([flush_interval, IDLE_TIMEOUT])
+            .min()
+            .unwrap_or(IDLE_TIMEOUT);
+        let router = Router {
+            routes: routes
+                .iter()
+                .map(|r| (r.rule, max_level(&r.appenders)))
+                .collect(),
+            filters: filters
+                .into_iter()
+                .map(|x| {
+                    let level = x
+                        .appender
+                        .and_then(|name| appenders.get(name))
+                        .map_or(LevelFilter::Off, |x| x.level);
+                    (x, level)
+                })
+                .collect(),
+            default_level,
+            mirror_level: mirror.as_ref().map_or(LevelFilter::Off, |x| x.level),
+            missed_log: HashMap::default(),
+            last_log: HashMap::default(),
+            clock: clock.clone(),
+            renames,
+            #[cfg(feature = "message_filter")]
+            message_rules,
+        };
+        let renderer = Renderer {
+            offset,
+            time_format,
+            precision,
+            cache_time,
+            id: RENDERERS.fetch_add(1, Ordering::Relaxed),
+            formatter,
+            target_formatters,
+            appender_formatters,
+            global_fields,
+            pool,
+            #[cfg(feature = "redact")]
+            redactions,
+            sanitize,
+            max_message_len,
+            multiline,
+            enrichers,
+        };
+        let binary = renderer.binary();
+        let strip = routes
+            .iter()
+            .flat_map(|r| r.appenders.iter())
+            .chain(appenders.values())
+            .chain([&root])
+            .chain(mirror.as_ref())
+            .any(|x| !x.colors);
+        LogWorker {
+            router,
+            renderer: Arc::new(renderer),
+            writers: Writers {
+                routes: routes.into_iter().map(|r| r.appenders).collect(),
+                root,
+                appenders,
+                attached,
+                mirror,
+                metrics,
+                tap,
+                flush_interval,
+                tick,
+                last_check: None,
+                binary,
+                strip,
+            },
+            workers: workers.max(1),
+            progress,
+            on_internal_error,
+            reorder: reorder_window.map(|window| Reorder::new(window, clock)),
+        }
+    }
+
+    /// Render stage, shared with log calls writing to per-thread files
+    pub(crate) fn renderer(&self) -> Arc<Renderer> {
+        self.renderer.clone()
+    }
+
+    /// Spawn log thread(s), return the handle of the thread that finishes last
+    pub(crate) fn spawn(
+        self,
+        receiver: queue::Receiver<LoggerInput>,
+    ) -> std::io::Result<JoinHandle<()>> {
+        if self.workers == 1 {
+            let handler = self.on_internal_error.clone();
+            return std::thread::Builder::new()
+                .name("logger".to_string())
+                .spawn(move || with_error_handler(&handler, || self.run(receiver)));
+        }
+
+        let LogWorker {
+            mut router,
+            renderer,
+            mut writers,
+            workers,
+            progress,
+            on_internal_error,
+            mut reorder,
+        } = self;
+        let mut to_formatters = Vec::with_capacity(workers);
+        let mut from_formatters = Vec::with_capacity(workers);
+        for ix in 0..workers {
+            let (job_sender, job_receiver) = bounded::<Job<Prepared>>(1024);
+            let (line_sender, line_receiver) = bounded::<Job<Option<Rendered>>>(1024);
+            let renderer = renderer.clone();
+            let handler = on_internal_error.clone();
+            std::thread::Builder::new()
+                .name(format!("logger-fmt-{}", ix))
+                .spawn(move || {
+                    with_error_handler(&handler, || {
+                        for job in job_receiver {
+                            let job = match job {
+                                Job::Record(prepared) => Job::Record(renderer.render(prepared)),
+                                Job::Dropped(count) => Job::Dropped(count),
+                                Job::Flush(reply) => Job::Flush(reply),
+                                Job::Probe(mut probe) => {
+                                    probe.rendered = Some(Instant::now());
+                                    Job::Probe(probe)
+                                }
+                                Job::Quit(deadline, report, reply) => {
+                                    Job::Quit(deadline, report, reply)
+                                }
+                            };
+                            if line_sender.send(job).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                })?;
+            to_formatters.push(job_sender);
+            from_formatters.push(line_receiver);
+        }
+
+        std::thread::Builder::new()
+            .name("logger-router".to_string())
+            .spawn(move || {
+                let mut next = 0;
+                let mut send = |job: Job<Prepared>| {
+                    let _ = to_formatters[next].send(job);
+                    next = (next + 1) % to_formatters.len();
+                };
+                let mut route = |log_msg: LogMsg, send: &mut dyn FnMut(Job<Prepared>)| {
+                    if let Some(prepared) = router.prepare(log_msg) {
+                        send(Job::Record(prepared));
+                    }
+                };
+                // drain the queue and pass the quit request to the write stage
+                let quit = |first,
+                            reorder: &mut Option<Reorder>,
+                            route: &mut RouteMsg<'_>,
+                            send: &mut dyn FnMut(Job<Prepared>)| {
+                    let mut jobs = Vec::new();
+                    let mut keep = |job| jobs.push(job);
+                    let quit = drain(&receiver, first, &progress, |job| match job {
+                        Job::Record(log_msg) => {
+                            Reorder::handle(reorder, Some(log_msg), false, |x| route(x, &mut keep));
+                        }
+                        Job::Dropped(count) => {
+                            Reorder::handle(reorder, None, true, |x| route(x, &mut keep));
+                            keep(Job::Dropped(count));
+                        }
+                        Job::Flush(reply) => {
+                            Reorder::handle(reorder, None, true, |x| route(x, &mut keep));
+                            keep(Job::Flush(reply));
+                        }
+                        Job::Probe(_) | Job::Quit(..) => (),
+                    });
+                    Reorder::handle(reorder, None, true, |x| route(x, &mut keep));
+                    for job in jobs {
+                        send(job);
+                    }
+                    if let Some(reply) = quit {
+                        send(Job::Quit(progress.deadline(), progress.report(), reply));
+                    }
+                };
+                loop {
+                    let input = match &reorder {
+                        None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                        Some(x) => receiver.recv_timeout(x.timeout(IDLE_TIMEOUT)),
+                    };
+                    match input {
+                        // no more messages are accepted by logger once shutdown begins
+                        Ok(input) if progress.begun() => {
+                            return quit(input, &mut reorder, &mut route, &mut send)
+                        }
+                        Ok(LoggerInput::LogMsg(log_msg)) => {
+                            Reorder::handle(&mut reorder, Some(log_msg), false, |x| {
+                                route(x, &mut send)
+                            });
+                        }
+                        Ok(LoggerInput::Batch(batch)) => {
+                            for log_msg in batch {
+                                Reorder::handle(&mut reorder, Some(log_msg), false, |x| {
+                                    route(x, &mut send)
+                                });
+                            }
+                        }
+                        Ok(LoggerInput::Dropped(count)) => {
+                            Reorder::handle(&mut reorder, None, true, |x| route(x, &mut send));
+                            send(Job::Dropped(count));
+                        }
+                        Ok(LoggerInput::Flush(reply)) => {
+                            Reorder::handle(&mut reorder, None, true, |x| route(x, &mut send));
+                            send(Job::Flush(reply));
+                        }
+                        Ok(LoggerInput::Probe(mut probe)) => {
+                            Reorder::handle(&mut reorder, None, true, |x| route(x, &mut send));
+                            probe.routed = Some(Instant::now());
+                            send(Job::Probe(probe));
+                        }
+                        Ok(input @ LoggerInput::Quit(_)) => {
+                            return quit(input, &mut reorder, &mut route, &mut send)
+                        }
+                        Err(RecvTimeoutError::Timeout) => {
+                            Reorder::handle(&mut reorder, None, false, |x| route(x, &mut send));
+                        }
+                        Err(RecvTimeoutError::Disconnected) => {
+                            Reorder::handle(&mut reorder, None, true, |x| route(x, &mut send));
+                            return;
+                        }
+                    }
+                }
+            })?;
+
+        std::thread::Builder::new()
+            .name("logger".to_string())
+            .spawn(move || {
+                with_error_handler(&on_internal_error, || {
+                    let mut next = 0;
+                    loop {
+                        match from_formatters[next].recv_timeout(writers.tick) {
+                            Ok(job) => {
+                                next = (next + 1) % from_formatters.len();
+                                match job {
+                                    Job::Record(Some(rendered)) => writers.write(rendered),
+                                    Job::Record(None) => (),
+                                    Job::Dropped(count) => writers.dropped(count),
+                                    Job::Flush(reply) => {
+                                        let _ = reply.send(writers.flush());
+                                    }
+                                    Job::Probe(probe) => writers.probe(probe),
+                                    Job::Quit(deadline, report, reply) => {
+                                        writers.stop(deadline);
+                                        let _ = reply.send(report);
+                                        return;
+                                    }
+                                }
+                            }
+                            Err(RecvTimeoutError::Timeout) => writers.idle(),
+                            Err(RecvTimeoutError::Disconnected) => {
+                                // logger dropped without being installed
+                                writers.stop(None);
+                                return;
+                            }
+                        }
+                    }
+                })
+            })
+    }
+
+    /// Run all stages in current thread
+    fn run(mut self, receiver: queue::Receiver<LoggerInput>) {
+        let mut reorder = self.reorder.take();
+        loop {
+            let timeout = reorder
+                .as_ref()
+                .map_or(self.writers.tick, |x| x.timeout(self.writers.tick));
+            match receiver.recv_timeout(timeout) {
+                // no more messages are accepted by logger once shutdown begins
+                Ok(input) if self.progress.begun() => {
+                    return self.quit(input, &receiver, &mut reorder)
+                }
+                Ok(LoggerInput::LogMsg(log_msg)) => {
+                    Reorder::handle(&mut reorder, Some(log_msg), false, |x| self.write(x))
+                }
+                Ok(LoggerInput::Batch(batch)) => {
+                    for log_msg in batch {
+                        Reorder::handle(&mut reorder, Some(log_msg), false, |x| self.write(x));
+                    }
+                }
+                Ok(LoggerInput::Dropped(count)) => {
+                    Reorder::handle(&mut reorder, None, true, |x| self.write(x));
+                    self.writers.dropped(count);
+                }
+                Ok(LoggerInput::Flush(reply)) => {
+                    Reorder::handle(&mut reorder, None, true, |x| self.write(x));
+                    // the caller may have given up waiting
+                    let _ = reply.send(self.writers.flush());
+                }
+                Ok(LoggerInput::Probe(mut probe)) => {
+                    Reorder::handle(&mut reorder, None, true, |x| self.write(x));
+                    probe.routed = Some(Instant::now());
+                    self.writers.probe(probe);
+                }
+                Ok(input @ LoggerInput::Quit(_)) => {
+                    return self.quit(input, &receiver, &mut reorder)
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    Reorder::handle(&mut reorder, None, false, |x| self.write(x));
+                    self.writers.idle();
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    // logger dropped without being installed
+                    Reorder::handle(&mut reorder, None, true, |x| self.write(x));
+                    self.writers.stop(None);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Drain the queue, flush appenders and reply to the quit request
+    fn quit(
+        &mut self,
+        first: LoggerInput,
+        receiver: &queue::Receiver<LoggerInput>,
+        reorder: &mut Option<Reorder>,
+    ) {
+        let progress = self.progress.clone();
+        let mut flushes = Vec::new();
+        let quit = drain(receiver, first, &progress, |job| match job {
+            Job::Record(log_msg) => {
+                Reorder::handle(reorder, Some(log_msg), false, |x| self.write(x))
+            }
+            Job::Dropped(count) => {
+                Reorder::handle(reorder, None, true, |x| self.write(x));
+                self.writers.dropped(count);
+            }
+            Job::Flush(reply) => flushes.push(reply),
+            Job::Probe(_) | Job::Quit(..) => (),
+        });
+        Reorder::handle(reorder, None, true, |x| self.write(x));
+        self.writers.stop(progress.deadline());
+        for reply in flushes {
+            let _ = reply.send(LoggerOutput::Flushed);
+        }
+        if let Some(reply) = quit {
+            let _ = reply.send(progress.report());
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, log_msg: LogMsg) {
+        if let Some(rendered) = self
+            .router
+            .prepare(log_msg)
+            .and_then(|x| self.renderer.render(x))
+        {
+            self.writers.write(rendered);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ftlog::access::{CombinedLogFormatter, CommonLogFormatter};
+use ftlog::access_log;
+use ftlog::format::RecordFormatter;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn access_lines<F: RecordFormatter + 'static>(formatter: F) -> Vec<String> {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(formatter)
+        .utc()
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    let latency = Duration::from_micros(1250);
+    access_log!(logger: logger, "GET", "/index.html", 200, latency, 2326, "curl/8.0");
+    access_log!(logger: logger, target: "api", "POST", "/say \"hi\"", 302, latency, 0, "Mozilla/5.0";
+        client = "10.0.0.7", user = "alice", referer = "https://example.com/", protocol = "HTTP/2.0");
+    drop(logger);
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    content
+        .lines()
+        .map(|line| {
+            // drop time like `[14/Jun/2023:03:13:26 +0000]`
+            let start = line.find('[').unwrap();
+            let end = line.find(']').unwrap();
+            assert!(line[start..end].ends_with(" +0000"), "{}", line);
+            format!("{}[]{}", &line[..start], &line[end + 1..])
+        })
+        .collect()
+}
+
+#[test]
+fn test_access_log_formats() {
+    let common = access_lines(CommonLogFormatter::new());
+    let combined = access_lines(CombinedLogFormatter::new().latency(true));
+    if cfg!(feature = "kv") {
+        assert_eq!(
+            common,
+            [
+                r#"- - - [] "GET /index.html HTTP/1.1" 200 2326"#,
+                r#"10.0.0.7 - alice [] "POST /say \"hi\" HTTP/2.0" 302 -"#,
+            ]
+        );
+        assert_eq!(
+            combined,
+            [
+                r#"- - - [] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/8.0" 1250"#,
+                r#"10.0.0.7 - alice [] "POST /say \"hi\" HTTP/2.0" 302 - "https://example.com/" "Mozilla/5.0" 1250"#,
+            ]
+        );
+    } else {
+        assert_eq!(common.len(), 2);
+        assert_eq!(combined.len(), 2);
+    }
+}
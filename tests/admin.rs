@@ -0,0 +1,115 @@
+#![cfg(feature = "admin")]
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+use ftlog::admin::{Admin, Body};
+use ftlog::{log_to, Level};
+use http::{Method, Request, StatusCode};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn request(admin: &Admin, method: Method, uri: &str, body: &str) -> (StatusCode, String) {
+    let req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(body.to_string())
+        .unwrap();
+    let resp = admin.handle(&req);
+    match resp.into_parts() {
+        (parts, Body::Full(body)) => (parts.status, String::from_utf8(body).unwrap()),
+        (_, Body::Events(_)) => panic!("unexpected event stream"),
+    }
+}
+
+#[test]
+fn test_loglevel() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder().root(buffer.clone()).build().unwrap();
+    let admin = Admin::new(&logger);
+
+    let get = |uri| request(&admin, Method::GET, uri, "");
+    assert_eq!(get("/loglevel"), (StatusCode::OK, "info\n".to_string()));
+    log_to!(logger, Level::Debug, "dropped");
+    let resp = request(&admin, Method::PUT, "/admin/loglevel", "debug\n");
+    assert_eq!(resp.0, StatusCode::NO_CONTENT);
+    log_to!(logger, Level::Debug, "debug");
+
+    let resp = request(&admin, Method::PUT, "/loglevel?target=app%3A%3Adb", "warn");
+    assert_eq!(resp.0, StatusCode::NO_CONTENT);
+    assert_eq!(get("/loglevel?target=app::db::pool").1, "warn\n");
+    log_to!(logger, target: "app::db", Level::Info, "dropped");
+    request(&admin, Method::PUT, "/loglevel?target=app::db", "reset");
+    assert_eq!(get("/loglevel?target=app::db").1, "debug\n");
+
+    let resp = request(&admin, Method::PUT, "/loglevel", "loud");
+    assert_eq!(
+        resp,
+        (StatusCode::BAD_REQUEST, "invalid level loud\n".into())
+    );
+    let resp = request(&admin, Method::POST, "/loglevel", "debug");
+    assert_eq!(resp.0, StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(get("/metrics").0, StatusCode::NOT_FOUND);
+
+    drop(logger);
+    assert_eq!(get("/loglevel").0, StatusCode::SERVICE_UNAVAILABLE);
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(!content.contains("dropped"), "{}", content);
+    assert!(content.contains(" debug\n"), "{}", content);
+}
+
+#[test]
+fn test_logtail() {
+    let logger = ftlog::builder().root(Buffer::default()).build().unwrap();
+    let admin = Admin::new(&logger);
+    let req = Request::get("/logtail?level=warn").body("").unwrap();
+    let (parts, body) = admin.handle(&req).into_parts();
+    assert_eq!(parts.headers["content-type"], "text/event-stream");
+    let Body::Events(mut events) = body else {
+        panic!("expect an event stream");
+    };
+
+    log_to!(logger, Level::Info, "skipped");
+    log_to!(logger, Level::Warn, "first\nsecond");
+    let event = String::from_utf8(events.next().unwrap()).unwrap();
+    assert!(event.starts_with("data: "), "{}", event);
+    assert!(event.ends_with(" first\ndata: second\n\n"), "{}", event);
+
+    drop(logger);
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn test_serve() {
+    let logger = ftlog::builder().root(Buffer::default()).build().unwrap();
+    let addr = Admin::new(&logger).serve("127.0.0.1:0").unwrap();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "PUT /loglevel HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nerror"
+    )
+    .unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 204 No Content\r\n"), "{}", resp);
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET /loglevel HTTP/1.1\r\n\r\n").unwrap();
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).unwrap();
+    assert!(resp.starts_with("HTTP/1.1 200 OK\r\n"), "{}", resp);
+    assert!(resp.ends_with("\r\n\r\nerror\n"), "{}", resp);
+}
@@ -0,0 +1,53 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::{log_to, Level, LevelFilter};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_also_stderr() {
+    let root = Buffer::default();
+    let audit = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .root_log_level(LevelFilter::Error)
+        .route("audit", audit.clone())
+        .also_stderr(LevelFilter::Warn)
+        .build()
+        .unwrap();
+    log_to!(logger, target: "audit", Level::Info, "not mirrored");
+    log_to!(logger, target: "audit", Level::Warn, "routed and mirrored");
+    // below the root level, written by the mirror alone
+    log_to!(logger, target: "app", Level::Warn, "mirrored only");
+    log::Log::flush(&logger);
+
+    let stats = logger.stats();
+    let mirrored = stats
+        .appenders
+        .iter()
+        .find(|x| x.name == "stderr")
+        .unwrap()
+        .bytes_written;
+    drop(logger);
+    assert!(root.0.lock().unwrap().is_empty());
+    let audit = String::from_utf8(audit.0.lock().unwrap().clone()).unwrap();
+    let lines = audit.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", audit);
+    let routed = lines[1].len() as u64 + 1;
+    // the line of the third record differs only by message
+    let expected = 2 * routed + "mirrored only".len() as u64 - "routed and mirrored".len() as u64;
+    assert_eq!(mirrored, expected, "{}", audit);
+}
@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ftlog::appender::{Appender, Sink};
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Hooks(Arc<Mutex<Vec<String>>>);
+
+impl Write for Hooks {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().push("write".to_string());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().push("flush".to_string());
+        Ok(())
+    }
+}
+
+impl Appender for Hooks {
+    fn flush_with_deadline(&mut self, timeout: Duration) -> std::io::Result<()> {
+        assert!(timeout <= Duration::from_secs(5));
+        self.0
+            .lock()
+            .unwrap()
+            .push("flush_with_deadline".to_string());
+        Ok(())
+    }
+
+    fn on_shutdown(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().push("on_shutdown".to_string());
+        Ok(())
+    }
+
+    fn healthcheck(&mut self) -> std::io::Result<()> {
+        Err(std::io::Error::other("disk full"))
+    }
+}
+
+#[test]
+fn test_appender_hooks() {
+    let hooks = Hooks::default();
+    let logger = ftlog::builder()
+        .root(Sink::new(hooks.clone()))
+        .shutdown_timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "first");
+
+    // appenders are checked once log thread is idle
+    let mut healthy = true;
+    for _ in 0..50 {
+        std::thread::sleep(Duration::from_millis(20));
+        let stats = logger.stats();
+        let root = stats.appenders.iter().find(|x| x.name == "root").unwrap();
+        if !root.healthy {
+            assert_eq!(root.error.as_deref(), Some("disk full"));
+            healthy = false;
+            break;
+        }
+    }
+    assert!(!healthy);
+
+    drop(logger);
+    let calls = hooks.0.lock().unwrap();
+    assert_eq!(calls[0], "write");
+    assert_eq!(
+        calls[calls.len() - 2..],
+        ["flush_with_deadline", "on_shutdown"],
+        "{:?}",
+        calls
+    );
+}
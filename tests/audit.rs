@@ -0,0 +1,69 @@
+#![cfg(feature = "audit")]
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use ftlog::appender::audit::{self, AuditError, AuditFileAppender};
+use ftlog::log_to;
+
+const KEY: &[u8] = b"audit key";
+
+fn write(path: &Path, messages: &[&str]) {
+    let appender = AuditFileAppender::builder()
+        .path(path)
+        .key(KEY.to_vec())
+        .checkpoint(2)
+        .build()
+        .unwrap();
+    let logger = ftlog::builder().root(appender).build().unwrap();
+    for message in messages {
+        log_to!(logger, log::Level::Info, "{}", message);
+    }
+    drop(logger);
+}
+
+fn verify(path: &Path, key: &[u8]) -> Result<audit::Verified, AuditError> {
+    audit::verify(BufReader::new(File::open(path).unwrap()), key)
+}
+
+#[test]
+fn audit_chain() {
+    let dir = std::env::temp_dir().join(format!("ftlog-audit-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("audit.log");
+
+    write(&path, &["granted admin", "line\nbreak", "revoked admin"]);
+    let verified = verify(&path, KEY).unwrap();
+    assert_eq!(verified.records, 3);
+    // one after two records, one when dropped
+    assert_eq!(verified.checkpoints, 2);
+    assert!(verified.sealed);
+
+    // appending continues the chain
+    write(&path, &["granted root"]);
+    let verified = verify(&path, KEY).unwrap();
+    assert_eq!(verified.records, 4);
+    assert_eq!(verified.checkpoints, 3);
+
+    assert!(matches!(
+        verify(&path, b"wrong key"),
+        Err(AuditError::BadCheckpoint { records: 2 })
+    ));
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let edited = content.replace("revoked admin", "revoked guest");
+    std::fs::write(&path, edited).unwrap();
+    assert!(matches!(
+        verify(&path, KEY),
+        Err(AuditError::Tampered { record: 2 })
+    ));
+
+    // opening a tampered log fails
+    assert!(AuditFileAppender::builder()
+        .path(&path)
+        .key(KEY.to_vec())
+        .build()
+        .is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,22 @@
+use ftlog::bench::{self, BenchConfig};
+use ftlog::OverflowPolicy;
+
+#[test]
+fn test_bench() {
+    let config = BenchConfig {
+        threads: 2,
+        records_per_thread: 1_000,
+        ..Default::default()
+    };
+    let report = bench::run(
+        ftlog::builder()
+            .workers(2)
+            .overflow_policy(OverflowPolicy::Block),
+        &config,
+    )
+    .unwrap();
+    assert_eq!(report.records, 2_000);
+    assert_eq!(report.dropped, 0);
+    assert_eq!(report.call_latency.count, 2_000);
+    assert!(report.throughput > 0.);
+}
@@ -0,0 +1,81 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::binary::{decode, BinaryFormat, Decoder};
+use ftlog::{log_to, Level};
+use log::{Log, Record};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_binary_format() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(BinaryFormat::new())
+        .workers(2)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    for i in 0..10 {
+        log_to!(logger, target: "db", Level::Warn, "slow query {}", i);
+        log_to!(logger, target: "net", Level::Info, "connected\nto {}", i);
+    }
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .target("db")
+            .key_values(&[("user", "alice")])
+            .args(format_args!("denied"))
+            .build(),
+    );
+    drop(logger);
+
+    let bytes = buffer.0.lock().unwrap().clone();
+    let records = Decoder::new(bytes.as_slice())
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(records.len(), 21);
+    for (i, pair) in records[..20].chunks(2).enumerate() {
+        assert_eq!(pair[0].level, Level::Warn);
+        assert_eq!(pair[0].target, "db");
+        assert_eq!(pair[0].args, format!("slow query {}", i));
+        assert_eq!(pair[1].level, Level::Info);
+        assert_eq!(pair[1].target, "net");
+        assert_eq!(pair[1].args, format!("connected\nto {}", i));
+        assert!(pair[0].time <= pair[1].time);
+    }
+    let last = &records[20];
+    assert_eq!(last.level, Level::Error);
+    if cfg!(feature = "kv") {
+        assert_eq!(last.key_values, [("user".to_string(), "alice".to_string())]);
+    }
+
+    let mut text = Vec::new();
+    assert_eq!(decode(bytes.as_slice(), &mut text).unwrap(), 21);
+    let text = String::from_utf8(text).unwrap();
+    let expected = if cfg!(feature = "kv") {
+        " ERROR db user=alice denied\n"
+    } else {
+        " ERROR db denied\n"
+    };
+    assert!(text.ends_with(expected), "{}", text);
+
+    // a frame cut by a crash is an error, records before it are still decoded
+    let mut decoder = Decoder::new(&bytes[..bytes.len() - 1]);
+    for _ in 0..20 {
+        decoder.next_record().unwrap().unwrap();
+    }
+    assert!(decoder.next_record().is_err());
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "tracing")]
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+
+#[test]
+fn test_capture_tracing() {
+    let dir = std::env::temp_dir().join(format!("ftlog-capture-tracing-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("capture.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .capture_tracing(true)
+        .try_init()
+        .expect("logger build or set failed");
+    log::info!("from log");
+    tracing::warn!(user = "alice", "from tracing");
+    drop(guard);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", content);
+    assert!(lines[0].ends_with(" from log"), "{}", lines[0]);
+    assert!(lines[1].contains(" WARN "), "{}", lines[1]);
+    assert!(
+        lines[1].ends_with(" user=alice from tracing"),
+        "{}",
+        lines[1]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,125 @@
+use std::fs::read_to_string;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use ftlog::appender::{FileAppender, Period};
+use ftlog::clock::ManualClock;
+use ftlog::{log_to, Level, LogTimezone, TimeFormat};
+
+#[test]
+fn test_manual_clock_rotation() {
+    let dir = std::env::temp_dir().join(format!("ftlog-clock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // 2022-10-24T16:00:00Z
+    let start = UNIX_EPOCH + Duration::from_secs(1_666_627_200);
+    let clock = Arc::new(ManualClock::new(start));
+
+    let appender = FileAppender::builder()
+        .path(dir.join("app.log"))
+        .rotate(Period::Minute)
+        .timezone(LogTimezone::Utc)
+        .clock(clock.clone())
+        .build();
+    let logger = ftlog::builder()
+        .clock(clock.clone())
+        .time_format(TimeFormat::UnixEpoch)
+        .root(appender)
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "first");
+    ftlog::Log::flush(&logger);
+    clock.advance(Duration::from_secs(61));
+    log_to!(logger, Level::Info, "second");
+    drop(logger);
+
+    let first = read_to_string(dir.join("app-20221024T1600.log")).unwrap();
+    assert!(first.starts_with("1666627200000 0ms "), "{}", first);
+    assert!(first.ends_with(" first\n"), "{}", first);
+    let second = read_to_string(dir.join("app-20221024T1601.log")).unwrap();
+    assert!(second.starts_with("1666627261000 0ms "), "{}", second);
+    assert!(second.ends_with(" second\n"), "{}", second);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_expire_compressed() {
+    let dir = std::env::temp_dir().join(format!("ftlog-expire-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // files compressed or written just now, by the system clock
+    for name in [
+        "app-20221001T1600.log.gz",
+        "app-20221024T1559.log.gz",
+        "app-20221001T1600.log",
+        "app-20221001.log.gz",
+        "other-20221001T1600.log.gz",
+    ] {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+    // 2022-10-24T16:00:00Z
+    let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_666_627_200));
+    let appender = FileAppender::builder()
+        .path(dir.join("app.log"))
+        .rotate(Period::Minute)
+        .expire(ftlog::appender::Duration::days(7))
+        .timezone(LogTimezone::Utc)
+        .clock(clock)
+        .build();
+    drop(appender);
+
+    assert!(!dir.join("app-20221001T1600.log.gz").exists());
+    for name in [
+        "app-20221024T1559.log.gz",
+        "app-20221001T1600.log",
+        "app-20221001.log.gz",
+        "other-20221001T1600.log.gz",
+    ] {
+        assert!(dir.join(name).exists(), "{} is deleted", name);
+    }
+    let log = read_to_string(dir.join("app-20221024T1600.log")).unwrap();
+    assert_eq!(log, "Log file deleted: app-20221001T1600.log.gz");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_with_now_month_boundary() {
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    use ftlog::clock::FnClock;
+    use time::OffsetDateTime;
+
+    let dir = std::env::temp_dir().join(format!("ftlog-with-now-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // 2022-10-31T23:59:00Z
+    let now = Arc::new(AtomicI64::new(1_667_260_740));
+    let read = {
+        let now = now.clone();
+        move || OffsetDateTime::from_unix_timestamp(now.load(Ordering::Relaxed)).unwrap()
+    };
+    let appender = FileAppender::builder()
+        .path(dir.join("app.log"))
+        .rotate(Period::Day)
+        .timezone(LogTimezone::Utc)
+        .clock(FnClock::new(read.clone()))
+        .build();
+    let logger = ftlog::builder()
+        .with_now(read)
+        .utc()
+        .root(appender)
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "october");
+    ftlog::Log::flush(&logger);
+    now.fetch_add(120, Ordering::Relaxed);
+    log_to!(logger, Level::Info, "november");
+    drop(logger);
+
+    let october = read_to_string(dir.join("app-20221031.log")).unwrap();
+    assert!(october.starts_with("2022-10-31 23:59:00"), "{}", october);
+    assert!(october.ends_with(" october\n"), "{}", october);
+    let november = read_to_string(dir.join("app-20221101.log")).unwrap();
+    assert!(november.starts_with("2022-11-01 00:01:00"), "{}", november);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,98 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::appender::{Appender, Sink};
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A buffer pretending to be a terminal
+#[derive(Clone, Default)]
+struct Terminal(Buffer);
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Appender for Terminal {
+    fn colors(&self) -> bool {
+        true
+    }
+}
+
+fn written(buffer: &Buffer) -> String {
+    String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap()
+}
+
+#[test]
+fn test_colors_stripped_for_files() {
+    let file = Buffer::default();
+    let terminal = Terminal::default();
+    let logger = ftlog::builder()
+        .color(true)
+        .with_source_location(false)
+        .with_thread(false)
+        .root(file.clone())
+        .appender("console", Sink::new(terminal.clone()))
+        .filter(|_, _, target| target == "console", "console")
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Error, "static");
+    log_to!(logger, Level::Warn, "with {}", "args");
+    log_to!(logger, target: "console", Level::Error, "static");
+    log_to!(logger, target: "console", Level::Info, "colored {}", 1);
+    drop(logger);
+
+    let file = written(&file);
+    assert!(!file.contains('\x1b'), "{:?}", file);
+    assert!(file.contains("ERROR static\n"), "{:?}", file);
+    assert!(file.contains("WARN with args\n"), "{:?}", file);
+
+    let terminal = written(&terminal.0);
+    assert!(
+        terminal.contains("\x1b[31mERROR\x1b[0m static\n"),
+        "{:?}",
+        terminal
+    );
+    assert!(
+        terminal.contains("\x1b[32mINFO\x1b[0m colored 1\n"),
+        "{:?}",
+        terminal
+    );
+}
+
+#[test]
+fn test_console_colors() {
+    use std::io::IsTerminal;
+
+    use ftlog::appender::console::ConsoleAppender;
+
+    let console = ConsoleAppender::stderr();
+    if cfg!(not(windows)) {
+        assert_eq!(console.colors(), std::io::stderr().is_terminal());
+    }
+    let logger = ftlog::builder()
+        .color(true)
+        .root(Sink::new(console))
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "to console");
+}
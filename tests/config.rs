@@ -0,0 +1,109 @@
+#![cfg(feature = "config")]
+use std::fs::read_to_string;
+
+use ftlog::{log_to, ConfigError, InitError, Level};
+
+#[test]
+fn test_log4rs_config() {
+    let dir = std::env::temp_dir().join(format!("ftlog-config-{}", std::process::id()));
+    let config = format!(
+        r#"
+refresh_rate: 30 seconds
+appenders:
+  app:
+    kind: file
+    path: {dir}/app.log
+    encoder:
+      pattern: "{{l:<5}} {{t}} - {{m}}{{n}}"
+  requests:
+    kind: file
+    path: {dir}/requests.log
+  audit:
+    kind: file
+    path: {dir}/audit.log
+root:
+  level: warn
+  appenders:
+    - app
+loggers:
+  app::db:
+    level: info
+  app::requests:
+    level: info
+    appenders:
+      - requests
+    additive: false
+  app::requests::audit:
+    appenders:
+      - audit
+"#,
+        dir = dir.display()
+    );
+    let logger = ftlog::config::from_yaml(&config).unwrap().build().unwrap();
+    log_to!(logger, target: "app", Level::Info, "below root level");
+    log_to!(logger, target: "app", Level::Warn, "root");
+    log_to!(logger, target: "app::db::pool", Level::Info, "db");
+    log_to!(logger, target: "app::dbx", Level::Info, "not in db module");
+    log_to!(logger, target: "app::requests", Level::Debug, "below requests level");
+    log_to!(logger, target: "app::requests", Level::Info, "request");
+    log_to!(logger, target: "app::requests::audit", Level::Info, "audit");
+    drop(logger);
+
+    let app = read_to_string(dir.join("app.log")).unwrap();
+    assert_eq!(app, "WARN  app - root\nINFO  app::db::pool - db\n");
+    let requests = read_to_string(dir.join("requests.log")).unwrap();
+    assert_eq!(
+        requests,
+        "INFO  app::requests - request\nINFO  app::requests::audit - audit\n"
+    );
+    let audit = read_to_string(dir.join("audit.log")).unwrap();
+    assert_eq!(audit, "INFO  app::requests::audit - audit\n");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_invalid_config() {
+    let unknown = "root:\n  appenders:\n    - missing\n";
+    let size = "appenders:\n  file:\n    kind: rolling_file\n    path: app.log\n    policy:\n      trigger:\n        kind: size\n        limit: 10 mb\n";
+    for config in [
+        unknown,
+        size,
+        "root: [",
+        "appenders:\n  x:\n    kind: kafka\n",
+    ] {
+        assert!(
+            matches!(
+                ftlog::config::from_yaml(config),
+                Err(InitError::InvalidConfig(_))
+            ),
+            "{}",
+            config
+        );
+    }
+    assert!(matches!(
+        ftlog::config::from_yaml(unknown),
+        Err(InitError::InvalidConfig(ConfigError::UnknownAppender(name))) if name == "missing"
+    ));
+}
+
+#[test]
+fn test_config_error_location() {
+    let path = std::env::temp_dir().join(format!("ftlog-config-{}.yaml", std::process::id()));
+    std::fs::write(&path, "root:\n  level: info\n  appenders: 3\n").unwrap();
+    let err = ftlog::config::load(&path).err();
+    std::fs::remove_file(&path).unwrap();
+    let Some(InitError::InvalidConfig(ConfigError::Parse {
+        location: Some(location),
+        ..
+    })) = &err
+    else {
+        panic!("{:?}", err);
+    };
+    assert_eq!(location.path.as_deref(), Some(path.as_path()));
+    assert_eq!(location.line, 3);
+    assert!(err
+        .unwrap()
+        .to_string()
+        .contains(&format!("{}:3:", path.display())));
+}
@@ -0,0 +1,21 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ftlog::appender::console::ConsoleAppender;
+use ftlog::appender::Sink;
+use ftlog::{log_to, Level};
+
+#[test]
+fn test_console_suspend() {
+    let suspended = Arc::new(AtomicUsize::new(0));
+    let counter = suspended.clone();
+    let console = ConsoleAppender::stderr().suspend(move |write| {
+        counter.fetch_add(1, Ordering::Relaxed);
+        write();
+    });
+    let logger = ftlog::builder().root(Sink::new(console)).build().unwrap();
+    log_to!(logger, Level::Info, "above progress bars");
+    log_to!(logger, Level::Warn, "also above");
+    drop(logger);
+    assert_eq!(suspended.load(Ordering::Relaxed), 2);
+}
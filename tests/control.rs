@@ -0,0 +1,117 @@
+#![cfg(all(unix, feature = "control"))]
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+
+use ftlog::appender::FileAppender;
+use ftlog::control::Listen;
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Client(BufReader<UnixStream>);
+
+impl Client {
+    /// Send a request, returning lines of the response
+    fn request(&mut self, request: &str) -> Vec<String> {
+        writeln!(self.0.get_mut(), "{}", request).unwrap();
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            self.0.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_string();
+            let done = line == "ok" || line.starts_with("error: ");
+            lines.push(line);
+            if done {
+                return lines;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_control() {
+    let dir = std::env::temp_dir().join(format!("ftlog-control-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("log.sock");
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .root(buffer.clone())
+        .attach(
+            "file",
+            FileAppender::new(dir.join("app.log")),
+            log::LevelFilter::Info,
+        )
+        .control(Listen::Unix(socket.clone()))
+        .build()
+        .unwrap();
+    let mut client = Client(BufReader::new(UnixStream::connect(&socket).unwrap()));
+
+    assert_eq!(client.request("level"), ["info", "ok"]);
+    log_to!(logger, Level::Debug, "dropped");
+    assert_eq!(client.request("level debug"), ["ok"]);
+    log_to!(logger, Level::Debug, "debug");
+    assert_eq!(client.request("target noisy warn"), ["ok"]);
+    assert_eq!(client.request("targets"), ["noisy warn", "ok"]);
+    log_to!(logger, target: "noisy::db", Level::Info, "dropped");
+    log_to!(logger, target: "noisy::db", Level::Warn, "warn");
+    assert_eq!(client.request("target noisy reset"), ["ok"]);
+    assert_eq!(client.request("targets"), ["ok"]);
+    log_to!(logger, target: "noisy::db", Level::Info, "info");
+
+    // move the log file away like logrotate, and reopen it
+    assert_eq!(client.request("flush"), ["ok"]);
+    std::fs::rename(dir.join("app.log"), dir.join("app.log.1")).unwrap();
+    assert_eq!(client.request("rotate"), ["ok"]);
+    log_to!(logger, Level::Info, "reopened");
+    assert_eq!(client.request("flush"), ["ok"]);
+    let stats = client.request("stats");
+    assert!(
+        stats.contains(&"records.debug 1".to_string()),
+        "{:?}",
+        stats
+    );
+    assert!(stats.contains(&"records.info 2".to_string()), "{:?}", stats);
+
+    assert_eq!(
+        client.request("level verbose"),
+        ["error: invalid level verbose"]
+    );
+    assert_eq!(client.request("reboot"), ["error: unknown request reboot"]);
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let messages = content
+        .lines()
+        .map(|line| line.rsplit(' ').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["debug", "warn", "info", "reopened"]);
+    let moved = std::fs::read_to_string(dir.join("app.log.1")).unwrap();
+    assert!(moved.ends_with(" info\n"), "{}", moved);
+    let reopened = std::fs::read_to_string(dir.join("app.log")).unwrap();
+    assert!(reopened.ends_with(" reopened\n"), "{}", reopened);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_remote_address() {
+    let err = ftlog::builder()
+        .control(Listen::Tcp("0.0.0.0:7070".parse().unwrap()))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,55 @@
+use std::sync::Mutex;
+
+use ftlog::binary::Decoder;
+use ftlog_core::{LevelFilter, Sink};
+
+/// Frames written by the no_std front-end
+struct Frames(Mutex<Vec<u8>>);
+
+impl Sink for Frames {
+    fn write(&self, frame: &[u8]) {
+        self.0.lock().unwrap().extend_from_slice(frame);
+    }
+
+    fn now_nanos(&self) -> i64 {
+        1_666_627_200_000_000_000
+    }
+}
+
+static FRAMES: Frames = Frames(Mutex::new(Vec::new()));
+
+#[test]
+fn test_core_frames_decoded() {
+    ftlog_core::set_sink(&FRAMES).unwrap();
+    assert!(ftlog_core::set_sink(&FRAMES).is_err());
+    ftlog_core::set_max_level(LevelFilter::Info);
+
+    for i in 0..2 {
+        ftlog_core::info!(target: "power", "battery at {}%", 12 - i);
+    }
+    ftlog_core::debug!("filtered out");
+    ftlog_core::error!("{}", "x".repeat(1000));
+    let reader_attached = FRAMES.0.lock().unwrap().len();
+    ftlog_core::redefine_targets();
+    ftlog_core::warn!(target: "power", "low battery");
+
+    let frames = FRAMES.0.lock().unwrap().clone();
+    let records = Decoder::new(frames.as_slice())
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    let lines = records.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4, "{:?}", lines);
+    assert_eq!(lines[0], "2022-10-24T16:00:00Z INFO power battery at 12%");
+    assert_eq!(lines[1], "2022-10-24T16:00:00Z INFO power battery at 11%");
+    assert_eq!(records[2].target, "core");
+    assert_eq!(
+        records[2].args.len(),
+        ftlog_core::FRAME_CAPACITY - ftlog_core::frame::RECORD_OVERHEAD
+    );
+
+    // a reader attached later learns the target defined again
+    let records = Decoder::new(&frames[reader_attached..])
+        .map(|x| x.unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(records, ["2022-10-24T16:00:00Z WARN power low battery"]);
+}
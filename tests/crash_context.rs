@@ -0,0 +1,57 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::{log_to, Level, LevelFilter};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_crash_context() {
+    let buffer = Buffer::default();
+    let logger = Arc::new(
+        ftlog::builder()
+            .max_log_level(LevelFilter::Trace)
+            .crash_context(LevelFilter::Info, 2)
+            .root(buffer.clone())
+            .build()
+            .unwrap(),
+    );
+    log_to!(logger, Level::Debug, "evicted");
+    log_to!(logger, Level::Trace, "kept 1");
+    log_to!(logger, Level::Debug, "kept 2");
+    log_to!(logger, Level::Info, "written");
+    let other = logger.clone();
+    std::thread::spawn(move || {
+        log_to!(other, Level::Debug, "other thread");
+    })
+    .join()
+    .unwrap();
+    log_to!(logger, Level::Error, "failed");
+    log_to!(logger, Level::Error, "failed again");
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let messages = content
+        .lines()
+        .map(|line| line.rsplit(' ').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        ["written", "1", "2", "failed", "again"],
+        "{}",
+        content
+    );
+    assert!(content.contains(" TRACE "), "{}", content);
+}
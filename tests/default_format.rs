@@ -0,0 +1,184 @@
+use std::fs::read_to_string;
+
+use ftlog::{appender::FileAppender, FtLogFormatter};
+use log::{Level, Log, Record};
+
+fn log_with(name: &str, builder: ftlog::Builder, record: &Record) -> String {
+    let path = std::env::temp_dir().join(format!(
+        "ftlog-default-format-{}-{}",
+        std::process::id(),
+        name
+    ));
+
+    let logger = builder
+        .root(FileAppender::new(&path))
+        .build()
+        .expect("logger build failed");
+    logger.log(record);
+    logger.flush();
+
+    let content = read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    content
+}
+
+#[test]
+fn test_default_format_options() {
+    let record = Record::builder()
+        .level(Level::Info)
+        .file_static(Some("src/appender/file.rs"))
+        .line(Some(42))
+        .args(format_args!("message"))
+        .build();
+
+    let content = log_with(
+        "thread.log",
+        ftlog::builder().format(FtLogFormatter).with_thread(false),
+        &record,
+    );
+    assert!(
+        content.ends_with(" INFO [src/appender/file.rs:42] message\n"),
+        "{}",
+        content
+    );
+
+    let content = log_with(
+        "location.log",
+        ftlog::builder()
+            .with_thread(false)
+            .with_source_location(false),
+        &record,
+    );
+    assert!(content.ends_with(" INFO message\n"), "{}", content);
+
+    let content = log_with(
+        "abbreviate.log",
+        ftlog::builder().abbreviate_source_path(true),
+        &record,
+    );
+    assert!(
+        content.ends_with(" INFO test_default_format_options [s/a/file.rs:42] message\n"),
+        "{}",
+        content
+    );
+
+    let _request = ftlog::context::insert("request_id", 7);
+    let content = log_with(
+        "context.log",
+        ftlog::builder()
+            .with_thread(false)
+            .global_field("service", "billing"),
+        &record,
+    );
+    assert!(
+        content.ends_with(" INFO [src/appender/file.rs:42] service=billing request_id=7 message\n"),
+        "{}",
+        content
+    );
+}
+
+#[test]
+fn test_static_message() {
+    let builder = || {
+        ftlog::builder()
+            .with_thread(false)
+            .global_field("service", "billing")
+    };
+    let content = log_with(
+        "static.log",
+        builder(),
+        &Record::builder()
+            .level(Level::Warn)
+            .file_static(Some("src/main.rs"))
+            .line(Some(7))
+            .args(format_args!("static"))
+            .build(),
+    );
+    assert!(
+        content.ends_with(" WARN [src/main.rs:7] service=billing static\n"),
+        "{}",
+        content
+    );
+
+    let file = String::from("src/generated.rs");
+    let content = log_with(
+        "borrowed.log",
+        builder(),
+        &Record::builder()
+            .level(Level::Warn)
+            .file(Some(&file))
+            .line(Some(8))
+            .args(format_args!("borrowed"))
+            .build(),
+    );
+    assert!(
+        content.ends_with(" WARN [src/generated.rs:8] service=billing borrowed\n"),
+        "{}",
+        content
+    );
+}
+
+#[cfg(feature = "kv")]
+#[test]
+fn test_key_values() {
+    let content = log_with(
+        "kv.log",
+        ftlog::builder()
+            .with_thread(false)
+            .with_source_location(false),
+        &Record::builder()
+            .level(Level::Info)
+            .key_values(&[("user_id", 42), ("limit", 0)])
+            .args(format_args!("logged in"))
+            .build(),
+    );
+    assert!(
+        content.ends_with(" INFO user_id=42 logged in\n"),
+        "{}",
+        content
+    );
+}
+
+#[cfg(feature = "kv")]
+#[test]
+fn test_reused_buffers() {
+    let path = std::env::temp_dir().join(format!(
+        "ftlog-default-format-{}-reused.log",
+        std::process::id()
+    ));
+    let logger = ftlog::builder()
+        .with_thread(false)
+        .with_source_location(false)
+        .root(FileAppender::new(&path))
+        .build()
+        .expect("logger build failed");
+    for i in 0..100 {
+        let _request = (i % 2 == 0).then(|| ftlog::context::insert("request_id", i));
+        let user = [("user_id", i)];
+        let key_values: &[(&str, i32)] = if i % 3 == 0 { &user } else { &[] };
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .key_values(&key_values)
+                .args(format_args!("record {}", i))
+                .build(),
+        );
+    }
+    logger.flush();
+
+    let content = read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 100);
+    for (i, line) in lines.iter().enumerate() {
+        let mut expected = String::from(" INFO");
+        if i % 2 == 0 {
+            expected += &format!(" request_id={}", i);
+        }
+        if i % 3 == 0 {
+            expected += &format!(" user_id={}", i);
+        }
+        expected += &format!(" record {}", i);
+        assert!(line.ends_with(&expected), "{}", line);
+    }
+}
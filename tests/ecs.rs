@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::EcsFormatter;
+use ftlog::Level;
+use log::{Log, Record};
+use serde_json::{json, Value};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_ecs_formatter() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(EcsFormatter)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Error)
+            .target("db")
+            .file(Some("src/db.rs"))
+            .line(Some(42))
+            .key_values(&[
+                ("error", "write failed"),
+                ("caused_by", "disk full"),
+                ("table", "users"),
+            ])
+            .args(format_args!("db write failed"))
+            .build(),
+    );
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let mut record: Value = serde_json::from_str(content.trim_end()).unwrap();
+    let timestamp = record
+        .as_object_mut()
+        .unwrap()
+        .remove("@timestamp")
+        .unwrap();
+    assert!(timestamp.as_str().unwrap().ends_with('Z'), "{}", timestamp);
+    let mut expected = json!({
+        "log.level": "ERROR",
+        "message": "db write failed",
+        "ecs.version": "8.11.0",
+        "log.logger": "db",
+        "log.origin.file.name": "src/db.rs",
+        "log.origin.file.line": 42,
+        "process.thread.name": "test_ecs_formatter",
+        "error.message": "write failed: disk full",
+        "labels": {"table": "users"},
+    });
+    if !cfg!(feature = "kv") {
+        let expected = expected.as_object_mut().unwrap();
+        expected.remove("error.message");
+        expected.remove("labels");
+    }
+    assert_eq!(record, expected);
+}
@@ -0,0 +1,89 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::enrich::{Enricher, Fields};
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+struct Pod(&'static str);
+
+impl Enricher for Pod {
+    fn enrich(&self, fields: &mut Fields<'_>) {
+        fields.insert("pod", self.0);
+    }
+}
+
+#[test]
+fn test_enrich() {
+    let root = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .enrich(Pod("web-7f9c"))
+        .enrich(|fields: &mut Fields<'_>| {
+            if fields.target().starts_with("db") {
+                fields.insert("region", "eu-west-1");
+            }
+            // kept from the first enricher
+            fields.insert("pod", "other");
+        })
+        .build()
+        .unwrap();
+    log_to!(logger, target: "app", Level::Info, "static");
+    log_to!(logger, target: "db::pool", Level::Warn, "formatted {}", 42);
+    drop(logger);
+
+    let lines = root.lines();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(lines[0].ends_with("] pod=web-7f9c static"), "{}", lines[0]);
+    assert!(
+        lines[1].ends_with("] pod=web-7f9c region=eu-west-1 formatted 42"),
+        "{}",
+        lines[1]
+    );
+}
+
+#[cfg(feature = "kv")]
+#[test]
+fn test_enrich_keeps_call_fields() {
+    let root = Buffer::default();
+    let guard = ftlog::builder()
+        .root(root.clone())
+        .enrich(|fields: &mut Fields<'_>| {
+            let user = fields.get("user").unwrap_or("none").to_uppercase();
+            fields.insert("user", "enricher");
+            fields.insert("owner", user);
+        })
+        .formatter(ftlog::format::Format::Json)
+        .try_init()
+        .unwrap();
+    log::info!(target: "app", user = "alice"; "logged in");
+    drop(guard);
+
+    let lines = root.lines();
+    assert_eq!(lines.len(), 1, "{:?}", lines);
+    assert!(lines[0].contains(r#""user":"alice""#), "{}", lines[0]);
+    assert!(lines[0].contains(r#""owner":"ALICE""#), "{}", lines[0]);
+}
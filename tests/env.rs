@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::env::{EnvBuilder, Target};
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_env_builder() {
+    let buffer = Buffer::default();
+    let logger = EnvBuilder::new()
+        .parse_filters("warn,db=debug,db::pool=error")
+        .filter_module("net", log::LevelFilter::Info)
+        .format_timestamp(None)
+        .target(Target::Pipe(Box::new(buffer.clone())))
+        .into_builder()
+        .build()
+        .unwrap();
+    log_to!(logger, target: "app", Level::Info, "below default level");
+    log_to!(logger, target: "app", Level::Warn, "default");
+    log_to!(logger, target: "db::query", Level::Debug, "db");
+    log_to!(logger, target: "db::pool", Level::Warn, "below pool level");
+    log_to!(logger, target: "net", Level::Info, "net");
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(
+        content,
+        "[WARN  app] default\n[DEBUG db::query] db\n[INFO  net] net\n"
+    );
+}
+
+#[test]
+fn test_env_builder_defaults() {
+    let buffer = Buffer::default();
+    let logger = EnvBuilder::new()
+        .format_timestamp_millis()
+        .target(Target::Pipe(Box::new(buffer.clone())))
+        .into_builder()
+        .build()
+        .unwrap();
+    log_to!(logger, target: "app", Level::Warn, "below error");
+    log_to!(logger, target: "app", Level::Error, "failed");
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    // e.g. [2023-06-14T03:13:26.160Z ERROR app] failed
+    assert_eq!(
+        content.len(),
+        "[2023-06-14T03:13:26.160Z ERROR app] failed\n".len()
+    );
+    assert!(content.ends_with("Z ERROR app] failed\n"), "{}", content);
+}
@@ -0,0 +1,66 @@
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::io::{Error, ErrorKind};
+
+use ftlog::appender::FileAppender;
+use ftlog::error::ErrorChain;
+
+#[derive(Debug)]
+struct LoadError(Error);
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to load config")
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+fn test_error_chain() {
+    let dir = std::env::temp_dir().join(format!("ftlog-error-chain-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("error.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .try_init()
+        .expect("logger build or set failed");
+    let err = LoadError(Error::new(ErrorKind::NotFound, "no such file"));
+    assert_eq!(
+        ErrorChain(&err).to_string(),
+        "failed to load config: no such file"
+    );
+    ftlog::error_chain!(err, "startup aborted after {} tries", 3);
+    ftlog::error_chain!(target: "config", err.0, "no source");
+    drop(guard);
+
+    // a backtrace follows the fields when enabled by `RUST_BACKTRACE`
+    let content = read_to_string(&path).unwrap();
+    assert!(content.contains(" ERROR "), "{}", content);
+    assert!(
+        content.contains("startup aborted after 3 tries\n"),
+        "{}",
+        content
+    );
+    #[cfg(feature = "kv")]
+    {
+        assert!(
+            content.contains(" error=failed to load config caused_by=no such file "),
+            "{}",
+            content
+        );
+        assert!(content.contains(" error=no such file "), "{}", content);
+        assert!(
+            !content.contains("caused_by=no such file no source"),
+            "{}",
+            content
+        );
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "kv")]
+use std::fmt::{Display, Formatter};
+use std::io::{Error, ErrorKind, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct LoadError(Error);
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to load config")
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+fn test_error_shorthand() {
+    let buffer = Buffer::default();
+    let guard = ftlog::builder()
+        .formatter(ftlog::format::Format::Json)
+        .root(buffer.clone())
+        .try_init()
+        .unwrap();
+    let e = LoadError(Error::new(ErrorKind::NotFound, "no such file"));
+    ftlog::error!(err = ?e; "db write failed");
+    ftlog::error!(target: "db", error = e);
+    ftlog::error!("plain {}", 1);
+    drop(guard);
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(buffer.0.lock().unwrap().clone())
+        .unwrap()
+        .lines()
+        .map(|x| serde_json::from_str(x).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    let fields = &lines[0]["fields"];
+    assert_eq!(lines[0]["message"], "db write failed");
+    assert_eq!(fields["error"], "failed to load config");
+    assert_eq!(fields["caused_by"], "no such file");
+    assert_eq!(
+        fields["error_debug"],
+        "LoadError(Custom { kind: NotFound, error: \"no such file\" })"
+    );
+    assert_eq!(lines[1]["target"], "db");
+    assert_eq!(lines[1]["message"], "");
+    assert_eq!(lines[2]["message"], "plain 1");
+    assert!(lines[2].get("fields").is_none());
+}
@@ -0,0 +1,43 @@
+use std::fs::read_to_string;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use ftlog::appender::FileAppender;
+use log::{Level, Log, Metadata, Record};
+
+static VERBOSE_DB: AtomicBool = AtomicBool::new(false);
+
+#[test]
+fn test_filter_fn() {
+    let path = std::env::temp_dir().join(format!("ftlog-filter-{}.log", std::process::id()));
+    let logger = ftlog::builder()
+        .filter_fn(|metadata| {
+            !metadata.target().starts_with("db") || VERBOSE_DB.load(Ordering::Relaxed)
+        })
+        .root(FileAppender::new(&path))
+        .build()
+        .expect("logger build failed");
+    let log = |target: &str, msg: &str| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target(target)
+                .args(format_args!("{}", msg))
+                .build(),
+        )
+    };
+
+    let db = Metadata::builder().level(Level::Info).target("db").build();
+    assert!(!logger.enabled(&db));
+    log("db", "suppressed");
+    log("app", "kept");
+    VERBOSE_DB.store(true, Ordering::Relaxed);
+    assert!(logger.enabled(&db));
+    log("db", "enabled at runtime");
+    logger.flush();
+
+    let content = read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(!content.contains("suppressed"), "{}", content);
+    assert!(content.contains("kept"), "{}", content);
+    assert!(content.contains("enabled at runtime"), "{}", content);
+}
@@ -0,0 +1,58 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Lines become visible only when flushed
+#[derive(Clone, Default)]
+struct Buffer {
+    pending: Arc<Mutex<Vec<u8>>>,
+    flushed: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        self.flushed.lock().unwrap().extend_from_slice(&pending);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_concurrent_flush() {
+    let buffer = Buffer::default();
+    let guard = ftlog::builder()
+        .root(buffer.clone())
+        .workers(2)
+        .flush_interval(std::time::Duration::from_secs(3600))
+        .try_init()
+        .expect("logger build or set failed");
+
+    let threads = (0..8)
+        .map(|t| {
+            let buffer = buffer.clone();
+            std::thread::spawn(move || {
+                for round in 0..50 {
+                    for i in 0..20 {
+                        log::info!("thread {} round {} record {}", t, round, i);
+                    }
+                    log::logger().flush();
+                    let flushed = buffer.flushed.lock().unwrap();
+                    let flushed = String::from_utf8_lossy(&flushed);
+                    let last = format!("thread {} round {} record 19\n", t, round);
+                    assert!(flushed.contains(&last), "{} missing after flush", last);
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    drop(guard);
+    // flushing after shutdown returns at once
+    log::logger().flush();
+}
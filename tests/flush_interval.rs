@@ -0,0 +1,45 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use ftlog::{log_to, Level, LevelFilter};
+
+/// Appender counting flushes
+#[derive(Clone, Default)]
+struct Flushes(Arc<AtomicUsize>);
+
+impl Write for Flushes {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_flush_interval() {
+    let (root, slow) = (Flushes::default(), Flushes::default());
+    let logger = ftlog::builder()
+        .flush_interval(Duration::from_millis(10))
+        .root(root.clone())
+        .attach("slow", slow.clone(), LevelFilter::Trace)
+        .appender_flush_interval("slow", Duration::from_secs(3600))
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "record");
+    std::thread::sleep(Duration::from_millis(300));
+
+    assert!(root.0.load(Ordering::SeqCst) > 0);
+    assert_eq!(slow.0.load(Ordering::SeqCst), 0);
+
+    let err = ftlog::builder()
+        .appender_flush_interval("missing", Duration::from_secs(1))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
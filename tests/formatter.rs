@@ -0,0 +1,207 @@
+use std::fs::read_to_string;
+use std::io::Write;
+
+use ftlog::appender::FileAppender;
+use ftlog::format::{LogRecord, RecordFormatter};
+
+struct Pipe;
+impl RecordFormatter for Pipe {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        write!(
+            buf,
+            "{}|{}|{}|{}|{}",
+            record.level(),
+            record.target(),
+            record.module_path().unwrap_or("-"),
+            record.thread().unwrap_or("-"),
+            record.missed().unwrap_or(-1),
+        )?;
+        for (key, value) in record.key_values() {
+            write!(buf, "|{}={}", key, value)?;
+        }
+        write!(buf, "|{}", record.args())
+    }
+}
+
+#[test]
+fn test_record_formatter() {
+    let dir = std::env::temp_dir().join(format!("ftlog-formatter-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("formatter.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .formatter(Pipe)
+        .global_field("service", "billing")
+        .try_init()
+        .expect("logger build or set failed");
+    std::thread::Builder::new()
+        .name("worker".to_string())
+        .spawn(|| {
+            log::info!(target: "app", user = "alice", id = 3; "logged in");
+            log::warn!(limit = 1000; "limited {}", 1);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    drop(guard);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    let key_values = if cfg!(feature = "kv") {
+        "|user=alice|id=3"
+    } else {
+        ""
+    };
+    assert_eq!(
+        lines,
+        [
+            format!(
+                "INFO|app|formatter|worker|-1|service=billing{}|logged in",
+                key_values
+            ),
+            "WARN|formatter|formatter|worker|0|service=billing|limited 1".to_string(),
+        ]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_format_for_target() {
+    let dir = std::env::temp_dir().join(format!("ftlog-target-format-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("target.log");
+
+    let logger = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .format_for_target("access", Pipe)
+        .format_for_target("access::json", ftlog::format::Format::Json)
+        .build()
+        .unwrap();
+    ftlog::log_to!(logger, target: "access", log::Level::Info, "GET /");
+    ftlog::log_to!(logger, target: "access::json", log::Level::Info, "GET /json");
+    ftlog::log_to!(logger, target: "app", log::Level::Info, "human-readable");
+    drop(logger);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", content);
+    assert!(
+        lines[0].starts_with("INFO|access|formatter|"),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[0].ends_with("|GET /"), "{}", lines[0]);
+    assert!(lines[1].starts_with('{'), "{}", lines[1]);
+    assert!(
+        lines[1].contains(r#""message":"GET /json""#),
+        "{}",
+        lines[1]
+    );
+    assert!(lines[2].contains(" INFO "), "{}", lines[2]);
+    assert!(lines[2].ends_with("] human-readable"), "{}", lines[2]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_appender_formatter() {
+    let dir = std::env::temp_dir().join(format!("ftlog-appender-format-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pretty = dir.join("pretty.log");
+    let default = dir.join("default.log");
+
+    let logger = ftlog::builder()
+        .root(FileAppender::new(&pretty))
+        .appender_formatter("root", ftlog::format::PrettyFormatter::new())
+        .attach("plain", FileAppender::new(&default), log::LevelFilter::Info)
+        .build()
+        .unwrap();
+    ftlog::log_to!(logger, target: "app", log::Level::Warn, "disk almost full\nat /var");
+    drop(logger);
+
+    // colors are removed for files
+    let content = read_to_string(&pretty).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", content);
+    assert!(lines[0].starts_with("  "), "{}", lines[0]);
+    assert!(
+        lines[0].ends_with("  WARN app: disk almost full"),
+        "{}",
+        lines[0]
+    );
+    assert_eq!(lines[1], "    at /var");
+    assert!(
+        lines[2].starts_with("    at tests/formatter.rs:"),
+        "{}",
+        lines[2]
+    );
+
+    let content = read_to_string(&default).unwrap();
+    assert!(content.contains("ms WARN "), "{}", content);
+    assert!(
+        content.ends_with("] disk almost full\nat /var\n"),
+        "{}",
+        content
+    );
+
+    let err = ftlog::builder()
+        .appender_formatter("missing", ftlog::format::Format::Json)
+        .build()
+        .err();
+    assert!(err.is_some());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "kv")]
+#[test]
+fn test_pretty_fields() {
+    let dir = std::env::temp_dir().join(format!("ftlog-pretty-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("pretty.log");
+
+    let logger = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .formatter(ftlog::format::PrettyFormatter::new().color(false))
+        .build()
+        .unwrap();
+    let key_values = [
+        ("error", "failed to load config"),
+        ("caused_by", "no such file: os error 2"),
+        ("user", "alice"),
+    ];
+    log::Log::log(
+        &logger,
+        &log::Record::builder()
+            .level(log::Level::Error)
+            .target("app")
+            .key_values(&key_values)
+            .args(format_args!("startup aborted"))
+            .build(),
+    );
+    drop(logger);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert!(
+        lines[0].ends_with(" ERROR app: startup aborted"),
+        "{}",
+        content
+    );
+    assert_eq!(
+        &lines[2..],
+        [
+            "    error: failed to load config",
+            "    caused_by:",
+            "       0: no such file",
+            "       1: os error 2",
+            "    user: alice",
+        ],
+        "{}",
+        content
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ftlog::governor::Governor;
+use ftlog::{log_to, Level, LevelFilter};
+
+/// Buffer slowing down log thread while `slow` is set
+#[derive(Clone, Default)]
+struct SlowBuffer {
+    content: Arc<Mutex<Vec<u8>>>,
+    slow: Arc<AtomicBool>,
+}
+
+impl Write for SlowBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.slow.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        self.content.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_governor() {
+    let buffer = SlowBuffer::default();
+    buffer.slow.store(true, Ordering::Relaxed);
+    let logger = ftlog::builder()
+        .max_log_level(LevelFilter::Debug)
+        .governor(Governor::new(10, Duration::from_millis(50), LevelFilter::Info).low_water(0))
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    for i in 0..200 {
+        log_to!(logger, Level::Debug, "backlog {}", i);
+    }
+    std::thread::sleep(Duration::from_millis(300));
+    log_to!(logger, Level::Debug, "throttled");
+    log_to!(logger, Level::Info, "under pressure");
+
+    buffer.slow.store(false, Ordering::Relaxed);
+    std::thread::sleep(Duration::from_millis(300));
+    log_to!(logger, Level::Debug, "restored");
+    drop(logger);
+
+    let content = String::from_utf8(buffer.content.lock().unwrap().clone()).unwrap();
+    let lines = content
+        .lines()
+        .filter(|x| !x.contains(" backlog "))
+        .map(|x| x.split_once("] ").unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "log queue stayed above 10 records for 50ms, dropping records below INFO",
+            "under pressure",
+            "log queue stayed below 0 records for 50ms, restoring records below INFO",
+            "restored"
+        ]
+    );
+    assert!(
+        content.contains(" WARN test_governor [src/lib.rs:"),
+        "{}",
+        content
+    );
+}
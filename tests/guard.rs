@@ -0,0 +1,30 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+
+#[test]
+fn test_guard_drop() {
+    let dir = std::env::temp_dir().join(format!("ftlog-guard-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("guard.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .unbounded()
+        .shutdown_timeout(std::time::Duration::from_secs(10))
+        .try_init()
+        .expect("logger build or set failed");
+    for i in 0..10_000 {
+        ftlog::info!("trailing record {}", i);
+    }
+    // no explicit flush, dropping the guard writes all queued messages
+    drop(guard);
+    ftlog::info!("ignored after drop");
+
+    let content = read_to_string(&path).unwrap();
+    assert_eq!(content.lines().count(), 10_000);
+    assert!(content.contains("trailing record 9999"));
+    assert!(!content.contains("ignored after drop"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
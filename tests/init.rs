@@ -0,0 +1,36 @@
+use ftlog::appender::FileAppender;
+use ftlog::{AppenderError, ConfigError, InitError};
+
+#[test]
+fn test_try_init_error() {
+    let err = ftlog::builder()
+        .filter(|_, _, _| true, "missing")
+        .try_init()
+        .err();
+    assert!(
+        matches!(
+            &err,
+            Some(InitError::InvalidConfig(ConfigError::UnknownAppender(name))) if name == "missing"
+        ),
+        "{:?}",
+        err
+    );
+
+    let _guard = ftlog::builder().try_init().expect("first init failed");
+    let err = ftlog::builder().try_init().err();
+    assert!(matches!(err, Some(InitError::AlreadySet(_))), "{:?}", err);
+}
+
+#[test]
+fn test_file_appender_error() {
+    let path = std::env::temp_dir()
+        .join(format!("ftlog-missing-{}", std::process::id()))
+        .join("app.log");
+    let err = FileAppender::try_new(&path).err();
+    assert!(
+        matches!(&err, Some(AppenderError::Open { path: x, .. }) if *x == path),
+        "{:?}",
+        err
+    );
+    assert!(err.unwrap().to_string().contains("app.log"));
+}
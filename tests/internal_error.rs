@@ -0,0 +1,114 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::appender::Sink;
+use ftlog::format::{LogRecord, RecordFormatter};
+use ftlog::{log_to, InternalError, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Broken;
+
+impl Write for Broken {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Panics on records saying `boom`
+struct Fragile;
+
+impl RecordFormatter for Fragile {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        if record.args() == "boom" {
+            panic!("formatter exploded");
+        }
+        write!(buf, "{}", record.args())
+    }
+}
+
+#[test]
+fn test_internal_error_hook() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let collected = errors.clone();
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(Fragile)
+        .root(buffer.clone())
+        .appender("broken", Sink::from(Broken))
+        .filter(|_, _, target| target == "broken", "broken")
+        .on_internal_error(move |e| collected.lock().unwrap().push(e.to_string()))
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "before");
+    log_to!(logger, Level::Info, "boom");
+    log_to!(logger, Level::Info, "after");
+    log_to!(logger, target: "broken", Level::Info, "lost");
+    drop(logger);
+
+    // log thread survives the panic
+    let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(written, "before\nafter\n");
+    let errors = errors.lock().unwrap();
+    assert!(
+        errors
+            .iter()
+            .any(|x| x.contains("formatter exploded") && x.contains("internal_error")),
+        "{:?}",
+        errors
+    );
+    assert!(
+        errors
+            .iter()
+            .any(|x| x.starts_with("write to appender broken failed")),
+        "{:?}",
+        errors
+    );
+
+    let e = InternalError::Format {
+        target: "app".to_string(),
+        message: "oops".to_string(),
+    };
+    assert!(std::error::Error::source(&e).is_none());
+}
+
+#[test]
+fn test_internal_error_hook_per_logger() {
+    let build = |errors: &Arc<Mutex<Vec<String>>>| {
+        let collected = errors.clone();
+        ftlog::builder()
+            .root(Sink::from(Broken))
+            .on_internal_error(move |e| collected.lock().unwrap().push(e.to_string()))
+            .build()
+            .unwrap()
+    };
+    let first_errors = Arc::new(Mutex::new(Vec::new()));
+    let second_errors = Arc::new(Mutex::new(Vec::new()));
+    let first = build(&first_errors);
+    // built later, must not take over the handler of `first`
+    let second = build(&second_errors);
+    log_to!(first, Level::Info, "first");
+    drop(first);
+    log_to!(second, Level::Info, "second");
+    log_to!(second, Level::Info, "second");
+    drop(second);
+
+    assert_eq!(first_errors.lock().unwrap().len(), 1);
+    assert_eq!(second_errors.lock().unwrap().len(), 2);
+}
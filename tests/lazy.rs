@@ -0,0 +1,59 @@
+#![cfg(feature = "kv")]
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ftlog::lazy;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_lazy_values() {
+    let buffer = Buffer::default();
+    let _guard = ftlog::builder()
+        .max_log_level(ftlog::LevelFilter::Info)
+        .root(buffer.clone())
+        .try_init()
+        .expect("logger build or set failed");
+
+    let calls = AtomicUsize::new(0);
+    let summary = || {
+        calls.fetch_add(1, Ordering::Relaxed);
+        "3 items"
+    };
+    log::debug!(payload = lazy(summary); "filtered");
+    log::info!(payload = lazy(summary); "logged");
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    let thread = Arc::new(Mutex::new(None));
+    let computed_in = thread.clone();
+    log::info!(payload = lazy(move || {
+        *computed_in.lock().unwrap() = std::thread::current().name().map(String::from);
+        "deferred"
+    })
+    .in_log_thread(); "sent");
+    log::logger().flush();
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", content);
+    assert!(
+        lines[0].ends_with(" payload=3 items logged"),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[1].ends_with(" payload=deferred sent"), "{}", lines[1]);
+    assert_eq!(thread.lock().unwrap().as_deref(), Some("logger"));
+}
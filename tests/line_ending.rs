@@ -0,0 +1,54 @@
+use std::io::Write;
+
+use ftlog::appender::{FileAppender, LineEnding};
+
+#[test]
+fn test_crlf() {
+    let dir = std::env::temp_dir().join(format!("ftlog-crlf-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("crlf.log");
+
+    let mut appender = FileAppender::builder()
+        .path(&path)
+        .line_ending(LineEnding::Crlf)
+        .header(|| "#Fields: message\n".to_string())
+        .build();
+    appender.write_all(b"first\nsecond\r\n").unwrap();
+    appender.flush().unwrap();
+    drop(appender);
+
+    let content = std::fs::read(&path).unwrap();
+    assert_eq!(content, b"#Fields: message\r\nfirst\r\nsecond\r\n");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "encoding")]
+#[test]
+fn test_encoding() {
+    let dir = std::env::temp_dir().join(format!("ftlog-encoding-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("gbk.log");
+
+    let logger = ftlog::builder()
+        .root(
+            FileAppender::builder()
+                .path(&path)
+                .encoding(encoding_rs::GBK)
+                .line_ending(LineEnding::Crlf)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    ftlog::log_to!(logger, log::Level::Info, "日志 ✓");
+    drop(logger);
+
+    let content = std::fs::read(&path).unwrap();
+    let (text, _, errors) = encoding_rs::GBK.decode(&content);
+    assert!(!errors);
+    // characters out of GBK are written as references
+    assert!(text.ends_with("] 日志 &#10003;\r\n"), "{}", text);
+    assert!(std::str::from_utf8(&content).is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -3,9 +3,12 @@ use std::{
     time::Instant,
 };
 
-use ftlog::appender::{Duration, FileAppender, Period};
+use ftlog::{
+    appender::{Duration, FileAppender, Period},
+    LoggerGuard,
+};
 
-pub fn setup() {
+pub fn setup() -> LoggerGuard {
     let logger = ftlog::Builder::new()
         .bounded(10000, true)
         .root(FileAppender::new("./root.log"))
@@ -18,7 +21,7 @@ pub fn setup() {
         )
         .build()
         .expect("logger build failed");
-    logger.init().expect("set logger failed");
+    logger.init().expect("set logger failed")
 }
 
 fn clean(dir: &str) {
@@ -41,7 +44,7 @@ fn clean(dir: &str) {
 #[test]
 fn test_speed() {
     // ~80MB
-    setup();
+    let _guard = setup();
     let elapsed1 = {
         // file
         let now = Instant::now();
@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn content(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[test]
+fn test_batches_keep_order() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .low_contention(true)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let logger = &logger;
+            s.spawn(move || {
+                for i in 0..100 {
+                    log_to!(logger, Level::Info, "thread {} record {}", t, i);
+                }
+            });
+        }
+    });
+    drop(logger);
+
+    let content = buffer.content();
+    assert_eq!(content.lines().count(), 400);
+    for t in 0..4 {
+        let records: Vec<usize> = content
+            .lines()
+            .filter_map(|x| x.split_once(&format!(" thread {} record ", t)))
+            .map(|(_, i)| i.parse().unwrap())
+            .collect();
+        assert_eq!(records, (0..100).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn test_lingering_batch() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .low_contention(true)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "lonely");
+    let start = Instant::now();
+    while !buffer.content().contains(" lonely\n") {
+        assert!(start.elapsed() < Duration::from_secs(5), "batch never sent");
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    log_to!(logger, Level::Info, "pending");
+    ftlog::Log::flush(&logger);
+    assert!(buffer.content().contains(" pending\n"));
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "message_filter")]
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{log_to, InitError, Level, LevelFilter};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn rules() -> ftlog::Builder {
+    ftlog::builder()
+        .allow_message("connection reset by database")
+        .deny_message("^connection reset")
+        .downgrade_message(r"retrying in \d+ms", Level::Debug)
+}
+
+fn log(builder: ftlog::Builder) -> String {
+    let buffer = Buffer::default();
+    let logger = builder.root(buffer.clone()).build().unwrap();
+    log_to!(logger, Level::Warn, "connection reset by {}", "peer");
+    log_to!(logger, Level::Warn, "connection reset by database");
+    log_to!(logger, Level::Warn, "retrying in {}ms", 100);
+    log_to!(logger, Level::Warn, "disk almost full");
+    drop(logger);
+    let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    text
+}
+
+#[test]
+fn test_message_rules() {
+    let text = log(rules());
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", text);
+    assert!(
+        lines[0].ends_with("connection reset by database"),
+        "{}",
+        text
+    );
+    assert!(lines[1].contains(" DEBUG "), "{}", text);
+    assert!(lines[1].ends_with("retrying in 100ms"), "{}", text);
+    assert!(lines[2].ends_with("disk almost full"), "{}", text);
+
+    // downgraded records are written by appenders of their new level
+    let text = log(rules().root_log_level(LevelFilter::Info));
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", text);
+    assert!(!text.contains("retrying"), "{}", text);
+
+    let text = log(rules().formatter(Format::Json));
+    assert!(text.contains(r#""level":"DEBUG""#), "{}", text);
+    assert!(!text.contains("by peer"), "{}", text);
+}
+
+#[test]
+fn test_invalid_pattern() {
+    let err = ftlog::builder().deny_message("(").try_init().err();
+    assert!(
+        matches!(err, Some(InitError::InvalidConfig(_))),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "metrics")]
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use ftlog::appender::{FileAppender, Period};
+use ftlog::clock::ManualClock;
+use ftlog::{log_to, Level, LogTimezone};
+use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+
+/// Recorder keeping counters by name and labels
+#[derive(Clone, Default)]
+struct Counters(Arc<Mutex<HashMap<String, Arc<AtomicU64>>>>);
+
+impl Counters {
+    fn get(&self, key: &str) -> u64 {
+        self.0
+            .lock()
+            .unwrap()
+            .get(key)
+            .map_or(0, |x| x.load(Ordering::Relaxed))
+    }
+}
+
+impl Recorder for Counters {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        let mut name = key.name().to_string();
+        for label in key.labels() {
+            name.push_str(&format!("{{{}={}}}", label.key(), label.value()));
+        }
+        Counter::from_arc(self.0.lock().unwrap().entry(name).or_default().clone())
+    }
+
+    fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+#[test]
+fn test_metrics_counters() {
+    let counters = Counters::default();
+    metrics::set_global_recorder(counters.clone()).unwrap();
+
+    let dir = std::env::temp_dir().join(format!("ftlog-metrics-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // 2022-10-24T16:00:00Z
+    let clock = Arc::new(ManualClock::new(
+        UNIX_EPOCH + Duration::from_secs(1_666_627_200),
+    ));
+    let logger = ftlog::builder()
+        .clock(clock.clone())
+        .root(
+            FileAppender::builder()
+                .path(dir.join("app.log"))
+                .rotate(Period::Minute)
+                .timezone(LogTimezone::Utc)
+                .clock(clock.clone())
+                .build(),
+        )
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "first");
+    log_to!(logger, Level::Warn, "second");
+    ftlog::Log::flush(&logger);
+    clock.advance(Duration::from_secs(61));
+    log_to!(logger, Level::Info, "third");
+    drop(logger);
+
+    assert_eq!(counters.get("ftlog_records_total{level=info}"), 2);
+    assert_eq!(counters.get("ftlog_records_total{level=warn}"), 1);
+    assert_eq!(counters.get("ftlog_rotations_total"), 1);
+    assert_eq!(counters.get("ftlog_dropped_total"), 0);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
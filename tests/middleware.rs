@@ -0,0 +1,110 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::middleware::{Action, Middleware, OwnedRecord};
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Downgrade noisy warnings of a dependency
+struct Downgrade;
+
+impl Middleware for Downgrade {
+    fn process(&self, record: &mut OwnedRecord) -> Action {
+        if record.target.starts_with("hyper") && record.level == Level::Warn {
+            record.level = Level::Info;
+            record.message.insert_str(0, "(downgraded) ");
+            return Action::Modify;
+        }
+        Action::Keep
+    }
+}
+
+#[test]
+fn test_middleware() {
+    let root = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .middleware(Downgrade)
+        .middleware(|record: &mut OwnedRecord| {
+            if record.target == "healthz" {
+                return Action::Drop;
+            }
+            // discarded, since no middleware returns `Modify` for the record
+            if record.target == "app" {
+                record.message.clear();
+            }
+            Action::Keep
+        })
+        .build()
+        .unwrap();
+    log_to!(logger, target: "hyper::proto", Level::Warn, "connection closed");
+    log_to!(logger, target: "healthz", Level::Info, "ok");
+    log_to!(logger, target: "app", Level::Warn, "kept");
+    drop(logger);
+
+    let lines = root.lines();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(
+        lines[0].contains(" INFO ") && lines[0].ends_with("] (downgraded) connection closed"),
+        "{}",
+        lines[0]
+    );
+    assert!(
+        lines[1].contains(" WARN ") && lines[1].ends_with("] kept"),
+        "{}",
+        lines[1]
+    );
+}
+
+#[cfg(feature = "kv")]
+#[test]
+fn test_middleware_routing_hint() {
+    let root = Buffer::default();
+    let payments = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .route_field("team", "payments", payments.clone())
+        .middleware(|record: &mut OwnedRecord| {
+            if !record.target.starts_with("payments") {
+                return Action::Keep;
+            }
+            record.key_values.insert("team", "payments");
+            record.target = "billing".to_string();
+            Action::Modify
+        })
+        .formatter(ftlog::format::Format::Json)
+        .build()
+        .unwrap();
+    log_to!(logger, target: "payments::stripe", Level::Info, "charged");
+    log_to!(logger, target: "app", Level::Info, "other");
+    drop(logger);
+
+    let lines = payments.lines();
+    assert_eq!(lines.len(), 1, "{:?}", lines);
+    assert!(lines[0].contains(r#""target":"billing""#), "{}", lines[0]);
+    assert!(lines[0].contains(r#""team":"payments""#), "{}", lines[0]);
+    assert_eq!(root.lines().len(), 1);
+}
@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{log_to, Level, Multiline};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log(builder: ftlog::Builder) -> String {
+    let buffer = Buffer::default();
+    let logger = builder.root(buffer.clone()).build().unwrap();
+    log_to!(
+        logger,
+        Level::Error,
+        "query failed:\n{}",
+        "SELECT *\nFROM users"
+    );
+    log_to!(logger, Level::Info, "static\nmessage");
+    drop(logger);
+    let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    text
+}
+
+#[test]
+fn test_multiline() {
+    let text = log(ftlog::builder());
+    assert_eq!(text.lines().count(), 5, "{}", text);
+
+    let text = log(ftlog::builder().multiline(Multiline::Escape));
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", text);
+    assert!(
+        lines[0].ends_with("] query failed:\\nSELECT *\\nFROM users"),
+        "{}",
+        text
+    );
+    assert!(lines[1].ends_with("] static\\nmessage"), "{}", text);
+
+    let text = log(ftlog::builder().multiline(Multiline::Indent("\t")));
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 5, "{}", text);
+    assert!(lines[0].ends_with("] query failed:"), "{}", text);
+    assert_eq!(lines[1..3], ["\tSELECT *", "\tFROM users"]);
+    assert_eq!(lines[4], "\tmessage");
+
+    let text = log(ftlog::builder()
+        .formatter(Format::Json)
+        .multiline(Multiline::Indent("  ")));
+    assert!(
+        text.contains(r#""message":"query failed:\n  SELECT *\n  FROM users""#),
+        "{}",
+        text
+    );
+}
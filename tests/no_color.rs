@@ -0,0 +1,33 @@
+use std::io::{stderr, IsTerminal};
+
+use ftlog::appender::console::{ColorChoice, ConsoleAppender};
+use ftlog::appender::Appender;
+
+#[test]
+fn test_color_choice() {
+    let terminal = stderr().is_terminal();
+    std::env::remove_var("NO_COLOR");
+    std::env::remove_var("CLICOLOR_FORCE");
+    assert_eq!(ConsoleAppender::stderr().colors(), terminal);
+
+    std::env::set_var("CLICOLOR_FORCE", "1");
+    assert!(ConsoleAppender::stderr().colors());
+    std::env::set_var("CLICOLOR_FORCE", "0");
+    assert_eq!(ConsoleAppender::stderr().colors(), terminal);
+
+    // NO_COLOR wins over CLICOLOR_FORCE
+    std::env::set_var("CLICOLOR_FORCE", "1");
+    std::env::set_var("NO_COLOR", "1");
+    assert!(!ConsoleAppender::stderr().colors());
+    std::env::set_var("NO_COLOR", "");
+    assert!(ConsoleAppender::stderr().colors());
+
+    // explicit choices ignore the environment
+    std::env::set_var("NO_COLOR", "1");
+    let always = ConsoleAppender::stderr().color(ColorChoice::Always);
+    assert!(always.colors());
+    std::env::remove_var("NO_COLOR");
+    let never = ConsoleAppender::stderr().color(ColorChoice::Never);
+    assert!(!never.colors());
+    std::env::remove_var("CLICOLOR_FORCE");
+}
@@ -0,0 +1,33 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+
+#[test]
+fn test_once_summary() {
+    let dir = std::env::temp_dir().join(format!("ftlog-once-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("once.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .once_summary(true)
+        .try_init()
+        .expect("logger build or set failed");
+    for i in 0..10 {
+        ftlog::warn_once!("deprecated {}", i);
+        ftlog::debug_once!("filtered by level");
+    }
+    drop(guard);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", content);
+    assert!(lines[0].ends_with(" deprecated 0"), "{}", lines[0]);
+    assert!(
+        lines[1].ends_with(" suppressed 9 subsequent occurrences"),
+        "{}",
+        lines[1]
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
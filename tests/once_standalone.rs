@@ -0,0 +1,38 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+
+#[test]
+fn test_once_summary_standalone() {
+    let dir = std::env::temp_dir().join(format!("ftlog-once-standalone-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let _guard = ftlog::builder()
+        .root(FileAppender::new(dir.join("global.log")))
+        .try_init()
+        .expect("logger build or set failed");
+    let standalone = ftlog::builder()
+        .root(FileAppender::new(dir.join("standalone.log")))
+        .once_summary(true)
+        .build()
+        .unwrap();
+    for i in 0..10 {
+        ftlog::warn_once!("deprecated {}", i);
+    }
+    log::logger().flush();
+    // the summary is written by the logger asking for it, not the global one
+    drop(standalone);
+
+    let global = read_to_string(dir.join("global.log")).unwrap();
+    assert_eq!(global.lines().count(), 1, "{}", global);
+    assert!(global.ends_with(" deprecated 0\n"), "{}", global);
+    let standalone = read_to_string(dir.join("standalone.log")).unwrap();
+    assert_eq!(standalone.lines().count(), 1, "{}", standalone);
+    assert!(
+        standalone.ends_with(" suppressed 9 subsequent occurrences\n"),
+        "{}",
+        standalone
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
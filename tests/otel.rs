@@ -0,0 +1,55 @@
+#![cfg(feature = "otel")]
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::{log_to, Level};
+use opentelemetry::trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState};
+use opentelemetry::Context;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_otel_ids() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder().root(buffer.clone()).build().unwrap();
+    log_to!(logger, Level::Info, "outside span");
+    {
+        let span_context = SpanContext::new(
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap(),
+            SpanId::from_hex("00f067aa0ba902b7").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        let _attached = Context::current()
+            .with_remote_span_context(span_context)
+            .attach();
+        let _request = ftlog::context::insert("request_id", 42);
+        log_to!(logger, Level::Info, "inside span");
+    }
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", content);
+    assert!(lines[0].ends_with(" outside span"), "{}", lines[0]);
+    assert!(
+        lines[1].ends_with(
+            " request_id=42 trace_id=4bf92f3577b34da6a3ce929d0e0e4736 span_id=00f067aa0ba902b7 inside span"
+        ),
+        "{}",
+        lines[1]
+    );
+}
@@ -0,0 +1,69 @@
+use std::io::Write;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use ftlog::OverflowPolicy;
+use log::{Level, Log, Record};
+
+/// Appender that blocks on first write until released, to saturate the channel
+struct Gate {
+    entered: Option<mpsc::Sender<()>>,
+    release: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Write for Gate {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(entered) = self.entered.take() {
+            entered.send(()).unwrap();
+            let (released, cond) = &*self.release;
+            let _released = cond
+                .wait_while(released.lock().unwrap(), |released| !*released)
+                .unwrap();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log(logger: &ftlog::Logger, msg: &str) {
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("{}", msg))
+            .build(),
+    );
+}
+
+#[test]
+fn test_on_overflow() {
+    let (entered, wait_entered) = mpsc::channel();
+    let release = Arc::new((Mutex::new(false), Condvar::new()));
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let recorded = calls.clone();
+    let logger = ftlog::builder()
+        .root(Gate {
+            entered: Some(entered),
+            release: release.clone(),
+        })
+        .channel_capacity(1)
+        .overflow_policy(OverflowPolicy::DropNewest)
+        .on_overflow(move |dropped| recorded.lock().unwrap().push(dropped))
+        .build()
+        .expect("logger build failed");
+
+    log(&logger, "first");
+    wait_entered.recv().unwrap();
+    log(&logger, "queued");
+    for _ in 0..3 {
+        log(&logger, "discarded");
+    }
+    *release.0.lock().unwrap() = true;
+    release.1.notify_all();
+    logger.flush();
+
+    // the first drop is reported at once, later ones wait for the next second
+    assert_eq!(*calls.lock().unwrap(), vec![1]);
+    assert_eq!(logger.stats().dropped, 3);
+}
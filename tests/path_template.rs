@@ -0,0 +1,26 @@
+use std::io::Write;
+
+use ftlog::appender::FileAppender;
+use ftlog::AppenderError;
+
+#[test]
+fn test_path_placeholders() {
+    let dir = std::env::temp_dir().join(format!("ftlog-template-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::env::set_var("FTLOG_TEST_INSTANCE", "pod-7");
+
+    let mut appender = FileAppender::new(dir.join("${FTLOG_TEST_INSTANCE}-${PID}.log"));
+    appender.write_all(b"hello\n").unwrap();
+    drop(appender);
+    let path = dir.join(format!("pod-7-{}.log", std::process::id()));
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "hello\n");
+
+    let err = FileAppender::try_new(dir.join("${FTLOG_TEST_UNSET}.log")).err();
+    assert!(
+        matches!(&err, Some(AppenderError::Placeholder { name, .. }) if name == "FTLOG_TEST_UNSET"),
+        "{:?}",
+        err
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,48 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::per_thread;
+use ftlog::{log_to, Level};
+
+#[test]
+fn test_per_thread() {
+    let dir = std::env::temp_dir().join(format!("ftlog-per-thread-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.log");
+    let logger = ftlog::builder().per_thread(&path).build().unwrap();
+
+    std::thread::scope(|s| {
+        for t in 0..3 {
+            let logger = &logger;
+            s.spawn(move || {
+                for i in 0..100 {
+                    log_to!(logger, Level::Info, "thread {} record {}", t, i);
+                }
+                log_to!(logger, Level::Warn, "thread {} done\nbye", t);
+            });
+        }
+    });
+    drop(logger);
+
+    let files = per_thread::files(&path).unwrap();
+    assert_eq!(files.len(), 3, "{:?}", files);
+    let first = read_to_string(&files[0]).unwrap();
+    assert_eq!(first.lines().count(), 102, "{}", first);
+
+    let mut merged = Vec::new();
+    per_thread::merge(&path, &mut merged).unwrap();
+    let merged = String::from_utf8(merged).unwrap();
+    let lines: Vec<_> = merged.lines().collect();
+    assert_eq!(lines.len(), 306);
+    let times: Vec<_> = lines
+        .iter()
+        .filter(|x| *x != &"bye")
+        .map(|x| x.split(' ').take(2).collect::<Vec<_>>())
+        .collect();
+    assert!(times.windows(2).all(|w| w[0] <= w[1]), "{}", merged);
+    for t in 0..3 {
+        assert!(merged.contains(&format!(" thread {} record 99\n", t)));
+        assert!(merged.contains(&format!(" thread {} done\nbye\n", t)));
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,74 @@
+use std::io::Write;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use ftlog::OverflowPolicy;
+use log::{Level, Log, Record};
+
+/// Appender that blocks on first write until released, to saturate the channel
+struct Gate {
+    entered: Option<mpsc::Sender<()>>,
+    release: Arc<(Mutex<bool>, Condvar)>,
+    content: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for Gate {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(entered) = self.entered.take() {
+            entered.send(()).unwrap();
+            let (released, cond) = &*self.release;
+            let _released = cond
+                .wait_while(released.lock().unwrap(), |released| !*released)
+                .unwrap();
+        }
+        self.content.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log(logger: &ftlog::Logger, level: Level, msg: &str) {
+    logger.log(
+        &Record::builder()
+            .level(level)
+            .args(format_args!("{}", msg))
+            .build(),
+    );
+}
+
+#[test]
+fn test_priority_reserve() {
+    let (entered, wait_entered) = mpsc::channel();
+    let release = Arc::new((Mutex::new(false), Condvar::new()));
+    let content = Arc::new(Mutex::new(Vec::new()));
+    let logger = ftlog::builder()
+        .root(Gate {
+            entered: Some(entered),
+            release: release.clone(),
+            content: content.clone(),
+        })
+        .channel_capacity(2)
+        .priority_reserve(1)
+        .overflow_policy(OverflowPolicy::DropNewest)
+        .build()
+        .expect("logger build failed");
+
+    log(&logger, Level::Info, "first");
+    wait_entered.recv().unwrap();
+    log(&logger, Level::Info, "queued 1");
+    log(&logger, Level::Info, "queued 2");
+    log(&logger, Level::Warn, "discarded");
+    log(&logger, Level::Error, "reserved");
+    *release.0.lock().unwrap() = true;
+    release.1.notify_all();
+    logger.flush();
+
+    let content = String::from_utf8(content.lock().unwrap().clone()).unwrap();
+    for msg in ["first", "queued 1", "queued 2", "reserved"] {
+        assert!(content.contains(msg), "{}", content);
+    }
+    assert!(!content.contains("discarded"), "{}", content);
+    assert_eq!(logger.stats().dropped, 1);
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "prometheus")]
+use ftlog::prometheus::Collector;
+use ftlog::{log_to, Level};
+use prometheus::{Registry, TextEncoder};
+
+#[test]
+fn test_prometheus_collector() {
+    let logger = ftlog::builder()
+        .root(std::io::sink())
+        .appender("audit", std::io::sink())
+        .build()
+        .unwrap();
+    let registry = Registry::new();
+    registry
+        .register(Box::new(Collector::for_logger(&logger)))
+        .unwrap();
+    for _ in 0..3 {
+        log_to!(logger, Level::Info, "info");
+    }
+    log_to!(logger, Level::Warn, "warn");
+    log::Log::flush(&logger);
+
+    let text = TextEncoder::new()
+        .encode_to_string(&registry.gather())
+        .unwrap();
+    for line in [
+        "ftlog_records_total{level=\"info\"} 3",
+        "ftlog_records_total{level=\"warn\"} 1",
+        "ftlog_records_total{level=\"error\"} 0",
+        "ftlog_dropped_total 0",
+        "ftlog_queue_depth 0",
+        "ftlog_bytes_written_total{appender=\"audit\"} 0",
+        "ftlog_rotations_total 0",
+    ] {
+        assert!(text.lines().any(|x| x == line), "{}\n{}", line, text);
+    }
+    assert!(
+        !text.contains("ftlog_bytes_written_total{appender=\"root\"} 0"),
+        "{}",
+        text
+    );
+}
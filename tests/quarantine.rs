@@ -0,0 +1,95 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ftlog::{log_to, Level, LevelFilter};
+use log::Log;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appender failing writes while `broken`
+#[derive(Clone, Default)]
+struct Flaky {
+    broken: Arc<AtomicBool>,
+    lines: Buffer,
+}
+
+impl Write for Flaky {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.broken.load(Ordering::SeqCst) {
+            return Err(std::io::Error::other("broken pipe"));
+        }
+        self.lines.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_quarantine() {
+    let buffer = Buffer::default();
+    let flaky = Flaky::default();
+    flaky.broken.store(true, Ordering::SeqCst);
+    let logger = ftlog::builder()
+        .root(buffer.clone())
+        .attach("flaky", flaky.clone(), LevelFilter::Trace)
+        .build()
+        .unwrap();
+    for _ in 0..4 {
+        log_to!(logger, Level::Info, "failed");
+    }
+    logger.flush();
+    let stats = logger.stats();
+    let stats = stats.appenders.iter().find(|x| x.name == "flaky").unwrap();
+    assert!(!stats.healthy);
+    assert_eq!(stats.error.as_deref(), Some("broken pipe"));
+
+    flaky.broken.store(false, Ordering::SeqCst);
+    // probed once a second while log thread is idle
+    std::thread::sleep(Duration::from_millis(1500));
+    log_to!(logger, Level::Info, "restored");
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines = content
+        .lines()
+        .map(|line| match line.strip_prefix("-- ") {
+            Some(notice) => notice.to_string(),
+            None => line.rsplit(' ').next().unwrap().to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "failed",
+            "failed",
+            "failed",
+            "appender `flaky` quarantined after 3 failed writes, broken pipe --",
+            "failed",
+            "appender `flaky` restored --",
+            "restored",
+        ],
+        "{}",
+        content
+    );
+    let flaky = String::from_utf8(flaky.lines.0.lock().unwrap().clone()).unwrap();
+    let flaky = flaky.lines().collect::<Vec<_>>();
+    assert_eq!(flaky.len(), 2, "{:?}", flaky);
+    assert_eq!(flaky[0], "-- appender `flaky` restored --");
+    assert!(flaky[1].ends_with(" restored"), "{:?}", flaky);
+}
@@ -0,0 +1,98 @@
+#![cfg(feature = "query")]
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use ftlog::query::Search;
+use ftlog::LogTimezone;
+use time::macros::datetime;
+
+fn search() -> Search {
+    let dir = std::env::temp_dir().join(format!("ftlog-query-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("app-20240101T10.log"),
+        "2024-01-01 10:00:00.000+00 0ms INFO main [src/main.rs:1] start\n\
+         2024-01-01 10:59:59.999+00 0ms ERROR main [src/main.rs:2] panicked\n\
+         stack backtrace:\n\
+         2024-01-01 18:59:59.999+08 0ms INFO main [src/main.rs:3] ticked\n",
+    )
+    .unwrap();
+    let mut gz = GzEncoder::new(
+        std::fs::File::create(dir.join("app-20240101T11.log.gz")).unwrap(),
+        Default::default(),
+    );
+    gz.write_all(
+        b"2024-01-01T11:00:00.5+00:00 0ms INFO main [src/main.rs:4] compressed\n\
+          {\"time\":\"2024-01-01 11:30:00.000+00\",\"level\":\"INFO\",\"message\":\"json\"}\n",
+    )
+    .unwrap();
+    gz.finish().unwrap();
+    std::fs::write(
+        dir.join("app-20240101T12.log"),
+        "2024-01-01 12:00:00.000+00 0ms INFO main [src/main.rs:5] later\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("app.log"), "2024-01-01 11:00:00.000+00 other\n").unwrap();
+    std::fs::write(dir.join("other-20240101T11.log"), "").unwrap();
+    std::fs::write(dir.join("app-2024010111.log"), "").unwrap();
+    Search::new(dir, "app").timezone(LogTimezone::Utc)
+}
+
+fn messages(
+    search: &Search,
+    range: impl std::ops::RangeBounds<time::OffsetDateTime>,
+) -> Vec<String> {
+    search
+        .records(range)
+        .unwrap()
+        .map(|x| {
+            let text = x.unwrap().text;
+            text.split_once("] ")
+                .map_or_else(|| text.clone(), |(_, msg)| msg.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn test_search() {
+    let search = search();
+    let files = search.files().unwrap();
+    let names = files
+        .iter()
+        .map(|x| x.file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "app-20240101T10.log",
+            "app-20240101T11.log.gz",
+            "app-20240101T12.log"
+        ]
+    );
+
+    assert_eq!(
+        messages(&search, ..),
+        [
+            "start",
+            "panicked\nstack backtrace:",
+            "ticked",
+            "compressed",
+            r#"{"time":"2024-01-01 11:30:00.000+00","level":"INFO","message":"json"}"#,
+            "later"
+        ]
+    );
+    assert_eq!(
+        messages(
+            &search,
+            datetime!(2024-01-01 10:30 UTC)..datetime!(2024-01-01 11:30 UTC)
+        ),
+        ["panicked\nstack backtrace:", "ticked", "compressed"]
+    );
+    let records = search
+        .records(datetime!(2024-01-01 11:00:00.5 UTC)..=datetime!(2024-01-01 11:00:00.5 UTC))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(records[0].path.ends_with("app-20240101T11.log.gz"));
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "redact")]
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::redact::{BEARER_TOKEN, CREDIT_CARD, EMAIL};
+use ftlog::{Builder, Level};
+use log::{Log, Record};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log_record(builder: Builder) -> String {
+    let buffer = Buffer::default();
+    let logger = builder
+        .redact(CREDIT_CARD, "****")
+        .redact(EMAIL, "<email>")
+        .redact(BEARER_TOKEN, "Bearer ****")
+        .redact(r"user_(\d+)", "user_$1#")
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .key_values(&[
+                ("contact", "alice@example.com"),
+                ("auth", "bearer eyJhbGciOi.J9"),
+            ])
+            .args(format_args!("user_42 paid with 4111-1111-1111-1111"))
+            .build(),
+    );
+    drop(logger);
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(!content.contains("alice@"), "{}", content);
+    assert!(!content.contains("4111"), "{}", content);
+    assert!(!content.contains("eyJ"), "{}", content);
+    content
+}
+
+#[test]
+fn test_redact() {
+    let line = log_record(ftlog::builder());
+    assert!(line.ends_with("user_42# paid with ****\n"), "{}", line);
+    if cfg!(feature = "kv") {
+        assert!(
+            line.contains("contact=<email> auth=Bearer ****"),
+            "{}",
+            line
+        );
+    }
+
+    let line = log_record(ftlog::builder().formatter(Format::Json));
+    let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(record["message"], "user_42# paid with ****");
+    if cfg!(feature = "kv") {
+        assert_eq!(record["fields"]["contact"], "<email>");
+    }
+
+    let err = ftlog::builder().redact("(", "").build().err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+#[test]
+fn test_rename_target() {
+    let root = Buffer::default();
+    let db = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .rename_target("my_app::internal::db::pool", "db")
+        .rename_target("my_app::internal", "internal")
+        .route("db", db.clone())
+        .format_for_target("db", Format::Json)
+        .build()
+        .unwrap();
+    log_to!(logger, target: "my_app::internal::db::pool", Level::Info, "pool");
+    log_to!(logger, target: "my_app::internal::db::pool::conn", Level::Info, "conn");
+    log_to!(logger, target: "my_app::internal::cache", Level::Info, "cache");
+    log_to!(logger, target: "my_app", Level::Info, "kept");
+    drop(logger);
+
+    let lines = db.lines();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(lines[0].contains(r#""target":"db","#), "{}", lines[0]);
+    assert!(lines[1].contains(r#""target":"db::conn","#), "{}", lines[1]);
+    let lines = root.lines();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert!(lines[0].ends_with("] cache"), "{}", lines[0]);
+    assert!(lines[1].ends_with("] kept"), "{}", lines[1]);
+}
+
+#[test]
+fn test_rename_target_in_record() {
+    let root = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .formatter(Format::Json)
+        .rename_target("my_app::internal", "internal")
+        .build()
+        .unwrap();
+    log_to!(logger, target: "my_app::internal::cache", Level::Info, "cache");
+    drop(logger);
+
+    let lines = root.lines();
+    assert!(
+        lines[0].contains(r#""target":"internal::cache","#),
+        "{}",
+        lines[0]
+    );
+}
@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use ftlog::clock::ManualClock;
+use ftlog::{log_to, Level, LevelFilter, TimeFormat};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_reorder_window() {
+    let buffer = Buffer::default();
+    let clock = Arc::new(ManualClock::new(
+        UNIX_EPOCH + Duration::from_secs(1_666_627_200),
+    ));
+    let logger = Arc::new(
+        ftlog::builder()
+            .max_log_level(LevelFilter::Trace)
+            .clock(clock.clone())
+            .time_format(TimeFormat::UnixEpoch)
+            // kept records are sent after later ones of other threads
+            .crash_context(LevelFilter::Info, 8)
+            .reorder_window(Duration::from_secs(1))
+            .root(buffer.clone())
+            .build()
+            .unwrap(),
+    );
+    log_to!(logger, Level::Debug, "kept");
+    clock.advance(Duration::from_millis(1));
+    let other = logger.clone();
+    std::thread::spawn(move || {
+        log_to!(other, Level::Info, "other");
+    })
+    .join()
+    .unwrap();
+    clock.advance(Duration::from_millis(1));
+    log_to!(logger, Level::Error, "failed");
+    ftlog::Log::flush(&*logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines = content
+        .lines()
+        .map(|line| {
+            let (time, _) = line.split_once(' ').unwrap();
+            (
+                time.to_string(),
+                line.rsplit(' ').next().unwrap().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            ("1666627200000".to_string(), "kept".to_string()),
+            ("1666627200001".to_string(), "other".to_string()),
+            ("1666627200002".to_string(), "failed".to_string()),
+        ],
+        "{}",
+        content
+    );
+}
@@ -0,0 +1,106 @@
+use std::io::Write;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use ftlog::{log_to, Level, Log, OverflowPolicy, QueueKind};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appender that blocks on first write until released, to saturate the queue
+struct Gate {
+    entered: Option<mpsc::Sender<()>>,
+    release: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl Write for Gate {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(entered) = self.entered.take() {
+            entered.send(()).unwrap();
+            let (released, cond) = &*self.release;
+            let _released = cond
+                .wait_while(released.lock().unwrap(), |released| !*released)
+                .unwrap();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_ring_blocking() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .queue(QueueKind::Ring(4))
+        .overflow_policy(OverflowPolicy::Block)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let logger = &logger;
+            s.spawn(move || {
+                for i in 0..250 {
+                    log_to!(logger, Level::Info, "thread {} record {}", t, i);
+                }
+            });
+        }
+    });
+    logger.flush();
+    assert_eq!(logger.stats().dropped, 0);
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(content.lines().count(), 1000);
+    assert!(content.contains(" thread 3 record 249\n"));
+}
+
+#[test]
+fn test_ring_drop_newest() {
+    let (entered, wait_entered) = mpsc::channel();
+    let release = Arc::new((Mutex::new(false), Condvar::new()));
+    let logger = ftlog::builder()
+        .root(Gate {
+            entered: Some(entered),
+            release: release.clone(),
+        })
+        .queue(QueueKind::Ring(2))
+        .overflow_policy(OverflowPolicy::DropNewest)
+        .build()
+        .unwrap();
+
+    log_to!(logger, Level::Info, "first");
+    wait_entered.recv().unwrap();
+    for _ in 0..5 {
+        log_to!(logger, Level::Info, "queued or discarded");
+    }
+    *release.0.lock().unwrap() = true;
+    release.1.notify_all();
+    logger.flush();
+    assert_eq!(logger.stats().dropped, 3);
+}
+
+#[test]
+fn test_ring_unbounded() {
+    let result = ftlog::builder()
+        .queue(QueueKind::Ring(16))
+        .unbounded()
+        .build();
+    assert_eq!(
+        result.err().map(|e| e.kind()),
+        Some(std::io::ErrorKind::InvalidInput)
+    );
+}
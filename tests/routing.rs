@@ -0,0 +1,162 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+use log::LevelFilter;
+
+#[test]
+fn test_routing() {
+    let dir = std::env::temp_dir().join(format!("ftlog-routing-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let root = dir.join("root.log");
+    let debug = dir.join("debug.log");
+    let warn = dir.join("warn.log");
+    let routed = dir.join("routed.log");
+    let audit = dir.join("audit.log");
+
+    let _guard = ftlog::builder()
+        .max_log_level(LevelFilter::Debug)
+        .root(FileAppender::new(&root))
+        .root_log_level(LevelFilter::Info)
+        .attach("debug", FileAppender::new(&debug), LevelFilter::Debug)
+        .attach("warn", FileAppender::new(&warn), LevelFilter::Warn)
+        .filter(|_msg, _level, target| target == "routed", "routed")
+        .appender("routed", FileAppender::new(&routed))
+        .route("audit::", FileAppender::new(&audit))
+        .try_init()
+        .expect("logger build or set failed");
+
+    ftlog::debug!("debug message");
+    ftlog::info!("info message");
+    ftlog::warn!("warn message");
+    ftlog::info!(target: "routed", "routed message");
+    ftlog::debug!(target: "audit::login", "audit message");
+    ftlog::logger().flush();
+
+    let root = read_to_string(root).unwrap();
+    assert!(!root.contains("debug message"));
+    assert!(root.contains("info message"));
+    assert!(root.contains("warn message"));
+    assert!(!root.contains("routed message"));
+    assert!(!root.contains("audit message"));
+
+    let debug = read_to_string(debug).unwrap();
+    assert!(debug.contains("debug message"));
+    assert!(debug.contains("info message"));
+    assert!(debug.contains("warn message"));
+    assert!(!debug.contains("routed message"));
+    assert!(!debug.contains("audit message"));
+
+    let warn = read_to_string(warn).unwrap();
+    assert!(!warn.contains("debug message"));
+    assert!(!warn.contains("info message"));
+    assert!(warn.contains("warn message"));
+
+    let routed = read_to_string(routed).unwrap();
+    assert_eq!(routed.lines().count(), 1);
+    assert!(routed.contains("routed message"));
+
+    let audit = read_to_string(audit).unwrap();
+    assert_eq!(audit.lines().count(), 1);
+    assert!(audit.contains("audit message"));
+
+    let stats = ftlog::stats().expect("ftlog not installed");
+    assert_eq!(stats.dropped, 0);
+    let written = |name: &str| {
+        stats
+            .appenders
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.bytes_written)
+            .unwrap()
+    };
+    assert_eq!(written("root"), root.len() as u64);
+    assert_eq!(written("audit::"), audit.len() as u64);
+    assert!(stats.write_latency.count >= 5);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[derive(Clone, Default)]
+struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[test]
+fn test_field_routing() {
+    use log::{Level, Log, Record};
+
+    let root = Buffer::default();
+    let audit = Buffer::default();
+    let acme = Buffer::default();
+    let tenants = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .route_field("audit", "true", audit.clone())
+        .route_field("tenant", "acme", acme.clone())
+        .route_field("tenant", None, tenants.clone())
+        .route("app::", Buffer::default())
+        .build()
+        .unwrap();
+    let log = |kvs: &[(&str, &str)], msg: &str| {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("app::login")
+                .key_values(&kvs)
+                .args(format_args!("{}", msg))
+                .build(),
+        )
+    };
+    if cfg!(feature = "kv") {
+        log(&[("audit", "true")], "audit message");
+        log(&[("audit", "false"), ("tenant", "acme")], "acme message");
+        log(&[("tenant", "globex")], "globex message");
+    }
+    {
+        let _tenant = ftlog::context::insert("tenant", "acme");
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .target("other")
+                .args(format_args!("context message"))
+                .build(),
+        );
+    }
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("other")
+            .args(format_args!("plain message"))
+            .build(),
+    );
+    logger.flush();
+
+    let root = root.text();
+    assert_eq!(root.lines().count(), 1, "{}", root);
+    assert!(root.contains("plain message"));
+    let acme = acme.text();
+    assert!(acme.contains("context message"), "{}", acme);
+    if cfg!(feature = "kv") {
+        assert_eq!(audit.text().lines().count(), 1);
+        assert!(audit.text().contains("audit message"));
+        assert_eq!(acme.lines().count(), 2, "{}", acme);
+        assert!(acme.contains("acme message"));
+        assert_eq!(tenants.text().lines().count(), 1);
+        assert!(tenants.text().contains("globex message"));
+    }
+}
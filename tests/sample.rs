@@ -0,0 +1,32 @@
+#![cfg(feature = "random_drop")]
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+use log::{Level, LevelFilter, Log, Record};
+
+#[test]
+fn test_sample() {
+    let path = std::env::temp_dir().join(format!("ftlog-sample-{}.log", std::process::id()));
+    let logger = ftlog::builder()
+        .max_log_level(LevelFilter::Trace)
+        .sample(LevelFilter::Debug, 0.)
+        .sample(LevelFilter::Trace, 1.)
+        .root(FileAppender::new(&path))
+        .build()
+        .expect("logger build failed");
+    for level in [Level::Info, Level::Debug, Level::Trace] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .args(format_args!("{} message", level))
+                .build(),
+        );
+    }
+    logger.flush();
+
+    let content = read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(content.contains("INFO message"), "{}", content);
+    assert!(!content.contains("DEBUG message"), "{}", content);
+    assert!(!content.contains("TRACE message"), "{}", content);
+}
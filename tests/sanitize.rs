@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::Level;
+use log::{Log, Record};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log(builder: ftlog::Builder) -> String {
+    let buffer = Buffer::default();
+    let logger = builder.root(buffer.clone()).build().unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("app")
+            .key_values(&[("user", "\x1b[2Jalice\r")])
+            .args(format_args!(
+                "\x1b[31mred\x1b[0m\n2023-06-14 INFO forged\x07"
+            ))
+            .build(),
+    );
+    drop(logger);
+    let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    text
+}
+
+#[test]
+fn test_sanitize() {
+    let line = log(ftlog::builder().sanitize(true));
+    assert_eq!(line.lines().count(), 1, "{}", line);
+    assert!(!line.contains('\x1b'), "{}", line);
+    assert!(
+        line.ends_with("red\\n2023-06-14 INFO forged\\x07\n"),
+        "{}",
+        line
+    );
+    if cfg!(feature = "kv") {
+        assert!(line.contains("user=alice\\r "), "{}", line);
+    }
+
+    let line = log(ftlog::builder().sanitize(true).formatter(Format::Json));
+    assert!(!line.contains("u001b"), "{}", line);
+    assert!(
+        line.contains(r#""message":"red\\n2023-06-14 INFO forged\\x07""#),
+        "{}",
+        line
+    );
+
+    // left alone by default, but colors of an appender that is not a terminal
+    let line = log(ftlog::builder());
+    assert_eq!(line.lines().count(), 2, "{}", line);
+    assert!(line.contains("red\n2023-06-14 INFO forged\x07"), "{}", line);
+}
@@ -0,0 +1,76 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{Builder, Level};
+use log::{Log, Record};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log_record(builder: Builder) -> String {
+    let buffer = Buffer::default();
+    let logger = builder
+        .scrub(|msg, kvs| {
+            if let Some(user) = kvs.get_mut("user_id") {
+                *user = format!("<{}>", user.len());
+            }
+            kvs.remove("password");
+            kvs.insert("scrubbed", "true");
+            msg.truncate(9);
+        })
+        .scrub(|msg, kvs| {
+            assert_eq!(kvs.get("scrubbed"), Some("true"));
+            msg.push('.');
+        })
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .key_values(&[("user_id", "alice"), ("password", "hunter2")])
+            .args(format_args!("logged in {}", "with a password"))
+            .build(),
+    );
+    drop(logger);
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    content
+}
+
+#[test]
+fn test_scrub() {
+    let line = log_record(ftlog::builder());
+    assert!(line.ends_with(" logged in.\n"), "{}", line);
+    assert!(!line.contains("hunter2"), "{}", line);
+    if cfg!(feature = "kv") {
+        assert!(
+            line.contains(" user_id=<5> scrubbed=true logged"),
+            "{}",
+            line
+        );
+    }
+
+    let line = log_record(ftlog::builder().formatter(Format::Json));
+    let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(record["message"], "logged in.");
+    if cfg!(feature = "kv") {
+        assert_eq!(
+            record["fields"],
+            serde_json::json!({"user_id": "<5>", "scrubbed": "true"})
+        );
+    } else {
+        assert!(record.get("fields").is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ftlog::stats::SelfTestError;
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[test]
+fn test_selftest() {
+    for workers in [1, 3] {
+        let root = Buffer::default();
+        let alert = Buffer::default();
+        let logger = ftlog::builder()
+            .root(root.clone())
+            .appender("alert", alert.clone())
+            .workers(workers)
+            .build()
+            .unwrap();
+        log_to!(logger, Level::Info, "before");
+
+        let report = logger.selftest(Duration::from_secs(5)).unwrap();
+        assert!(report.healthy(), "{:?}", report);
+        let mut names = report
+            .appenders
+            .iter()
+            .map(|x| x.name.as_str())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["alert", "root"]);
+        assert!(report.total >= report.queued + report.rendered);
+        assert_eq!(report.stats.records[2], (Level::Info, 1));
+
+        // written after queued records, regardless of appender levels
+        let lines = root.text();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        assert!(lines[0].ends_with("before"));
+        assert_eq!(lines[1], "-- selftest --");
+        assert_eq!(alert.text(), "-- selftest --\n");
+    }
+}
+
+#[test]
+fn test_selftest_not_installed() {
+    assert_eq!(
+        ftlog::selftest(Duration::from_secs(1)).err(),
+        Some(SelfTestError::NotInstalled)
+    );
+}
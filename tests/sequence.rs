@@ -0,0 +1,129 @@
+use std::io::Write;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{log_to, Level, OverflowPolicy};
+use log::Log;
+
+/// Appender that blocks on first write until released, to saturate the channel
+struct Gate {
+    entered: Option<mpsc::Sender<()>>,
+    release: Arc<(Mutex<bool>, Condvar)>,
+    lines: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for Gate {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(entered) = self.entered.take() {
+            entered.send(()).unwrap();
+            let (released, cond) = &*self.release;
+            let _released = cond
+                .wait_while(released.lock().unwrap(), |released| !*released)
+                .unwrap();
+        }
+        self.lines.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_drop_markers() {
+    let (entered, wait_entered) = mpsc::channel();
+    let release = Arc::new((Mutex::new(false), Condvar::new()));
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let logger = ftlog::builder()
+        .root(Gate {
+            entered: Some(entered),
+            release: release.clone(),
+            lines: lines.clone(),
+        })
+        .channel_capacity(2)
+        .overflow_policy(OverflowPolicy::DropNewest)
+        .sequence(true)
+        .drop_markers(true)
+        .build()
+        .unwrap();
+
+    log_to!(logger, Level::Info, "first");
+    wait_entered.recv().unwrap();
+    log_to!(logger, Level::Info, "queued");
+    log_to!(logger, Level::Info, "queued");
+    for _ in 0..3 {
+        log_to!(logger, Level::Info, "discarded");
+    }
+    *release.0.lock().unwrap() = true;
+    release.1.notify_all();
+    logger.flush();
+    log_to!(logger, Level::Info, "after");
+    logger.flush();
+
+    let content = String::from_utf8(lines.lock().unwrap().clone()).unwrap();
+    let lines = content
+        .lines()
+        .map(|line| match line.split_once("ms ") {
+            Some((_, rest)) => {
+                let (seq, _) = rest.split_once(' ').unwrap();
+                format!("{} {}", seq, line.rsplit(' ').next().unwrap())
+            }
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        [
+            "#0 first",
+            "#1 queued",
+            "#2 queued",
+            "-- 3 records dropped --",
+            "#6 after"
+        ],
+        "{}",
+        content
+    );
+}
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sequence_json() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(Format::Json)
+        .sequence(true)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "first");
+    log_to!(logger, Level::Info, "second");
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", content);
+    assert!(
+        lines[0].contains(r#","seq":0,"level":"INFO","#),
+        "{}",
+        content
+    );
+    assert!(
+        lines[1].contains(r#","seq":1,"level":"INFO","#),
+        "{}",
+        content
+    );
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "serde")]
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::{LogRecord, RecordFormatter};
+use log::{Level, Log, Record};
+use serde::Serialize;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn content(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[derive(Serialize)]
+struct User {
+    id: u64,
+    name: &'static str,
+    roles: Vec<&'static str>,
+}
+
+struct KeyValues;
+
+impl RecordFormatter for KeyValues {
+    fn format(&self, record: &LogRecord<'_>, buf: &mut Vec<u8>) -> std::io::Result<()> {
+        for (key, value) in record.key_values() {
+            write!(buf, "{}={}|", key, value)?;
+        }
+        writeln!(buf, "{}", record.args())
+    }
+}
+
+fn log_user(logger: &ftlog::Logger) {
+    let user = User {
+        id: 7,
+        name: "alice",
+        roles: vec!["admin"],
+    };
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .key_values(&[
+                ("user", log::kv::Value::from_serde(&user)),
+                ("name", log::kv::Value::from_serde(&"bob")),
+                ("count", log::kv::Value::from_serde(&3)),
+            ])
+            .args(format_args!("logged in"))
+            .build(),
+    );
+}
+
+#[test]
+fn test_serde_values() {
+    let default = Buffer::default();
+    let logger = ftlog::builder().root(default.clone()).build().unwrap();
+    log_user(&logger);
+    drop(logger);
+    assert!(
+        default.content().ends_with(
+            r#" user={"id":7,"name":"alice","roles":["admin"]} name=bob count=3 logged in
+"#
+        ),
+        "{}",
+        default.content()
+    );
+
+    let formatted = Buffer::default();
+    let logger = ftlog::builder()
+        .root(formatted.clone())
+        .formatter(KeyValues)
+        .build()
+        .unwrap();
+    log_user(&logger);
+    drop(logger);
+    assert_eq!(
+        formatted.content(),
+        "user={\"id\":7,\"name\":\"alice\",\"roles\":[\"admin\"]}|name=bob|count=3|logged in\n"
+    );
+}
@@ -0,0 +1,53 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Buffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+#[test]
+fn test_set_format() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder().root(buffer.clone()).build().unwrap();
+    log_to!(logger, Level::Info, "default {}", 1);
+    log::Log::flush(&logger);
+    logger.set_format(Format::Json);
+    log_to!(logger, Level::Info, "json {}", 2);
+    log::Log::flush(&logger);
+    logger.set_format(Format::Text);
+    log_to!(logger, Level::Warn, "text {}", 3);
+    drop(logger);
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert!(lines[0].contains(" INFO "), "{}", lines[0]);
+    assert!(lines[0].ends_with("] default 1"), "{}", lines[0]);
+    assert!(lines[1].starts_with('{'), "{}", lines[1]);
+    assert!(lines[1].contains(r#""message":"json 2""#), "{}", lines[1]);
+    assert!(lines[2].contains("ms WARN "), "{}", lines[2]);
+    assert!(lines[2].contains("[tests/set_format.rs:"), "{}", lines[2]);
+    assert!(lines[2].ends_with("] text 3"), "{}", lines[2]);
+}
@@ -0,0 +1,105 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::{log_to, Level, LevelFilter};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+    fn messages(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| line.rsplit(' ').next().unwrap().to_string())
+            .collect()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_set_level() {
+    let root = Buffer::default();
+    let warn = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .attach("warn", warn.clone(), LevelFilter::Warn)
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Debug, "dropped");
+    logger.set_level(LevelFilter::Debug);
+    log_to!(logger, Level::Debug, "debug");
+    logger.set_level(LevelFilter::Error);
+    log_to!(logger, Level::Warn, "warn");
+    log_to!(logger, Level::Error, "error");
+    drop(logger);
+
+    assert_eq!(root.messages(), ["debug", "error"]);
+    // attached appenders keep their own level
+    assert_eq!(warn.messages(), ["error"]);
+}
+
+#[test]
+fn test_fixed_root_level() {
+    let root = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .root_log_level(LevelFilter::Info)
+        .build()
+        .unwrap();
+    logger.set_level(LevelFilter::Debug);
+    log_to!(logger, Level::Debug, "debug");
+    log_to!(logger, Level::Info, "info");
+    drop(logger);
+
+    assert_eq!(root.messages(), ["info"]);
+}
+
+#[test]
+fn test_set_target_level() {
+    let root = Buffer::default();
+    let logger = ftlog::builder().root(root.clone()).build().unwrap();
+    logger.set_target_level("app", Some(LevelFilter::Debug));
+    logger.set_target_level("app::db", Some(LevelFilter::Warn));
+    log_to!(logger, target: "app::http", Level::Debug, "http");
+    log_to!(logger, target: "app::db", Level::Info, "dropped");
+    log_to!(logger, target: "app::db", Level::Warn, "db");
+    log_to!(logger, target: "other", Level::Debug, "dropped");
+    logger.set_target_level("app", None);
+    log_to!(logger, target: "app::http", Level::Debug, "dropped");
+    drop(logger);
+
+    assert_eq!(root.messages(), ["http", "db"]);
+}
+
+#[test]
+fn test_silence() {
+    let root = Buffer::default();
+    let logger = ftlog::builder()
+        .root(root.clone())
+        .silence("h2")
+        .silence_below("sqlx", LevelFilter::Debug)
+        .silence_below("sqlx::query", LevelFilter::Warn)
+        .build()
+        .unwrap();
+    log_to!(logger, target: "h2::codec", Level::Error, "dropped");
+    log_to!(logger, target: "sqlx::query", Level::Info, "dropped");
+    log_to!(logger, target: "sqlx::query", Level::Warn, "slow");
+    log_to!(logger, target: "sqlx::pool", Level::Debug, "pool");
+    log_to!(logger, target: "app", Level::Info, "app");
+    logger.set_target_level("h2", None);
+    log_to!(logger, target: "h2::codec", Level::Error, "h2");
+    drop(logger);
+
+    assert_eq!(root.messages(), ["slow", "pool", "app", "h2"]);
+}
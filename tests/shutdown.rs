@@ -0,0 +1,34 @@
+use std::{fs::read_to_string, time::Duration};
+
+use ftlog::appender::FileAppender;
+
+#[test]
+fn test_shutdown() {
+    let dir = std::env::temp_dir().join(format!("ftlog-shutdown-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("shutdown.log");
+
+    let _guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .unbounded()
+        .try_init()
+        .expect("logger build or set failed");
+    for i in 0..10_000 {
+        ftlog::info!("record {}", i);
+    }
+    let report = ftlog::shutdown(Duration::from_secs(10)).unwrap();
+    assert!(report.joined);
+    assert_eq!(report.abandoned, 0);
+    assert!(report.flushed <= 10_000);
+    ftlog::info!("ignored after shutdown");
+
+    // second shutdown is a no-op
+    let report = ftlog::shutdown(Duration::from_secs(10)).unwrap();
+    assert_eq!(report.flushed, 0);
+
+    let content = read_to_string(&path).unwrap();
+    assert_eq!(content.lines().count(), 10_000);
+    assert!(!content.contains("ignored after shutdown"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
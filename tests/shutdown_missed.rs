@@ -0,0 +1,52 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Appender taking 300ms to write each record
+#[derive(Clone, Default)]
+struct Stuck(Arc<AtomicUsize>);
+
+impl Write for Stuck {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        std::thread::sleep(Duration::from_millis(300));
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_shutdown_missed() {
+    let stuck = Stuck::default();
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let collected = errors.clone();
+    let _guard = ftlog::builder()
+        .root(stuck.clone())
+        .unbounded()
+        .on_internal_error(move |e| collected.lock().unwrap().push(e.to_string()))
+        .try_init()
+        .expect("logger build or set failed");
+    for i in 0..10 {
+        ftlog::info!("record {}", i);
+    }
+    let report = ftlog::shutdown(Duration::from_millis(500)).unwrap();
+    let written = stuck.0.load(Ordering::Relaxed);
+    // log thread is still writing a record when the deadline passes
+    assert!(!report.joined, "{:?}", report);
+    assert!(report.flushed <= written, "{:?} {}", report, written);
+    // records still queued are abandoned, records being written are neither
+    assert!(report.flushed + report.abandoned >= 8, "{:?}", report);
+    assert!(report.flushed + report.abandoned <= 10, "{:?}", report);
+    // reported to the handler instead of stderr
+    let errors = errors.lock().unwrap();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(
+        errors[0].contains(&format!("{} log messages may be lost", report.abandoned)),
+        "{:?}",
+        errors
+    );
+}
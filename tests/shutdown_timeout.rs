@@ -0,0 +1,44 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Appender taking 50ms to write each record
+#[derive(Clone, Default)]
+struct Slow(Arc<AtomicUsize>);
+
+impl Write for Slow {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        std::thread::sleep(Duration::from_millis(50));
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_shutdown_timeout() {
+    let slow = Slow::default();
+    let _guard = ftlog::builder()
+        .root(slow.clone())
+        .unbounded()
+        .try_init()
+        .expect("logger build or set failed");
+    for i in 0..40 {
+        ftlog::info!("record {}", i);
+    }
+    let report = ftlog::shutdown(Duration::from_secs(1)).unwrap();
+    let written = slow.0.load(Ordering::Relaxed);
+    // records queued when shutdown began are written until the deadline, the rest
+    // are abandoned so that log thread is joined in time
+    assert!(report.joined, "{:?}", report);
+    assert!(report.flushed > 0, "{:?}", report);
+    assert!(report.abandoned > 0, "{:?}", report);
+    // but the record being written when shutdown began
+    assert!(written == report.flushed || written == report.flushed + 1);
+    assert!(report.flushed + report.abandoned >= 39, "{:?}", report);
+    assert!(report.flushed + report.abandoned <= 40, "{:?}", report);
+}
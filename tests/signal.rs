@@ -0,0 +1,41 @@
+#![cfg(all(unix, feature = "signal"))]
+use std::time::{Duration, Instant};
+
+use ftlog::signal::SIGUSR2;
+use ftlog::{Level, LevelFilter, Log, Metadata};
+
+fn debug_enabled(logger: &ftlog::Logger) -> bool {
+    logger.enabled(&Metadata::builder().level(Level::Debug).build())
+}
+
+fn signal_until(logger: &ftlog::Logger, enabled: bool) {
+    let pid = std::process::id().to_string();
+    let status = std::process::Command::new("kill")
+        .args(["-USR2", &pid])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let start = Instant::now();
+    while debug_enabled(logger) != enabled {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "level not toggled"
+        );
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_toggle_on_signal() {
+    let logger = ftlog::builder()
+        .max_log_level(LevelFilter::Warn)
+        .toggle_on_signal(SIGUSR2, LevelFilter::Debug)
+        .root(std::io::sink())
+        .build()
+        .unwrap();
+    assert!(!debug_enabled(&logger));
+    signal_until(&logger, true);
+    signal_until(&logger, false);
+    assert!(logger.enabled(&Metadata::builder().level(Level::Warn).build()));
+    assert!(!logger.enabled(&Metadata::builder().level(Level::Info).build()));
+}
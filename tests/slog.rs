@@ -0,0 +1,34 @@
+#![cfg(feature = "slog")]
+use std::fs::read_to_string;
+
+use ftlog::{appender::FileAppender, slog::FtLogDrain};
+use slog::{o, Drain};
+
+#[test]
+fn test_slog_drain() {
+    let dir = std::env::temp_dir().join(format!("ftlog-slog-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("slog.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .try_init()
+        .expect("logger build or set failed");
+    let root = slog::Logger::root(FtLogDrain.fuse(), o!("service" => "billing"));
+    slog::debug!(root, "filtered by ftlog level");
+    slog::crit!(root, "logged in {}", 1; "user" => "alice");
+    drop(guard);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1, "{}", content);
+    assert!(lines[0].contains(" ERROR "), "{}", lines[0]);
+    let expected = if cfg!(feature = "kv") {
+        " service=billing user=alice logged in 1"
+    } else {
+        " logged in 1"
+    };
+    assert!(lines[0].ends_with(expected), "{}", lines[0]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,127 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ftlog::appender::spill::Spill;
+use ftlog::appender::Appender;
+
+/// Collector that can be taken down
+#[derive(Clone, Default)]
+struct Collector {
+    down: Arc<AtomicBool>,
+    received: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for Collector {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.down.load(Ordering::Relaxed) {
+            return Err(std::io::ErrorKind::ConnectionRefused.into());
+        }
+        self.received.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Appender for Collector {}
+
+impl Collector {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.received.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|x| x.to_string())
+            .collect()
+    }
+}
+
+#[test]
+fn test_spill_and_replay() {
+    let dir = std::env::temp_dir().join(format!("ftlog-spill-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let collector = Collector::default();
+    let mut spill = Spill::new(collector.clone(), &dir, 1 << 20).unwrap();
+
+    spill.write_all(b"first\n").unwrap();
+    collector.down.store(true, Ordering::Relaxed);
+    spill.write_all(b"second\n").unwrap();
+    spill.write_all(b"third\n").unwrap();
+    assert_eq!(collector.lines(), ["first"]);
+    assert!(spill.backlog() > 0);
+
+    // kept on disk across restarts
+    drop(spill);
+    let mut spill = Spill::new(collector.clone(), &dir, 1 << 20).unwrap();
+    assert!(spill.backlog() > 0);
+    spill.write_all(b"fourth\n").unwrap();
+    assert_eq!(collector.lines(), ["first"]);
+
+    collector.down.store(false, Ordering::Relaxed);
+    std::thread::sleep(Duration::from_millis(1100));
+    spill.write_all(b"fifth\n").unwrap();
+    assert_eq!(spill.backlog(), 0);
+    assert_eq!(
+        collector.lines(),
+        ["first", "second", "third", "fourth", "fifth"]
+    );
+    drop(spill);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_spill_bounded() {
+    let dir = std::env::temp_dir().join(format!("ftlog-spill-bounded-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let collector = Collector::default();
+    collector.down.store(true, Ordering::Relaxed);
+    let mut spill = Spill::new(collector.clone(), &dir, 2 << 20).unwrap();
+
+    let line = [b'x'; 1023];
+    for _ in 0..8 * 1024 {
+        spill.write_all(&line).unwrap();
+    }
+    assert!(spill.backlog() <= 2 << 20, "{}", spill.backlog());
+
+    collector.down.store(false, Ordering::Relaxed);
+    spill.flush_with_deadline(Duration::from_secs(1)).unwrap();
+    assert_eq!(spill.backlog(), 0);
+    let received = collector.received.lock().unwrap().len();
+    assert!(received > 1 << 20 && received <= 2 << 20, "{}", received);
+    drop(spill);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_spill_dropped_reported() {
+    let dir = std::env::temp_dir().join(format!("ftlog-spill-dropped-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let collector = Collector::default();
+    collector.down.store(true, Ordering::Relaxed);
+    let spill = Spill::new(collector.clone(), &dir, 2 << 20).unwrap();
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let collected = errors.clone();
+    let logger = ftlog::builder()
+        .root(ftlog::appender::Sink::new(spill))
+        .on_internal_error(move |e| collected.lock().unwrap().push(e.to_string()))
+        .build()
+        .unwrap();
+
+    let line = "x".repeat(1000);
+    for _ in 0..4 * 1024 {
+        ftlog::log_to!(logger, ftlog::Level::Info, "{}", line);
+    }
+    drop(logger);
+    let errors = errors.lock().unwrap();
+    assert!(!errors.is_empty());
+    assert!(
+        errors
+            .iter()
+            .all(|x| x.starts_with("spill buffer at") && x.contains("oldest segment")),
+        "{:?}",
+        errors
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,40 @@
+use std::fs::read_to_string;
+
+use ftlog::{appender::FileAppender, log_to, Level, LevelFilter};
+
+#[test]
+fn test_standalone_loggers() {
+    let dir = std::env::temp_dir().join(format!("ftlog-standalone-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let access = ftlog::builder()
+        .with_thread(false)
+        .with_source_location(false)
+        .root(FileAppender::new(dir.join("access.log")))
+        .build()
+        .unwrap();
+    let app = ftlog::builder()
+        .max_log_level(LevelFilter::Warn)
+        .root(FileAppender::new(dir.join("app.log")))
+        .build()
+        .unwrap();
+    log_to!(access, Level::Info, "GET /index.html {}", 200);
+    log_to!(app, Level::Info, "below level of app log");
+    log_to!(app, target: "db", Level::Error, "connection lost");
+    // records are written when loggers are dropped
+    drop(access);
+    drop(app);
+
+    let access = read_to_string(dir.join("access.log")).unwrap();
+    assert!(
+        access.ends_with(" INFO GET /index.html 200\n"),
+        "{}",
+        access
+    );
+    let app = read_to_string(dir.join("app.log")).unwrap();
+    assert_eq!(app.lines().count(), 1, "{}", app);
+    assert!(app.contains(" ERROR test_standalone_loggers "), "{}", app);
+    assert!(app.ends_with(" connection lost\n"), "{}", app);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,88 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::Level;
+use log::{Log, Record};
+use serde_json::{json, Value};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Log two records in `format`, returning what is written
+fn log_records(format: Format) -> Vec<u8> {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(format)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    for _ in 0..2 {
+        logger.log(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("app")
+                .file(Some("src/main.rs"))
+                .line(Some(7))
+                .key_values(&[("user", "alice")])
+                .args(format_args!("say \"hi\"\n{}", "a".repeat(40)))
+                .build(),
+        );
+    }
+    drop(logger);
+    let bytes = buffer.0.lock().unwrap().clone();
+    bytes
+}
+
+fn check(mut record: Value) {
+    let time = record.as_object_mut().unwrap().remove("time").unwrap();
+    assert!(time.is_string());
+    let mut expected = json!({
+        "level": "WARN",
+        "target": "app",
+        "thread": "test_structured_formats",
+        "file": "src/main.rs",
+        "line": 7,
+        "message": format!("say \"hi\"\n{}", "a".repeat(40)),
+        "fields": {"user": "alice"},
+    });
+    if !cfg!(feature = "kv") {
+        expected.as_object_mut().unwrap().remove("fields");
+    }
+    assert_eq!(record, expected);
+}
+
+#[test]
+fn test_structured_formats() {
+    let json = String::from_utf8(log_records(Format::Json)).unwrap();
+    let lines = json.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", json);
+    for line in lines {
+        check(serde_json::from_str(line).unwrap());
+    }
+
+    let msgpack = log_records(Format::MsgPack);
+    let mut reader = msgpack.as_slice();
+    for _ in 0..2 {
+        check(rmp_serde::from_read(&mut reader).unwrap());
+    }
+    assert!(reader.is_empty());
+
+    let cbor = log_records(Format::Cbor);
+    let mut reader = cbor.as_slice();
+    for _ in 0..2 {
+        check(ciborium::from_reader(&mut reader).unwrap());
+    }
+    assert!(reader.is_empty());
+}
@@ -0,0 +1,60 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::{log_to, Level};
+use log::Log;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_subscribe() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder().root(buffer.clone()).build().unwrap();
+    log_to!(logger, Level::Info, "before subscribing");
+    logger.flush();
+    let records = logger.subscribe();
+    let dropped = logger.subscribe();
+    drop(dropped);
+
+    log_to!(logger, Level::Warn, "first");
+    log_to!(logger, Level::Info, "second");
+    let first = records.recv().unwrap();
+    assert_eq!(first.level, Level::Warn);
+    assert!(first.line.ends_with(" first\n"), "{}", first.line);
+    assert!(records.recv().unwrap().line.ends_with(" second\n"));
+
+    drop(logger);
+    assert!(records.recv().is_err());
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(content.lines().count(), 3, "{}", content);
+}
+
+#[test]
+fn test_slow_subscriber() {
+    let logger = ftlog::builder().root(Buffer::default()).build().unwrap();
+    let records = logger.subscribe();
+    for i in 0..2000 {
+        log_to!(logger, Level::Info, "{}", i);
+    }
+    drop(logger);
+    let received = records.iter().collect::<Vec<_>>();
+    assert_eq!(received.len(), 1024);
+    assert!(received[0].line.ends_with(" 0\n"));
+}
+
+#[test]
+fn test_no_global_logger() {
+    assert!(ftlog::subscribe().recv().is_err());
+}
@@ -0,0 +1,28 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+use log::{Level, LevelFilter, Log, Record};
+
+#[test]
+fn test_sync_level() {
+    let path = std::env::temp_dir().join(format!("ftlog-sync-{}.log", std::process::id()));
+    let logger = ftlog::builder()
+        .sync_level(LevelFilter::Error)
+        .root(FileAppender::new(&path))
+        .build()
+        .expect("logger build failed");
+    for (level, msg) in [(Level::Info, "queued"), (Level::Error, "written")] {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .args(format_args!("{}", msg))
+                .build(),
+        );
+    }
+
+    // records before the synchronous one are written too
+    let content = read_to_string(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert!(content.contains("queued\n"), "{}", content);
+    assert!(content.ends_with("written\n"), "{}", content);
+}
@@ -0,0 +1,70 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::{Facility, Rfc3164Formatter};
+use ftlog::Level;
+use log::{Log, Record};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_rfc3164_formatter() {
+    let buffer = Buffer::default();
+    let logger = ftlog::builder()
+        .formatter(
+            Rfc3164Formatter::new()
+                .facility(Facility::Local0)
+                .hostname("web01")
+                .tag("billing service"),
+        )
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Warn)
+            .key_values(&[("user", "alice")])
+            .args(format_args!("line one\nline two"))
+            .build(),
+    );
+    drop(logger);
+
+    let content = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    // local0 * 8 + warning
+    let (pri, rest) = content.split_at(5);
+    assert_eq!(pri, "<132>");
+    let (timestamp, rest) = rest.split_at(15);
+    // e.g. `Jun  4 11:13:26`
+    let separators = timestamp
+        .char_indices()
+        .filter(|(_, c)| !c.is_ascii_alphanumeric())
+        .map(|(i, _)| i)
+        .filter(|i| *i != 4)
+        .collect::<Vec<_>>();
+    assert_eq!(separators, [3, 6, 9, 12], "{}", timestamp);
+    let suffix = if cfg!(feature = "kv") {
+        " user=alice"
+    } else {
+        ""
+    };
+    assert_eq!(
+        rest,
+        format!(
+            " web01 billingservice[{}]: line one#012line two{}\n",
+            std::process::id(),
+            suffix
+        )
+    );
+}
@@ -0,0 +1,61 @@
+use std::io::Read;
+use std::net::TcpListener;
+use std::time::Duration;
+
+use ftlog::appender::tcp::TcpAppender;
+use ftlog::appender::Sink;
+use ftlog::{log_to, Level};
+
+#[test]
+fn test_tcp_appender() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        received
+    });
+
+    let logger = ftlog::builder()
+        .root(Sink::new(
+            TcpAppender::new(addr.to_string()).timeout(Duration::from_secs(5)),
+        ))
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "first");
+    log_to!(logger, Level::Info, "second");
+    // shutting down closes the connection
+    drop(logger);
+
+    let received = server.join().unwrap();
+    let lines = received.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", received);
+    assert!(lines[0].ends_with(" first"), "{}", received);
+    assert!(lines[1].ends_with(" second"), "{}", received);
+}
+
+#[cfg(feature = "tls")]
+#[test]
+fn test_tls_handshake_failure() {
+    use std::io::Write;
+
+    use ftlog::appender::tcp::Tls;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        // not a TLS server
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n");
+    });
+    let mut appender = TcpAppender::new(addr.to_string())
+        .timeout(Duration::from_secs(5))
+        .tls(Tls::new().server_name("localhost"))
+        .unwrap();
+    assert!(appender.write_all(b"line\n").is_err());
+
+    assert!(Tls::new()
+        .client_cert("./missing.pem", "./missing.key")
+        .is_err());
+}
@@ -0,0 +1,73 @@
+use std::fs::read_to_string;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ftlog::{
+    appender::FileAppender, log_to, Level, Log, TimeFormat, TimePrecision, TimestampSource,
+};
+
+#[test]
+fn test_unix_epoch_micros() {
+    let dir = std::env::temp_dir().join(format!("ftlog-time-format-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("time.log");
+
+    let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .time_format(TimeFormat::UnixEpoch)
+        .time_precision(TimePrecision::Micros)
+        .try_init()
+        .expect("logger build or set failed");
+    ftlog::info!("epoch");
+    drop(guard);
+    let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+    let content = read_to_string(&path).unwrap();
+    let micros: u128 = content.split(' ').next().unwrap().parse().unwrap();
+    assert!(before.as_micros() <= micros && micros <= after.as_micros());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Appender that is slow on first write
+#[derive(Clone, Default)]
+struct Slow(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Slow {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut content = self.0.lock().unwrap();
+        if content.is_empty() {
+            std::thread::sleep(Duration::from_millis(300));
+        }
+        content.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_timestamp_source() {
+    let appender = Slow::default();
+    let logger = ftlog::builder()
+        .root(appender.clone())
+        .time_format(TimeFormat::UnixEpoch)
+        .timestamp_source(TimestampSource::LogThread)
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "first");
+    let called = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    log_to!(logger, Level::Info, "second");
+    logger.flush();
+
+    // taken after the slow write of the first record
+    let content = String::from_utf8(appender.0.lock().unwrap().clone()).unwrap();
+    let second = content.lines().nth(1).unwrap();
+    let millis: u128 = second.split(' ').next().unwrap().parse().unwrap();
+    assert!(millis >= called.as_millis() + 200, "{}", content);
+    assert!(second.contains(" 0ms "), "{}", content);
+}
@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use ftlog::appender::timeout::Timeout;
+use ftlog::appender::Appender;
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink stalled until released
+#[derive(Clone, Default)]
+struct Stalled {
+    release: Arc<(Mutex<bool>, Condvar)>,
+    lines: Buffer,
+}
+
+impl Write for Stalled {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let (released, cond) = &*self.release;
+        let _released = cond
+            .wait_while(released.lock().unwrap(), |released| !*released)
+            .unwrap();
+        self.lines.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_timeout() {
+    let sink = Stalled::default();
+    let fallback = Buffer::default();
+    let mut appender =
+        Timeout::new(sink.clone(), Duration::from_millis(50)).fallback(fallback.clone());
+    // one line held by the stalled sink, then the queue fills up
+    for _ in 0..1025 {
+        appender.write_all(b"queued\n").unwrap();
+    }
+    assert!(!appender.degraded());
+    let start = Instant::now();
+    appender.write_all(b"late\n").unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(appender.degraded());
+    assert!(appender.healthcheck().is_ok());
+    // degraded, given up at once
+    let start = Instant::now();
+    appender.write_all(b"later\n").unwrap();
+    assert!(start.elapsed() < Duration::from_millis(50));
+    assert_eq!(&*fallback.0.lock().unwrap(), b"late\nlater\n");
+
+    *sink.release.0.lock().unwrap() = true;
+    sink.release.1.notify_all();
+    appender
+        .flush_with_deadline(Duration::from_secs(5))
+        .unwrap();
+    assert!(!appender.degraded());
+    appender.write_all(b"recovered\n").unwrap();
+    appender.flush().unwrap();
+    let lines = String::from_utf8(sink.lines.0.lock().unwrap().clone()).unwrap();
+    assert_eq!(lines.lines().count(), 1026);
+    assert!(lines.ends_with("queued\nrecovered\n"));
+}
+
+#[test]
+fn test_write_timeout_without_fallback() {
+    let sink = Stalled::default();
+    let mut appender = Timeout::new(sink.clone(), Duration::from_millis(10));
+    for _ in 0..1025 {
+        appender.write_all(b"queued\n").unwrap();
+    }
+    let error = appender.write_all(b"late\n").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    assert!(appender.healthcheck().is_err());
+    *sink.release.0.lock().unwrap() = true;
+    sink.release.1.notify_all();
+}
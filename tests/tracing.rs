@@ -0,0 +1,41 @@
+#![cfg(feature = "tracing")]
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[test]
+fn test_tracing_layer() {
+    let dir = std::env::temp_dir().join(format!("ftlog-tracing-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tracing.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .try_init()
+        .expect("logger build or set failed");
+    let subscriber = tracing_subscriber::registry().with(ftlog::tracing::layer().with_spans(true));
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    tracing::debug!("filtered by ftlog level");
+    {
+        let span = tracing::info_span!("request", id = 7);
+        let _enter = span.enter();
+        tracing::warn!(user = "alice", "logged in {}", 1);
+    }
+    drop(guard);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", content);
+    assert!(lines[0].ends_with(" id=7 enter request"), "{}", lines[0]);
+    assert!(lines[1].contains(" WARN "), "{}", lines[1]);
+    assert!(
+        lines[1].ends_with(" id=7 user=alice logged in 1"),
+        "{}",
+        lines[1]
+    );
+    assert!(lines[2].ends_with(" exit request"), "{}", lines[2]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,62 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use ftlog::format::Format;
+use ftlog::{log_to, Level};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn log(builder: ftlog::Builder) -> Vec<String> {
+    let buffer = Buffer::default();
+    let logger = builder
+        .max_message_len(32)
+        .root(buffer.clone())
+        .build()
+        .unwrap();
+    log_to!(logger, Level::Info, "{}", "short");
+    log_to!(logger, Level::Info, "{}", "日志".repeat(20));
+    log_to!(
+        logger,
+        Level::Info,
+        "static message that is longer than the limit"
+    );
+    drop(logger);
+    let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    text.lines().map(String::from).collect()
+}
+
+#[test]
+fn test_max_message_len() {
+    let lines = log(ftlog::builder());
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert!(lines[0].ends_with("] short"), "{}", lines[0]);
+    assert!(
+        lines[1].ends_with("] 日志…(truncated 114 bytes)"),
+        "{}",
+        lines[1]
+    );
+    assert!(
+        lines[2].ends_with("] static me…(truncated 35 bytes)"),
+        "{}",
+        lines[2]
+    );
+
+    let lines = log(ftlog::builder().formatter(Format::Json));
+    assert!(
+        lines[1].contains(r#""message":"日志…(truncated 114 bytes)""#),
+        "{}",
+        lines[1]
+    );
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "tui")]
+use ftlog::tui::LogView;
+use ftlog::{log_to, Level};
+use log::LevelFilter;
+use ratatui::backend::TestBackend;
+use ratatui::style::Color;
+use ratatui::Terminal;
+
+fn screen(view: &LogView) -> (Vec<String>, Terminal<TestBackend>) {
+    let mut terminal = Terminal::new(TestBackend::new(80, 3)).unwrap();
+    terminal
+        .draw(|frame| frame.render_widget(view, frame.area()))
+        .unwrap();
+    let buffer = terminal.backend().buffer().clone();
+    let rows = (0..3)
+        .map(|y| {
+            (0..80)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    (rows, terminal)
+}
+
+#[test]
+fn test_log_view() {
+    let logger = ftlog::builder()
+        .root(std::io::sink())
+        .with_thread(false)
+        .with_source_location(false)
+        .build()
+        .unwrap();
+    let mut view = LogView::new(logger.subscribe(), 3);
+    log_to!(logger, Level::Info, "connected");
+    log_to!(logger, Level::Error, "disk full");
+    log_to!(logger, Level::Info, "retrying");
+    log_to!(logger, Level::Warn, "disk slow");
+    drop(logger);
+
+    assert_eq!(view.poll(), 4);
+    // the oldest line is dropped
+    assert_eq!(view.records().count(), 3);
+    let (rows, terminal) = screen(&view);
+    assert!(rows[0].ends_with("disk full"), "{:?}", rows);
+    assert!(rows[2].ends_with("disk slow"), "{:?}", rows);
+    assert_eq!(terminal.backend().buffer()[(0, 0)].fg, Color::Red);
+
+    view.set_level(LevelFilter::Warn);
+    view.set_search("slow");
+    let (rows, _) = screen(&view);
+    assert_eq!(rows[..2], ["", ""]);
+    assert!(rows[2].ends_with("disk slow"), "{:?}", rows);
+}
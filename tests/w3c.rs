@@ -0,0 +1,89 @@
+use std::fs::read_to_string;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use ftlog::appender::{FileAppender, Period};
+use ftlog::clock::ManualClock;
+use ftlog::format::W3cFormatter;
+use ftlog::{Level, LogTimezone};
+use log::{Log, Record};
+
+fn request(logger: &ftlog::Logger, path: &str, status: u16, agent: &str) {
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("access")
+            .key_values(&[
+                ("cs_uri_stem", log::kv::Value::from(path)),
+                ("sc_status", log::kv::Value::from(status)),
+                ("cs(User-Agent)", log::kv::Value::from(agent)),
+            ])
+            .args(format_args!("done"))
+            .build(),
+    );
+}
+
+#[test]
+fn test_w3c_rotation() {
+    let dir = std::env::temp_dir().join(format!("ftlog-w3c-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // 2022-10-24T16:00:00Z
+    let start = UNIX_EPOCH + Duration::from_secs(1_666_627_200);
+    let clock = Arc::new(ManualClock::new(start));
+
+    let format = W3cFormatter::new([
+        "date",
+        "time",
+        "x-level",
+        "cs-uri-stem",
+        "sc-status",
+        "cs(User-Agent)",
+        "x-message",
+    ]);
+    let appender = FileAppender::builder()
+        .path(dir.join("access.log"))
+        .rotate(Period::Minute)
+        .timezone(LogTimezone::Utc)
+        .clock(clock.clone())
+        .header(format.header())
+        .build();
+    let logger = ftlog::builder()
+        .clock(clock.clone())
+        .formatter(format)
+        .root(appender)
+        .build()
+        .unwrap();
+    request(&logger, "/index.html", 200, "curl/8.0");
+    ftlog::Log::flush(&logger);
+    clock.advance(Duration::from_secs(61));
+    request(&logger, "/a b", 404, "Mozilla \"5.0\"");
+    drop(logger);
+
+    let fields = "#Fields: date time x-level cs-uri-stem sc-status cs(User-Agent) x-message\n";
+    let (kv_first, kv_second) = if cfg!(feature = "kv") {
+        (
+            "/index.html 200 curl/8.0",
+            r#""/a b" 404 "Mozilla ""5.0""""#,
+        )
+    } else {
+        ("- - -", "- - -")
+    };
+    for (file, line) in [
+        (
+            "access-20221024T1600.log",
+            format!("2022-10-24 16:00:00 INFO {} done\n", kv_first),
+        ),
+        (
+            "access-20221024T1601.log",
+            format!("2022-10-24 16:01:01 INFO {} done\n", kv_second),
+        ),
+    ] {
+        let content = read_to_string(dir.join(file)).unwrap();
+        assert!(content.starts_with("#Version: 1.0\n#Date: "), "{}", content);
+        let (header, record) = content.split_at(content.find(fields).unwrap() + fields.len());
+        assert_eq!(header.lines().count(), 3, "{}", content);
+        assert_eq!(record, line);
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
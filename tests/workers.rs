@@ -0,0 +1,37 @@
+use std::fs::read_to_string;
+
+use ftlog::appender::FileAppender;
+
+#[test]
+fn test_workers_keep_order() {
+    let dir = std::env::temp_dir().join(format!("ftlog-workers-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("root.log");
+    let limited = dir.join("limited.log");
+
+    let guard = ftlog::builder()
+        .root(FileAppender::new(&path))
+        .route("limited", FileAppender::new(&limited))
+        .unbounded()
+        .workers(4)
+        .try_init()
+        .expect("logger build or set failed");
+    for i in 0..10_000 {
+        ftlog::info!("record {}", i);
+        ftlog::info!(target: "limited", limit=60_000; "limited record {}", i);
+    }
+    log::logger().flush();
+    ftlog::info!("after flush");
+    drop(guard);
+
+    let content = read_to_string(&path).unwrap();
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 10_001);
+    for (i, line) in lines[..10_000].iter().enumerate() {
+        assert!(line.ends_with(&format!(" record {}", i)), "{}", line);
+    }
+    assert!(lines[10_000].ends_with("after flush"));
+    assert_eq!(read_to_string(&limited).unwrap().lines().count(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}